@@ -0,0 +1,41 @@
+# steppe's shell integration for fish, found by putting this directory's
+# parent on XDG_DATA_DIRS. fish 4 marks its prompts with OSC 133 itself, so
+# older ones get the marks here, and every one gets OSC 7.
+
+if set -q STEPPE_XDG_DATA_DIRS
+    set -gx XDG_DATA_DIRS (string split : -- $STEPPE_XDG_DATA_DIRS)
+    set -e STEPPE_XDG_DATA_DIRS
+else
+    set -e XDG_DATA_DIRS
+end
+
+status is-interactive; or exit
+
+function __steppe_cwd --on-event fish_prompt
+    printf '\e]7;file://%s%s\a' $hostname $PWD
+end
+
+if test (string split . -- $version)[1] -lt 4
+    function __steppe_prompt_start --on-event fish_prompt
+        printf '\e]133;A\a'
+    end
+
+    function __steppe_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
+
+    function __steppe_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+
+    # config.fish comes after this, so the prompt's wrapped once it's drawn the first time
+    function __steppe_wrap_prompt --on-event fish_prompt
+        functions -e __steppe_wrap_prompt
+        functions -q fish_prompt; or return
+        functions -c fish_prompt __steppe_user_prompt
+        function fish_prompt
+            __steppe_user_prompt
+            printf '\e]133;B\a'
+        end
+    end
+end
@@ -0,0 +1,54 @@
+# steppe's shell integration for bash, started with `--rcfile` pointing here
+# in place of ~/.bashrc. It reads the user's own files first, then marks
+# prompts and commands with OSC 133 and says where the shell is with OSC 7.
+
+if [[ -n "$STEPPE_BASH_LOGIN" ]]; then
+    # --rcfile only counts for shells that aren't login shells, so this
+    # reads what `bash -l` would have
+    unset STEPPE_BASH_LOGIN
+    [[ -f /etc/profile ]] && source /etc/profile
+    for __steppe_file in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [[ -f "$__steppe_file" ]]; then
+            source "$__steppe_file"
+            break
+        fi
+    done
+    unset __steppe_file
+else
+    [[ -f /etc/bash.bashrc ]] && source /etc/bash.bashrc
+    [[ -f ~/.bashrc ]] && source ~/.bashrc
+fi
+
+if [[ -z "$__steppe_integrated" ]]; then
+    __steppe_integrated=1
+
+    __steppe_prompt_start() {
+        __steppe_status=$?
+        __steppe_in_prompt=1
+    }
+
+    __steppe_prompt_end() {
+        if [[ -n "$__steppe_ran" ]]; then
+            printf '\e]133;D;%s\a' "$__steppe_status"
+        fi
+        __steppe_ran=
+        printf '\e]133;A\a'
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD"
+        # prompts that are rebuilt every time lose the mark, so it's put back
+        [[ "$PS1" == *'\e]133;B'* ]] || PS1="$PS1"'\[\e]133;B\a\]'
+        __steppe_in_prompt=
+    }
+
+    # bash has no preexec, but the DEBUG trap runs before every command
+    __steppe_preexec() {
+        [[ -n "$__steppe_in_prompt" || -n "$__steppe_ran" || -n "$COMP_LINE" ]] && return
+        [[ "$BASH_COMMAND" == __steppe_prompt_start ]] && return
+        __steppe_ran=1
+        printf '\e]133;C\a'
+    }
+
+    __steppe_user_prompt_command="${PROMPT_COMMAND%;}"
+    PROMPT_COMMAND="__steppe_prompt_start${__steppe_user_prompt_command:+; $__steppe_user_prompt_command}; __steppe_prompt_end"
+    unset __steppe_user_prompt_command
+    trap '__steppe_preexec' DEBUG
+fi
@@ -0,0 +1,38 @@
+# steppe's shell integration for powershell, dot-sourced with -Command once the
+# profile's run: OSC 133 around prompts and commands, and OSC 7 with the
+# directory before every prompt
+
+if ($global:__SteppeIntegrated) { return }
+$global:__SteppeIntegrated = $true
+$global:__SteppeRan = $false
+$global:__SteppeUserPrompt = $function:prompt
+
+function global:prompt {
+    $exitStatus = if ($?) { 0 } elseif ($global:LASTEXITCODE) { $global:LASTEXITCODE } else { 1 }
+    $esc = [char]27
+    $bel = [char]7
+
+    $marks = ""
+    if ($global:__SteppeRan) {
+        $marks += "$esc]133;D;$exitStatus$bel"
+        $global:__SteppeRan = $false
+    }
+    $marks += "$esc]133;A$bel"
+    if ($PWD.Provider.Name -eq "FileSystem") {
+        $marks += "$esc]7;file://$([System.Net.Dns]::GetHostName())/$($PWD.ProviderPath.TrimStart('/') -replace '\\', '/')$bel"
+    }
+
+    $marks + (& $global:__SteppeUserPrompt) + "$esc]133;B$bel"
+}
+
+# there's no preexec, but PSReadLine hands back every line before it's run
+if (Get-Command PSConsoleHostReadLine -ErrorAction SilentlyContinue) {
+    $global:__SteppeUserReadLine = $function:PSConsoleHostReadLine
+
+    function global:PSConsoleHostReadLine {
+        $line = & $global:__SteppeUserReadLine
+        $global:__SteppeRan = $true
+        [Console]::Write("$([char]27)]133;C$([char]7)")
+        $line
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::{error::SteppeError, screen::ScreenLine, AppState};
+
+/// A line on the screen the way a screen reader should read it: rows that
+/// wrapped joined back up, wide characters once, and no trailing blanks
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessibleLine {
+    pub text: String,
+    /// The screen row it starts on
+    pub row: usize,
+    /// How many rows it takes up, more than one when it wrapped
+    pub row_count: usize,
+}
+
+impl AccessibleLine {
+    fn new(line: &ScreenLine) -> Self {
+        let first_row = line.cells.first().map_or(0, |&(row, _)| row);
+        let last_row = line.cells.last().map_or(first_row, |&(row, _)| row);
+
+        Self {
+            text: line.text.trim_end().to_string(),
+            row: first_row.max(0) as usize,
+            row_count: (last_row - first_row + 1) as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessibleCursor {
+    pub row: usize,
+    pub col: usize,
+    /// Which of the lines it's on
+    pub line: usize,
+    /// How many characters into that line it is, which can be past the end
+    /// of its text when the cursor's out in the blanks after it
+    pub offset: usize,
+}
+
+/// What's on a terminal's screen for assistive tech, and what's changed
+/// since it was last asked for
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessibleSnapshot {
+    pub lines: Vec<AccessibleLine>,
+    pub cursor: AccessibleCursor,
+    /// How many lines scrolled off the top since the last snapshot, which
+    /// the rest of the lines moved up by
+    pub scrolled: usize,
+    /// The lines that are new or different since the last snapshot, once
+    /// scrolling's accounted for. Every line, the first time.
+    pub changed: Vec<usize>,
+    /// Whether a full-screen program (an editor, `less`, ...) has the
+    /// alternate screen up, where reading it line by line makes less sense
+    pub alternate_screen: bool,
+}
+
+/// Which line `lines` has the cell at `row`, `col` in, and how far into it
+/// that is. Wide characters' spacer cells aren't in `cells`, so the cursor
+/// sitting on one counts as being on the character after it.
+fn find_cursor(lines: &[ScreenLine], row: usize, col: usize) -> (usize, usize) {
+    let row = row as i32;
+
+    lines
+        .iter()
+        .enumerate()
+        .find_map(|(index, line)| {
+            line.cells
+                .iter()
+                .position(|&(cell_row, cell_col)| cell_row == row && cell_col >= col)
+                .map(|offset| (index, offset))
+        })
+        .unwrap_or((lines.len().saturating_sub(1), 0))
+}
+
+/// How many lines the screen's scrolled up by since `last`. It's the first
+/// shift that leaves what's still on the screen where it was moved up to,
+/// not counting the last line (usually the prompt, which may have been
+/// typed on meanwhile), or 0 if there isn't one.
+fn scrolled_by(last: &[String], lines: &[String]) -> usize {
+    (1..last.len())
+        .find(|&shift| {
+            let kept = &last[shift..last.len() - 1];
+            !kept.iter().all(String::is_empty) && lines.starts_with(kept)
+        })
+        .unwrap_or(0)
+}
+
+/// The screen as lines of text with where the cursor is, for the frontend
+/// to give screen readers rather than what xterm draws, along with which
+/// lines changed since the last call for it to announce.
+#[tauri::command]
+pub async fn async_get_accessible_snapshot(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<AccessibleSnapshot, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let screen = terminal.screen.lock().await;
+
+    let screen_lines = screen.lines();
+    let (row, col) = screen.cursor();
+    let (line, offset) = find_cursor(&screen_lines, row, col);
+    let alternate_screen = screen.alternate_screen();
+    drop(screen);
+
+    let lines: Vec<AccessibleLine> = screen_lines.iter().map(AccessibleLine::new).collect();
+    let texts: Vec<String> = lines.iter().map(|line| line.text.clone()).collect();
+
+    let mut last = terminal.accessible_lines.lock().await;
+    let scrolled = scrolled_by(&last, &texts);
+    let changed = texts
+        .iter()
+        .enumerate()
+        .filter(|(index, text)| last.get(index + scrolled) != Some(*text))
+        .map(|(index, _)| index)
+        .collect();
+    *last = texts;
+
+    Ok(AccessibleSnapshot {
+        lines,
+        cursor: AccessibleCursor { row, col, line, offset },
+        scrolled,
+        changed,
+        alternate_screen,
+    })
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Antialiasing {
+    None,
+    #[default]
+    Grayscale,
+    Subpixel,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hinting {
+    None,
+    #[default]
+    Slight,
+    Medium,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SubpixelOrder {
+    #[default]
+    Rgb,
+    Bgr,
+    Vrgb,
+    Vbgr,
+}
+
+/// How glyphs get rasterized.
+///
+/// These end up as CSS variables, so they're only as good as the webview's
+/// support for them: webkit only understands the antialiasing setting (and
+/// only with the DOM renderer), and macOS has had subpixel antialiasing
+/// turned off system-wide since Mojave, so `subpixel` renders as grayscale
+/// there. Hinting and subpixel order are passed along for custom CSS to use.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontRenderingOptions {
+    pub antialiasing: Antialiasing,
+    pub hinting: Hinting,
+    pub subpixel_order: SubpixelOrder,
+}
+
+/// The name an enum variant serializes to
+fn css_value<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl FontRenderingOptions {
+    fn css_vars(&self) -> HashMap<&'static str, String> {
+        // in terms of `-webkit-font-smoothing`
+        let antialiasing = match self.antialiasing {
+            Antialiasing::None => "none",
+            Antialiasing::Grayscale => "antialiased",
+            Antialiasing::Subpixel => "subpixel-antialiased",
+        };
+
+        HashMap::from([
+            ("--font-antialiasing", antialiasing.to_string()),
+            ("--font-hinting", css_value(&self.hinting)),
+            ("--font-subpixel-order", css_value(&self.subpixel_order)),
+        ])
+    }
+}
+
+#[tauri::command]
+pub async fn async_get_font_rendering(state: State<'_, AppState>) -> Result<FontRenderingOptions, SteppeError> {
+    Ok(*state.font_rendering.lock().await)
+}
+
+#[tauri::command]
+pub async fn async_set_font_rendering(
+    opts: FontRenderingOptions,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    *state.font_rendering.lock().await = opts;
+    app.emit("css-vars-changed", opts.css_vars())?;
+
+    Ok(())
+}
+
+/// Allowed range for the extra space between cells, in pixels
+const CELL_SPACING_RANGE: std::ops::RangeInclusive<i32> = -5..=20;
+
+/// Spacing around each cell, plus the size a cell ended up being once the
+/// frontend laid it out.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CellMetrics {
+    pub line_height_multiplier: f64,
+    pub horizontal_spacing_px: i32,
+    pub vertical_spacing_px: i32,
+    /// 0 until the frontend has measured a cell
+    pub effective_cell_width_px: f64,
+    pub effective_cell_height_px: f64,
+}
+
+impl CellMetrics {
+    /// How many pixels wide and high `rows` by `cols` cells are, or 0 by 0
+    /// (which programs take as unknown) before the frontend's measured a cell
+    pub fn pixel_size(&self, rows: u16, cols: u16) -> (u16, u16) {
+        let pixels = |cells: u16, cell_px: f64| (f64::from(cells) * cell_px).round().min(f64::from(u16::MAX)) as u16;
+
+        (
+            pixels(cols, self.effective_cell_width_px),
+            pixels(rows, self.effective_cell_height_px),
+        )
+    }
+}
+
+impl Default for CellMetrics {
+    fn default() -> Self {
+        Self {
+            line_height_multiplier: 1.0,
+            horizontal_spacing_px: 0,
+            vertical_spacing_px: 0,
+            effective_cell_width_px: 0.0,
+            effective_cell_height_px: 0.0,
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn async_get_cell_metrics(state: State<'_, AppState>) -> Result<CellMetrics, SteppeError> {
+    Ok(*state.cell_metrics.lock().await)
+}
+
+#[tauri::command]
+pub async fn async_set_line_height(value: f64, app: AppHandle, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    // xterm.js refuses anything below 1
+    if !value.is_finite() || value < 1.0 {
+        return Err(SteppeError::InvalidConfig(format!("line height must be at least 1, got {value}")));
+    }
+
+    let mut metrics = state.cell_metrics.lock().await;
+    metrics.line_height_multiplier = value;
+    app.emit("cell-metrics-changed", *metrics)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_cell_spacing(
+    horizontal: i32,
+    vertical: i32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if !CELL_SPACING_RANGE.contains(&horizontal) || !CELL_SPACING_RANGE.contains(&vertical) {
+        return Err(SteppeError::InvalidConfig(format!(
+            "cell spacing must be between {} and {} pixels",
+            CELL_SPACING_RANGE.start(),
+            CELL_SPACING_RANGE.end()
+        )));
+    }
+
+    let mut metrics = state.cell_metrics.lock().await;
+    metrics.horizontal_spacing_px = horizontal;
+    metrics.vertical_spacing_px = vertical;
+    app.emit("cell-metrics-changed", *metrics)?;
+
+    Ok(())
+}
+
+/// The frontend tells us how big a cell actually is after applying the metrics
+#[tauri::command]
+pub async fn async_report_cell_size(width: f64, height: f64, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let mut metrics = state.cell_metrics.lock().await;
+    metrics.effective_cell_width_px = width;
+    metrics.effective_cell_height_px = height;
+
+    Ok(())
+}
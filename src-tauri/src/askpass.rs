@@ -0,0 +1,245 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    hash::{BuildHasher, RandomState},
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, Sender},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::error::SteppeError;
+
+/// Set for steppe when ssh runs it as its askpass program, as `port:token`
+/// for the steppe that's running
+const ASKPASS_ENV: &str = "STEPPE_ASKPASS";
+
+/// The terminal the ssh that's asking is running in, if it's in one
+const TERMINAL_ENV: &str = "STEPPE_ASKPASS_TERMINAL";
+
+/// How long a prompt waits for an answer before ssh is told there isn't one
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+static SERVER: OnceLock<Option<Server>> = OnceLock::new();
+
+/// What ssh wants, going by `SSH_ASKPASS_PROMPT`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptKind {
+    /// A passphrase or password, or an answer like `yes` to an unknown host key
+    Secret,
+    /// Only a yes or no, like using a key that's set to need confirming
+    Confirm,
+    /// Something to show, like touching a security key, with no answer
+    Notice,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    token: String,
+    prompt: String,
+    kind: PromptKind,
+    terminal_id: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Response {
+    answer: Option<String>,
+}
+
+/// Sent as `ssh://prompt`, for `async_answer_ssh_prompt` to answer
+#[derive(Clone, Serialize)]
+struct SshPrompt {
+    id: u32,
+    terminal_id: Option<u32>,
+    prompt: String,
+    kind: PromptKind,
+}
+
+/// Where askpass runs of steppe ask the running one
+struct Server {
+    port: u16,
+    /// So only the ssh steppe started can ask, and not anything else on this machine
+    token: String,
+    next_id: AtomicU32,
+    pending: Mutex<HashMap<u32, Sender<Option<String>>>>,
+}
+
+/// 128 bits nothing else can guess, from std's per-process hash keys
+fn token() -> String {
+    let first = RandomState::new().hash_one(std::process::id());
+    let second = RandomState::new().hash_one(Instant::now());
+
+    format!("{first:016x}{second:016x}")
+}
+
+/// Pass what's asked over `stream` on to the frontend, and its answer back
+fn answer(app: &AppHandle, server: &Server, stream: TcpStream) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let request: Request = serde_json::from_str(&line)?;
+    if request.token != server.token {
+        return Ok(());
+    }
+
+    let id = server.next_id.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = mpsc::channel();
+    server
+        .pending
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(id, sender);
+
+    let prompt = SshPrompt {
+        id,
+        terminal_id: request.terminal_id,
+        prompt: request.prompt,
+        kind: request.kind,
+    };
+    let answer = match app.emit("ssh://prompt", prompt) {
+        Ok(()) => receiver.recv_timeout(PROMPT_TIMEOUT).ok().flatten(),
+        Err(err) => {
+            eprintln!("could not send ssh prompt: {err}");
+            None
+        }
+    };
+    server.pending.lock().unwrap_or_else(|err| err.into_inner()).remove(&id);
+
+    serde_json::to_writer(&stream, &Response { answer })?;
+    Ok(())
+}
+
+/// The server askpass runs ask, started the first time it's needed. `None`
+/// if it couldn't be, in which case ssh asks in the terminal like it would anyway.
+fn server(app: &AppHandle) -> Option<&'static Server> {
+    SERVER
+        .get_or_init(|| {
+            let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+                Ok(listener) => listener,
+                Err(err) => {
+                    eprintln!("could not start the ssh askpass server: {err}");
+                    return None;
+                }
+            };
+
+            let port = listener.local_addr().ok()?.port();
+            let app = app.clone();
+            thread::Builder::new()
+                .name("ssh-askpass".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        let app = app.clone();
+                        // a prompt can wait minutes on someone, and others shouldn't wait behind it
+                        thread::spawn(move || {
+                            let Some(Some(server)) = SERVER.get() else {
+                                return;
+                            };
+                            if let Err(err) = answer(&app, server, stream) {
+                                eprintln!("could not answer ssh prompt: {err}");
+                            }
+                        });
+                    }
+                })
+                .ok()?;
+
+            Some(Server {
+                port,
+                token: token(),
+                next_id: AtomicU32::new(0),
+                pending: Mutex::new(HashMap::new()),
+            })
+        })
+        .as_ref()
+}
+
+/// The environment that has ssh ask steppe's frontend for passphrases and
+/// the like, rather than the terminal. `require` is `SSH_ASKPASS_REQUIRE`:
+/// `prefer` for ssh in a terminal, `force` for one without.
+pub fn env(app: &AppHandle, terminal_id: Option<u32>, require: &str) -> Vec<(&'static str, OsString)> {
+    let (Some(server), Ok(exe)) = (server(app), std::env::current_exe()) else {
+        return Vec::new();
+    };
+
+    let mut env = vec![
+        ("SSH_ASKPASS", exe.into_os_string()),
+        ("SSH_ASKPASS_REQUIRE", require.into()),
+        (ASKPASS_ENV, format!("{}:{}", server.port, server.token).into()),
+    ];
+    if let Some(terminal_id) = terminal_id {
+        env.push((TERMINAL_ENV, terminal_id.to_string().into()));
+    }
+
+    env
+}
+
+/// Ask the running steppe what ssh asked this one, as its askpass program
+fn ask(target: &str) -> io::Result<Option<String>> {
+    let (port, token) = target
+        .split_once(':')
+        .ok_or_else(|| io::Error::other(format!("{ASKPASS_ENV} isn't port:token")))?;
+    let port: u16 = port.parse().map_err(io::Error::other)?;
+
+    let kind = match std::env::var("SSH_ASKPASS_PROMPT").as_deref() {
+        Ok("confirm") => PromptKind::Confirm,
+        Ok("none") => PromptKind::Notice,
+        _ => PromptKind::Secret,
+    };
+    let request = Request {
+        token: token.to_string(),
+        prompt: std::env::args().nth(1).unwrap_or_default(),
+        kind,
+        terminal_id: std::env::var(TERMINAL_ENV).ok().and_then(|id| id.parse().ok()),
+    };
+
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+    serde_json::to_writer(&stream, &request)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: Response = serde_json::from_str(&line)?;
+
+    Ok(response.answer)
+}
+
+/// When ssh's started steppe as its askpass program, answer it and return
+/// the exit code to quit with, before anything else about steppe starts.
+/// `None` when this is steppe proper.
+pub fn run_if_asked() -> Option<i32> {
+    let target = std::env::var(ASKPASS_ENV).ok()?;
+
+    Some(match ask(&target) {
+        Ok(Some(answer)) => {
+            println!("{answer}");
+            0
+        }
+        // cancelled, or a notice that's been seen
+        Ok(None) => 1,
+        Err(err) => {
+            eprintln!("steppe askpass: {err}");
+            1
+        }
+    })
+}
+
+/// Answer the `ssh://prompt` with `id`, or cancel it with `None`.
+#[tauri::command]
+pub async fn async_answer_ssh_prompt(id: u32, answer: Option<String>) -> Result<(), SteppeError> {
+    let sender = SERVER
+        .get()
+        .and_then(Option::as_ref)
+        .and_then(|server| server.pending.lock().unwrap_or_else(|err| err.into_inner()).remove(&id))
+        .ok_or(SteppeError::SshPromptNotFound(id))?;
+
+    // it's only gone if the prompt's timed out in the meantime
+    let _ = sender.send(answer);
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, io::Write};
+
+use tauri::{AppHandle, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{error::SteppeError, write_limit, AppState, SESSION_ID};
+
+/// A vim style register name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BufferName {
+    /// `+` and `*`, both of which just mean the system clipboard here
+    Clipboard,
+    Named(char),
+}
+
+impl TryFrom<&str> for BufferName {
+    type Error = SteppeError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        let mut chars = name.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some('+' | '*'), None) => Ok(Self::Clipboard),
+            (Some(c @ ('a'..='z' | '0'..='9')), None) => Ok(Self::Named(c)),
+            _ => Err(SteppeError::InvalidBufferName(name.to_string())),
+        }
+    }
+}
+
+/// Named buffers to copy to and paste from, on top of the system clipboard.
+#[derive(Debug, Default)]
+pub struct Buffers(HashMap<char, String>);
+
+async fn get_buffer(app: &AppHandle, state: &AppState, name: &str) -> Result<Option<String>, SteppeError> {
+    match BufferName::try_from(name)? {
+        BufferName::Clipboard => Ok(Some(app.clipboard().read_text()?)),
+        BufferName::Named(c) => Ok(state.buffers.lock().await.0.get(&c).cloned()),
+    }
+}
+
+#[tauri::command]
+pub async fn async_set_named_buffer(
+    name: String,
+    content: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    match BufferName::try_from(name.as_str())? {
+        BufferName::Clipboard => app.clipboard().write_text(content)?,
+        BufferName::Named(c) => {
+            state.buffers.lock().await.0.insert(c, content);
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_get_named_buffer(
+    name: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SteppeError> {
+    get_buffer(&app, &state, &name).await
+}
+
+/// Paste a buffer into the shell, as if it was typed. Empty buffers paste nothing.
+#[tauri::command]
+pub async fn async_paste_from_buffer(
+    name: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if let Some(content) = get_buffer(&app, &state, &name).await? {
+        let terminal = state.terminal(SESSION_ID).await?;
+        write_limit::throttle(&state, content.len()).await;
+        write!(terminal.writer.lock().await, "{}", content)?;
+    }
+
+    Ok(())
+}
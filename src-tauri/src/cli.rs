@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use portable_pty::CommandBuilder;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    error::SteppeError, next_terminal_id, spawn_in_terminal, spawn_terminal, window::MAIN_WINDOW, AppState,
+    ShellProcess,
+};
+
+/// What steppe was launched with, so it can stand in for `x-terminal-emulator`
+/// and be started from desktop files:
+///
+/// - `-e`/`--command`: everything after it is the command to run instead of the shell
+/// - `--working-directory <dir>`: where to start
+/// - `--hold`: keep the terminal open once the command exits, rather than quitting
+/// - `attach`: only bring back the window of the steppe that's already running
+/// - `serve`: run with no window, serving terminals over websockets instead
+///
+/// `attach <id>` is handled before any of this, see `remote::run_if_attaching`,
+/// and so are `serve`'s own arguments, see `serve::options_if_serving`.
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    pub command: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+    pub hold: bool,
+    pub attach: bool,
+    pub serve: bool,
+}
+
+impl LaunchOptions {
+    /// Read the arguments after the program name. Anything unknown is
+    /// warned about rather than refused, since a terminal that won't open is worse.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                // same as xterm, the rest of the line belongs to the command
+                "-e" | "--command" => options.command = args.by_ref().collect(),
+                "--hold" => options.hold = true,
+                "--working-directory" => options.working_directory = args.next().map(PathBuf::from),
+                "attach" => options.attach = true,
+                // the rest of the line is `serve`'s own
+                "serve" => {
+                    options.serve = true;
+                    break;
+                }
+                _ => match arg.strip_prefix("--working-directory=") {
+                    Some(dir) => options.working_directory = Some(PathBuf::from(dir)),
+                    None => eprintln!("ignoring unknown argument {arg:?}"),
+                },
+            }
+        }
+
+        options
+    }
+
+    /// The command to run in place of the shell, started in `cwd` if given.
+    /// A single argument is a whole command line for the shell to run
+    /// (`steppe -e "htop -d 10"`), more than one is a program and its arguments.
+    pub fn command(&self, cwd: Option<&Path>) -> Option<(CommandBuilder, ShellProcess)> {
+        let (program, args) = match self.command.as_slice() {
+            [] => return None,
+            [line] => shell_command(line),
+            [program, args @ ..] => (program.clone(), args.to_vec()),
+        };
+
+        let mut cmd = CommandBuilder::new(&program);
+        cmd.args(&args);
+        if let Some(cwd) = cwd {
+            cmd.cwd(cwd);
+        }
+
+        let process = ShellProcess {
+            program,
+            shell_profile: None,
+            ssh_profile: None,
+            wsl_distro: None,
+            docker_container: None,
+            k8s_shell: None,
+            pid: None,
+            init_command: None,
+        };
+
+        Some((cmd, process))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn shell_command(line: &str) -> (String, Vec<String>) {
+    ("cmd.exe".to_string(), vec!["/C".to_string(), line.to_string()])
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell_command(line: &str) -> (String, Vec<String>) {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    (shell, vec!["-c".to_string(), line.to_string()])
+}
+
+/// What the main terminal should start with: the launch options the first
+/// time, nothing special after that (e.g. once the command's exited and
+/// another shell's asked for).
+pub async fn take(state: &AppState) -> LaunchOptions {
+    std::mem::take(&mut *state.launch.lock().await)
+}
+
+/// Open a terminal in the running steppe for a second launch of it, like
+/// `steppe -e htop` or just `steppe`. `cwd` is where that launch happened,
+/// which is where the terminal starts unless it was given somewhere else.
+pub async fn open(app: &AppHandle, options: LaunchOptions, cwd: &Path) -> Result<(), SteppeError> {
+    let working_directory = options
+        .working_directory
+        .as_ref()
+        .map_or_else(|| cwd.to_path_buf(), |dir| cwd.join(dir));
+
+    let Some((cmd, process)) = options.command(Some(&working_directory)) else {
+        spawn_terminal(app, None, Some(working_directory), MAIN_WINDOW).await?;
+        return Ok(());
+    };
+
+    let state = app.state::<AppState>();
+    let terminal_id = next_terminal_id(&state).await?;
+    spawn_in_terminal(terminal_id, cmd, process, app.clone(), &state)
+        .await
+        .map_err(SteppeError::Pty)?;
+
+    app.emit_to(MAIN_WINDOW, "terminal://spawned", terminal_id)?;
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::{io::Write, sync::atomic::Ordering};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tauri::{AppHandle, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{error::SteppeError, AppState, SubTerminal};
+
+/// OSC 52 with base64 text: put it on the clipboard, if programs are allowed to.
+pub fn set(app: &AppHandle, state: &AppState, data: &str) -> Result<(), SteppeError> {
+    if !state.clipboard_write.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    // xterm clears the clipboard for anything that isn't base64, but there's
+    // no clearing it through the plugin, so it's just left alone
+    let Ok(text) = STANDARD.decode(data) else {
+        return Ok(());
+    };
+
+    app.clipboard().write_text(String::from_utf8_lossy(&text))?;
+
+    Ok(())
+}
+
+/// OSC 52 with `?`: answer with what's on the clipboard, if programs are
+/// allowed to see it. Anything that can print to the terminal could ask,
+/// including whatever's on the other end of an ssh connection.
+pub async fn query(
+    app: &AppHandle,
+    state: &AppState,
+    terminal: &SubTerminal,
+    selection: &str,
+) -> Result<(), SteppeError> {
+    if !state.clipboard_read.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let text = app.clipboard().read_text()?;
+    // an empty selection means the default, which is the clipboard
+    let selection = if selection.is_empty() { "c" } else { selection };
+
+    let reply = format!("\x1b]52;{selection};{}\x07", STANDARD.encode(text));
+    terminal.writer.lock().await.write_all(reply.as_bytes())?;
+
+    Ok(())
+}
+
+/// Whether programs can put text on the clipboard with OSC 52. On by default.
+#[tauri::command]
+pub async fn async_set_allow_clipboard_write(allow: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.clipboard_write.store(allow, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Whether programs can read the clipboard with OSC 52. Off by default.
+#[tauri::command]
+pub async fn async_set_allow_clipboard_read(allow: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.clipboard_read.store(allow, Ordering::Relaxed);
+    Ok(())
+}
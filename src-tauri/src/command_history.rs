@@ -0,0 +1,240 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::{async_runtime, State};
+
+use crate::{
+    cwd, error::SteppeError, get_config_dir, redact, shell_integration::FinishedCommand, AppState, SubTerminal,
+};
+
+/// How many matches a search returns when it isn't told
+const DEFAULT_SEARCH_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    /// Where it was last run, if the shell said
+    pub cwd: Option<PathBuf>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub timestamp_ms: u64,
+    /// How many times it's been run in that directory
+    pub runs: u64,
+}
+
+/// Every command run in any terminal with shell integration, kept in
+/// `<config dir>/history.sqlite3`. A command run again in the same directory
+/// updates its entry rather than adding another.
+#[derive(Debug)]
+pub struct CommandHistory {
+    pub enabled: bool,
+    pub database: Database,
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            database: Database::default(),
+        }
+    }
+}
+
+/// The history's database, opened the first time it's needed. It's only
+/// used off the runtime, so cloning it out of `state.command_history` means
+/// nothing waits on that while it's read or written.
+#[derive(Debug, Clone, Default)]
+pub struct Database(Arc<Mutex<Option<Connection>>>);
+
+fn history_path() -> PathBuf {
+    get_config_dir().join("history.sqlite3")
+}
+
+fn open() -> Result<Connection, SteppeError> {
+    let connection = Connection::open(history_path())?;
+    // an unknown cwd is kept as '' rather than NULL, so it still counts for dedupe
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS commands (
+            command TEXT NOT NULL,
+            cwd TEXT NOT NULL,
+            exit_code INTEGER,
+            duration_ms INTEGER NOT NULL,
+            timestamp_ms INTEGER NOT NULL,
+            runs INTEGER NOT NULL DEFAULT 1,
+            PRIMARY KEY (command, cwd)
+        );
+        CREATE INDEX IF NOT EXISTS commands_by_time ON commands (timestamp_ms);",
+    )?;
+
+    Ok(connection)
+}
+
+impl Database {
+    /// Run `f` with the connection, somewhere its IO won't hold up the runtime
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T, SteppeError> + Send + 'static,
+    ) -> Result<T, SteppeError> {
+        let connection = self.0.clone();
+        async_runtime::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|err| err.into_inner());
+            if connection.is_none() {
+                *connection = Some(open()?);
+            }
+
+            f(connection.as_ref().expect("opened above"))
+        })
+        .await?
+    }
+
+    /// Up to `limit` commands starting with `prefix`, run in `cwd` if given,
+    /// newest first
+    pub async fn search(
+        &self,
+        prefix: String,
+        cwd: Option<PathBuf>,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, SteppeError> {
+        self.run(move |connection| {
+            // compared with substr rather than LIKE, so % and _ in the prefix aren't wildcards
+            let mut statement = connection.prepare(
+                "SELECT command, cwd, exit_code, duration_ms, timestamp_ms, runs FROM commands
+                WHERE substr(command, 1, length(?1)) = ?1 AND (?2 IS NULL OR cwd = ?2)
+                ORDER BY timestamp_ms DESC
+                LIMIT ?3",
+            )?;
+
+            let entries = statement
+                .query_map(
+                    params![prefix, cwd.map(|cwd| cwd.display().to_string()), limit as i64],
+                    |row| {
+                        let cwd: String = row.get(1)?;
+                        Ok(HistoryEntry {
+                            command: row.get(0)?,
+                            cwd: Some(PathBuf::from(cwd)).filter(|cwd| !cwd.as_os_str().is_empty()),
+                            exit_code: row.get(2)?,
+                            duration_ms: row.get(3)?,
+                            timestamp_ms: row.get(4)?,
+                            runs: row.get(5)?,
+                        })
+                    },
+                )?
+                .collect::<Result<_, _>>()?;
+
+            Ok(entries)
+        })
+        .await
+    }
+
+    /// The `limit` directories commands were last run in, most recent first
+    pub async fn recent_dirs(&self, limit: usize) -> Result<Vec<PathBuf>, SteppeError> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT cwd FROM commands WHERE cwd != ''
+                GROUP BY cwd
+                ORDER BY max(timestamp_ms) DESC
+                LIMIT ?1",
+            )?;
+
+            let dirs = statement
+                .query_map(params![limit as i64], |row| row.get::<_, String>(0))?
+                .map(|cwd| cwd.map(PathBuf::from))
+                .collect::<Result<_, _>>()?;
+
+            Ok(dirs)
+        })
+        .await
+    }
+}
+
+/// Add a command that just finished in `terminal` to the history, unless the
+/// user turned it off or the command starts with a space (like bash's
+/// `HISTCONTROL=ignorespace`). Secrets in it are redacted first.
+pub async fn record(state: &AppState, terminal: &SubTerminal, finished: &FinishedCommand) -> Result<(), SteppeError> {
+    let Some(command) = &finished.command else {
+        return Ok(());
+    };
+    if command.starts_with(' ') || command.trim().is_empty() {
+        return Ok(());
+    }
+
+    let database = {
+        let history = state.command_history.lock().await;
+        if !history.enabled {
+            return Ok(());
+        }
+        history.database.clone()
+    };
+
+    let cwd = match &finished.cwd {
+        Some(cwd) => Some(cwd.clone()),
+        None => cwd::terminal_cwd(terminal).await,
+    };
+    let command = redact::current(state).redact(command.trim()).into_owned();
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let (exit_code, duration_ms) = (finished.exit_code, finished.duration.as_millis() as u64);
+
+    database
+        .run(move |connection| {
+            connection.execute(
+                "INSERT INTO commands (command, cwd, exit_code, duration_ms, timestamp_ms) VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (command, cwd) DO UPDATE SET
+                    exit_code = excluded.exit_code,
+                    duration_ms = excluded.duration_ms,
+                    timestamp_ms = excluded.timestamp_ms,
+                    runs = runs + 1",
+                params![
+                    command,
+                    cwd.map(|cwd| cwd.display().to_string()).unwrap_or_default(),
+                    exit_code,
+                    duration_ms,
+                    timestamp_ms,
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+}
+
+/// Commands starting with `prefix`, run in `cwd` if given, newest first.
+#[tauri::command]
+pub async fn async_search_command_history(
+    prefix: String,
+    cwd: Option<PathBuf>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<HistoryEntry>, SteppeError> {
+    let database = state.command_history.lock().await.database.clone();
+    database
+        .search(prefix, cwd, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+}
+
+#[tauri::command]
+pub async fn async_clear_command_history(state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let database = state.command_history.lock().await.database.clone();
+    database
+        .run(|connection| {
+            connection.execute("DELETE FROM commands", [])?;
+
+            Ok(())
+        })
+        .await
+}
+
+/// Stop (or start) saving commands. Turning it off doesn't delete what's
+/// already there, that's what clearing is for.
+#[tauri::command]
+pub async fn async_set_command_history_enabled(enabled: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.command_history.lock().await.enabled = enabled;
+    Ok(())
+}
@@ -0,0 +1,592 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
+
+use deno_ast::ParseDiagnostic;
+use deno_runtime::deno_core::{
+    error::{AnyError, JsError},
+    serde_v8, v8, ModuleId, ModuleSpecifier,
+};
+use deno_runtime::deno_fs::RealFs;
+use deno_runtime::permissions::RuntimePermissionDescriptorParser;
+use deno_runtime::worker::{MainWorker, WorkerOptions, WorkerServiceOptions};
+use notify::{RecursiveMode, Watcher};
+use serde::{de::DeserializeOwned, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    time::{sleep_until, Instant},
+};
+
+use crate::{
+    appearance::{self, CellMetrics, FontRenderingOptions},
+    config_permissions, crash,
+    cursor::{self, CursorColors},
+    error::SteppeError,
+    get_config_dir, get_config_path, gpu, keybindings,
+    module_loader::{self, ModuleInfo, TsModuleLoader},
+    output_batch::{self, BatchModeOptions},
+    plugins, process_icons,
+    renderer::{self, RendererBackend},
+    scrollback,
+    settings::{self, Settings},
+    shell_integration, shell_profile,
+    size::{self, SizeConstraints},
+    startup_notification::{self, StartupNotificationOptions},
+    steppe_api,
+    url_opener::UrlOpener,
+    AppState,
+};
+
+/// Editors tend to write a file in a few steps (truncate, write, rename...),
+/// so wait for them to settle before reloading
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Types for the `steppe` global, for editors to pick up in config.ts
+pub const STEPPE_TYPES: &str = include_str!("../steppe.d.ts");
+
+/// Types for what the `steppe` global's setters take, which steppe.d.ts imports
+pub const CONFIG_TYPES: &str = include_str!("../steppe-config.d.ts");
+
+/// What config.js can export, which are the same as the settings file's keys
+const KNOWN_EXPORTS: [&str; 32] = [
+    "fontSize",
+    "scrollbackLines",
+    "outputCoalesceBytes",
+    "outputCoalesceMs",
+    "defaultShell",
+    "loginShell",
+    "shellArgs",
+    "shellIntegration",
+    "shellEnv",
+    "initCommand",
+    "theme",
+    "quake",
+    "keepAliveInTray",
+    "maxImageBytes",
+    "linkPatterns",
+    "onExit",
+    "tasks",
+    "startupLayout",
+    "remoteAttach",
+    "redaction",
+    "fileDrop",
+    "scrollbackSpill",
+    "environmentSnapshot",
+    "commandNotifications",
+    "terminalIdentity",
+    "allowClipboardWrite",
+    "allowClipboardRead",
+    "commandHistory",
+    "confirmClose",
+    "restoreSession",
+    "detachOnClose",
+    "windowTitleFromTerminal",
+];
+
+#[derive(Clone, Serialize)]
+struct ConfigReloaded {
+    path: PathBuf,
+}
+
+/// Something wrong with config.js, a module it imports or the settings file,
+/// sent as `config://error`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    /// What the module threw, or why it couldn't be loaded or used
+    pub message: String,
+    /// Where in `path` it went wrong, both 1-based, when that's known
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl ConfigError {
+    pub fn new(path: &Path, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            message: message.into(),
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn at(self, line: usize, column: usize) -> Self {
+        Self {
+            line: Some(line),
+            column: Some(column),
+            ..self
+        }
+    }
+
+    /// What deno gave back for `path`, pointing at wherever it threw, which
+    /// might be in a module it imports
+    fn from_deno(path: &Path, err: &AnyError) -> Self {
+        let error = Self::new(path, err.to_string());
+
+        // typescript that didn't parse never gets as far as v8
+        if let Some(diagnostic) = err.downcast_ref::<ParseDiagnostic>() {
+            let position = diagnostic.display_position();
+            let path = diagnostic.specifier.to_file_path().unwrap_or(error.path.clone());
+            return Self { path, ..error }.at(position.line_number, position.column_number);
+        }
+
+        // the innermost frame that's in a file, rather than steppe's own api
+        let frame = err.downcast_ref::<JsError>().and_then(|err| {
+            err.frames.iter().find_map(|frame| {
+                let path = ModuleSpecifier::parse(frame.file_name.as_deref()?)
+                    .ok()?
+                    .to_file_path()
+                    .ok()?;
+                let line = usize::try_from(frame.line_number?).ok()?;
+                let column = usize::try_from(frame.column_number?).ok()?;
+                Some((path, line, column))
+            })
+        });
+
+        match frame {
+            Some((path, line, column)) => Self { path, ..error }.at(line, column),
+            None => error,
+        }
+    }
+}
+
+/// Start config.js in a fresh worker. A module can only be evaluated once per
+/// worker, so every reload needs its own.
+async fn start(app: &AppHandle, path: &Path, file_settings: Settings) -> Result<MainWorker, ConfigError> {
+    // deno boilerplate from https://github.com/denoland/deno/blob/main/runtime/examples/extension/main.rs
+    let main_module = ModuleSpecifier::from_file_path(path)
+        .map_err(|_| ConfigError::new(path, format!("{} isn't an absolute path", path.display())))?;
+
+    let fs = Arc::new(RealFs);
+
+    let permission_desc_parser = Arc::new(RuntimePermissionDescriptorParser::new(fs.clone()));
+    let (permissions, run) = config_permissions::permissions(permission_desc_parser)
+        .map_err(|message| ConfigError::new(&config_permissions::manifest_path(), message))?;
+
+    let module_loader = TsModuleLoader::new(app.clone()).map_err(|err| ConfigError::new(path, err.to_string()))?;
+
+    let mut worker = MainWorker::bootstrap_from_options(
+        main_module.clone(),
+        WorkerServiceOptions {
+            module_loader: Rc::new(module_loader),
+            permissions,
+            blob_store: Default::default(),
+            broadcast_channel: Default::default(),
+            feature_checker: Default::default(),
+            node_services: Default::default(),
+            npm_process_state_provider: Default::default(),
+            root_cert_store_provider: Default::default(),
+            shared_array_buffer_store: Default::default(),
+            compiled_wasm_module_store: Default::default(),
+            v8_code_cache: Default::default(),
+            fs,
+        },
+        WorkerOptions {
+            extensions: vec![steppe_api::steppe::init_ops_and_esm(app.clone(), run.clone())],
+            ..Default::default()
+        },
+    );
+
+    let module_id = worker
+        .preload_main_module(&main_module)
+        .await
+        .map_err(|err| ConfigError::from_deno(path, &err))?;
+    worker
+        .evaluate_module(module_id)
+        .await
+        .map_err(|err| ConfigError::from_deno(path, &err))?;
+
+    let exported = read_settings(&mut worker, module_id).map_err(|message| ConfigError::new(path, message))?;
+    // they're typed into a shell, which can run anything
+    if exported.tasks.is_some() {
+        run.check_all("exporting tasks")
+            .map_err(|message| ConfigError::new(path, message))?;
+    }
+    settings::apply(app, &app.state::<AppState>(), file_settings.merge(exported))
+        .await
+        .map_err(|err| ConfigError::new(path, err.to_string()))?;
+
+    Ok(worker)
+}
+
+/// The settings config.js exports, which win over the settings file's
+fn read_settings(worker: &mut MainWorker, module_id: ModuleId) -> Result<Settings, String> {
+    check_exports(worker, module_id)?;
+
+    let settings = Settings {
+        font_size: read_export(worker, module_id, "fontSize")?,
+        scrollback_lines: read_export(worker, module_id, "scrollbackLines")?,
+        output_coalesce_bytes: read_export(worker, module_id, "outputCoalesceBytes")?,
+        output_coalesce_ms: read_export(worker, module_id, "outputCoalesceMs")?,
+        default_shell: read_export(worker, module_id, "defaultShell")?,
+        login_shell: read_export(worker, module_id, "loginShell")?,
+        shell_args: read_export(worker, module_id, "shellArgs")?,
+        shell_integration: read_export(worker, module_id, "shellIntegration")?,
+        shell_env: read_export(worker, module_id, "shellEnv")?,
+        init_command: read_export(worker, module_id, "initCommand")?,
+        theme: read_export(worker, module_id, "theme")?,
+        quake: read_export(worker, module_id, "quake")?,
+        keep_alive_in_tray: read_export(worker, module_id, "keepAliveInTray")?,
+        max_image_bytes: read_export(worker, module_id, "maxImageBytes")?,
+        link_patterns: read_export(worker, module_id, "linkPatterns")?,
+        on_exit: read_export(worker, module_id, "onExit")?,
+        tasks: read_export(worker, module_id, "tasks")?,
+        startup_layout: read_export(worker, module_id, "startupLayout")?,
+        remote_attach: read_export(worker, module_id, "remoteAttach")?,
+        redaction: read_export(worker, module_id, "redaction")?,
+        file_drop: read_export(worker, module_id, "fileDrop")?,
+        scrollback_spill: read_export(worker, module_id, "scrollbackSpill")?,
+        environment_snapshot: read_export(worker, module_id, "environmentSnapshot")?,
+        command_notifications: read_export(worker, module_id, "commandNotifications")?,
+        terminal_identity: read_export(worker, module_id, "terminalIdentity")?,
+        allow_clipboard_write: read_export(worker, module_id, "allowClipboardWrite")?,
+        allow_clipboard_read: read_export(worker, module_id, "allowClipboardRead")?,
+        command_history: read_export(worker, module_id, "commandHistory")?,
+        confirm_close: read_export(worker, module_id, "confirmClose")?,
+        restore_session: read_export(worker, module_id, "restoreSession")?,
+        detach_on_close: read_export(worker, module_id, "detachOnClose")?,
+        window_title_from_terminal: read_export(worker, module_id, "windowTitleFromTerminal")?,
+    };
+    settings.validate()?;
+
+    Ok(settings)
+}
+
+/// Make sure config.js doesn't export anything steppe would quietly ignore,
+/// like a misspelled setting
+fn check_exports(worker: &mut MainWorker, module_id: ModuleId) -> Result<(), String> {
+    let namespace = worker
+        .js_runtime
+        .get_module_namespace(module_id)
+        .map_err(|err| err.to_string())?;
+
+    let scope = &mut worker.js_runtime.handle_scope();
+    let namespace = v8::Local::new(scope, namespace);
+    let Some(names) = namespace.get_own_property_names(scope, Default::default()) else {
+        return Ok(());
+    };
+
+    for index in 0..names.length() {
+        let Some(name) = names.get_index(scope, index) else {
+            continue;
+        };
+
+        let name = name.to_rust_string_lossy(scope);
+        if !KNOWN_EXPORTS.contains(&name.as_str()) {
+            return Err(format!(
+                "{name} isn't something config.js can export, only {}",
+                KNOWN_EXPORTS.join(", ")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// What config.js exports as `name`, or `None` if it doesn't
+fn read_export<T: DeserializeOwned>(
+    worker: &mut MainWorker,
+    module_id: ModuleId,
+    name: &str,
+) -> Result<Option<T>, String> {
+    let namespace = worker
+        .js_runtime
+        .get_module_namespace(module_id)
+        .map_err(|err| err.to_string())?;
+
+    let scope = &mut worker.js_runtime.handle_scope();
+    let namespace = v8::Local::new(scope, namespace);
+    let key = v8::String::new(scope, name).ok_or_else(|| format!("could not look up the {name} export"))?;
+
+    match namespace.get(scope, key.into()) {
+        Some(value) if !value.is_undefined() => serde_v8::from_v8(scope, value)
+            .map(Some)
+            .map_err(|err| format!("the {name} export is invalid: {err}")),
+        _ => Ok(None),
+    }
+}
+
+pub fn emit_error(app: &AppHandle, error: ConfigError) {
+    if let Err(err) = app.emit("config://error", error) {
+        eprintln!("could not send config error: {err}");
+    }
+}
+
+/// Whether `event` is config.js (or what it's allowed to do) getting saved
+fn touches_config(event: &notify::Result<notify::Event>, path: &Path) -> bool {
+    let permissions_path = config_permissions::manifest_path();
+
+    event.as_ref().is_ok_and(|event| {
+        !event.kind.is_access()
+            && event
+                .paths
+                .iter()
+                .any(|p| p == path || *p == permissions_path || settings::is_settings_file(p))
+    })
+}
+
+/// Wait until config.js (or what it's allowed to do) gets saved, returning
+/// `false` if the watcher's gone.
+async fn wait_for_change(changes: &mut UnboundedReceiver<notify::Result<notify::Event>>, path: &Path) -> bool {
+    while let Some(event) = changes.recv().await {
+        if !touches_config(&event, path) {
+            continue;
+        }
+
+        // drain the rest of this save
+        while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, changes.recv()).await {}
+
+        return true;
+    }
+
+    false
+}
+
+/// Wait until something asks for config.js to be reloaded. Anything else
+/// asked for meanwhile is dropped, since there's no worker to do it.
+async fn wait_for_reload(commands: &mut UnboundedReceiver<WorkerCommand>) {
+    while let Some(command) = commands.recv().await {
+        if let WorkerCommand::Reload = command {
+            return;
+        }
+    }
+
+    std::future::pending().await
+}
+
+/// Things other threads want done in the config worker, which is the only
+/// place js can run
+#[derive(Debug)]
+pub enum WorkerCommand {
+    ActivatePlugin(PathBuf),
+    DeactivatePlugin(PathBuf),
+    /// A keybinding bound to a js callback was pressed
+    RunKeybinding(u32),
+    /// A terminal printed a line matching a trigger with a js callback
+    RunTrigger {
+        id: u32,
+        terminal_id: u32,
+        line: String,
+    },
+    /// Start config.js over, as if it had been saved
+    Reload,
+}
+
+fn run_command(worker: &mut MainWorker, command: WorkerCommand) {
+    match command {
+        WorkerCommand::ActivatePlugin(path) => plugins::activate(worker, &path),
+        WorkerCommand::DeactivatePlugin(path) => plugins::deactivate(worker, &path),
+        WorkerCommand::RunKeybinding(id) => {
+            let script = format!("globalThis.__steppeKeybindings.run({id})");
+            if let Err(err) = worker.execute_script("steppe:keybindings", script.into()) {
+                eprintln!("could not run keybinding: {err}");
+            }
+        }
+        WorkerCommand::RunTrigger { id, terminal_id, line } => {
+            let script = format!(
+                "globalThis.__steppeTriggers.run({id}, {terminal_id}, {})",
+                serde_json::json!(line)
+            );
+            if let Err(err) = worker.execute_script("steppe:triggers", script.into()) {
+                eprintln!("could not run trigger: {err}");
+            }
+        }
+        // `run` starts the worker over itself
+        WorkerCommand::Reload => {}
+    }
+}
+
+/// Forget the keybindings and triggers the last run of config.js registered,
+/// since it's about to register them all over again.
+async fn clear_registrations(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    state.triggers.lock().await.clear();
+    state.palette_commands.lock().await.clear();
+
+    let mut keybindings = state.keybindings.lock().await;
+    keybindings.clear();
+
+    if let Err(err) = keybindings::emit_changed(app, &keybindings) {
+        eprintln!("could not send keybindings: {err}");
+    }
+}
+
+/// Put back what config.js's setters changed, so one taken out of config.js
+/// stops having an effect when it reloads rather than when steppe restarts.
+/// Settings config.js exports are put back to theirs by `settings::apply`.
+async fn reset_setters(app: &AppHandle) -> Result<(), SteppeError> {
+    let state = app.state::<AppState>();
+
+    *state.line_discipline.lock().await = None;
+    *state.url_opener.lock().await = UrlOpener::default();
+    state.url_history.lock().await.enabled = true;
+    crash::async_set_crash_reporting(false, None).await?;
+    renderer::async_set_renderer_backend(RendererBackend::default(), app.clone(), app.state()).await?;
+    size::async_set_session_size_constraints(SizeConstraints::default(), app.state()).await?;
+    state.expose_secrets_in_diagnostics.store(false, Ordering::Relaxed);
+    state.startup_delay_ms.store(0, Ordering::Relaxed);
+    state.startup_sequences.lock().await.clear();
+
+    appearance::async_set_font_rendering(FontRenderingOptions::default(), app.clone(), app.state()).await?;
+    let colors = CursorColors::default();
+    cursor::async_set_cursor_colors(colors.fg, colors.bg, app.clone(), app.state()).await?;
+    let metrics = CellMetrics::default();
+    appearance::async_set_line_height(metrics.line_height_multiplier, app.clone(), app.state()).await?;
+    let (horizontal, vertical) = (metrics.horizontal_spacing_px, metrics.vertical_spacing_px);
+    appearance::async_set_cell_spacing(horizontal, vertical, app.clone(), app.state()).await?;
+    state.focus_opacity.lock().await.reset();
+    process_icons::async_set_process_icon_map(Vec::new(), app.clone(), app.state()).await?;
+    gpu::async_stop_gpu_monitoring(app.state()).await?;
+
+    let notification = StartupNotificationOptions::default();
+    startup_notification::async_set_session_startup_notification(notification, app.state()).await?;
+    output_batch::async_set_output_batch_mode(BatchModeOptions::default(), app.clone(), app.state()).await?;
+    *state.write_limiter.lock().await = None;
+    state.ssh_profiles.lock().await.clear();
+    shell_profile::async_set_shell_profiles(Vec::new(), app.clone(), app.state()).await?;
+    let long_command_ms = shell_integration::DEFAULT_LONG_COMMAND_MS;
+    state.long_command_ms.store(long_command_ms, Ordering::Relaxed);
+    *state.output_logging.lock().await = None;
+    // open terminals are trimmed to whatever it ends up as once the settings are applied
+    let lines = scrollback::DEFAULT_SCROLLBACK_LINES;
+    state.scrollback_lines.store(lines, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Keep config.js and the plugins running, starting them over whenever config.js changes.
+async fn run(app: AppHandle, mut commands: UnboundedReceiver<WorkerCommand>) {
+    let path = get_config_path();
+
+    // kept up to date with whatever this version of steppe has
+    for (name, types) in [("steppe.d.ts", STEPPE_TYPES), ("steppe-config.d.ts", CONFIG_TYPES)] {
+        if let Err(err) = fs::write(get_config_dir().join(name), types) {
+            eprintln!("could not write {name}: {err}");
+        }
+    }
+
+    let (events, mut changes) = unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = events.send(event);
+    }) {
+        Ok(watcher) => watcher,
+        Err(err) => return eprintln!("could not watch config: {err}"),
+    };
+
+    // watch the directory rather than the file, since saving by renaming
+    // over it would leave a file watch pointing at the old one
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        return eprintln!("could not watch config: {err}");
+    }
+
+    // what was loaded last time, to tell when reloading changes it
+    let mut graph: Vec<ModuleInfo> = Vec::new();
+    loop {
+        module_loader::graph(&app.state::<AppState>()).clear();
+        clear_registrations(&app).await;
+        if let Err(err) = reset_setters(&app).await {
+            eprintln!("could not reset what config.js set: {err}");
+        }
+
+        let file_settings = settings::load().unwrap_or_else(|error| {
+            emit_error(&app, error);
+            Settings::default()
+        });
+
+        match start(&app, &path, file_settings.clone()).await {
+            Ok(mut worker) => {
+                // anything asked for while there was no worker is covered by starting the enabled ones
+                while commands.try_recv().is_ok() {}
+                plugins::activate_enabled(&app, &mut worker).await;
+                graph = module_loader::emit_graph_changes(&app, &graph);
+
+                if let Err(err) = app.emit("config://reloaded", ConfigReloaded { path: path.clone() }) {
+                    eprintln!("could not send config reload: {err}");
+                }
+
+                // timers, `steppe.onOutput` and so on keep going until the config
+                // changes, and plugins can be turned on and off all the while
+                let mut idle = false;
+                // when to reload, once a save's started. It's kept out here so a
+                // command coming in partway through the debounce doesn't lose it
+                let mut reload_at: Option<Instant> = None;
+                loop {
+                    tokio::select! {
+                        result = worker.run_event_loop(false), if !idle => {
+                            idle = true;
+                            if let Err(err) = result {
+                                emit_error(&app, ConfigError::from_deno(&path, &err));
+                            }
+                        }
+                        Some(command) = commands.recv() => {
+                            if let WorkerCommand::Reload = command {
+                                plugins::deactivate_all(&mut worker);
+                                break;
+                            }
+
+                            run_command(&mut worker, command);
+                            // activating a plugin can load more
+                            graph = module_loader::emit_graph_changes(&app, &graph);
+                            idle = false;
+                        }
+                        event = changes.recv() => match event {
+                            // anything at all pushes a save that's started back,
+                            // until it's settled
+                            Some(event) if reload_at.is_some() || touches_config(&event, &path) => {
+                                reload_at = Some(Instant::now() + DEBOUNCE);
+                            }
+                            Some(_) => {}
+                            // the watcher's gone
+                            None => {
+                                plugins::deactivate_all(&mut worker);
+                                return;
+                            }
+                        },
+                        _ = sleep_until(reload_at.unwrap_or_else(Instant::now)), if reload_at.is_some() => {
+                            plugins::deactivate_all(&mut worker);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(error) => {
+                emit_error(&app, error);
+                graph = module_loader::emit_graph_changes(&app, &graph);
+
+                // the settings file still counts when config.js is broken, and
+                // when that's broken too it's steppe's defaults
+                if let Err(err) = settings::apply(&app, &app.state::<AppState>(), file_settings).await {
+                    eprintln!("could not apply settings: {err}");
+                }
+
+                tokio::select! {
+                    changed = wait_for_change(&mut changes, &path) => if !changed {
+                        return;
+                    },
+                    _ = wait_for_reload(&mut commands) => {}
+                }
+            }
+        }
+    }
+}
+
+/// Run config.js and the plugins with the `steppe` global, and again every
+/// time config.js changes, emitting `config://reloaded` or `config://error` each time.
+pub fn spawn_worker(app: AppHandle, commands: UnboundedReceiver<WorkerCommand>) {
+    // deno workers aren't `Send`, so they get a thread (and runtime) of their own
+    thread::spawn(move || {
+        match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime.block_on(run(app, commands)),
+            Err(err) => eprintln!("could not start the config runtime: {err}"),
+        }
+    });
+}
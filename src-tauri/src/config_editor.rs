@@ -0,0 +1,248 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use deno_ast::{MediaType, ParseParams};
+use deno_runtime::deno_core::ModuleSpecifier;
+use serde::Serialize;
+
+use crate::{config::STEPPE_TYPES, error::SteppeError, get_config_path, settings::Settings};
+
+/// config.js (or config.ts) as it is on disk, for editing in steppe itself
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// One option config.js can export, and the settings file can set
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigOption {
+    pub name: String,
+    /// Its type as `SteppeSettings` in steppe.d.ts has it, like `number` or
+    /// `"close" | "restart" | "hold"`, or `unknown` if it's not there
+    pub ts_type: String,
+    pub description: Option<String>,
+}
+
+/// What a settings ui needs to know to check what it's given: every option,
+/// and steppe.d.ts for an editor to check config.js against as a whole
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSchema {
+    pub options: Vec<ConfigOption>,
+    pub types: &'static str,
+}
+
+/// `/** ... */` without the stars, as one line
+fn doc_text(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches("/**")
+                .trim_end_matches("*/")
+                .trim_start_matches('*')
+                .trim()
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The members of `SteppeSettings` in steppe.d.ts, with their types and doc
+/// comments. Comments inside a member's type (the fields of an object type)
+/// are left out of it.
+fn declared_options() -> Vec<ConfigOption> {
+    let mut options = Vec::new();
+    let Some(start) = STEPPE_TYPES.find("interface SteppeSettings {") else {
+        return options;
+    };
+
+    let mut doc: Vec<&str> = Vec::new();
+    let mut in_doc = false;
+    let mut member: Option<(String, Vec<&str>)> = None;
+    // how deep in braces and brackets, past the interface's own
+    let mut depth = 0i32;
+
+    for line in STEPPE_TYPES[start..].lines().skip(1) {
+        let trimmed = line.trim();
+
+        if in_doc || trimmed.starts_with("/**") {
+            in_doc = !trimmed.ends_with("*/");
+            // a member's fields have docs of their own, which aren't the next member's
+            if member.is_none() {
+                doc.push(trimmed);
+            }
+            continue;
+        }
+
+        let opens = trimmed.matches(['{', '[']).count() as i32;
+        let closes = trimmed.matches(['}', ']']).count() as i32;
+
+        match &mut member {
+            Some((_, parts)) => parts.push(trimmed),
+            None if depth == 0 && trimmed == "}" => break,
+            None => {
+                let Some((name, ts_type)) = trimmed.split_once(':') else {
+                    continue;
+                };
+                member = Some((name.trim_end_matches('?').to_string(), vec![ts_type.trim()]));
+            }
+        }
+
+        depth += opens - closes;
+        if depth == 0 && trimmed.ends_with(';') {
+            if let Some((name, parts)) = member.take() {
+                let ts_type = parts.join(" ");
+                options.push(ConfigOption {
+                    name,
+                    ts_type: ts_type.trim_end_matches(';').to_string(),
+                    description: Some(doc_text(&doc)).filter(|doc| !doc.is_empty()),
+                });
+            }
+            doc.clear();
+        }
+    }
+
+    options
+}
+
+/// Make sure `text` at least parses as what `path` is, so a typo doesn't
+/// take the whole config down the moment it's saved
+fn check_syntax(path: &Path, text: &str) -> Result<(), SteppeError> {
+    let specifier = ModuleSpecifier::from_file_path(path)
+        .map_err(|_| SteppeError::InvalidConfig(format!("{} isn't a file path", path.display())))?;
+
+    deno_ast::parse_module(ParseParams {
+        media_type: MediaType::from_path(path),
+        specifier,
+        text: text.into(),
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .map_err(|err| SteppeError::InvalidConfig(err.to_string()))?;
+
+    Ok(())
+}
+
+/// `path` with `.suffix` on the end, like `config.js.bak`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+
+    path.with_file_name(name)
+}
+
+#[tauri::command]
+pub async fn async_read_config_source() -> Result<ConfigSource, SteppeError> {
+    let path = get_config_path();
+    let text = fs::read_to_string(&path)?;
+
+    Ok(ConfigSource { path, text })
+}
+
+/// Save `text` as config.js (or config.ts), which reloads it like any other
+/// save. What was there is kept next to it as `config.js.bak`, and `text`
+/// goes in whole or not at all, through a temp file that's renamed over it.
+#[tauri::command]
+pub async fn async_write_config_source(text: String) -> Result<(), SteppeError> {
+    let path = get_config_path();
+    check_syntax(&path, &text)?;
+
+    if path.exists() {
+        fs::copy(&path, sibling(&path, "bak"))?;
+    }
+
+    let temp = sibling(&path, "tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp, &path)?;
+
+    Ok(())
+}
+
+/// The names of `Settings`' fields, as config.js exports them
+fn setting_names() -> Result<Vec<String>, SteppeError> {
+    // every field's there as null, since nothing's skipped when it's `None`
+    match serde_json::to_value(Settings::default()).map_err(std::io::Error::from)? {
+        serde_json::Value::Object(fields) => Ok(fields.keys().cloned().collect()),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Every option, going by `Settings` so none are missed, with their types
+/// and docs from steppe.d.ts
+#[tauri::command]
+pub async fn async_get_config_schema() -> Result<ConfigSchema, SteppeError> {
+    let mut declared = declared_options();
+
+    let options = setting_names()?
+        .into_iter()
+        .map(|name| match declared.iter().position(|option| option.name == name) {
+            Some(index) => declared.swap_remove(index),
+            None => ConfigOption {
+                name,
+                ts_type: "unknown".to_string(),
+                description: None,
+            },
+        })
+        .collect();
+
+    Ok(ConfigSchema {
+        options,
+        types: STEPPE_TYPES,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_setting_is_declared() {
+        let declared = declared_options();
+
+        for name in setting_names().unwrap() {
+            let option = declared.iter().find(|option| option.name == name);
+            assert!(
+                option.is_some_and(|option| !option.ts_type.is_empty()),
+                "{name} isn't in SteppeSettings in steppe.d.ts"
+            );
+        }
+    }
+
+    #[test]
+    fn nothing_else_is_declared() {
+        let names = setting_names().unwrap();
+
+        for option in declared_options() {
+            assert!(
+                names.contains(&option.name),
+                "{} is in SteppeSettings in steppe.d.ts, but isn't a setting",
+                option.name
+            );
+        }
+    }
+
+    #[test]
+    fn reads_types_and_docs() {
+        let declared = declared_options();
+        let on_exit = declared.iter().find(|option| option.name == "onExit").unwrap();
+
+        assert_eq!(on_exit.ts_type, r#""close" | "restart" | "hold""#);
+        assert!(on_exit.description.as_deref().unwrap().starts_with("What happens once"));
+
+        // an object type's fields don't end up as options of their own
+        let quake = declared.iter().find(|option| option.name == "quake").unwrap();
+        assert!(quake.ts_type.starts_with('{') && quake.ts_type.contains("hotkey?: string;"));
+        assert!(!declared.iter().any(|option| option.name == "hotkey"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use deno_runtime::deno_permissions::{Permissions, PermissionsContainer, PermissionsOptions};
+use deno_runtime::permissions::RuntimePermissionDescriptorParser;
+use serde::Deserialize;
+
+use crate::get_config_dir;
+
+/// One capability in `permissions.json`: `true`/`false` for all or nothing, or a
+/// list of what's allowed (paths, hosts, commands... depending on the capability).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Grant {
+    All(bool),
+    Only(Vec<String>),
+}
+
+impl Grant {
+    /// The way deno's options say it, where `Some(vec![])` means everything
+    fn to_option(&self) -> Option<Vec<String>> {
+        match self {
+            Self::All(true) => Some(Vec::new()),
+            Self::All(false) => None,
+            Self::Only(allowed) if allowed.is_empty() => None,
+            Self::Only(allowed) => Some(allowed.clone()),
+        }
+    }
+}
+
+/// What config.js and plugins are allowed to do, from `<config dir>/permissions.json`.
+/// Anything left out gets the default, which reads the config dir but only
+/// writes to its `data` directory, and doesn't allow network access, running
+/// programs or ffi at all.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    read: Grant,
+    write: Grant,
+    net: Grant,
+    env: Grant,
+    run: Grant,
+    sys: Grant,
+    ffi: Grant,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            read: Grant::Only(vec![get_config_dir().to_string_lossy().to_string()]),
+            // not the whole config dir, or config.js could grant itself more
+            // in permissions.json (or change the settings and scripts steppe runs)
+            write: Grant::Only(vec![data_dir().to_string_lossy().to_string()]),
+            net: Grant::All(false),
+            env: Grant::All(true),
+            run: Grant::All(false),
+            sys: Grant::All(true),
+            ffi: Grant::All(false),
+        }
+    }
+}
+
+pub fn manifest_path() -> PathBuf {
+    get_config_dir().join("permissions.json")
+}
+
+/// Where config.js and plugins can keep what they write, unless permissions.json says otherwise
+pub fn data_dir() -> PathBuf {
+    get_config_dir().join("data")
+}
+
+/// What permissions.json lets config.js run, for steppe's own ops that start
+/// programs, which deno's checks never see
+#[derive(Debug, Clone)]
+pub struct RunPermission(Grant);
+
+impl RunPermission {
+    /// Whether `program` can be run, going by exactly how it's written in `run`
+    pub fn check(&self, program: &str) -> Result<(), String> {
+        let allowed = match &self.0 {
+            Grant::All(all) => *all,
+            Grant::Only(allowed) => allowed.iter().any(|allowed| allowed == program),
+        };
+
+        match allowed {
+            true => Ok(()),
+            false => Err(format!("permissions.json doesn't allow running {program}")),
+        }
+    }
+
+    /// For what can run anything at all, like lines typed into a shell
+    pub fn check_all(&self, what: &str) -> Result<(), String> {
+        match self.0 {
+            Grant::All(true) => Ok(()),
+            _ => Err(format!("{what} needs \"run\": true in permissions.json")),
+        }
+    }
+}
+
+fn load_manifest() -> Result<Manifest, String> {
+    let path = manifest_path();
+
+    match fs::read(&path) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|err| format!("{}: {err}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(err) => Err(format!("{}: {err}", path.display())),
+    }
+}
+
+/// Whether writing to `allowed` would let config.js write permissions.json
+fn covers_manifest(allowed: &[String]) -> bool {
+    let manifest = manifest_path();
+    allowed.iter().any(|path| manifest.starts_with(Path::new(path)))
+}
+
+/// The permissions config.js runs with, and what it can run through steppe's
+/// own ops. A broken manifest is an error rather than falling back to
+/// anything, so a typo can't quietly grant more than meant.
+pub fn permissions(
+    parser: Arc<RuntimePermissionDescriptorParser>,
+) -> Result<(PermissionsContainer, RunPermission), String> {
+    let manifest = load_manifest()?;
+    if let Grant::Only(allowed) = &manifest.write {
+        if covers_manifest(allowed) {
+            return Err(format!(
+                "write can't include {}, or config.js could give itself any permission",
+                manifest_path().display()
+            ));
+        }
+    }
+
+    let data_dir = data_dir();
+    fs::create_dir_all(&data_dir).map_err(|err| format!("{}: {err}", data_dir.display()))?;
+
+    let options = PermissionsOptions {
+        allow_read: manifest.read.to_option(),
+        allow_write: manifest.write.to_option(),
+        allow_net: manifest.net.to_option(),
+        allow_env: manifest.env.to_option(),
+        allow_run: manifest.run.to_option(),
+        allow_sys: manifest.sys.to_option(),
+        allow_ffi: manifest.ffi.to_option(),
+        // nobody's at a prompt, config.js runs in the background
+        prompt: false,
+        ..Default::default()
+    };
+
+    let permissions = Permissions::from_options(parser.as_ref(), &options).map_err(|err| err.to_string())?;
+
+    let run = RunPermission(manifest.run);
+
+    Ok((PermissionsContainer::new(parser, permissions), run))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_only_what_is_listed() {
+        let run = RunPermission(Grant::Only(vec!["git".to_string()]));
+        assert!(run.check("git").is_ok());
+        assert!(run.check("/tmp/git").is_err());
+        assert!(run.check_all("tasks").is_err());
+
+        assert!(RunPermission(Grant::All(true)).check_all("tasks").is_ok());
+        assert!(RunPermission(Grant::All(false)).check("git").is_err());
+    }
+
+    #[test]
+    fn manifest_is_not_writable_by_default() {
+        let Grant::Only(allowed) = Manifest::default().write else {
+            panic!("the default write grant should be a list");
+        };
+
+        assert!(!covers_manifest(&allowed));
+        assert!(covers_manifest(&[get_config_dir().to_string_lossy().to_string()]));
+    }
+}
@@ -0,0 +1,436 @@
+//! TypeScript declarations for the types configs pass to steppe, derived from
+//! the rust types through their serde impls: what the `steppe` global's
+//! setters take, and `Settings`. A build script can't see the crate's own
+//! types, so this runs as a test instead: it fails whenever
+//! `steppe-config.d.ts` is out of date, and rewrites it when run with
+//! `STEPPE_UPDATE_TYPES=1`. steppe.d.ts imports from it, and steppe keeps a
+//! copy of both next to the config.
+//!
+//! serde_reflection can't see `#[serde(default)]`, so which fields can be
+//! left out is found by leaving each out of a made-up value and seeing if it
+//! still deserializes, see `defaulted_fields`.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use serde_reflection::{ContainerFormat, Format, Named, Registry, Tracer, TracerConfig, VariantFormat};
+
+use crate::{
+    appearance::{Antialiasing, FontRenderingOptions, Hinting, SubpixelOrder},
+    environment::EnvironmentOptions,
+    file_drop::{DropSeparator, FileDropOptions},
+    identity::IdentityOptions,
+    links::{LinkPatterns, LinkRule},
+    mouse::MouseMode,
+    notifications::CommandNotificationOptions,
+    on_exit::OnExit,
+    opacity::Easing,
+    output_batch::BatchModeOptions,
+    panes::SplitDirection,
+    quake::QuakeOptions,
+    redact::RedactionOptions,
+    remote::RemoteAttachOptions,
+    renderer::RendererBackend,
+    scrollback_spill::SpillOptions,
+    size::{FixedSize, SizeConstraints},
+    startup::TerminalSequence,
+    startup_layout::{StartupSplit, StartupTerminal, StartupWindow},
+    startup_notification::StartupNotificationOptions,
+    tasks::Task,
+    termios::TermiosSettings,
+    themes::Theme,
+    url_opener::UrlOpenMode,
+    write_limit::WriteLimitOptions,
+};
+
+/// Stands in for `ThemeSource` while tracing, see `untagged_enums`
+#[derive(Deserialize)]
+#[serde(rename = "ThemeSource")]
+struct ThemeSourceName;
+
+/// Stands in for `StartupPane` while tracing, see `untagged_enums`
+#[derive(Deserialize)]
+#[serde(rename = "StartupPane")]
+struct StartupPaneName;
+
+/// Whether a value deserializes as some type, by the type's name
+type Probes = HashMap<String, fn(Value) -> bool>;
+
+const HEADER: &str = "// generated from the rust config types by src-tauri/src/config_types.rs, don't edit by hand\n";
+
+fn ts_type(format: &Format) -> String {
+    match format {
+        Format::Variable(_) => "unknown".to_string(),
+        Format::TypeName(name) => name.clone(),
+        Format::Unit => "null".to_string(),
+        Format::Bool => "boolean".to_string(),
+        Format::I8
+        | Format::I16
+        | Format::I32
+        | Format::I64
+        | Format::I128
+        | Format::U8
+        | Format::U16
+        | Format::U32
+        | Format::U64
+        | Format::U128
+        | Format::F32
+        | Format::F64 => "number".to_string(),
+        Format::Char | Format::Str => "string".to_string(),
+        Format::Bytes => "number[]".to_string(),
+        Format::Option(inner) => format!("{} | null", ts_type(inner)),
+        Format::Seq(inner) | Format::TupleArray { content: inner, .. } => match ts_type(inner) {
+            union if union.contains('|') => format!("({union})[]"),
+            inner => format!("{inner}[]"),
+        },
+        Format::Map { key, value } => format!("Record<{}, {}>", ts_type(key), ts_type(value)),
+        Format::Tuple(formats) => ts_tuple(formats),
+    }
+}
+
+fn ts_tuple(formats: &[Format]) -> String {
+    let formats: Vec<_> = formats.iter().map(ts_type).collect();
+    format!("[{}]", formats.join(", "))
+}
+
+/// `defaulted` are the ones that can be left out as well as options, which
+/// serde fills in with `None`
+fn ts_fields(fields: &[Named<Format>], defaulted: &HashSet<String>) -> String {
+    fields
+        .iter()
+        .map(|field| match &field.value {
+            value if matches!(value, Format::Option(_)) || defaulted.contains(&field.name) => {
+                format!("    {}?: {};\n", field.name, ts_type(value))
+            }
+            value => format!("    {}: {};\n", field.name, ts_type(value)),
+        })
+        .collect()
+}
+
+fn ts_declaration(name: &str, container: &ContainerFormat, defaulted: &HashSet<String>) -> String {
+    match container {
+        ContainerFormat::UnitStruct => format!("export type {name} = null;\n"),
+        ContainerFormat::NewTypeStruct(format) => format!("export type {name} = {};\n", ts_type(format)),
+        ContainerFormat::TupleStruct(formats) => format!("export type {name} = {};\n", ts_tuple(formats)),
+        ContainerFormat::Struct(fields) => {
+            format!("export interface {name} {{\n{}}}\n", ts_fields(fields, defaulted))
+        }
+        ContainerFormat::Enum(variants) => {
+            // externally tagged, which is serde's default
+            let variants: Vec<_> = variants
+                .values()
+                .map(|variant| {
+                    let tag = &variant.name;
+                    match &variant.value {
+                        VariantFormat::Unit | VariantFormat::Variable(_) => format!("\"{tag}\""),
+                        VariantFormat::NewType(format) => format!("{{ \"{tag}\": {} }}", ts_type(format)),
+                        VariantFormat::Tuple(formats) => format!("{{ \"{tag}\": {} }}", ts_tuple(formats)),
+                        VariantFormat::Struct(fields) => {
+                            format!("{{ \"{tag}\": {{\n{}}} }}", ts_fields(fields, &HashSet::new()))
+                        }
+                    }
+                })
+                .collect();
+
+            format!("export type {name} = {};\n", variants.join(" | "))
+        }
+    }
+}
+
+fn probe<T: DeserializeOwned>(value: Value) -> bool {
+    serde_json::from_value::<T>(value).is_ok()
+}
+
+/// Trace `T`, and remember how to check a value against it for
+/// `defaulted_fields`. An option can be checked against the same way as
+/// what's in it.
+fn trace<T: DeserializeOwned>(tracer: &mut Tracer, probes: &mut Probes) -> serde_reflection::Result<Format> {
+    let (format, _) = tracer.trace_simple_type::<T>()?;
+    let name = match &format {
+        Format::TypeName(name) => Some(name),
+        Format::Option(inner) => match inner.as_ref() {
+            Format::TypeName(name) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(name) = name {
+        probes.entry(name.clone()).or_insert(probe::<T>);
+    }
+
+    Ok(format)
+}
+
+/// A made-up value of the type `format` describes: zeroes, empty strings and
+/// lists, no options, and the first variant of each enum
+fn sample(format: &Format, registry: &Registry) -> Value {
+    let samples = |formats: &[Format]| Value::Array(formats.iter().map(|format| sample(format, registry)).collect());
+    let fields = |fields: &[Named<Format>]| {
+        Value::Object(
+            fields
+                .iter()
+                .map(|field| (field.name.clone(), sample(&field.value, registry)))
+                .collect(),
+        )
+    };
+
+    match format {
+        Format::TypeName(name) => match registry.get(name) {
+            Some(ContainerFormat::NewTypeStruct(format)) => sample(format, registry),
+            Some(ContainerFormat::TupleStruct(formats)) => samples(formats),
+            Some(ContainerFormat::Struct(named)) => fields(named),
+            Some(ContainerFormat::Enum(variants)) => match variants.values().next() {
+                Some(variant) => match &variant.value {
+                    VariantFormat::NewType(format) => {
+                        Value::Object([(variant.name.clone(), sample(format, registry))].into_iter().collect())
+                    }
+                    VariantFormat::Tuple(formats) => {
+                        Value::Object([(variant.name.clone(), samples(formats))].into_iter().collect())
+                    }
+                    VariantFormat::Struct(named) => {
+                        Value::Object([(variant.name.clone(), fields(named))].into_iter().collect())
+                    }
+                    VariantFormat::Unit | VariantFormat::Variable(_) => Value::String(variant.name.clone()),
+                },
+                None => Value::Null,
+            },
+            Some(ContainerFormat::UnitStruct) | None => Value::Null,
+        },
+        Format::Bool => Value::Bool(false),
+        Format::I8
+        | Format::I16
+        | Format::I32
+        | Format::I64
+        | Format::I128
+        | Format::U8
+        | Format::U16
+        | Format::U32
+        | Format::U64
+        | Format::U128
+        | Format::F32
+        | Format::F64 => Value::from(0),
+        Format::Char => Value::from("a"),
+        Format::Str => Value::from(""),
+        Format::Bytes | Format::Seq(_) => Value::Array(Vec::new()),
+        Format::Map { .. } => Value::Object(Default::default()),
+        Format::Tuple(formats) => samples(formats),
+        Format::TupleArray { content, size } => samples(&vec![content.as_ref().clone(); *size]),
+        Format::Unit | Format::Option(_) | Format::Variable(_) => Value::Null,
+    }
+}
+
+/// The fields of struct `name` that can be left out, going by whether a
+/// sample of it still deserializes without each one. Nothing can be for a
+/// struct whose sample doesn't deserialize to begin with, like one that
+/// checks its values as it goes.
+fn defaulted_fields(name: &str, registry: &Registry, probes: &Probes) -> HashSet<String> {
+    let Some(probe) = probes.get(name) else {
+        return HashSet::new();
+    };
+    let Value::Object(sample) = sample(&Format::TypeName(name.to_string()), registry) else {
+        return HashSet::new();
+    };
+    if !probe(Value::Object(sample.clone())) {
+        return HashSet::new();
+    }
+
+    sample
+        .keys()
+        .filter(|field| {
+            let mut without = sample.clone();
+            without.remove(*field);
+            probe(Value::Object(without))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Untagged enums take `deserialize_any`, which serde_reflection can't see
+/// into, so they're declared as the union of what each variant holds,
+/// traced on their own. Whatever holds one is traced with a stand-in named
+/// after it instead.
+fn untagged_enums(tracer: &mut Tracer, probes: &mut Probes) -> serde_reflection::Result<Vec<(String, Vec<Format>)>> {
+    Ok(vec![
+        (
+            "ThemeSource".to_string(),
+            vec![trace::<String>(tracer, probes)?, trace::<Theme>(tracer, probes)?],
+        ),
+        (
+            "StartupPane".to_string(),
+            vec![
+                trace::<StartupSplit<StartupPaneName>>(tracer, probes)?,
+                trace::<StartupTerminal>(tracer, probes)?,
+            ],
+        ),
+    ])
+}
+
+/// A field of `Settings`, as its type traced on its own
+fn setting<T: DeserializeOwned>(
+    tracer: &mut Tracer,
+    probes: &mut Probes,
+    name: &str,
+) -> serde_reflection::Result<(String, String)> {
+    let format = trace::<T>(tracer, probes)?;
+    Ok((name.to_string(), ts_type(&format)))
+}
+
+/// `Settings`' fields and their types. It can't be traced whole, since
+/// `theme` and `startupLayout` hold untagged enums, so each field's traced
+/// on its own, those two with stand-ins from `untagged_enums`.
+fn settings_fields(tracer: &mut Tracer, probes: &mut Probes) -> serde_reflection::Result<Vec<(String, String)>> {
+    Ok(vec![
+        setting::<Option<f64>>(tracer, probes, "fontSize")?,
+        setting::<Option<usize>>(tracer, probes, "scrollbackLines")?,
+        setting::<Option<usize>>(tracer, probes, "outputCoalesceBytes")?,
+        setting::<Option<u64>>(tracer, probes, "outputCoalesceMs")?,
+        setting::<Option<String>>(tracer, probes, "defaultShell")?,
+        setting::<Option<bool>>(tracer, probes, "loginShell")?,
+        setting::<Option<Vec<String>>>(tracer, probes, "shellArgs")?,
+        setting::<Option<bool>>(tracer, probes, "shellIntegration")?,
+        setting::<Option<HashMap<String, String>>>(tracer, probes, "shellEnv")?,
+        setting::<Option<String>>(tracer, probes, "initCommand")?,
+        setting::<Option<ThemeSourceName>>(tracer, probes, "theme")?,
+        setting::<Option<QuakeOptions>>(tracer, probes, "quake")?,
+        setting::<Option<bool>>(tracer, probes, "keepAliveInTray")?,
+        setting::<Option<usize>>(tracer, probes, "maxImageBytes")?,
+        setting::<Option<LinkPatterns>>(tracer, probes, "linkPatterns")?,
+        setting::<Option<OnExit>>(tracer, probes, "onExit")?,
+        setting::<Option<Vec<Task>>>(tracer, probes, "tasks")?,
+        setting::<Option<Vec<StartupWindow<StartupPaneName>>>>(tracer, probes, "startupLayout")?,
+        setting::<Option<RemoteAttachOptions>>(tracer, probes, "remoteAttach")?,
+        setting::<Option<RedactionOptions>>(tracer, probes, "redaction")?,
+        setting::<Option<FileDropOptions>>(tracer, probes, "fileDrop")?,
+        setting::<Option<SpillOptions>>(tracer, probes, "scrollbackSpill")?,
+        setting::<Option<EnvironmentOptions>>(tracer, probes, "environmentSnapshot")?,
+        setting::<Option<CommandNotificationOptions>>(tracer, probes, "commandNotifications")?,
+        setting::<Option<IdentityOptions>>(tracer, probes, "terminalIdentity")?,
+        setting::<Option<bool>>(tracer, probes, "allowClipboardWrite")?,
+        setting::<Option<bool>>(tracer, probes, "allowClipboardRead")?,
+        setting::<Option<bool>>(tracer, probes, "commandHistory")?,
+        setting::<Option<bool>>(tracer, probes, "confirmClose")?,
+        setting::<Option<bool>>(tracer, probes, "restoreSession")?,
+        setting::<Option<bool>>(tracer, probes, "detachOnClose")?,
+        setting::<Option<bool>>(tracer, probes, "windowTitleFromTerminal")?,
+    ])
+}
+
+/// Declarations for every type a config can hand to a setter, and for `Settings`.
+pub fn typescript_declarations() -> serde_reflection::Result<String> {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    let mut probes = Probes::new();
+
+    trace::<TermiosSettings>(&mut tracer, &mut probes)?;
+    trace::<UrlOpenMode>(&mut tracer, &mut probes)?;
+    trace::<RendererBackend>(&mut tracer, &mut probes)?;
+    trace::<SizeConstraints>(&mut tracer, &mut probes)?;
+    trace::<FontRenderingOptions>(&mut tracer, &mut probes)?;
+    trace::<StartupNotificationOptions>(&mut tracer, &mut probes)?;
+    trace::<TerminalSequence>(&mut tracer, &mut probes)?;
+    trace::<MouseMode>(&mut tracer, &mut probes)?;
+    trace::<BatchModeOptions>(&mut tracer, &mut probes)?;
+    trace::<Easing>(&mut tracer, &mut probes)?;
+    trace::<WriteLimitOptions>(&mut tracer, &mut probes)?;
+
+    // enums inside structs only get their first variant traced, so trace them on their own too
+    trace::<Antialiasing>(&mut tracer, &mut probes)?;
+    trace::<Hinting>(&mut tracer, &mut probes)?;
+    trace::<SubpixelOrder>(&mut tracer, &mut probes)?;
+    trace::<DropSeparator>(&mut tracer, &mut probes)?;
+    // same for enums inside an option
+    trace::<OnExit>(&mut tracer, &mut probes)?;
+    // and structs only ever inside others or a list, so `defaulted_fields` can check them
+    trace::<FixedSize>(&mut tracer, &mut probes)?;
+    trace::<LinkRule>(&mut tracer, &mut probes)?;
+    trace::<Task>(&mut tracer, &mut probes)?;
+    trace::<SplitDirection>(&mut tracer, &mut probes)?;
+    trace::<StartupWindow<StartupPaneName>>(&mut tracer, &mut probes)?;
+
+    let untagged = untagged_enums(&mut tracer, &mut probes)?;
+    let settings = settings_fields(&mut tracer, &mut probes)?;
+
+    let registry = tracer.registry()?;
+    let mut declarations = HEADER.to_string();
+    for (name, container) in &registry {
+        declarations.push('\n');
+        match untagged.iter().find(|(untagged, _)| untagged == name) {
+            Some((_, variants)) => {
+                let variants: Vec<_> = variants.iter().map(ts_type).collect();
+                declarations.push_str(&format!("export type {name} = {};\n", variants.join(" | ")));
+            }
+            None => {
+                let defaulted = defaulted_fields(name, &registry, &probes);
+                declarations.push_str(&ts_declaration(name, container, &defaulted));
+            }
+        }
+    }
+
+    // everything in it's an option, filled in with `None` when it's missing
+    declarations.push_str("\n/** What config.js can export, and settings.toml or settings.json can set */\n");
+    declarations.push_str("export interface Settings {\n");
+    for (name, ts_type) in settings {
+        declarations.push_str(&format!("    {name}?: {ts_type};\n"));
+    }
+    declarations.push_str("}\n");
+
+    Ok(declarations)
+}
+
+#[test]
+fn traces_every_setting() {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    let mut traced: Vec<String> = settings_fields(&mut tracer, &mut Probes::new())
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    // every field's there as null, since nothing's skipped when it's `None`
+    let serde_json::Value::Object(fields) = serde_json::to_value(crate::settings::Settings::default()).unwrap() else {
+        panic!("Settings doesn't serialize as an object");
+    };
+    let mut fields: Vec<String> = fields.keys().cloned().collect();
+    fields.sort();
+
+    traced.sort();
+    assert_eq!(traced, fields, "settings_fields is out of step with Settings");
+}
+
+#[test]
+fn declares_types_as_serde_has_them() {
+    let declarations = typescript_declarations().unwrap();
+
+    assert!(declarations.contains("export type OnExit = \"close\" | \"restart\" | \"hold\";\n"));
+    assert!(declarations.contains("    quake?: QuakeOptions | null;\n"));
+    assert!(declarations.contains("    shellEnv?: Record<string, string> | null;\n"));
+    assert!(declarations.contains("    tasks?: Task[] | null;\n"));
+    // `#[serde(default)]` on the field, and on the whole struct
+    assert!(declarations.contains("    newTerminal?: boolean;\n"));
+    assert!(declarations.contains("    exclude?: string[];\n"));
+    assert!(declarations.contains("    maxLineBufferSize: number;\n"));
+    // untagged
+    assert!(declarations.contains("export type ThemeSource = string | Theme;\n"));
+    assert!(declarations.contains("export type StartupPane = StartupSplit | StartupTerminal;\n"));
+    assert!(declarations.contains("    startupLayout?: StartupWindow[] | null;\n"));
+    assert!(!declarations.contains("unknown"));
+}
+
+#[test]
+fn verify_types() {
+    use std::{env, fs, path::Path};
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("steppe-config.d.ts");
+    let generated = typescript_declarations().unwrap();
+
+    if env::var_os("STEPPE_UPDATE_TYPES").is_some() {
+        fs::write(&path, generated).unwrap();
+        return;
+    }
+
+    let snapshot = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        snapshot == generated,
+        "steppe-config.d.ts is out of date, rerun with STEPPE_UPDATE_TYPES=1 to regenerate it"
+    );
+}
@@ -0,0 +1,339 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, State, Window};
+
+use crate::{error::SteppeError, get_config_dir, palette, ssh::SshProfile, AppState};
+
+/// A saved host, with where it's filed and when it was last used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    /// Picked by the book, so whatever's passed in when adding is ignored
+    #[serde(default)]
+    pub id: u32,
+    /// How to connect, same as a profile from config.js. Its name is what
+    /// terminals connected with it remember it by.
+    #[serde(flatten)]
+    pub profile: SshProfile,
+    /// Like `work/staging`, or empty for the top level
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Milliseconds since the epoch, `None` if it's never been connected to
+    #[serde(default)]
+    pub last_connected_ms: Option<u64>,
+    #[serde(default)]
+    pub times_connected: u32,
+}
+
+impl Connection {
+    /// Whether every word of `query` is in the name, host, user, folder or a
+    /// tag, ignoring case
+    fn matches(&self, query: &str) -> bool {
+        let fields = [
+            Some(&self.profile.name),
+            Some(&self.profile.host),
+            self.profile.user.as_ref(),
+            Some(&self.folder),
+        ];
+        let haystack: Vec<String> = fields
+            .into_iter()
+            .flatten()
+            .chain(&self.tags)
+            .map(|field| field.to_lowercase())
+            .collect();
+
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.iter().any(|field| field.contains(word)))
+    }
+}
+
+/// Hosts saved from the frontend rather than config.js, kept in
+/// `<config dir>/connections.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionBook {
+    next_id: u32,
+    connections: Vec<Connection>,
+    /// Counts up with each snapshot
+    #[serde(skip)]
+    version: u64,
+}
+
+/// The newest version of the book that's been written, so a save that's
+/// slow getting to the disk can't write over a newer one
+static SAVED_VERSION: Mutex<u64> = Mutex::new(0);
+
+fn book_path() -> PathBuf {
+    get_config_dir().join("connections.json")
+}
+
+/// The book as it was, to write out without holding `state.connections`
+struct Snapshot {
+    version: u64,
+    contents: Vec<u8>,
+}
+
+impl Snapshot {
+    /// Write it to `connections.json` off the runtime, unless a newer one's
+    /// been written already
+    async fn save(self) -> Result<(), SteppeError> {
+        async_runtime::spawn_blocking(move || {
+            let mut saved = SAVED_VERSION.lock().unwrap_or_else(|err| err.into_inner());
+            if *saved >= self.version {
+                return Ok(());
+            }
+
+            fs::write(book_path(), &self.contents)?;
+            *saved = self.version;
+
+            Ok(())
+        })
+        .await?
+    }
+}
+
+impl ConnectionBook {
+    /// Pick up the book from last time, starting empty if there isn't one or
+    /// it can't be read.
+    pub fn load() -> Self {
+        fs::read(book_path())
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// What to save, once `state.connections` has been let go of
+    fn snapshot(&mut self) -> Result<Snapshot, SteppeError> {
+        self.version += 1;
+        let contents = serde_json::to_vec_pretty(self).map_err(std::io::Error::from)?;
+
+        Ok(Snapshot {
+            version: self.version,
+            contents,
+        })
+    }
+
+    /// The profile of the connection named `name`
+    pub fn profile(&self, name: &str) -> Option<SshProfile> {
+        self.connections
+            .iter()
+            .find(|connection| connection.profile.name == name)
+            .map(|connection| connection.profile.clone())
+    }
+
+    /// The names of every connection
+    pub fn names(&self) -> Vec<String> {
+        self.connections
+            .iter()
+            .map(|connection| connection.profile.name.clone())
+            .collect()
+    }
+
+    /// Make sure `connection` can connect, and that its name is its own
+    fn validate(&self, connection: &Connection) -> Result<(), SteppeError> {
+        if connection.profile.name.trim().is_empty() {
+            return Err(SteppeError::InvalidConfig("a connection needs a name".to_string()));
+        }
+        connection.profile.validate()?;
+
+        let taken = self
+            .connections
+            .iter()
+            .any(|other| other.id != connection.id && other.profile.name == connection.profile.name);
+        if taken {
+            return Err(SteppeError::InvalidConfig(format!(
+                "there's already a connection named {:?}",
+                connection.profile.name
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn get_mut(&mut self, id: u32) -> Result<&mut Connection, SteppeError> {
+        self.connections
+            .iter_mut()
+            .find(|connection| connection.id == id)
+            .ok_or(SteppeError::ConnectionNotFound(id))
+    }
+}
+
+/// Make sure `connection` doesn't take the name of a profile from config.js,
+/// which would win over it
+async fn check_name(state: &AppState, connection: &Connection) -> Result<(), SteppeError> {
+    let name = &connection.profile.name;
+    if state
+        .ssh_profiles
+        .lock()
+        .await
+        .iter()
+        .any(|profile| &profile.name == name)
+    {
+        return Err(SteppeError::InvalidConfig(format!(
+            "config.js already has an ssh profile named {name:?}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Note that the connection named `name` was just connected to, if there's
+/// one in the book.
+pub async fn touch(state: &AppState, name: &str) {
+    let mut book = state.connections.lock().await;
+    let Some(connection) = book
+        .connections
+        .iter_mut()
+        .find(|connection| connection.profile.name == name)
+    else {
+        return;
+    };
+
+    connection.last_connected_ms = Some(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    );
+    connection.times_connected += 1;
+
+    let saved = match book.snapshot() {
+        Ok(snapshot) => {
+            drop(book);
+            snapshot.save().await
+        }
+        Err(err) => Err(err),
+    };
+    if let Err(err) = saved {
+        eprintln!("could not save connections: {err}");
+    }
+}
+
+/// The connections matching every word of `query`, or all of them, by folder
+/// and then name.
+#[tauri::command]
+pub async fn async_list_connections(
+    query: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Connection>, SteppeError> {
+    let query = query.unwrap_or_default();
+    let mut connections: Vec<Connection> = state
+        .connections
+        .lock()
+        .await
+        .connections
+        .iter()
+        .filter(|connection| connection.matches(&query))
+        .cloned()
+        .collect();
+    connections.sort_by(|a, b| {
+        a.folder
+            .cmp(&b.folder)
+            .then_with(|| a.profile.name.cmp(&b.profile.name))
+    });
+
+    Ok(connections)
+}
+
+/// Save `connection` as a new one, returning it with its id.
+#[tauri::command]
+pub async fn async_add_connection(
+    mut connection: Connection,
+    state: State<'_, AppState>,
+) -> Result<Connection, SteppeError> {
+    check_name(&state, &connection).await?;
+
+    let mut book = state.connections.lock().await;
+    connection.id = book.next_id;
+    connection.last_connected_ms = None;
+    connection.times_connected = 0;
+    book.validate(&connection)?;
+
+    book.next_id += 1;
+    book.connections.push(connection.clone());
+    let snapshot = book.snapshot()?;
+    drop(book);
+    snapshot.save().await?;
+
+    Ok(connection)
+}
+
+/// Replace the connection with `connection`'s id, keeping when it was last
+/// connected to.
+#[tauri::command]
+pub async fn async_update_connection(connection: Connection, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    check_name(&state, &connection).await?;
+
+    let mut book = state.connections.lock().await;
+    book.validate(&connection)?;
+
+    let existing = book.get_mut(connection.id)?;
+    *existing = Connection {
+        last_connected_ms: existing.last_connected_ms,
+        times_connected: existing.times_connected,
+        ..connection
+    };
+
+    let snapshot = book.snapshot()?;
+    drop(book);
+    snapshot.save().await
+}
+
+#[tauri::command]
+pub async fn async_remove_connection(id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let mut book = state.connections.lock().await;
+    book.get_mut(id)?;
+    book.connections.retain(|connection| connection.id != id);
+
+    let snapshot = book.snapshot()?;
+    drop(book);
+    snapshot.save().await
+}
+
+/// Move everything in folder `from` (and the folders in it) to `to`, which
+/// is how folders get renamed or moved.
+#[tauri::command]
+pub async fn async_rename_connection_folder(
+    from: String,
+    to: String,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let from = from.trim_matches('/');
+    let to = to.trim_matches('/');
+
+    let mut book = state.connections.lock().await;
+    for connection in &mut book.connections {
+        let rest = match connection.folder.strip_prefix(from) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest.to_string(),
+            _ => continue,
+        };
+        connection.folder = format!("{to}{rest}").trim_start_matches('/').to_string();
+    }
+
+    let snapshot = book.snapshot()?;
+    drop(book);
+    snapshot.save().await
+}
+
+/// Open a tab in the calling window connected to connection `id`.
+#[tauri::command]
+pub async fn async_connect(
+    id: u32,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let name = state.connections.lock().await.get_mut(id)?.profile.name.clone();
+
+    palette::open_ssh_tab(&app, &state, name, window.label()).await
+}
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use tauri::{
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    AppHandle, LogicalPosition, Manager, State, Window,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{error::SteppeError, paste, url_opener, AppState};
+
+const COPY: &str = "context-menu:copy";
+const PASTE: &str = "context-menu:paste";
+const OPEN_URL: &str = "context-menu:open-url";
+
+/// What was under the cursor when the menu was opened.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextMenuContext {
+    /// The terminal it was opened over
+    pub terminal_id: u32,
+    pub selection: Option<String>,
+    pub url: Option<String>,
+}
+
+#[tauri::command]
+pub async fn async_show_context_menu(
+    x: f64,
+    y: f64,
+    context: ContextMenuContext,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let menu = Menu::new(&window)?;
+
+    menu.append(&MenuItem::with_id(&window, COPY, "Copy", context.selection.is_some(), None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(&window, PASTE, "Paste", true, None::<&str>)?)?;
+
+    if context.url.is_some() {
+        menu.append(&PredefinedMenuItem::separator(&window)?)?;
+        menu.append(&MenuItem::with_id(&window, OPEN_URL, "Open URL", true, None::<&str>)?)?;
+    }
+
+    // the selection comes back in through `handle_menu_event`, which only gets the item id
+    *state.context_menu.lock().await = context;
+
+    window.popup_menu_at(&menu, LogicalPosition::new(x, y))?;
+
+    Ok(())
+}
+
+pub async fn execute_context_menu_action(app: &AppHandle, id: &str) -> Result<(), SteppeError> {
+    let state = app.state::<AppState>();
+    let context = state.context_menu.lock().await.clone();
+
+    match id {
+        COPY => {
+            if let Some(selection) = context.selection {
+                app.clipboard().write_text(selection)?;
+            }
+        }
+        PASTE => paste::paste_clipboard(app, &state, context.terminal_id).await?,
+        OPEN_URL => {
+            if let Some(url) = context.url {
+                url_opener::open_url(app, &state, context.terminal_id, &url).await?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let app = app.clone();
+
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = execute_context_menu_action(&app, &event.id().0).await {
+            eprintln!("context menu action failed: {err}");
+        }
+    });
+}
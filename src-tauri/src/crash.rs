@@ -0,0 +1,216 @@
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    fmt::Display,
+    fs::{create_dir_all, File},
+    io, panic,
+    process::{self, Command},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{error::SteppeError, get_config_dir, reader};
+
+/// Number of running shells, for the crash report.
+pub static ACTIVE_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Where to send crash reports, if the user opted into it.
+static UPLOAD_ENDPOINT: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Serialize)]
+struct CrashReport {
+    message: String,
+    location: Option<String>,
+    backtrace: String,
+    active_sessions: usize,
+    os_version: String,
+    steppe_version: &'static str,
+    uptime_seconds: u64,
+}
+
+/// Replace the default panic behaviour with one that leaves a crash report
+/// in `<config dir>/crashes` before exiting.
+pub fn install_panic_hook() {
+    let started = Instant::now();
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        // still print the usual message to stderr
+        default_hook(info);
+
+        // a terminal's output thread starts itself over, see `reader::spawn_reader`
+        let recovers = thread::current()
+            .name()
+            .is_some_and(|name| name.starts_with(reader::OUTPUT_THREAD_PREFIX));
+        if recovers {
+            return;
+        }
+
+        let report = CrashReport {
+            message: panic_message(info.payload()),
+            location: info.location().map(|location| location.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+            active_sessions: ACTIVE_SESSIONS.load(Ordering::Relaxed),
+            os_version: os_info::get().to_string(),
+            steppe_version: env!("CARGO_PKG_VERSION"),
+            uptime_seconds: started.elapsed().as_secs(),
+        };
+
+        if let Err(err) = write_report(&report) {
+            eprintln!("could not write crash report: {err}");
+        }
+
+        // the lock could be poisoned if we panicked while holding it
+        let endpoint = UPLOAD_ENDPOINT.try_lock().ok().and_then(|endpoint| endpoint.clone());
+        if let Some(endpoint) = endpoint {
+            if let Err(err) = ureq::post(&endpoint).send_json(&report) {
+                eprintln!("could not upload crash report: {err}");
+            }
+        }
+
+        process::exit(1);
+    }));
+}
+
+/// What a panic was given to say, if it was a string
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<non-string panic payload>".to_string())
+}
+
+fn write_report(report: &CrashReport) -> Result<(), SteppeError> {
+    let dir = get_config_dir().join("crashes");
+    create_dir_all(&dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let file = File::create(dir.join(format!("{timestamp}.json")))?;
+    serde_json::to_writer_pretty(file, report).map_err(std::io::Error::from)?;
+
+    Ok(())
+}
+
+/// What to try when steppe's window can't be opened
+#[cfg(target_os = "linux")]
+pub const WINDOW_HINT: &str = "steppe needs WebKitGTK (webkit2gtk-4.1) installed and a display to open its window on. \
+     Starting it from a terminal shows more of what went wrong.";
+#[cfg(windows)]
+pub const WINDOW_HINT: &str = "steppe needs the Microsoft Edge WebView2 runtime, which can be installed from \
+     https://developer.microsoft.com/microsoft-edge/webview2/";
+#[cfg(not(any(target_os = "linux", windows)))]
+pub const WINDOW_HINT: &str = "Starting steppe from a terminal shows more of what went wrong.";
+
+/// What to try when steppe's config directory can't be set up
+pub const CONFIG_DIR_HINT: &str =
+    "Check that steppe's config directory can be written to, or set STEPPE_CONFIG_DIR to one that can.";
+
+/// Show an error in the system's own dialog, since there's no window of
+/// steppe's to show it in
+#[cfg(target_os = "macos")]
+fn show_error_dialog(title: &str, message: &str) -> io::Result<()> {
+    // as arguments, so nothing in them needs escaping
+    Command::new("osascript")
+        .args(["-e", "on run argv"])
+        .args([
+            "-e",
+            "display alert (item 1 of argv) message (item 2 of argv) as critical",
+        ])
+        .args(["-e", "end run", title, message])
+        .status()?;
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn show_error_dialog(title: &str, message: &str) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // without a console flashing up behind it
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command"])
+        .arg(
+            "Add-Type -AssemblyName PresentationFramework; \
+             [System.Windows.MessageBox]::Show($env:STEPPE_ERROR_MESSAGE, $env:STEPPE_ERROR_TITLE, 'OK', 'Error')",
+        )
+        .env("STEPPE_ERROR_TITLE", title)
+        .env("STEPPE_ERROR_MESSAGE", message)
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()?;
+
+    Ok(())
+}
+
+/// Whichever of the usual dialog programs is installed
+#[cfg(not(any(target_os = "macos", windows)))]
+fn show_error_dialog(title: &str, message: &str) -> io::Result<()> {
+    let xmessage = format!("{title}\n\n{message}");
+    let dialogs: [(&str, Vec<&str>); 3] = [
+        (
+            "zenity",
+            vec!["--error", "--no-markup", "--title", title, "--text", message],
+        ),
+        ("kdialog", vec!["--title", title, "--error", message]),
+        ("xmessage", vec!["-center", &xmessage]),
+    ];
+
+    for (program, args) in dialogs {
+        match Command::new(program).args(args).status() {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            result => return result.map(|_| ()),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no zenity, kdialog or xmessage",
+    ))
+}
+
+/// Exit because steppe can't start, saying why and what to do about it
+/// in a dialog as well as on stderr
+pub fn exit_on_startup_error(err: impl Display, hint: &str) -> ! {
+    let message = format!("{err}\n\n{hint}");
+    eprintln!("steppe couldn't start: {message}");
+
+    if let Err(err) = show_error_dialog("steppe couldn't start", &message) {
+        eprintln!("could not show an error dialog: {err}");
+    }
+
+    process::exit(1);
+}
+
+/// The endpoint crash reports get uploaded to, if crash reporting is enabled.
+pub fn upload_endpoint() -> Option<String> {
+    UPLOAD_ENDPOINT.lock().unwrap_or_else(|err| err.into_inner()).clone()
+}
+
+#[tauri::command]
+pub async fn async_set_crash_reporting(enabled: bool, endpoint: Option<String>) -> Result<(), SteppeError> {
+    let endpoint = match (enabled, endpoint) {
+        (false, _) => None,
+        (true, Some(endpoint)) => Some(endpoint),
+        (true, None) => {
+            return Err(SteppeError::InvalidConfig(
+                "crash reporting needs an endpoint to upload to".into(),
+            ))
+        }
+    };
+
+    *UPLOAD_ENDPOINT.lock().unwrap_or_else(|err| err.into_inner()) = endpoint;
+
+    Ok(())
+}
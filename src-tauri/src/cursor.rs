@@ -0,0 +1,80 @@
+use std::sync::atomic::Ordering;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+/// DECSCNM, reverse video for the whole screen
+pub const INVERSE_VIDEO_MODE: u16 = 5;
+
+/// Colors of the block cursor, as hex strings
+#[derive(Debug, Clone, Serialize)]
+pub struct CursorColors {
+    /// The text under the cursor
+    pub fg: String,
+    /// The cursor block itself
+    pub bg: String,
+}
+
+impl Default for CursorColors {
+    // same as xterm.js
+    fn default() -> Self {
+        Self {
+            fg: "#000000".to_string(),
+            bg: "#ffffff".to_string(),
+        }
+    }
+}
+
+pub fn validate_hex(color: &str) -> Result<(), SteppeError> {
+    let digits = color.strip_prefix('#').unwrap_or_default();
+
+    if matches!(digits.len(), 3 | 6 | 8) && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(SteppeError::InvalidConfig(format!("{color} is not a hex color")))
+    }
+}
+
+/// The colors as they should currently be drawn, swapped while inverse video is on
+async fn effective_colors(state: &AppState) -> CursorColors {
+    let CursorColors { fg, bg } = state.cursor_colors.lock().await.clone();
+
+    if state.inverse_video.load(Ordering::Acquire) {
+        CursorColors { fg: bg, bg: fg }
+    } else {
+        CursorColors { fg, bg }
+    }
+}
+
+/// Called when the program in the pty toggles inverse video
+pub async fn set_inverse_video(app: &AppHandle, state: &AppState, enabled: bool) -> Result<(), SteppeError> {
+    if state.inverse_video.swap(enabled, Ordering::AcqRel) != enabled {
+        app.emit("cursor-colors-changed", effective_colors(state).await)?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_get_cursor_colors(state: State<'_, AppState>) -> Result<(String, String), SteppeError> {
+    let CursorColors { fg, bg } = effective_colors(&state).await;
+    Ok((fg, bg))
+}
+
+#[tauri::command]
+pub async fn async_set_cursor_colors(
+    fg: String,
+    bg: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    validate_hex(&fg)?;
+    validate_hex(&bg)?;
+
+    *state.cursor_colors.lock().await = CursorColors { fg, bg };
+    app.emit("cursor-colors-changed", effective_colors(&state).await)?;
+
+    Ok(())
+}
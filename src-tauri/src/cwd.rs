@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, process_info, AppState, SubTerminal};
+
+#[derive(Clone, Serialize)]
+struct CwdChanged {
+    terminal_id: u32,
+    cwd: PathBuf,
+}
+
+/// The path out of OSC 7's `file://host/path`. The host is left off, since
+/// for ssh and wsl terminals it's somewhere else anyway.
+pub fn parse_osc7(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    Some(PathBuf::from(path.as_ref()))
+}
+
+/// `path` with a leading `~` as the home directory, for directories written
+/// out in config.js
+pub fn expand_home(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix('~') {
+        Some("") => dirs::home_dir(),
+        Some(rest) if rest.starts_with(['/', '\\']) => Some(dirs::home_dir()?.join(&rest[1..])),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+/// Where the shell in `terminal` is: what it last reported over OSC 7, or
+/// failing that, the working directory of the shell process itself.
+pub async fn terminal_cwd(terminal: &SubTerminal) -> Option<PathBuf> {
+    if let Some(cwd) = terminal.shell_integration.lock().await.cwd() {
+        return Some(cwd.to_path_buf());
+    }
+
+    let shell = terminal.shell.get()?;
+    // the ssh, docker or kubectl client's cwd is a local one, which says nothing about the remote shell
+    if shell.ssh_profile.is_some() || shell.docker_container.is_some() || shell.k8s_shell.is_some() {
+        return None;
+    }
+
+    process_info::cwd(shell.pid?)
+}
+
+/// Where a new local shell opened from `terminal` should start, which is only
+/// where `terminal` is if that's a local shell too
+pub async fn inherited_cwd(terminal: &SubTerminal) -> Option<PathBuf> {
+    let shell = terminal.shell.get()?;
+    if shell.ssh_profile.is_some()
+        || shell.wsl_distro.is_some()
+        || shell.docker_container.is_some()
+        || shell.k8s_shell.is_some()
+    {
+        return None;
+    }
+
+    terminal_cwd(terminal).await
+}
+
+pub fn emit_changed(app: &AppHandle, terminal_id: u32, cwd: PathBuf) -> tauri::Result<()> {
+    app.emit("terminal://cwd-changed", CwdChanged { terminal_id, cwd })
+}
+
+/// Where the shell in terminal `terminal_id` is, if that can be found out.
+#[tauri::command]
+pub async fn async_get_cwd(terminal_id: u32, state: State<'_, AppState>) -> Result<Option<PathBuf>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    Ok(terminal_cwd(&terminal).await)
+}
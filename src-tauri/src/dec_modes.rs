@@ -0,0 +1,53 @@
+/// Picks DEC private mode changes (`CSI ? Pm h` / `CSI ? Pm l`) out of pty
+/// output. State is kept between calls, so a sequence split across two reads
+/// is still seen.
+#[derive(Debug, Default)]
+pub struct DecModeScanner {
+    state: ScanState,
+    params: Vec<u16>,
+    current: Option<u16>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum ScanState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Private,
+}
+
+impl DecModeScanner {
+    /// Calls `on_change(mode, enabled)` for every mode set or reset in `data`.
+    pub fn scan(&mut self, data: &[u8], mut on_change: impl FnMut(u16, bool)) {
+        for &byte in data {
+            self.state = match (self.state, byte) {
+                // an escape always starts a new sequence, even in the middle of another one
+                (_, 0x1b) => ScanState::Escape,
+                (ScanState::Escape, b'[') => ScanState::Csi,
+                (ScanState::Csi, b'?') => {
+                    self.params.clear();
+                    self.current = None;
+                    ScanState::Private
+                }
+                (ScanState::Private, b'0'..=b'9') => {
+                    let digit = u16::from(byte - b'0');
+                    self.current = Some(self.current.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    ScanState::Private
+                }
+                (ScanState::Private, b';') => {
+                    self.params.push(self.current.take().unwrap_or(0));
+                    ScanState::Private
+                }
+                (ScanState::Private, b'h' | b'l') => {
+                    self.params.push(self.current.take().unwrap_or(0));
+                    for &mode in &self.params {
+                        on_change(mode, byte == b'h');
+                    }
+                    ScanState::Ground
+                }
+                _ => ScanState::Ground,
+            }
+        }
+    }
+}
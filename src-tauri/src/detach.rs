@@ -0,0 +1,82 @@
+use std::sync::atomic::Ordering;
+
+use std::path::PathBuf;
+
+use tauri::{async_runtime, AppHandle, Manager, RunEvent, State, WebviewWindowBuilder};
+
+use crate::{
+    cli::{self, LaunchOptions},
+    error::SteppeError,
+    AppState,
+};
+
+/// Keep running with no window once the last one closes, so the shells
+/// (and whatever's running in them) stay alive until something attaches again,
+/// or the tray brings the window back.
+pub fn handle_run_event(app: &AppHandle, event: RunEvent) {
+    // `code` is only set when exiting was asked for explicitly, rather than
+    // because the windows went away
+    if let RunEvent::ExitRequested { code: None, api, .. } = event {
+        let state = app.state::<AppState>();
+        if state.detach_on_close.load(Ordering::Relaxed)
+            || state.keep_alive_in_tray.load(Ordering::Relaxed)
+            || state.serving.load(Ordering::Relaxed)
+        {
+            api.prevent_exit();
+        }
+    }
+}
+
+/// Bring the window back, e.g. for `steppe attach` while steppe is still
+/// running detached. The new page picks the terminals back up, replaying
+/// their scrollback.
+pub fn attach(app: &AppHandle) -> Result<(), SteppeError> {
+    if let Some(window) = app.get_webview_window("main") {
+        window.unminimize()?;
+        window.set_focus()?;
+        return Ok(());
+    }
+
+    let Some(config) = app.config().app.windows.first() else {
+        return Ok(());
+    };
+
+    WebviewWindowBuilder::from_config(app, config)?.build()?;
+
+    Ok(())
+}
+
+/// Called in the running instance when steppe gets launched again, with the
+/// new launch's arguments, instead of starting a whole other steppe (and deno
+/// runtime). The window's brought back, and unless that's all it was (`steppe
+/// attach`, or the window had been closed and comes back with its terminals)
+/// a new terminal opens in it for the launch's command or directory.
+pub fn handle_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    let options = LaunchOptions::parse(args.into_iter().skip(1));
+    if options.serve {
+        eprintln!("steppe serve has to be the first steppe running, so it's been ignored");
+        return;
+    }
+
+    let detached = app.get_webview_window("main").is_none();
+    if let Err(err) = attach(app) {
+        eprintln!("could not attach: {err}");
+    }
+
+    if options.attach || (detached && options.command.is_empty()) {
+        return;
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = cli::open(&app, options, &PathBuf::from(cwd)).await {
+            eprintln!("could not open terminal: {err}");
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn async_set_detach_on_close(enabled: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.detach_on_close.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
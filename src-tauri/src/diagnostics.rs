@@ -0,0 +1,176 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::{
+    crash, error::SteppeError, event_log, get_config_dir, get_config_path,
+    pty_backend::{self, PtyBackendInfo},
+    redact::{self, Redactor},
+    renderer::RendererBackend, size::SizeConstraints,
+    termios::TermiosSettings, url_opener::UrlOpenMode, zip::ZipWriter, AppState,
+};
+
+/// Only the newest crash reports go in a diagnostic bundle
+const MAX_BUNDLED_CRASHES: usize = 20;
+
+#[derive(Serialize)]
+pub struct TerminalSummary {
+    id: u32,
+    has_shell: bool,
+    /// Output sent to the frontend that it hasn't written yet
+    unacked_bytes: usize,
+}
+
+/// A snapshot of [`AppState`] for the dev tools panel.
+#[derive(Serialize)]
+pub struct AppStateSummary {
+    session_count: usize,
+    terminals: Vec<TerminalSummary>,
+    line_discipline: Option<TermiosSettings>,
+    url_open_mode: UrlOpenMode,
+    custom_url_opener: Option<String>,
+    allow_file_urls: bool,
+    crash_report_endpoint: Option<String>,
+    renderer_backend: RendererBackend,
+    size_constraints: SizeConstraints,
+    pty_backend: PtyBackendInfo,
+}
+
+/// Values that could carry credentials (tokens in urls, arguments, ...) only
+/// show up in debug builds, unless the user explicitly asks for them.
+fn redact(value: String, expose: bool) -> String {
+    if cfg!(debug_assertions) || expose {
+        value
+    } else {
+        "<redacted>".to_string()
+    }
+}
+
+/// Where steppe's running, for bug reports
+#[derive(Serialize)]
+pub struct PlatformInfo {
+    steppe_version: &'static str,
+    os: String,
+    arch: &'static str,
+}
+
+async fn summary(state: &AppState, expose: bool) -> AppStateSummary {
+    let url_opener = state.url_opener.lock().await.clone();
+
+    let terminals = state
+        .terminals
+        .lock()
+        .await
+        .iter()
+        .map(|(&id, terminal)| TerminalSummary {
+            id,
+            has_shell: terminal.has_terminal.load(Ordering::Acquire),
+            unacked_bytes: *terminal.unacked_bytes.lock().unwrap_or_else(|err| err.into_inner()),
+        })
+        .collect();
+
+    AppStateSummary {
+        session_count: crash::ACTIVE_SESSIONS.load(Ordering::Relaxed),
+        terminals,
+        line_discipline: *state.line_discipline.lock().await,
+        url_open_mode: url_opener.mode,
+        custom_url_opener: url_opener
+            .custom_command
+            .map(|(program, args)| redact(format!("{program} {}", args.join(" ")), expose)),
+        allow_file_urls: url_opener.allow_file_urls,
+        crash_report_endpoint: crash::upload_endpoint().map(|endpoint| redact(endpoint, expose)),
+        renderer_backend: *state.renderer_backend.lock().await,
+        size_constraints: *state.size_constraints.lock().await,
+        pty_backend: pty_backend::info(state).await,
+    }
+}
+
+#[tauri::command]
+pub async fn async_get_app_state_summary(state: State<'_, AppState>) -> Result<AppStateSummary, SteppeError> {
+    let expose = state.expose_secrets_in_diagnostics.load(Ordering::Relaxed);
+
+    Ok(summary(&state, expose).await)
+}
+
+#[tauri::command]
+pub async fn async_set_expose_secrets_in_diagnostics(
+    expose: bool,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    state.expose_secrets_in_diagnostics.store(expose, Ordering::Relaxed);
+    Ok(())
+}
+
+fn to_json(value: &impl Serialize, redactor: &Redactor) -> Result<Vec<u8>, SteppeError> {
+    let json = serde_json::to_string_pretty(value).map_err(std::io::Error::from)?;
+
+    Ok(redactor.redact(&json).into_owned().into_bytes())
+}
+
+/// The newest crash reports, by name, which is when they were written
+fn crash_reports() -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(get_config_dir().join("crashes")) else {
+        return Vec::new();
+    };
+
+    let mut reports: Vec<(String, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let contents = fs::read_to_string(entry.path()).ok()?;
+            Some((entry.file_name().to_string_lossy().into_owned(), contents))
+        })
+        .collect();
+    reports.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let older = reports.len().saturating_sub(MAX_BUNDLED_CRASHES);
+    reports.drain(..older);
+
+    reports
+}
+
+/// Zip up what a bug report needs: the platform, pty backend and a summary
+/// of steppe's state, the config, crash reports and the session event log.
+/// Everything's redacted, exposing secrets in diagnostics or not. Returns
+/// where it was saved, in the downloads directory if there is one.
+#[tauri::command]
+pub async fn async_create_diagnostic_bundle(state: State<'_, AppState>) -> Result<PathBuf, SteppeError> {
+    let redactor = redact::current(&state);
+    let mut zip = ZipWriter::default();
+
+    let platform = PlatformInfo {
+        steppe_version: env!("CARGO_PKG_VERSION"),
+        os: os_info::get().to_string(),
+        arch: std::env::consts::ARCH,
+    };
+    zip.add("platform.json", &to_json(&platform, &redactor)?)?;
+    let backend = pty_backend::info(&state).await;
+    zip.add("pty_backend.json", &to_json(&backend, &redactor)?)?;
+    zip.add("app_state.json", &to_json(&summary(&state, false).await, &redactor)?)?;
+    zip.add("events.json", &to_json(&event_log::global(&state).await, &redactor)?)?;
+
+    let config_path = get_config_path();
+    if let Ok(config) = fs::read_to_string(&config_path) {
+        let name = config_path.file_name().unwrap_or_default().to_string_lossy();
+        zip.add(&name, redactor.redact(&config).as_bytes())?;
+    }
+
+    for (name, report) in crash_reports() {
+        zip.add(&format!("crashes/{name}"), redactor.redact(&report).as_bytes())?;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dirs::download_dir()
+        .unwrap_or_else(get_config_dir)
+        .join(format!("steppe-diagnostics-{timestamp}.zip"));
+    fs::write(&path, zip.finish()?)?;
+
+    Ok(path)
+}
@@ -0,0 +1,119 @@
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State, Window};
+
+use crate::{error::SteppeError, next_terminal_id, spawn_in_terminal, window, AppState, ShellProcess};
+
+/// Run with the container's `sh`, prints the best shell it has: bash, zsh or
+/// ash if they're there, otherwise `sh` itself
+pub const DETECT_SHELL: &str = "for shell in bash zsh ash; do command -v \"$shell\" && exit; done; echo sh";
+
+/// A running container, as `docker ps` lists it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerContainer {
+    #[serde(rename(deserialize = "ID"))]
+    pub id: String,
+    /// Comma separated, if it has more than one
+    #[serde(rename(deserialize = "Names"))]
+    pub name: String,
+    #[serde(rename(deserialize = "Image"))]
+    pub image: String,
+    /// Like `Up 2 hours`
+    #[serde(rename(deserialize = "Status"))]
+    pub status: String,
+}
+
+/// Run `docker` with `args` and hand back what it printed, or what it
+/// complained about if it failed (daemon not running, no such container, ...)
+async fn docker(args: &[&str]) -> Result<String, SteppeError> {
+    let output = tokio::process::Command::new("docker").args(args).output().await?;
+
+    if !output.status.success() {
+        return Err(SteppeError::Docker(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The shell `DETECT_SHELL` printed
+pub fn detected_shell(output: &str) -> String {
+    output.lines().next().unwrap_or("sh").trim().to_string()
+}
+
+async fn detect_shell(container: &str) -> Result<String, SteppeError> {
+    let output = docker(&["exec", container, "sh", "-c", DETECT_SHELL]).await?;
+    Ok(detected_shell(&output))
+}
+
+/// `docker exec` into `container`, running `shell` there. Going through the
+/// docker cli means contexts and `DOCKER_HOST` work like they do anywhere else.
+fn command(container: &str, shell: &str) -> CommandBuilder {
+    let mut cmd = CommandBuilder::new("docker");
+    // the TERM steppe sets is only for the docker cli, the shell needs its own
+    cmd.args(["exec", "-it", "-e", "TERM=xterm-256color", container, shell]);
+
+    cmd
+}
+
+/// Start a shell in `container` in terminal `terminal_id`
+async fn exec(terminal_id: u32, container: String, app: AppHandle, state: &AppState) -> Result<(), SteppeError> {
+    let shell = detect_shell(&container).await?;
+
+    let process = ShellProcess {
+        program: "docker".to_string(),
+        shell_profile: None,
+        ssh_profile: None,
+        wsl_distro: None,
+        docker_container: Some(container.clone()),
+        k8s_shell: None,
+        pid: None,
+        init_command: None,
+    };
+
+    spawn_in_terminal(terminal_id, command(&container, &shell), process, app, state)
+        .await
+        .map_err(SteppeError::Pty)
+}
+
+#[tauri::command]
+pub async fn async_list_docker_containers() -> Result<Vec<DockerContainer>, SteppeError> {
+    let output = docker(&["ps", "--format", "{{json .}}"]).await?;
+
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|err| SteppeError::Docker(err.to_string())))
+        .collect()
+}
+
+/// Like `async_create_shell`, but runs a shell inside `container` (by id or
+/// name) instead.
+#[tauri::command]
+pub async fn async_create_docker_exec(
+    terminal_id: u32,
+    container: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    exec(terminal_id, container, app, &state).await
+}
+
+/// Open a tab in the calling window with a shell inside `container`. Returns
+/// the new terminal's id.
+#[tauri::command]
+pub async fn async_open_docker_tab(
+    container: String,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<u32, SteppeError> {
+    let terminal_id = next_terminal_id(&state).await?;
+    window::assign(&state, terminal_id, window.label()).await;
+
+    exec(terminal_id, container, app.clone(), &state).await?;
+    app.emit_to(window.label(), "terminal://spawned", terminal_id)?;
+
+    Ok(terminal_id)
+}
@@ -0,0 +1,98 @@
+use portable_pty::CommandBuilder;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{
+    cwd,
+    error::SteppeError,
+    next_terminal_id,
+    panes::{self, SplitDirection},
+    spawn_in_terminal, ssh, window, AppState, ShellProcess,
+};
+
+/// What a terminal's shell was started with (profile, arguments,
+/// environment and all), to start another like it
+#[derive(Clone)]
+pub struct StartedWith {
+    cmd: CommandBuilder,
+    process: ShellProcess,
+}
+
+impl StartedWith {
+    pub fn new(cmd: CommandBuilder, process: ShellProcess) -> Self {
+        Self { cmd, process }
+    }
+
+    pub fn cmd(&self) -> &CommandBuilder {
+        &self.cmd
+    }
+}
+
+/// Start what terminal `terminal_id` was started with in a new terminal for
+/// window `window`, in the directory it's in now if it's a local shell.
+/// Returns the new terminal's id, without letting the window know about it.
+async fn start(app: &AppHandle, state: &AppState, terminal_id: u32, window: &str) -> Result<u32, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    // serial ports, tmux panes and playback weren't started with anything
+    let started_with = terminal
+        .started_with
+        .get()
+        .cloned()
+        .ok_or(SteppeError::NoProcess(terminal_id))?;
+    let cwd = cwd::inherited_cwd(&terminal).await;
+    drop(terminal);
+
+    let new = next_terminal_id(state).await?;
+    window::assign(state, new, window).await;
+
+    let StartedWith { mut cmd, mut process } = started_with;
+    // the connection's own setup (asking for passphrases in a prompt, port forwards) goes with the new terminal's id
+    if let Some(profile) = process.ssh_profile {
+        ssh::async_create_ssh(new, profile, app.clone(), app.state()).await?;
+        return Ok(new);
+    }
+
+    if let Some(cwd) = cwd {
+        cmd.cwd(cwd);
+    }
+    process.pid = None;
+    spawn_in_terminal(new, cmd, process, app.clone(), state)
+        .await
+        .map_err(SteppeError::Pty)?;
+
+    Ok(new)
+}
+
+/// Open another terminal like terminal `terminal_id`: the same profile (or
+/// ssh connection, wsl distro, container, ...), environment and directory.
+/// With `split`, it goes next to it in a split pane rather than in a tab of
+/// its own. Returns the new terminal's id.
+pub async fn duplicate(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    split: Option<SplitDirection>,
+) -> Result<u32, SteppeError> {
+    let label = window::window_of(state, terminal_id).await;
+    if split.is_some() {
+        panes::check_splittable(state, &label, terminal_id).await?;
+    }
+
+    let new = start(app, state, terminal_id, &label).await?;
+    app.emit_to(&label, "terminal://spawned", new)?;
+
+    if let Some(direction) = split {
+        panes::insert(app, state, &label, terminal_id, direction, new).await?;
+    }
+
+    Ok(new)
+}
+
+#[tauri::command]
+pub async fn async_duplicate_terminal(
+    terminal_id: u32,
+    split: Option<SplitDirection>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<u32, SteppeError> {
+    duplicate(&app, &state, terminal_id, split).await
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{foreground, redact, AppState, SubTerminal};
+
+/// Variables that look like they hold a secret, which never get written
+/// down, going by the same names redaction goes by
+const SENSITIVE_NAME: &str = r"(?i)password|passwd|secret|token|api_?key|access_?key|credential|private_?key";
+
+/// Variables that are about the one shell or terminal they were set in, and
+/// would only be wrong in another
+const PER_SHELL: [&str; 8] = ["PWD", "OLDPWD", "SHLVL", "_", "TERM", "COLUMNS", "LINES", "WINDOWID"];
+
+/// Remembering what's changed in a shell's environment since it started (an
+/// activated virtualenv, `nix develop`, ...) for when it's started again
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct EnvironmentOptions {
+    /// On by default
+    pub enabled: Option<bool>,
+    /// Regexes for more variable names to leave out, on top of ones that
+    /// look like they hold a secret
+    pub exclude: Vec<String>,
+}
+
+impl EnvironmentOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        RegexSet::new(&self.exclude).map_err(|err| format!("environmentSnapshot.exclude: {err}"))?;
+
+        Ok(())
+    }
+}
+
+/// The environment process `pid` was started with. Shells don't update
+/// theirs when they `export` something, so this is only what they started
+/// with, but a shell started by another (like `nix develop` does) starts
+/// with everything the first one had set up for it.
+#[cfg(target_os = "linux")]
+fn read(pid: u32) -> Option<Vec<(String, String)>> {
+    let environ = std::fs::read(format!("/proc/{pid}/environ")).ok()?;
+
+    Some(
+        environ
+            .split(|&byte| byte == 0)
+            .filter_map(|entry| {
+                let (name, value) = std::str::from_utf8(entry).ok()?.split_once('=')?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// macOS and windows only keep it in the process's own memory
+#[cfg(not(target_os = "linux"))]
+fn read(_pid: u32) -> Option<Vec<(String, String)>> {
+    None
+}
+
+/// What's different about the environment of whatever's in the foreground
+/// of `terminal` (the shell, when nothing else is running) from what the
+/// shell was started with, less anything that's left out. `None` if it
+/// can't be read, or it's not a local shell whose environment would mean
+/// anything to another one.
+async fn read_changes(
+    state: &AppState,
+    options: &EnvironmentOptions,
+    terminal: &SubTerminal,
+) -> Option<BTreeMap<String, String>> {
+    let shell = terminal.shell.get()?;
+    // the ssh, docker or kubectl client's environment is a local one, which says nothing about the remote shell
+    if shell.ssh_profile.is_some()
+        || shell.wsl_distro.is_some()
+        || shell.docker_container.is_some()
+        || shell.k8s_shell.is_some()
+    {
+        return None;
+    }
+
+    let started_with = terminal.started_with.get()?;
+    let pid = match foreground::foreground_process(terminal).await {
+        Some(process) => process.pid,
+        None => shell.pid?,
+    };
+    let environment = read(pid)?;
+
+    let sensitive = Regex::new(SENSITIVE_NAME).expect("the sensitive name pattern is valid");
+    // checked when the settings were loaded
+    let excluded = RegexSet::new(&options.exclude).unwrap_or_else(|_| RegexSet::empty());
+    let redactor = redact::current(state);
+
+    let changes = environment
+        .into_iter()
+        .filter(|(name, value)| started_with.cmd().get_env(name).and_then(|old| old.to_str()) != Some(value))
+        .filter(|(name, _)| {
+            !name.starts_with("STEPPE_")
+                && !PER_SHELL.contains(&name.as_str())
+                && !sensitive.is_match(name)
+                && !excluded.is_match(name)
+        })
+        // a token in a variable with an innocent enough name
+        .filter(|(_, value)| redactor.redact(value) == value.as_str())
+        .collect();
+
+    Some(changes)
+}
+
+/// Read what's changed in terminal `terminal`'s environment, and keep it for
+/// once the shell's gone. Returns whether `environmentSnapshot` is on.
+pub async fn snapshot(state: &AppState, terminal: &SubTerminal) -> bool {
+    let options = state
+        .settings
+        .lock()
+        .await
+        .environment_snapshot
+        .clone()
+        .unwrap_or_default();
+    if !options.enabled.unwrap_or(true) {
+        return false;
+    }
+
+    if let Some(changes) = read_changes(state, &options, terminal).await {
+        *terminal.environment.lock().unwrap_or_else(|err| err.into_inner()) = Some(changes);
+    }
+
+    true
+}
+
+/// What's changed in terminal `terminal`'s environment since its shell
+/// started: as it is now while it's running, or as it was last seen once
+/// it's gone
+pub async fn changes(state: &AppState, terminal: &SubTerminal) -> Option<BTreeMap<String, String>> {
+    if !snapshot(state, terminal).await {
+        return None;
+    }
+
+    terminal
+        .environment
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+}
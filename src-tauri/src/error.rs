@@ -0,0 +1,108 @@
+use serde::{Serialize, Serializer};
+
+/// Errors returned from tauri commands back to the frontend.
+#[derive(Debug, thiserror::Error)]
+pub enum SteppeError {
+    #[error("{0} is not supported on this platform")]
+    UnsupportedPlatformFeature(&'static str),
+    #[error("pty error: {0}")]
+    Pty(String),
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
+    #[error("refusing to open {0}")]
+    BlockedUrl(String),
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    #[error("{0:?} is not a buffer name, use a-z, 0-9, + or *")]
+    InvalidBufferName(String),
+    #[error("no session with id {0}")]
+    SessionNotFound(u32),
+    #[error("timed out: {0}")]
+    Timeout(String),
+    #[error("couldn't find the desktop wallpaper")]
+    WallpaperNotFound,
+    #[error("input isn't being recorded")]
+    NotRecording,
+    #[error("terminal {0} isn't being recorded")]
+    NotRecordingTerminal(u32),
+    #[error("terminal {0}'s output isn't being logged")]
+    NotLoggingOutput(u32),
+    #[error("terminal {0}'s output kept crashing, so it's stopped being shown")]
+    Corrupted(u32),
+    #[error("nothing's running in terminal {0}")]
+    NoProcess(u32),
+    #[error("terminal {0} isn't playing anything back")]
+    NotPlayback(u32),
+    #[error("invalid recording: {0}")]
+    InvalidRecording(String),
+    #[error("no profile named {0:?}")]
+    ProfileNotFound(String),
+    #[error("no plugin named {0:?}")]
+    PluginNotFound(String),
+    #[error("no module {0} is loaded")]
+    ModuleNotLoaded(String),
+    #[error("no ssh profile named {0:?}")]
+    SshProfileNotFound(String),
+    #[error("terminal {0} isn't connected over ssh")]
+    NotSsh(u32),
+    #[error("no connection with id {0}")]
+    ConnectionNotFound(u32),
+    #[error("no ssh prompt {0} is waiting on an answer")]
+    SshPromptNotFound(u32),
+    #[error("ssh agent: {0}")]
+    SshAgent(String),
+    #[error("remote attach: {0}")]
+    RemoteAttach(String),
+    #[error("serve: {0}")]
+    Serve(String),
+    #[error("file transfer failed: {0}")]
+    Transfer(String),
+    #[error("port forward failed: {0}")]
+    PortForward(String),
+    #[error("docker: {0}")]
+    Docker(String),
+    #[error("kubectl: {0}")]
+    Kubernetes(String),
+    #[error("wsl: {0}")]
+    Wsl(String),
+    #[error("watch: {0}")]
+    Watch(String),
+    #[error("terminal {0} isn't part of a tmux session")]
+    NotTmux(u32),
+    #[error("no palette item {0:?}")]
+    PaletteItemNotFound(String),
+    #[error("no task named {0:?}")]
+    TaskNotFound(String),
+    #[error("terminal {0} isn't in a split pane")]
+    NotInPane(u32),
+    #[error("a pane's share of its split has to be between 0 and 1, not {0}")]
+    InvalidPaneRatio(f64),
+    #[error("invalid image: {0}")]
+    InvalidImage(String),
+    #[error("image is {0} bytes, more than the {1} allowed")]
+    ImageTooLarge(usize, usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error(transparent)]
+    Clipboard(#[from] tauri_plugin_clipboard_manager::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Serial(#[from] serialport::Error),
+    #[error(transparent)]
+    Notification(#[from] tauri_plugin_notification::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    GlobalShortcut(#[from] tauri_plugin_global_shortcut::Error),
+}
+
+// tauri needs command errors to be serializable, and the frontend only
+// ever shows the message anyways
+impl Serialize for SteppeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::{error::SteppeError, AppState};
+
+/// Older events get dropped past this many
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub enum SessionEventKind {
+    Created,
+    ShellSpawned,
+    ShellExited { code: i32 },
+    Resized { rows: u16, cols: u16 },
+    Titled(String),
+    CwdChanged(PathBuf),
+    RecordingStarted,
+    RecordingStopped,
+    Suspended,
+    Resumed,
+    Error(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    pub timestamp_ms: u64,
+    pub kind: SessionEventKind,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SessionEventLog(VecDeque<SessionEvent>);
+
+impl SessionEventLog {
+    fn push(&mut self, event: SessionEvent) {
+        if self.0.len() == MAX_EVENTS {
+            self.0.pop_front();
+        }
+
+        self.0.push_back(event);
+    }
+}
+
+/// What happened to each session, plus everything from every session in one place.
+#[derive(Debug, Default)]
+pub struct EventLogs {
+    sessions: HashMap<u32, SessionEventLog>,
+    global: SessionEventLog,
+}
+
+pub async fn record(state: &AppState, session_id: u32, kind: SessionEventKind, detail: Option<String>) {
+    let event = SessionEvent {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        kind,
+        detail,
+    };
+
+    let mut logs = state.event_log.lock().await;
+    logs.global.push(event.clone());
+    logs.sessions.entry(session_id).or_default().push(event);
+}
+
+#[tauri::command]
+pub async fn async_get_session_event_log(
+    session_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Vec<SessionEvent>, SteppeError> {
+    let logs = state.event_log.lock().await;
+    let log = logs.sessions.get(&session_id).ok_or(SteppeError::SessionNotFound(session_id))?;
+
+    Ok(log.0.iter().cloned().collect())
+}
+
+#[tauri::command]
+pub async fn async_clear_session_event_log(session_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let mut logs = state.event_log.lock().await;
+    let log = logs.sessions.get_mut(&session_id).ok_or(SteppeError::SessionNotFound(session_id))?;
+    log.0.clear();
+
+    Ok(())
+}
+
+/// Everything from every session, oldest first
+pub async fn global(state: &AppState) -> Vec<SessionEvent> {
+    state.event_log.lock().await.global.0.iter().cloned().collect()
+}
+
+#[tauri::command]
+pub async fn async_get_global_event_log(state: State<'_, AppState>) -> Result<Vec<SessionEvent>, SteppeError> {
+    Ok(global(&state).await)
+}
@@ -0,0 +1,412 @@
+use std::{fmt::Write as _, fs, path::PathBuf};
+
+use alacritty_terminal::{
+    grid::{Dimensions, Grid},
+    index::{Column, Line},
+    term::cell::{Cell, Flags},
+    vte::ansi::{Color, NamedColor, Rgb},
+};
+use serde::Deserialize;
+use tauri::{AppHandle, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{
+    error::SteppeError,
+    redact::{self, Redactor},
+    scrollback::{self, visible_text, Scrollback, ScrollbackRange},
+    themes::Theme,
+    AppState,
+};
+
+/// xterm.js's colors, for exporting while no theme is set
+const DEFAULT_FOREGROUND: &str = "#ffffff";
+const DEFAULT_BACKGROUND: &str = "#2f2f2f";
+const DEFAULT_ANSI: [&str; 16] = [
+    "#2e3436", "#cc0000", "#4e9a06", "#c4a000", "#3465a4", "#75507b", "#06989a", "#d3d7cf", "#555753", "#ef2929",
+    "#8ae234", "#fce94f", "#729fcf", "#ad7fa8", "#34e2e2", "#eeeeec",
+];
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// What's on screen, without any styling
+    Text,
+    /// Text with SGR sequences for its colors and styles, for `cat` or `less -R`
+    Ansi,
+    /// A `<pre>` with inline styles, to paste into docs
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportRange {
+    /// Just what's on screen
+    Screen,
+    /// The scrollback and the screen
+    All,
+    /// `count` lines from `start`, counting from the oldest line kept
+    Lines { start: usize, count: usize },
+}
+
+/// Cells that only take up room for a wide character next to them
+const WIDE_CHAR_SPACERS: Flags = Flags::WIDE_CHAR_SPACER.union(Flags::LEADING_WIDE_CHAR_SPACER);
+
+/// The part of a cell's look that gets exported
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    flags: Flags,
+}
+
+impl Style {
+    const EXPORTED_FLAGS: Flags = Flags::BOLD
+        .union(Flags::DIM)
+        .union(Flags::ITALIC)
+        .union(Flags::UNDERLINE)
+        .union(Flags::INVERSE)
+        .union(Flags::HIDDEN)
+        .union(Flags::STRIKEOUT);
+
+    fn of(cell: &Cell) -> Self {
+        Self {
+            fg: cell.fg,
+            bg: cell.bg,
+            flags: cell.flags & Self::EXPORTED_FLAGS,
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.fg == Color::Named(NamedColor::Foreground)
+            && self.bg == Color::Named(NamedColor::Background)
+            && self.flags.is_empty()
+    }
+}
+
+/// A row's cells as runs of the same style, without the blanks at the end,
+/// and whether it soft wraps onto the next
+fn row_runs(grid: &Grid<Cell>, line: Line) -> (Vec<(Style, String)>, bool) {
+    let row = &grid[line];
+    let cols = grid.columns();
+
+    let cells: Vec<&Cell> = (0..cols)
+        .map(|col| &row[Column(col)])
+        // the second half of a wide character has nothing of its own to show
+        .filter(|cell| !cell.flags.intersects(WIDE_CHAR_SPACERS))
+        .collect();
+    let len = cells
+        .iter()
+        .rposition(|cell| cell.c != ' ' || !Style::of(cell).is_default())
+        .map_or(0, |last| last + 1);
+
+    let mut runs: Vec<(Style, String)> = Vec::new();
+    for cell in &cells[..len] {
+        let style = Style::of(cell);
+        match runs.last_mut() {
+            Some((last, text)) if *last == style => text.push(cell.c),
+            _ => runs.push((style, cell.c.to_string())),
+        }
+    }
+
+    let wrapped = cols > 0 && row[Column(cols - 1)].flags.contains(Flags::WRAPLINE);
+    (runs, wrapped)
+}
+
+/// The lines `range` covers, from the top of the scrollback (negative) down the screen
+fn lines(grid: &Grid<Cell>, range: ExportRange) -> impl Iterator<Item = Line> {
+    let history = grid.history_size() as i32;
+    let screen = grid.screen_lines() as i32;
+
+    let (start, end) = match range {
+        ExportRange::Screen => (0, screen),
+        ExportRange::All => (-history, screen),
+        ExportRange::Lines { start, count } => {
+            let start = (start as i32).saturating_sub(history).min(screen);
+            (start, start.saturating_add(count as i32).min(screen))
+        }
+    };
+
+    (start..end).map(Line)
+}
+
+/// The scrollback's lines from before the top of `grid`'s, which is only
+/// as long as it is in memory, for exporting everything. Lines are lined up
+/// by counting back from the cursor's, so a program that moved the cursor
+/// around a lot can throw that off by a few.
+fn older_lines(scrollback: &Scrollback, grid: &Grid<Cell>) -> ScrollbackRange {
+    let cols = grid.columns();
+    let wrapped = |line: i32| cols > 0 && grid[Line(line)][Column(cols - 1)].flags.contains(Flags::WRAPLINE);
+    // a line the terminal wrapped is one line of output
+    let on_grid = (-(grid.history_size() as i32)..grid.cursor.point.line.0)
+        .filter(|&line| !wrapped(line))
+        .count();
+
+    let end = scrollback.current_line().saturating_sub(on_grid);
+    scrollback.range(scrollback.first_line(), end)
+}
+
+fn sgr_color(params: &mut Vec<String>, color: Color, base: u8, bright_base: u8, extended: u8) {
+    match color {
+        Color::Named(named) => match named as usize {
+            index @ 0..=7 => params.push((base as usize + index).to_string()),
+            index @ 8..=15 => params.push((bright_base as usize + index - 8).to_string()),
+            // the default colors, and the dim and cursor ones only a renderer cares about
+            _ => {}
+        },
+        Color::Indexed(index) => params.push(format!("{extended};5;{index}")),
+        Color::Spec(Rgb { r, g, b }) => params.push(format!("{extended};2;{r};{g};{b}")),
+    }
+}
+
+fn sgr(style: &Style) -> String {
+    let mut params = vec!["0".to_string()];
+
+    for (flag, param) in [
+        (Flags::BOLD, "1"),
+        (Flags::DIM, "2"),
+        (Flags::ITALIC, "3"),
+        (Flags::UNDERLINE, "4"),
+        (Flags::INVERSE, "7"),
+        (Flags::HIDDEN, "8"),
+        (Flags::STRIKEOUT, "9"),
+    ] {
+        if style.flags.contains(flag) {
+            params.push(param.to_string());
+        }
+    }
+
+    sgr_color(&mut params, style.fg, 30, 90, 38);
+    sgr_color(&mut params, style.bg, 40, 100, 48);
+
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// The colors cells get drawn in, from the theme if there is one
+struct Palette {
+    foreground: String,
+    background: String,
+    ansi: Vec<String>,
+}
+
+impl Palette {
+    fn new(theme: Option<Theme>) -> Self {
+        match theme {
+            Some(theme) => Self {
+                foreground: theme.foreground,
+                background: theme.background,
+                ansi: theme.ansi,
+            },
+            None => Self {
+                foreground: DEFAULT_FOREGROUND.to_string(),
+                background: DEFAULT_BACKGROUND.to_string(),
+                ansi: DEFAULT_ANSI.iter().map(|color| color.to_string()).collect(),
+            },
+        }
+    }
+
+    /// `color` as css, or `None` for the default it's drawn over anyway
+    fn css(&self, color: Color) -> Option<String> {
+        match color {
+            Color::Named(named) => match named as usize {
+                index @ 0..=15 => Some(self.ansi[index].clone()),
+                _ => None,
+            },
+            Color::Indexed(index @ 0..=15) => Some(self.ansi[index as usize].clone()),
+            // the 6x6x6 color cube
+            Color::Indexed(index @ 16..=231) => {
+                let level = |n: u8| if n == 0 { 0 } else { n * 40 + 55 };
+                let index = index - 16;
+                Some(format!(
+                    "#{:02x}{:02x}{:02x}",
+                    level(index / 36),
+                    level(index / 6 % 6),
+                    level(index % 6)
+                ))
+            }
+            // and the grays after it
+            Color::Indexed(index) => {
+                let gray = 8 + (index - 232) * 10;
+                Some(format!("#{gray:02x}{gray:02x}{gray:02x}"))
+            }
+            Color::Spec(Rgb { r, g, b }) => Some(format!("#{r:02x}{g:02x}{b:02x}")),
+        }
+    }
+
+    fn span_style(&self, style: &Style) -> String {
+        let (mut fg, mut bg) = (self.css(style.fg), self.css(style.bg));
+        if style.flags.contains(Flags::INVERSE) {
+            (fg, bg) = (
+                Some(bg.unwrap_or_else(|| self.background.clone())),
+                Some(fg.unwrap_or_else(|| self.foreground.clone())),
+            );
+        }
+
+        let mut css = String::new();
+        if let Some(fg) = fg {
+            let _ = write!(css, "color:{fg};");
+        }
+        if let Some(bg) = bg {
+            let _ = write!(css, "background:{bg};");
+        }
+        for (flag, rule) in [
+            (Flags::BOLD, "font-weight:bold;"),
+            (Flags::DIM, "opacity:0.5;"),
+            (Flags::ITALIC, "font-style:italic;"),
+            (Flags::UNDERLINE, "text-decoration:underline;"),
+            (Flags::STRIKEOUT, "text-decoration:line-through;"),
+            (Flags::HIDDEN, "visibility:hidden;"),
+        ] {
+            if style.flags.contains(flag) {
+                css.push_str(rule);
+            }
+        }
+
+        css
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// `range` of what's in `grid`, as `format`, with any secrets redacted.
+/// `older` is output from before the grid's scrollback, which goes first
+/// as plain text since there aren't any cells for it.
+fn export(
+    grid: &Grid<Cell>,
+    older: &[String],
+    format: ExportFormat,
+    range: ExportRange,
+    palette: &Palette,
+    redactor: &Redactor,
+) -> String {
+    let mut out = String::new();
+
+    if let ExportFormat::Html = format {
+        let _ = write!(
+            out,
+            "<pre style=\"color:{};background:{};font-family:monospace\">",
+            palette.foreground, palette.background
+        );
+    }
+
+    for line in older {
+        let text = visible_text(line);
+        let text = redactor.redact(&text);
+        match format {
+            ExportFormat::Html => out.push_str(&escape_html(&text)),
+            ExportFormat::Text | ExportFormat::Ansi => out.push_str(&text),
+        }
+        out.push('\n');
+    }
+
+    // a long line the terminal wrapped goes back to being one line, which
+    // also means a secret that wrapped is still redacted
+    let mut runs = Vec::new();
+    let mut lines = lines(grid, range).peekable();
+    while let Some(line) = lines.next() {
+        let (row, wrapped) = row_runs(grid, line);
+        runs.extend(row);
+        if wrapped && lines.peek().is_some() {
+            continue;
+        }
+
+        redactor.redact_runs(&mut runs);
+        for (style, text) in &runs {
+            match format {
+                ExportFormat::Text => out.push_str(text),
+                ExportFormat::Ansi => {
+                    out.push_str(&sgr(style));
+                    out.push_str(text);
+                }
+                ExportFormat::Html if style.is_default() => out.push_str(&escape_html(text)),
+                ExportFormat::Html => {
+                    let _ = write!(
+                        out,
+                        "<span style=\"{}\">{}</span>",
+                        palette.span_style(style),
+                        escape_html(text)
+                    );
+                }
+            }
+        }
+
+        if let ExportFormat::Ansi = format {
+            if runs.iter().any(|(style, _)| !style.is_default()) {
+                out.push_str("\x1b[0m");
+            }
+        }
+
+        if !wrapped {
+            out.push('\n');
+        }
+        runs.clear();
+    }
+
+    if let ExportFormat::Html = format {
+        out.push_str("</pre>\n");
+    }
+
+    out
+}
+
+async fn export_terminal(
+    state: &AppState,
+    terminal_id: u32,
+    format: ExportFormat,
+    range: ExportRange,
+) -> Result<String, SteppeError> {
+    let palette = Palette::new(state.theme.lock().await.clone());
+    let terminal = state.terminal(terminal_id).await?;
+    let redactor = redact::current(state);
+
+    if let ExportRange::All = range {
+        // what spilled to disk, and whatever else the scrollback has that the
+        // screen's let go of, read back off the runtime with a copy of the
+        // screen so the terminal isn't held up meanwhile
+        let (grid, older) = {
+            let screen = terminal.screen.lock().await;
+            let older = older_lines(&*terminal.scrollback.lock().await, screen.grid());
+
+            (screen.grid().clone(), older)
+        };
+        let older = scrollback::read(older, ScrollbackRange::lines).await?;
+
+        return Ok(export(&grid, &older, format, range, &palette, &redactor));
+    }
+
+    let screen = terminal.screen.lock().await;
+    Ok(export(screen.grid(), &[], format, range, &palette, &redactor))
+}
+
+/// Put what's on terminal `terminal_id`'s screen on the clipboard as html,
+/// with plain text for anything that doesn't take html
+pub async fn copy_as_html(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let html = export_terminal(state, terminal_id, ExportFormat::Html, ExportRange::Screen).await?;
+    let text = export_terminal(state, terminal_id, ExportFormat::Text, ExportRange::Screen).await?;
+    app.clipboard().write_html(html, Some(text))?;
+
+    Ok(())
+}
+
+/// Terminal `terminal_id`'s screen, scrollback or some of its lines as plain
+/// text, ansi or html. It's written to `path` if there is one, and returned
+/// otherwise.
+#[tauri::command]
+pub async fn async_export_terminal(
+    terminal_id: u32,
+    format: ExportFormat,
+    range: ExportRange,
+    path: Option<PathBuf>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SteppeError> {
+    let exported = export_terminal(&state, terminal_id, format, range).await?;
+
+    match path {
+        Some(path) => {
+            fs::write(path, exported)?;
+            Ok(None)
+        }
+        None => Ok(Some(exported)),
+    }
+}
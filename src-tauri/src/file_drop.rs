@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, DragDropEvent, Manager, Window, WindowEvent};
+
+use crate::{error::SteppeError, file_transfer, paste, wsl, AppState};
+
+/// How the paths of more than one dropped file go in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DropSeparator {
+    /// On one line, like they'd be typed as arguments
+    #[default]
+    Space,
+    /// A line each, which asks first like any paste of more than one line
+    Newline,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct FileDropOptions {
+    pub separator: DropSeparator,
+}
+
+/// The kinds of quoting shells understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    /// sh, bash, zsh and the like
+    Posix,
+    /// Like posix, but backslashes escape in single quotes too
+    Fish,
+    Cmd,
+    PowerShell,
+}
+
+impl Quoting {
+    /// What `program` (a path or just a name, with or without `.exe`) understands
+    fn of(program: &str) -> Self {
+        let name = program.rsplit(['/', '\\']).next().unwrap_or(program).to_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "cmd" => Self::Cmd,
+            "powershell" | "pwsh" => Self::PowerShell,
+            "fish" => Self::Fish,
+            _ => Self::Posix,
+        }
+    }
+
+    /// `path` as one word, quoted only if it has to be
+    fn quote(self, path: &str) -> String {
+        // anything else (spaces, `$`, `~`, `&`, ...) means something to one shell or another
+        let safe = match self {
+            Self::Posix | Self::Fish => "/._-+,:@%",
+            Self::Cmd | Self::PowerShell => "\\/._-:",
+        };
+        if !path.is_empty() && path.chars().all(|c| c.is_alphanumeric() || safe.contains(c)) {
+            return path.to_string();
+        }
+
+        match self {
+            Self::Posix => file_transfer::quote(path),
+            Self::Fish => format!("'{}'", path.replace('\\', r"\\").replace('\'', r"\'")),
+            // windows paths can't have a `"` in them. `%` expands even in
+            // quotes, so it's escaped outside them
+            Self::Cmd => format!("\"{}\"", path.replace('%', "\"^%\"")),
+            // powershell takes the curly quotes as quotes too
+            Self::PowerShell => {
+                let mut quoted = String::from("'");
+                for c in path.chars() {
+                    if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+                        quoted.push(c);
+                    }
+                    quoted.push(c);
+                }
+                quoted.push('\'');
+                quoted
+            }
+        }
+    }
+}
+
+/// Type the paths of `paths` into terminal `terminal_id`, quoted for its
+/// shell, and as its wsl distro sees them for a wsl shell. An ssh
+/// connection gets the files uploaded instead, since the paths wouldn't
+/// mean anything on the other end.
+pub async fn drop_files(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    paths: &[PathBuf],
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    // there's nothing to type them into yet
+    let Some(shell) = terminal.shell.get() else {
+        return Ok(());
+    };
+
+    if shell.ssh_profile.is_some() {
+        for path in paths {
+            file_transfer::upload(app, state, terminal_id, path).await?;
+        }
+        return Ok(());
+    }
+
+    let quoting = Quoting::of(&shell.program);
+    let mut words = Vec::with_capacity(paths.len());
+    for path in paths {
+        let path = match &shell.wsl_distro {
+            Some(distro) => wsl::linux_path(distro, path).await?,
+            None => path.to_string_lossy().into_owned(),
+        };
+        words.push(quoting.quote(&path));
+    }
+
+    let options = state.settings.lock().await.file_drop.clone().unwrap_or_default();
+    let text = match options.separator {
+        // with a space after, for whatever's typed next
+        DropSeparator::Space => format!("{} ", words.join(" ")),
+        DropSeparator::Newline => words.join("\n"),
+    };
+    paste::paste(app, state, terminal_id, &text, false).await?;
+
+    Ok(())
+}
+
+/// Files dropped on a window go to the terminal last focused in it
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+        return;
+    };
+
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    let paths = paths.clone();
+    async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let Some(terminal_id) = state.focused_terminals.lock().await.get(&label).copied() else {
+            return;
+        };
+
+        if let Err(err) = drop_files(&app, &state, terminal_id, &paths).await {
+            eprintln!("could not drop files into terminal {terminal_id}: {err}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each shell, a path, and how it's typed
+    const QUOTED: [(Quoting, &str, &str); 20] = [
+        (Quoting::Posix, "/home/leo/notes.txt", "/home/leo/notes.txt"),
+        (Quoting::Posix, "/tmp/50%.txt", "/tmp/50%.txt"),
+        (Quoting::Posix, "/tmp/my file.txt", "'/tmp/my file.txt'"),
+        (Quoting::Posix, "/tmp/it's", r"'/tmp/it'\''s'"),
+        (Quoting::Posix, "/tmp/$HOME", "'/tmp/$HOME'"),
+        (Quoting::Posix, "", "''"),
+        (Quoting::Fish, "/tmp/notes.txt", "/tmp/notes.txt"),
+        (Quoting::Fish, "/tmp/my file.txt", "'/tmp/my file.txt'"),
+        (Quoting::Fish, "/tmp/it's", r"'/tmp/it\'s'"),
+        (Quoting::Fish, r"/tmp/back\slash", r"'/tmp/back\\slash'"),
+        (Quoting::Cmd, r"C:\Users\leo\notes.txt", r"C:\Users\leo\notes.txt"),
+        (Quoting::Cmd, r"C:\Program Files\a.txt", r#""C:\Program Files\a.txt""#),
+        (Quoting::Cmd, r"C:\a&b.txt", r#""C:\a&b.txt""#),
+        (Quoting::Cmd, r"C:\%PATH%.txt", r#""C:\"^%"PATH"^%".txt""#),
+        (Quoting::Cmd, r"C:\100%", r#""C:\100"^%"""#),
+        (Quoting::PowerShell, r"C:\Users\leo\a.txt", r"C:\Users\leo\a.txt"),
+        (Quoting::PowerShell, r"C:\My Files\a.txt", r"'C:\My Files\a.txt'"),
+        (Quoting::PowerShell, r"C:\it's", r"'C:\it''s'"),
+        (Quoting::PowerShell, "C:\\it\u{2019}s", "'C:\\it\u{2019}\u{2019}s'"),
+        (Quoting::PowerShell, r"C:\$env:x", r"'C:\$env:x'"),
+    ];
+
+    #[test]
+    fn quotes_for_each_shell() {
+        for (quoting, path, quoted) in QUOTED {
+            assert_eq!(quoting.quote(path), quoted, "{quoting:?} quoting {path:?}");
+        }
+    }
+
+    #[test]
+    fn knows_shells_by_name() {
+        assert_eq!(Quoting::of("/usr/bin/fish"), Quoting::Fish);
+        assert_eq!(Quoting::of(r"C:\Windows\System32\cmd.exe"), Quoting::Cmd);
+        assert_eq!(Quoting::of("pwsh"), Quoting::PowerShell);
+        assert_eq!(Quoting::of("PowerShell.EXE"), Quoting::PowerShell);
+        assert_eq!(Quoting::of("/bin/zsh"), Quoting::Posix);
+        assert_eq!(Quoting::of("nu"), Quoting::Posix);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::Child,
+};
+
+use crate::{cwd, error::SteppeError, ssh, AppState};
+
+/// Don't flood the frontend with progress for every chunk
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How many `name (n).ext`s a download tries before giving up
+const MAX_DOWNLOAD_NAMES: u32 = 1000;
+
+#[derive(Clone, Serialize)]
+struct TransferProgress {
+    terminal_id: u32,
+    /// The file on whichever end it's coming from
+    path: String,
+    transferred: u64,
+    /// `None` when the size couldn't be found out up front
+    total: Option<u64>,
+    done: bool,
+}
+
+/// Quote `path` for the remote shell, or anything else that splits words like sh does
+pub fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// Copy `from` into `to`, emitting `file-transfer-progress` along the way.
+async fn copy_with_progress(
+    app: &AppHandle,
+    mut from: impl AsyncRead + Unpin,
+    mut to: impl AsyncWrite + Unpin,
+    mut progress: TransferProgress,
+) -> Result<(), SteppeError> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut last_emit = Instant::now();
+
+    loop {
+        let len = from.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+
+        to.write_all(&buf[..len]).await?;
+        progress.transferred += len as u64;
+
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            app.emit("file-transfer-progress", progress.clone())?;
+        }
+    }
+
+    to.flush().await?;
+
+    progress.done = true;
+    app.emit("file-transfer-progress", progress)?;
+
+    Ok(())
+}
+
+/// `name` with ` (n)` before its extension, for the `n`th file of that name
+fn numbered(name: &Path, n: u32) -> PathBuf {
+    if n == 0 {
+        return name.to_path_buf();
+    }
+
+    let stem = name.file_stem().unwrap_or(name.as_os_str()).to_string_lossy();
+    match name.extension() {
+        Some(extension) => format!("{stem} ({n}).{}", extension.to_string_lossy()).into(),
+        None => format!("{stem} ({n})").into(),
+    }
+}
+
+/// Create a file called `name` in `dir`, or `name (1)`, `name (2)` and so on
+/// if that's taken, so a download never overwrites anything.
+async fn create_free(dir: &Path, name: &Path) -> Result<(File, PathBuf), SteppeError> {
+    for n in 0..MAX_DOWNLOAD_NAMES {
+        let path = dir.join(numbered(name, n));
+        match OpenOptions::new().write(true).create_new(true).open(&path).await {
+            Ok(file) => return Ok((file, path)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Err(SteppeError::Transfer(format!(
+        "there are too many files called {} in {} already",
+        name.display(),
+        dir.display()
+    )))
+}
+
+/// Wait for ssh to finish, turning whatever it complained about into an error.
+async fn finish(child: Child) -> Result<(), SteppeError> {
+    let output = child.wait_with_output().await?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    Err(SteppeError::Transfer(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+}
+
+/// Send a local file to the ssh host terminal `terminal_id` is connected to.
+/// It lands wherever the remote shell is, if it says so over OSC 7, and the
+/// home directory otherwise.
+pub async fn upload(app: &AppHandle, state: &AppState, terminal_id: u32, local_path: &Path) -> Result<(), SteppeError> {
+    let profile = ssh::terminal_profile(state, terminal_id).await?;
+
+    let name = local_path
+        .file_name()
+        .ok_or_else(|| SteppeError::Transfer(format!("{} isn't a file", local_path.display())))?
+        .to_string_lossy()
+        .to_string();
+
+    let destination = match cwd::terminal_cwd(&*state.terminal(terminal_id).await?).await {
+        // the remote end is unix, whatever this end is
+        Some(cwd) => format!("{}/{name}", cwd.to_string_lossy().trim_end_matches('/')),
+        None => name,
+    };
+
+    let file = File::open(local_path).await?;
+    let total = file.metadata().await?.len();
+
+    let mut child = profile
+        .remote_command(&format!("cat > {}", quote(&destination)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let progress = TransferProgress {
+        terminal_id,
+        path: local_path.to_string_lossy().to_string(),
+        transferred: 0,
+        total: Some(total),
+        done: false,
+    };
+    // closing stdin is what tells cat it's done
+    copy_with_progress(app, file, stdin, progress).await?;
+
+    finish(child).await
+}
+
+#[tauri::command]
+pub async fn async_upload_file(
+    terminal_id: u32,
+    local_path: PathBuf,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    upload(&app, &state, terminal_id, &local_path).await
+}
+
+/// Fetch `path` from the ssh host terminal `terminal_id` is connected to into
+/// the downloads folder, returning where it ended up. If there's a file of
+/// that name there already, it gets a number rather than replacing it.
+#[tauri::command]
+pub async fn async_download_remote_file(
+    terminal_id: u32,
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<PathBuf, SteppeError> {
+    let profile = ssh::terminal_profile(&state, terminal_id).await?;
+
+    let name = Path::new(&path)
+        .file_name()
+        .ok_or_else(|| SteppeError::Transfer(format!("{path} isn't a file")))?;
+    let downloads = app.path().download_dir()?;
+
+    // just for the progress, so don't give up if it doesn't work
+    let total = profile
+        .remote_command(&format!("wc -c < {}", quote(&path)))
+        .output()
+        .await
+        .ok()
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok());
+
+    let mut child = profile
+        .remote_command(&format!("cat {}", quote(&path)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let (file, destination) = create_free(&downloads, Path::new(name)).await?;
+    let progress = TransferProgress {
+        terminal_id,
+        path,
+        transferred: 0,
+        total,
+        done: false,
+    };
+    copy_with_progress(&app, stdout, file, progress).await?;
+
+    finish(child).await?;
+
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_go_before_the_extension() {
+        assert_eq!(numbered(Path::new("notes.txt"), 0), PathBuf::from("notes.txt"));
+        assert_eq!(numbered(Path::new("notes.txt"), 2), PathBuf::from("notes (2).txt"));
+        assert_eq!(
+            numbered(Path::new("archive.tar.gz"), 1),
+            PathBuf::from("archive.tar (1).gz")
+        );
+        assert_eq!(numbered(Path::new("Makefile"), 1), PathBuf::from("Makefile (1)"));
+        assert_eq!(numbered(Path::new(".bashrc"), 1), PathBuf::from(".bashrc (1)"));
+    }
+}
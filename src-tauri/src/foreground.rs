@@ -0,0 +1,195 @@
+use std::{collections::HashMap, sync::atomic::Ordering, time::Duration};
+
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State, Window, WindowEvent};
+
+use crate::{environment, error::SteppeError, process_icons, process_info, window, AppState, SubTerminal};
+
+/// How often to check what's running in the foreground
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Whatever the user is running in the shell right now, or the shell itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForegroundProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Clone, Serialize)]
+struct ForegroundProcessChanged {
+    session_id: u32,
+    pid: u32,
+    name: String,
+}
+
+/// Something that isn't the shell, which closing the window would kill
+#[derive(Debug, Clone, Serialize)]
+struct BusyTerminal {
+    terminal_id: u32,
+    pid: u32,
+    name: String,
+}
+
+/// The process group leader in the foreground of the pty, from `tcgetpgrp`.
+#[cfg(unix)]
+pub async fn foreground_process(terminal: &SubTerminal) -> Option<ForegroundProcess> {
+    let pid = terminal.pty_pair.lock().await.master.process_group_leader()?;
+    let pid = u32::try_from(pid).ok()?;
+
+    Some(ForegroundProcess {
+        pid,
+        name: process_info::name(pid)?,
+    })
+}
+
+/// ConPTY doesn't say what's in the foreground, and portable-pty doesn't try
+#[cfg(not(unix))]
+pub async fn foreground_process(_terminal: &SubTerminal) -> Option<ForegroundProcess> {
+    None
+}
+
+/// Whether closing `terminal` would take something down with it: a program
+/// running in the shell, or an ssh connection (whose foreground process is
+/// the shell steppe started). Where the pty can't say what's in the
+/// foreground, anything the shell started counts.
+async fn busy_process(terminal: &SubTerminal) -> Option<ForegroundProcess> {
+    let shell = terminal.shell.get()?;
+    let process = match foreground_process(terminal).await {
+        Some(process) => process,
+        None => return process_info::running(terminal),
+    };
+
+    (shell.ssh_profile.is_some() || shell.pid != Some(process.pid)).then_some(process)
+}
+
+/// The terminals in window `label` that closing it would take something down with
+async fn busy_terminals(state: &AppState, label: &str) -> Vec<BusyTerminal> {
+    let mut busy = Vec::new();
+    for terminal_id in window::terminals_in(state, label).await {
+        let Ok(terminal) = state.terminal(terminal_id).await else {
+            continue;
+        };
+
+        if let Some(process) = busy_process(&terminal).await {
+            busy.push(BusyTerminal {
+                terminal_id,
+                pid: process.pid,
+                name: process.name,
+            });
+        }
+    }
+
+    busy
+}
+
+/// Keep an eye on what's running in every terminal, emitting
+/// `foreground-process-changed` (and `session-process-changed`, with its
+/// icon) whenever it's something new, and taking another look at its
+/// environment then.
+pub fn spawn_tracker(app: AppHandle) {
+    async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<AppState>();
+            let terminals: Vec<_> = state
+                .terminals
+                .lock()
+                .await
+                .iter()
+                .map(|(id, terminal)| (*id, terminal.clone()))
+                .collect();
+
+            let mut seen = HashMap::new();
+            for (terminal_id, terminal) in &terminals {
+                if let Some(process) = foreground_process(terminal).await {
+                    seen.insert(*terminal_id, process);
+                }
+            }
+
+            let mut current = state.foreground_process.lock().await;
+            let icons = state.process_icons.lock().await;
+            for (terminal_id, process) in &seen {
+                if current.get(terminal_id) == Some(process) {
+                    continue;
+                }
+
+                if let Some((_, terminal)) = terminals.iter().find(|(id, _)| id == terminal_id) {
+                    environment::snapshot(&state, terminal).await;
+                }
+
+                let changed = ForegroundProcessChanged {
+                    session_id: *terminal_id,
+                    pid: process.pid,
+                    name: process.name.clone(),
+                };
+
+                if let Err(err) = app.emit("foreground-process-changed", changed) {
+                    eprintln!("could not send foreground process: {err}");
+                }
+                process_icons::emit_changed(&app, &icons, *terminal_id, &process.name);
+            }
+
+            // terminals that have closed are dropped along the way
+            *current = seen;
+        }
+    });
+}
+
+/// Hold off on closing the window while something other than a shell is
+/// running, sending `window://close-requested` with what's running instead so
+/// the frontend can ask first. Returns whether the close was stopped.
+pub fn close_blocked(window: &Window, event: &WindowEvent) -> bool {
+    let WindowEvent::CloseRequested { api, .. } = event else {
+        return false;
+    };
+
+    let state = window.state::<AppState>();
+    // the frontend already asked, this is the close it asked for
+    if state.close_confirmed.swap(false, Ordering::Relaxed) || !state.confirm_close.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    // same as saving the session, a spawned task could be too late
+    let busy = async_runtime::block_on(busy_terminals(&state, window.label()));
+    if busy.is_empty() {
+        return false;
+    }
+
+    api.prevent_close();
+    if let Err(err) = window.emit_to(window.label(), "window://close-requested", busy) {
+        eprintln!("could not ask to close: {err}");
+        return false;
+    }
+
+    true
+}
+
+/// What's in the foreground of terminal `terminal_id`, which is the shell
+/// itself when nothing's running. Always `None` on windows.
+#[tauri::command]
+pub async fn async_get_foreground_process(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Option<ForegroundProcess>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    Ok(foreground_process(&terminal).await)
+}
+
+/// Close the window after all, once the user's said to over `window://close-requested`.
+#[tauri::command]
+pub async fn async_confirm_close(window: Window, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.close_confirmed.store(true, Ordering::Relaxed);
+    window.close()?;
+
+    Ok(())
+}
+
+/// Whether closing the window asks first when something's still running.
+#[tauri::command]
+pub async fn async_set_confirm_close(enabled: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.confirm_close.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
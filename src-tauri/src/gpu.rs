@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuMemoryInfo {
+    pub used_mb: f64,
+    pub total_mb: f64,
+    pub renderer: String,
+}
+
+#[derive(Clone, Serialize)]
+struct GpuMemoryUpdated {
+    used_mb: f64,
+    total_mb: f64,
+}
+
+fn to_mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// NVIDIA cards, through the driver's management library. Works on linux and
+/// windows, as long as the proprietary driver is installed.
+#[cfg(any(target_os = "linux", windows))]
+fn nvml_memory_usage() -> Option<GpuMemoryInfo> {
+    use std::sync::OnceLock;
+
+    use nvml_wrapper::Nvml;
+
+    // loading nvml is slow, and it fails the same way every time on machines without it
+    static NVML: OnceLock<Option<Nvml>> = OnceLock::new();
+
+    let nvml = NVML.get_or_init(|| Nvml::init().ok()).as_ref()?;
+    let device = nvml.device_by_index(0).ok()?;
+    let memory = device.memory_info().ok()?;
+
+    Some(GpuMemoryInfo {
+        used_mb: to_mb(memory.used),
+        total_mb: to_mb(memory.total),
+        renderer: device.name().unwrap_or_else(|_| "nvidia".to_string()),
+    })
+}
+
+/// Drivers that report vram through sysfs, which is mainly amdgpu.
+#[cfg(target_os = "linux")]
+fn drm_memory_usage() -> Option<GpuMemoryInfo> {
+    use std::fs;
+
+    let read_bytes = |path: std::path::PathBuf| fs::read_to_string(path).ok()?.trim().parse::<u64>().ok();
+
+    fs::read_dir("/sys/class/drm").ok()?.flatten().find_map(|entry| {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        // skip connectors like card0-HDMI-A-1
+        if !name.starts_with("card") || name.contains('-') {
+            return None;
+        }
+
+        let device = entry.path().join("device");
+        let used = read_bytes(device.join("mem_info_vram_used"))?;
+        let total = read_bytes(device.join("mem_info_vram_total"))?;
+
+        let renderer = fs::read_to_string(device.join("uevent"))
+            .ok()
+            .and_then(|uevent| {
+                uevent
+                    .lines()
+                    .find_map(|line| line.strip_prefix("DRIVER=").map(str::to_string))
+            })
+            .unwrap_or_else(|| name.to_string());
+
+        Some(GpuMemoryInfo {
+            used_mb: to_mb(used),
+            total_mb: to_mb(total),
+            renderer,
+        })
+    })
+}
+
+/// Memory usage of the first gpu we know how to ask, if any.
+pub fn gpu_memory_usage() -> Option<GpuMemoryInfo> {
+    #[cfg(any(target_os = "linux", windows))]
+    if let Some(info) = nvml_memory_usage() {
+        return Some(info);
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(info) = drm_memory_usage() {
+        return Some(info);
+    }
+
+    None
+}
+
+#[tauri::command]
+pub async fn async_get_gpu_memory_usage() -> Result<Option<GpuMemoryInfo>, SteppeError> {
+    Ok(gpu_memory_usage())
+}
+
+/// Emit `gpu-memory-updated` every `interval_ms`, replacing any monitor that was already running.
+#[tauri::command]
+pub async fn async_start_gpu_monitoring(
+    interval_ms: u64,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if interval_ms == 0 {
+        return Err(SteppeError::InvalidConfig("gpu monitoring interval must be above 0".into()));
+    }
+
+    let task = async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+
+        loop {
+            interval.tick().await;
+
+            if let Some(GpuMemoryInfo { used_mb, total_mb, .. }) = gpu_memory_usage() {
+                if app.emit("gpu-memory-updated", GpuMemoryUpdated { used_mb, total_mb }).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    if let Some(previous) = state.gpu_monitor.lock().await.replace(task) {
+        previous.abort();
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_stop_gpu_monitoring(state: State<'_, AppState>) -> Result<(), SteppeError> {
+    if let Some(task) = state.gpu_monitor.lock().await.take() {
+        task.abort();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,858 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{Cursor, Read, Write},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::ZlibDecoder;
+use image::{DynamicImage, ImageFormat, ImageReader, RgbImage, RgbaImage};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    error::SteppeError,
+    identity::{self, Query},
+    AppState, SubTerminal,
+};
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+const ENQ: u8 = 0x05;
+
+/// The biggest image a program can send, unless config says otherwise
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// How long any other sequence can get whatever the image limit is, so a
+/// small one doesn't cut off titles or what's being copied (OSC 52)
+const MIN_SEQUENCE_LEN: usize = 2 * 1024 * 1024;
+
+/// Longer than any query `identity` answers, so the rest just goes on through
+const MAX_CSI_LEN: usize = 32;
+
+/// The most pixels an image can have, the same as xterm's image addon draws.
+/// A small png or sixel can say it's far bigger than that.
+const MAX_IMAGE_PIXELS: u64 = 4096 * 4096;
+
+/// Images kept around for kitty's `a=p` to show again
+const MAX_STORED_IMAGES: usize = 32;
+
+#[derive(Debug, Default, Clone, Copy)]
+enum ScanState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    /// Inside an APC, and `escape` once an ESC's been seen (the start of ST)
+    Apc {
+        escape: bool,
+    },
+    /// Inside a DCS, same as an APC
+    Dcs {
+        escape: bool,
+    },
+    /// Inside an OSC, which can end with BEL too
+    Osc {
+        escape: bool,
+    },
+}
+
+impl ScanState {
+    /// What comes after the ESC that starts the string being read
+    fn introducer(self) -> u8 {
+        match self {
+            Self::Dcs { .. } => b'P',
+            Self::Osc { .. } => b']',
+            _ => b'_',
+        }
+    }
+
+    /// The same string, once an ESC's been seen in it
+    fn escaped(self) -> Self {
+        match self {
+            Self::Dcs { .. } => Self::Dcs { escape: true },
+            Self::Osc { .. } => Self::Osc { escape: true },
+            _ => Self::Apc { escape: true },
+        }
+    }
+}
+
+/// Pty output once the image protocols are pulled out of it
+#[derive(Debug)]
+pub enum Chunk {
+    /// Everything else, which goes to the screen as usual
+    Output(Vec<u8>),
+    /// A whole sixel DCS, from `ESC P` to `ESC \`
+    Sixel(Vec<u8>),
+    /// What's between a kitty graphics APC's `ESC _` and `ESC \`, starting with `G`
+    Kitty(Vec<u8>),
+    /// A whole iterm inline image OSC, from `ESC ] 1337;File=` to BEL or `ESC \`
+    Iterm(Vec<u8>),
+    /// Asking what the terminal is, or where its cursor is
+    Query(Query),
+}
+
+/// Placement and size keys from a kitty graphics command, see
+/// https://sw.kovidgoyal.net/kitty/graphics-protocol/#control-data-reference
+#[derive(Debug, Clone, Copy)]
+struct KittyControls {
+    /// `a`: `t` to transmit, `T` to transmit and show, `p` to show a stored image, `q` to query
+    action: u8,
+    /// `f`: 24 (rgb), 32 (rgba) or 100 (png)
+    format: u32,
+    /// `t`: `d` for the data itself, `f` for a file, `t` for a temporary file
+    medium: u8,
+    /// `o`: `z` for zlib
+    compressed: bool,
+    /// `s` and `v`, for raw pixels
+    width: u32,
+    height: u32,
+    /// `i`
+    id: u32,
+    /// `m`: more chunks of this image are coming
+    more: bool,
+    /// `q`: 1 to leave out OK replies, 2 to leave out errors too
+    quiet: u32,
+    /// `c` and `r`: how many cells to stretch the image over
+    columns: Option<u32>,
+    rows: Option<u32>,
+}
+
+impl Default for KittyControls {
+    fn default() -> Self {
+        Self {
+            action: b't',
+            format: 32,
+            medium: b'd',
+            compressed: false,
+            width: 0,
+            height: 0,
+            id: 0,
+            more: false,
+            quiet: 0,
+            columns: None,
+            rows: None,
+        }
+    }
+}
+
+impl KittyControls {
+    fn parse(controls: &[u8]) -> Self {
+        let mut parsed = Self::default();
+
+        for pair in controls.split(|byte| *byte == b',') {
+            let [key, b'=', value @ ..] = pair else {
+                continue;
+            };
+            let number = || std::str::from_utf8(value).ok()?.parse::<u32>().ok();
+
+            match key {
+                b'a' => parsed.action = value.first().copied().unwrap_or(b't'),
+                b'f' => parsed.format = number().unwrap_or(32),
+                b't' => parsed.medium = value.first().copied().unwrap_or(b'd'),
+                b'o' => parsed.compressed = value == b"z",
+                b's' => parsed.width = number().unwrap_or(0),
+                b'v' => parsed.height = number().unwrap_or(0),
+                b'i' => parsed.id = number().unwrap_or(0),
+                b'm' => parsed.more = value == b"1",
+                b'q' => parsed.quiet = number().unwrap_or(0),
+                b'c' => parsed.columns = number(),
+                b'r' => parsed.rows = number(),
+                _ => {}
+            }
+        }
+
+        parsed
+    }
+}
+
+/// An image for the frontend to draw at the cursor. Both protocols go out as
+/// something xterm's image addon draws: sixel as it came, and kitty as an
+/// iterm inline image.
+#[derive(Clone, Serialize)]
+struct GraphicsOutput {
+    terminal_id: u32,
+    protocol: &'static str,
+    data: String,
+    /// Where the cursor was on the backend's screen when the image came in
+    row: usize,
+    col: usize,
+    /// How many cells the program asked for the image to cover, if it did
+    columns: Option<u32>,
+    rows: Option<u32>,
+    /// The image's size in pixels, where it's known
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+/// Pulls kitty graphics and sixel out of a terminal's output before it's
+/// decoded as utf-8, and answers the queries programs send to see whether
+/// they're supported. Sequences can be split across reads, so this lives as
+/// long as the terminal's reader.
+#[derive(Debug, Default)]
+pub struct Graphics {
+    state: ScanState,
+    sequence: Vec<u8>,
+    /// Set once the sequence being read is longer than `max_len` allows
+    too_long: bool,
+    /// `maxImageBytes`, as of the last read
+    max_image_bytes: usize,
+    /// The controls and payload of a kitty image still coming in chunks
+    pending_kitty: Option<(KittyControls, Vec<u8>)>,
+    /// Kitty images that were given an id, as png, oldest first
+    stored: VecDeque<(u32, Vec<u8>)>,
+}
+
+fn is_sixel(dcs: &[u8]) -> bool {
+    // `ESC P <params> q`, where the params are numbers and semicolons
+    let params = dcs
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit() || **byte == b';')
+        .count();
+    dcs.get(params) == Some(&b'q')
+}
+
+/// The width and height a sixel image gives in its raster attributes
+/// (`"Pan;Pad;Ph;Pv`), if it starts with them
+fn sixel_size(dcs: &[u8]) -> Option<(u32, u32)> {
+    let start = dcs.iter().position(|byte| *byte == b'q')? + 1;
+    let attributes = dcs[start..].strip_prefix(b"\"")?;
+    let len = attributes
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit() || **byte == b';')
+        .count();
+
+    let numbers: Option<Vec<u32>> = std::str::from_utf8(&attributes[..len])
+        .ok()?
+        .split(';')
+        .map(|number| number.parse().ok())
+        .collect();
+    let [_, _, width, height] = numbers?[..] else {
+        return None;
+    };
+
+    Some((width, height))
+}
+
+fn too_many_pixels(width: u32, height: u32) -> bool {
+    u64::from(width) * u64::from(height) > MAX_IMAGE_PIXELS
+}
+
+/// An image's width and height in pixels, from its header
+fn dimensions(data: &[u8]) -> Result<(u32, u32), SteppeError> {
+    Ok(ImageReader::new(Cursor::new(data))
+        .with_guessed_format()?
+        .into_dimensions()?)
+}
+
+/// What an iterm inline image asks for, see https://iterm2.com/documentation-images.html
+#[derive(Debug, Default)]
+struct ItermImage {
+    /// `width` and `height`, when they're a number of cells rather than
+    /// pixels, a percentage or `auto`
+    columns: Option<u32>,
+    rows: Option<u32>,
+    /// The size of the image itself
+    width: u32,
+    height: u32,
+}
+
+/// Check an iterm inline image over, or `None` for a file that's being sent
+/// to be downloaded rather than shown
+fn iterm_image(sequence: &[u8], max_bytes: usize) -> Result<Option<ItermImage>, SteppeError> {
+    let body = sequence.strip_prefix(b"\x1b]1337;File=").unwrap_or(sequence);
+    let body = body
+        .strip_suffix(&[BEL])
+        .or_else(|| body.strip_suffix(b"\x1b\\"))
+        .unwrap_or(body);
+    let Some(colon) = body.iter().position(|byte| *byte == b':') else {
+        return Err(SteppeError::InvalidImage(
+            "there's nothing after the arguments".to_string(),
+        ));
+    };
+    let (args, payload) = (&body[..colon], &body[colon + 1..]);
+
+    let mut image = ItermImage::default();
+    let mut inline = false;
+    for arg in String::from_utf8_lossy(args).split(';') {
+        let Some((key, value)) = arg.split_once('=') else {
+            continue;
+        };
+
+        match key {
+            "inline" => inline = value == "1",
+            "width" => image.columns = value.parse().ok(),
+            "height" => image.rows = value.parse().ok(),
+            _ => {}
+        }
+    }
+    if !inline {
+        return Ok(None);
+    }
+
+    // some versions of imgcat wrap their base64
+    let payload: Vec<u8> = payload
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    let len = payload.len() / 4 * 3;
+    if len > max_bytes {
+        return Err(SteppeError::ImageTooLarge(len, max_bytes));
+    }
+
+    let data = STANDARD
+        .decode(&payload)
+        .map_err(|err| SteppeError::InvalidImage(err.to_string()))?;
+    (image.width, image.height) = dimensions(&data)?;
+    if too_many_pixels(image.width, image.height) {
+        return Err(SteppeError::InvalidImage(format!(
+            "it's {}x{}, more than the {MAX_IMAGE_PIXELS} pixels allowed",
+            image.width, image.height
+        )));
+    }
+
+    Ok(Some(image))
+}
+
+impl Graphics {
+    fn start(&mut self, state: ScanState) {
+        self.state = state;
+        self.sequence.clear();
+        self.too_long = false;
+    }
+
+    /// The byte after an ESC, which might start a sequence worth pulling out
+    fn escape(&mut self, byte: u8, output: &mut Vec<u8>) {
+        match byte {
+            b'_' => self.start(ScanState::Apc { escape: false }),
+            b'P' => self.start(ScanState::Dcs { escape: false }),
+            b']' => self.start(ScanState::Osc { escape: false }),
+            b'[' => self.start(ScanState::Csi),
+            // the first one was just an ESC on its own
+            ESC => output.push(ESC),
+            _ => {
+                output.extend([ESC, byte]);
+                self.state = ScanState::Ground;
+            }
+        }
+    }
+
+    /// Put a CSI that isn't a query back where it was
+    fn pass_through_csi(&mut self, output: &mut Vec<u8>) {
+        self.state = ScanState::Ground;
+        output.extend([ESC, b'[']);
+        output.extend_from_slice(&self.sequence);
+    }
+
+    /// The longest a sequence gets before it's dropped: an image as big as
+    /// `maxImageBytes`, once it's in base64
+    fn max_len(&self) -> usize {
+        (self.max_image_bytes / 3 * 4).max(MIN_SEQUENCE_LEN)
+    }
+
+    fn finish_string(&mut self, terminator: &[u8], output: &mut Vec<u8>, chunks: &mut Vec<Chunk>) {
+        let introducer = self.state.introducer();
+        self.state = ScanState::Ground;
+        let sequence = std::mem::take(&mut self.sequence);
+        let whole = |sequence: &[u8]| [&[ESC, introducer][..], sequence, terminator].concat();
+
+        let chunk = match introducer {
+            _ if self.too_long => None,
+            b'_' if sequence.first() == Some(&b'G') => Some(Chunk::Kitty(sequence)),
+            b'P' if is_sixel(&sequence) => Some(Chunk::Sixel(whole(&sequence))),
+            b']' if sequence.starts_with(b"1337;File=") => Some(Chunk::Iterm(whole(&sequence))),
+            _ => {
+                output.extend(whole(&sequence));
+                None
+            }
+        };
+
+        if let Some(chunk) = chunk {
+            chunks.push(Chunk::Output(std::mem::take(output)));
+            chunks.push(chunk);
+        }
+    }
+
+    /// Split `bytes` into the output that goes on as usual and the image
+    /// sequences and queries that don't, in the order they came. Images
+    /// bigger than `max_image_bytes` are dropped.
+    pub fn split(&mut self, bytes: &[u8], max_image_bytes: usize) -> Vec<Chunk> {
+        self.max_image_bytes = max_image_bytes;
+        let mut chunks = Vec::new();
+        let mut output = Vec::with_capacity(bytes.len());
+
+        for &byte in bytes {
+            match self.state {
+                ScanState::Ground if byte == ESC => self.state = ScanState::Escape,
+                ScanState::Ground if byte == ENQ => {
+                    chunks.push(Chunk::Output(std::mem::take(&mut output)));
+                    chunks.push(Chunk::Query(Query::Enquiry));
+                }
+                ScanState::Ground => output.push(byte),
+                ScanState::Escape => self.escape(byte, &mut output),
+                ScanState::Csi => {
+                    self.sequence.push(byte);
+
+                    if let Some(query) = Query::from_csi(&self.sequence) {
+                        self.state = ScanState::Ground;
+                        chunks.push(Chunk::Output(std::mem::take(&mut output)));
+                        chunks.push(Chunk::Query(query));
+                    } else if (0x40..=0x7e).contains(&byte) || self.sequence.len() > MAX_CSI_LEN {
+                        // anything else, or far too long to be a query, is the screen's to deal with
+                        self.pass_through_csi(&mut output);
+                    }
+                }
+                ScanState::Apc { escape: true } | ScanState::Dcs { escape: true } | ScanState::Osc { escape: true } => {
+                    if byte == b'\\' {
+                        self.finish_string(b"\x1b\\", &mut output, &mut chunks);
+                    } else {
+                        // an ESC that isn't ST cancels the string and starts something else
+                        self.state = ScanState::Escape;
+                        self.escape(byte, &mut output);
+                    }
+                }
+                ScanState::Osc { escape: false } if byte == BEL => {
+                    self.finish_string(&[BEL], &mut output, &mut chunks);
+                }
+                ScanState::Apc { .. } | ScanState::Dcs { .. } | ScanState::Osc { .. } if byte == ESC => {
+                    self.state = self.state.escaped();
+                }
+                ScanState::Apc { .. } | ScanState::Dcs { .. } | ScanState::Osc { .. } => {
+                    if self.sequence.len() < self.max_len() {
+                        self.sequence.push(byte);
+                    } else {
+                        self.too_long = true;
+                    }
+                }
+            }
+        }
+
+        chunks.push(Chunk::Output(output));
+        chunks.retain(|chunk| !matches!(chunk, Chunk::Output(output) if output.is_empty()));
+
+        chunks
+    }
+
+    /// Turn a kitty image into a png, from wherever it was sent
+    fn load_kitty(
+        controls: &KittyControls,
+        payload: &[u8],
+        allow_files: bool,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, String> {
+        let too_large = || format!("EFBIG:images can be up to {max_bytes} bytes");
+        let data = STANDARD
+            .decode(payload)
+            .map_err(|_| "EINVAL:the payload isn't base64".to_string())?;
+
+        let data = match controls.medium {
+            b'd' => data,
+            b'f' | b't' if allow_files => {
+                let path = String::from_utf8(data).map_err(|_| "EINVAL:the path isn't utf-8".to_string())?;
+                if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > max_bytes as u64) {
+                    return Err(too_large());
+                }
+                let contents = fs::read(&path).map_err(|err| format!("EBADF:{err}"))?;
+
+                // kitty only cleans up temporary files that say they're for this
+                if controls.medium == b't' && path.contains("tty-graphics-protocol") {
+                    let _ = fs::remove_file(&path);
+                }
+
+                contents
+            }
+            _ => return Err("EINVAL:unsupported transmission medium".to_string()),
+        };
+
+        let data = if controls.compressed {
+            let mut inflated = Vec::new();
+            // one more byte than's allowed, to tell a whole image from a cut off one
+            ZlibDecoder::new(data.as_slice())
+                .take(max_bytes as u64 + 1)
+                .read_to_end(&mut inflated)
+                .map_err(|err| format!("EINVAL:{err}"))?;
+            inflated
+        } else {
+            data
+        };
+        if data.len() > max_bytes {
+            return Err(too_large());
+        }
+
+        let (width, height) = match controls.format {
+            100 => dimensions(&data).map_err(|err| format!("EINVAL:{err}"))?,
+            _ => (controls.width, controls.height),
+        };
+        if too_many_pixels(width, height) {
+            return Err(format!("EFBIG:images can be up to {MAX_IMAGE_PIXELS} pixels"));
+        }
+
+        let image = match controls.format {
+            100 => return Ok(data),
+            24 => RgbImage::from_raw(controls.width, controls.height, data).map(DynamicImage::ImageRgb8),
+            32 => RgbaImage::from_raw(controls.width, controls.height, data).map(DynamicImage::ImageRgba8),
+            _ => return Err("EINVAL:unsupported format".to_string()),
+        }
+        .ok_or_else(|| "EINVAL:the data doesn't match the image's size".to_string())?;
+
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|err| format!("EINVAL:{err}"))?;
+
+        Ok(png.into_inner())
+    }
+
+    /// Act on a kitty graphics command, returning the png to show (if any)
+    /// and what to tell the program (if anything)
+    fn kitty(&mut self, command: &[u8], allow_files: bool) -> (Option<(Vec<u8>, KittyControls)>, Option<String>) {
+        let command = &command[1..];
+        let (controls, payload) = match command.iter().position(|byte| *byte == b';') {
+            Some(split) => (&command[..split], &command[split + 1..]),
+            None => (command, &[][..]),
+        };
+        let controls = KittyControls::parse(controls);
+
+        // later chunks only say whether there's more, the rest is from the first one
+        let (controls, payload) = match self.pending_kitty.take() {
+            Some((first, mut data)) => {
+                data.extend_from_slice(payload);
+                (
+                    KittyControls {
+                        more: controls.more,
+                        ..first
+                    },
+                    data,
+                )
+            }
+            None => (controls, payload.to_vec()),
+        };
+        if controls.more {
+            if payload.len() < self.max_len() {
+                self.pending_kitty = Some((controls, payload));
+            }
+            return (None, None);
+        }
+        let max_bytes = self.max_image_bytes;
+
+        let result = match controls.action {
+            b'q' => Self::load_kitty(&controls, &payload, allow_files, max_bytes).map(|_| None),
+            b't' | b'T' => Self::load_kitty(&controls, &payload, allow_files, max_bytes).map(|png| {
+                if controls.id != 0 {
+                    self.stored.retain(|(id, _)| *id != controls.id);
+                    if self.stored.len() == MAX_STORED_IMAGES {
+                        self.stored.pop_front();
+                    }
+                    self.stored.push_back((controls.id, png.clone()));
+                }
+
+                (controls.action == b'T').then_some(png)
+            }),
+            b'p' => self
+                .stored
+                .iter()
+                .find(|(id, _)| *id == controls.id)
+                .map(|(_, png)| Some(png.clone()))
+                .ok_or_else(|| "ENOENT:no image with that id".to_string()),
+            // deleting and animating, which there's nothing to do with here
+            _ => Ok(None),
+        };
+
+        // kitty only answers commands that gave an id
+        let reply = match &result {
+            _ if controls.id == 0 => None,
+            Ok(_) if controls.quiet >= 1 => None,
+            Err(_) if controls.quiet >= 2 => None,
+            Ok(_) => Some(format!("\x1b_Gi={};OK\x1b\\", controls.id)),
+            Err(err) => Some(format!("\x1b_Gi={};{err}\x1b\\", controls.id)),
+        };
+
+        (result.ok().flatten().map(|png| (png, controls)), reply)
+    }
+
+    /// Answer a query, or send an image on to the frontend
+    pub async fn handle(
+        &mut self,
+        app: &AppHandle,
+        terminal_id: u32,
+        terminal: &SubTerminal,
+        chunk: Chunk,
+    ) -> Result<(), SteppeError> {
+        let (protocol, data, columns, rows, size) = match chunk {
+            Chunk::Output(_) => return Ok(()),
+            Chunk::Query(query) => return identity::answer(&app.state::<AppState>(), terminal, query).await,
+            Chunk::Sixel(sixel) => {
+                if sixel.len() > self.max_image_bytes {
+                    return Err(SteppeError::ImageTooLarge(sixel.len(), self.max_image_bytes));
+                }
+                let size = sixel_size(&sixel);
+                if let Some((width, height)) = size.filter(|(width, height)| too_many_pixels(*width, *height)) {
+                    return Err(SteppeError::InvalidImage(format!(
+                        "it says it's {width}x{height}, more than the {MAX_IMAGE_PIXELS} pixels allowed"
+                    )));
+                }
+
+                ("sixel", String::from_utf8_lossy(&sixel).into_owned(), None, None, size)
+            }
+            Chunk::Iterm(sequence) => {
+                let Some(image) = iterm_image(&sequence, self.max_image_bytes)? else {
+                    return Ok(());
+                };
+
+                // the image addon draws these itself, so they go on as they came
+                (
+                    "iterm",
+                    String::from_utf8_lossy(&sequence).into_owned(),
+                    image.columns,
+                    image.rows,
+                    Some((image.width, image.height)),
+                )
+            }
+            Chunk::Kitty(command) => {
+                // a program on the other end of ssh has no business reading local files
+                let allow_files = !terminal.shell.get().is_some_and(|shell| shell.ssh_profile.is_some());
+                let (image, reply) = self.kitty(&command, allow_files);
+
+                if let Some(reply) = reply {
+                    terminal.writer.lock().await.write_all(reply.as_bytes())?;
+                }
+                let Some((png, controls)) = image else {
+                    return Ok(());
+                };
+
+                let mut inline = format!("\x1b]1337;File=inline=1;size={}", png.len());
+                if let Some(columns) = controls.columns {
+                    inline.push_str(&format!(";width={columns}"));
+                }
+                if let Some(rows) = controls.rows {
+                    inline.push_str(&format!(";height={rows}"));
+                }
+                inline.push(':');
+                inline.push_str(&STANDARD.encode(&png));
+                inline.push('\x07');
+
+                let size = dimensions(&png).ok();
+                ("kitty", inline, controls.columns, controls.rows, size)
+            }
+        };
+
+        let (row, col) = terminal.screen.lock().await.cursor();
+        app.emit(
+            "terminal://graphics",
+            GraphicsOutput {
+                terminal_id,
+                protocol,
+                data,
+                row,
+                col,
+                columns,
+                rows,
+                width: size.map(|(width, _)| width),
+                height: size.map(|(_, height)| height),
+            },
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::Crc;
+
+    use super::*;
+
+    fn graphics() -> Graphics {
+        Graphics {
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            ..Graphics::default()
+        }
+    }
+
+    /// The start of a png that says it's `width` by `height`, with no pixels
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        fn chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+            let mut crc = Crc::new();
+            crc.update(kind);
+            crc.update(data);
+
+            png.extend((data.len() as u32).to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            png.extend(crc.sum().to_be_bytes());
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let header = [&width.to_be_bytes()[..], &height.to_be_bytes(), &[8, 6, 0, 0, 0]].concat();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &[]);
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    fn reply(graphics: &mut Graphics, command: &str) -> Option<String> {
+        graphics.kitty(command.as_bytes(), false).1
+    }
+
+    #[test]
+    fn pulls_images_out_across_reads() {
+        let mut graphics = graphics();
+
+        let chunks = graphics.split(b"ab\x1b_Ga=T;AAAA", DEFAULT_MAX_IMAGE_BYTES);
+        assert!(matches!(&chunks[..], [Chunk::Output(output)] if output == b"ab"));
+        // ST split across reads too
+        assert!(graphics.split(b"AAAA\x1b", DEFAULT_MAX_IMAGE_BYTES).is_empty());
+        let chunks = graphics.split(b"\\cd", DEFAULT_MAX_IMAGE_BYTES);
+        assert!(matches!(
+            &chunks[..],
+            [Chunk::Kitty(command), Chunk::Output(output)] if command == b"Ga=T;AAAAAAAA" && output == b"cd"
+        ));
+
+        let chunks = graphics.split(b"\x1bPq#0~-\x1b\\", DEFAULT_MAX_IMAGE_BYTES);
+        assert!(matches!(&chunks[..], [Chunk::Sixel(sixel)] if sixel == b"\x1bPq#0~-\x1b\\"));
+    }
+
+    #[test]
+    fn leaves_other_strings_alone() {
+        let mut graphics = graphics();
+
+        for string in [&b"\x1b_not kitty\x1b\\"[..], b"\x1bP+q544e\x1b\\", b"\x1b]0;title\x07"] {
+            let chunks = graphics.split(string, DEFAULT_MAX_IMAGE_BYTES);
+            assert!(
+                matches!(&chunks[..], [Chunk::Output(output)] if output == string),
+                "{string:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn drops_unterminated_and_oversized_strings() {
+        let mut graphics = graphics();
+
+        // an ESC that isn't ST cancels the image, and what it starts goes on through
+        let chunks = graphics.split(b"\x1b_Ga=T;AAAA\x1b[31mx", DEFAULT_MAX_IMAGE_BYTES);
+        assert!(matches!(&chunks[..], [Chunk::Output(output)] if output == b"\x1b[31mx"));
+
+        let mut long = b"\x1b_Ga=T;".to_vec();
+        long.resize(long.len() + MIN_SEQUENCE_LEN + 1, b'A');
+        long.extend(b"\x1b\\ok");
+        let chunks = graphics.split(&long, 0);
+        assert!(matches!(&chunks[..], [Chunk::Output(output)] if output == b"ok"));
+    }
+
+    #[test]
+    fn reads_sixel_sizes() {
+        assert!(is_sixel(b"q#0~"));
+        assert!(is_sixel(b"0;1;0q#0~"));
+        assert!(!is_sixel(b"+q544e"));
+        assert!(!is_sixel(b""));
+
+        assert_eq!(sixel_size(b"\x1bPq\"1;1;20;10#0~\x1b\\"), Some((20, 10)));
+        assert_eq!(sixel_size(b"\x1bP0;1q\"1;1;100000;100000#0~"), Some((100000, 100000)));
+        assert_eq!(sixel_size(b"\x1bPq#0~\x1b\\"), None);
+        assert_eq!(sixel_size(b"\x1bPq\"1;1;20"), None);
+        assert_eq!(sixel_size(b"\x1bPq\"1;1;99999999999;1"), None);
+        assert!(too_many_pixels(100000, 100000));
+        assert!(!too_many_pixels(4096, 4096));
+    }
+
+    #[test]
+    fn parses_broken_kitty_controls() {
+        let controls = KittyControls::parse(b"a=T,f=zz,s=,=,x,v=4294967296,i=3,q=");
+
+        assert_eq!(controls.action, b'T');
+        assert_eq!(controls.format, 32);
+        assert_eq!((controls.width, controls.height), (0, 0));
+        assert_eq!(controls.id, 3);
+        assert_eq!(controls.quiet, 0);
+    }
+
+    #[test]
+    fn refuses_oversized_kitty_images() {
+        let mut graphics = graphics();
+
+        for size in ["s=100000,v=100000", "s=4294967295,v=4294967295"] {
+            let reply = reply(&mut graphics, &format!("Ga=T,f=24,{size},i=1;AAAA")).unwrap_or_default();
+            assert!(reply.starts_with("\x1b_Gi=1;EFBIG:"), "{size}: {reply:?}");
+        }
+
+        let png = STANDARD.encode(png_header(5000, 5000));
+        let reply = reply(&mut graphics, &format!("Ga=T,f=100,i=2;{png}")).unwrap_or_default();
+        assert!(reply.starts_with("\x1b_Gi=2;EFBIG:"), "{reply:?}");
+    }
+
+    #[test]
+    fn refuses_malformed_kitty_images() {
+        let mut graphics = graphics();
+
+        let replies = [
+            (
+                "Ga=T,f=24,s=2,v=2,i=1;AAAAAAAA",
+                "EINVAL:the data doesn't match the image's size",
+            ),
+            ("Ga=T,i=1;!!!!", "EINVAL:the payload isn't base64"),
+            ("Ga=T,f=7,s=1,v=1,i=1;AAAA", "EINVAL:unsupported format"),
+            ("Ga=T,t=s,i=1;AAAA", "EINVAL:unsupported transmission medium"),
+            // files aren't for programs over ssh
+            (
+                "Ga=T,t=f,i=1;L2V0Yy9wYXNzd2Q=",
+                "EINVAL:unsupported transmission medium",
+            ),
+            ("Ga=p,i=1", "ENOENT:no image with that id"),
+        ];
+        for (command, error) in replies {
+            assert_eq!(reply(&mut graphics, command), Some(format!("\x1b_Gi=1;{error}\x1b\\")));
+        }
+
+        // without an id there's nobody to tell
+        assert_eq!(reply(&mut graphics, "Ga=T;!!!!"), None);
+        assert_eq!(reply(&mut graphics, "G"), None);
+    }
+
+    #[test]
+    fn joins_chunked_kitty_images() {
+        let mut graphics = graphics();
+
+        assert_eq!(graphics.kitty(b"Ga=T,f=24,s=2,v=1,i=7,m=1;AAAA", false).1, None);
+        let (image, reply) = graphics.kitty(b"Gm=0;AAAA", false);
+
+        let (png, _) = image.expect("the image is whole");
+        assert_eq!(dimensions(&png).ok(), Some((2, 1)));
+        assert_eq!(reply.as_deref(), Some("\x1b_Gi=7;OK\x1b\\"));
+    }
+
+    #[test]
+    fn checks_iterm_images() {
+        let iterm = |args: &str, payload: &[u8]| format!("\x1b]1337;File={args}:{}\x07", STANDARD.encode(payload));
+
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::new(3, 2).write_to(&mut png, ImageFormat::Png).unwrap();
+        let png = png.into_inner();
+
+        let image = iterm_image(iterm("inline=1;width=4", &png).as_bytes(), DEFAULT_MAX_IMAGE_BYTES).unwrap();
+        let image = image.expect("it's inline");
+        assert_eq!((image.width, image.height, image.columns), (3, 2, Some(4)));
+
+        assert!(matches!(
+            iterm_image(iterm("name=eC50eHQ=", &png).as_bytes(), 1024),
+            Ok(None)
+        ));
+        assert!(matches!(
+            iterm_image(iterm("inline=1", &png).as_bytes(), 8),
+            Err(SteppeError::ImageTooLarge(..))
+        ));
+        assert!(matches!(
+            iterm_image(
+                iterm("inline=1", &png_header(5000, 5000)).as_bytes(),
+                DEFAULT_MAX_IMAGE_BYTES
+            ),
+            Err(SteppeError::InvalidImage(_))
+        ));
+        assert!(iterm_image(iterm("inline=1", b"not an image").as_bytes(), DEFAULT_MAX_IMAGE_BYTES).is_err());
+        assert!(matches!(
+            iterm_image(b"\x1b]1337;File=inline=1\x07", DEFAULT_MAX_IMAGE_BYTES),
+            Err(SteppeError::InvalidImage(_))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{io::Write, process::Command, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::SteppeError, pty_backend, AppState, SubTerminal};
+
+/// The `TERM` that means steppe's own terminfo entry
+pub const STEPPE_TERM: &str = "steppe";
+
+/// Compiled into ~/.terminfo when `term` is `STEPPE_TERM`
+const TERMINFO: &str = include_str!("../terminfo/steppe.terminfo");
+
+/// A VT220 with sixel graphics (4) and ansi color (22), which is what
+/// programs look for before sending sixel
+const DEVICE_ATTRIBUTES: &str = "62;4;22";
+
+/// Queries programs send to find out what they're running in. These are all
+/// answered here, rather than some by xterm.js and some not at all, so every
+/// answer agrees with the others and with `TERM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// ENQ
+    Enquiry,
+    /// DA1, `CSI c`
+    PrimaryDeviceAttributes,
+    /// DA2, `CSI > c`
+    SecondaryDeviceAttributes,
+    /// DSR 5, `CSI 5 n`
+    Status,
+    /// DSR 6, `CSI 6 n`
+    CursorPosition,
+    /// XTVERSION, `CSI > q`
+    Version,
+}
+
+impl Query {
+    /// The query a CSI is, going by what comes after `CSI`
+    pub fn from_csi(sequence: &[u8]) -> Option<Self> {
+        match sequence {
+            b"c" | b"0c" => Some(Self::PrimaryDeviceAttributes),
+            b">c" | b">0c" => Some(Self::SecondaryDeviceAttributes),
+            b"5n" => Some(Self::Status),
+            b"6n" => Some(Self::CursorPosition),
+            b">q" | b">0q" => Some(Self::Version),
+            _ => None,
+        }
+    }
+}
+
+/// What steppe says it is, to programs that ask and in `TERM`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct IdentityOptions {
+    /// What `TERM` is set to, `pty_backend::TERM` if it's unset.
+    /// `STEPPE_TERM` installs steppe's terminfo entry first.
+    pub term: Option<String>,
+    /// What ENQ is answered with. Nothing by default, since a program that
+    /// can choose it can have the terminal type whatever it likes.
+    pub answerback: Option<String>,
+    /// DA1's parameters, like `62;4;22`
+    pub device_attributes: Option<String>,
+    /// DA2's parameters: the terminal's type, version and cartridge (always
+    /// 0), like `1;100;0`
+    pub secondary_device_attributes: Option<String>,
+    /// What XTVERSION's answered with, `steppe(<version>)` by default
+    pub version: Option<String>,
+}
+
+impl IdentityOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(term) = &self.term {
+            if term.is_empty() || !term.chars().all(|c| c.is_ascii_graphic() && c != '=') {
+                return Err(format!("terminalIdentity.term {term:?} isn't a terminal name"));
+            }
+        }
+
+        for (name, params) in [
+            ("deviceAttributes", &self.device_attributes),
+            ("secondaryDeviceAttributes", &self.secondary_device_attributes),
+        ] {
+            if params
+                .as_deref()
+                .is_some_and(|params| !params.chars().all(|c| c.is_ascii_digit() || c == ';'))
+            {
+                return Err(format!("terminalIdentity.{name} can only have digits and `;`"));
+            }
+        }
+
+        for (name, text) in [("answerback", &self.answerback), ("version", &self.version)] {
+            if text.as_deref().is_some_and(|text| text.chars().any(char::is_control)) {
+                return Err(format!("terminalIdentity.{name} can't have control characters"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// This version of steppe as one number, the way DA2 has it: 0.1.0 is 100
+fn version_number() -> u32 {
+    env!("CARGO_PKG_VERSION")
+        .split('.')
+        .take(3)
+        .map(|part| part.parse::<u32>().unwrap_or(0))
+        .fold(0, |number, part| number * 100 + part)
+}
+
+async fn options(state: &AppState) -> IdentityOptions {
+    state
+        .settings
+        .lock()
+        .await
+        .terminal_identity
+        .clone()
+        .unwrap_or_default()
+}
+
+/// What `TERM` is set to for a new shell. An ssh connection gets xterm's
+/// instead of steppe's own, which the other end most likely doesn't have.
+pub async fn term(state: &AppState, ssh: bool) -> String {
+    match options(state).await.term {
+        Some(term) if term == STEPPE_TERM && ssh => pty_backend::TERM.to_string(),
+        Some(term) => term,
+        None => pty_backend::TERM.to_string(),
+    }
+}
+
+/// The answer to `query` in `terminal`, if there is one
+async fn reply(state: &AppState, terminal: &SubTerminal, query: Query) -> Option<String> {
+    let options = options(state).await;
+
+    Some(match query {
+        Query::Enquiry => options.answerback?,
+        Query::PrimaryDeviceAttributes => {
+            let params = options.device_attributes.as_deref().unwrap_or(DEVICE_ATTRIBUTES);
+            format!("\x1b[?{params}c")
+        }
+        Query::SecondaryDeviceAttributes => match options.secondary_device_attributes {
+            Some(params) => format!("\x1b[>{params}c"),
+            None => format!("\x1b[>1;{};0c", version_number()),
+        },
+        Query::Status => "\x1b[0n".to_string(),
+        Query::CursorPosition => {
+            let (row, col) = terminal.screen.lock().await.cursor();
+            format!("\x1b[{};{}R", row + 1, col + 1)
+        }
+        Query::Version => {
+            let version = options
+                .version
+                .unwrap_or_else(|| format!("steppe({})", env!("CARGO_PKG_VERSION")));
+            format!("\x1bP>|{version}\x1b\\")
+        }
+    })
+}
+
+/// Answer `query` from the program in `terminal`
+pub async fn answer(state: &AppState, terminal: &SubTerminal, query: Query) -> Result<(), SteppeError> {
+    if let Some(reply) = reply(state, terminal, query).await {
+        write!(terminal.writer.lock().await, "{reply}")?;
+    }
+
+    Ok(())
+}
+
+/// Compile steppe's terminfo entry into ~/.terminfo, where ncurses looks
+/// first, once per run so it's always this version's
+pub fn install_terminfo() -> Result<(), String> {
+    static INSTALLED: OnceLock<Result<(), String>> = OnceLock::new();
+
+    INSTALLED
+        .get_or_init(|| {
+            let dir = dirs::home_dir()
+                .ok_or("no home directory to install it in")?
+                .join(".terminfo");
+            let source = std::env::temp_dir().join(format!("steppe-{}.terminfo", std::process::id()));
+            std::fs::write(&source, TERMINFO).map_err(|err| err.to_string())?;
+
+            let output = Command::new("tic").arg("-x").arg("-o").arg(&dir).arg(&source).output();
+            let _ = std::fs::remove_file(&source);
+
+            match output {
+                Ok(output) if output.status.success() => Ok(()),
+                Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                Err(err) => Err(format!("could not run tic: {err}")),
+            }
+        })
+        .clone()
+}
@@ -0,0 +1,46 @@
+use std::collections::BTreeSet;
+
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+/// Where input typed into terminal `terminal_id` goes: every terminal in the
+/// broadcast group that's still open if it's in it, otherwise just itself.
+pub async fn targets(state: &AppState, terminal_id: u32) -> Vec<u32> {
+    let group = state.broadcast.lock().await;
+    if !group.contains(&terminal_id) {
+        return vec![terminal_id];
+    }
+
+    let terminals = state.terminals.lock().await;
+    group
+        .iter()
+        .copied()
+        .filter(|id| *id == terminal_id || terminals.contains_key(id))
+        .collect()
+}
+
+/// Send whatever's typed into any of `terminal_ids` to all of them, like
+/// running the same commands on a few servers at once. An empty list stops
+/// broadcasting.
+#[tauri::command]
+pub async fn async_set_broadcast(
+    terminal_ids: Vec<u32>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    for &terminal_id in &terminal_ids {
+        state.terminal(terminal_id).await?;
+    }
+
+    let group: BTreeSet<u32> = terminal_ids.into_iter().collect();
+    *state.broadcast.lock().await = group.clone();
+    app.emit("broadcast://changed", group)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_get_broadcast(state: State<'_, AppState>) -> Result<BTreeSet<u32>, SteppeError> {
+    Ok(state.broadcast.lock().await.clone())
+}
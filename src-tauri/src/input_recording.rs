@@ -0,0 +1,77 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{check_session, error::SteppeError, AppState};
+
+/// Something the user typed, and when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputEvent {
+    /// Since recording started
+    pub offset_ms: u64,
+    pub data: String,
+}
+
+/// Everything the user typed while recording. Unlike a full session
+/// recording this has none of the output, so it can be replayed as-is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedInput {
+    pub events: Vec<InputEvent>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct InputRecorder {
+    started: Instant,
+    events: Vec<InputEvent>,
+}
+
+impl InputRecorder {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> RecordedInput {
+        RecordedInput {
+            events: self.events,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Hang on to `data` if input is being recorded. Called with whatever actually
+/// got written to the pty.
+pub async fn record(state: &AppState, data: &str) {
+    if let Some(recorder) = &mut *state.input_recording.lock().await {
+        let offset_ms = recorder.started.elapsed().as_millis() as u64;
+        recorder.events.push(InputEvent {
+            offset_ms,
+            data: data.to_string(),
+        });
+    }
+}
+
+/// Start recording input, throwing away anything recorded so far.
+#[tauri::command]
+pub async fn async_start_input_recording(session_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    check_session(session_id)?;
+    *state.input_recording.lock().await = Some(InputRecorder::new());
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_stop_input_recording(
+    session_id: u32,
+    state: State<'_, AppState>,
+) -> Result<RecordedInput, SteppeError> {
+    check_session(session_id)?;
+
+    let recorder = state.input_recording.lock().await.take().ok_or(SteppeError::NotRecording)?;
+
+    Ok(recorder.finish())
+}
@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use portable_pty::CommandBuilder;
+
+use crate::get_config_dir;
+
+/// The scripts, by where they go under `<config dir>/shell-integration`
+const SCRIPTS: [(&str, &str); 5] = [
+    ("steppe.bash", include_str!("../shell-integration/steppe.bash")),
+    ("zsh/.zshenv", include_str!("../shell-integration/zsh/.zshenv")),
+    ("zsh/steppe.zsh", include_str!("../shell-integration/zsh/steppe.zsh")),
+    (
+        "fish/vendor_conf.d/steppe.fish",
+        include_str!("../shell-integration/fish/vendor_conf.d/steppe.fish"),
+    ),
+    ("steppe.ps1", include_str!("../shell-integration/steppe.ps1")),
+];
+
+fn write_scripts(dir: &Path) -> std::io::Result<()> {
+    for (name, contents) in SCRIPTS {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+    }
+
+    Ok(())
+}
+
+/// Where the scripts are, written out the first time they're needed so
+/// they're always this version of steppe's
+fn scripts_dir() -> Option<&'static Path> {
+    static SCRIPTS_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    SCRIPTS_DIR
+        .get_or_init(|| {
+            let dir = get_config_dir().join("shell-integration");
+            match write_scripts(&dir) {
+                Ok(()) => Some(dir),
+                Err(err) => {
+                    eprintln!("could not write shell integration scripts: {err}");
+                    None
+                }
+            }
+        })
+        .as_deref()
+}
+
+/// Have `cmd`, which runs `program`, load steppe's shell integration as it
+/// starts, so OSC 7 and 133 work without touching anyone's dotfiles. This
+/// passes the login flag too when `login` is set, since bash and powershell
+/// care where it goes. Returns false, leaving `cmd` alone, for shells it
+/// doesn't know.
+pub fn inject(cmd: &mut CommandBuilder, program: &str, login: bool) -> bool {
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    if !["bash", "zsh", "fish", "pwsh", "powershell"].contains(&name) {
+        return false;
+    }
+    let Some(dir) = scripts_dir() else {
+        return false;
+    };
+
+    match name {
+        "bash" => {
+            // --rcfile is ignored by login shells, so the script reads the login files itself
+            if login {
+                cmd.env("STEPPE_BASH_LOGIN", "1");
+            }
+            cmd.arg("--rcfile");
+            cmd.arg(dir.join("steppe.bash"));
+        }
+        "zsh" => {
+            if login {
+                cmd.arg("-l");
+            }
+            // .zshenv there puts this back before reading the user's own
+            if let Some(zdotdir) = std::env::var_os("ZDOTDIR") {
+                cmd.env("STEPPE_ZDOTDIR", zdotdir);
+            }
+            cmd.env("ZDOTDIR", dir.join("zsh"));
+        }
+        "fish" => {
+            // fish runs `vendor_conf.d/*.fish` from every data dir, and the script puts these back
+            let data_dirs = std::env::var_os("XDG_DATA_DIRS");
+            let mut paths = vec![dir.join("fish")];
+            match &data_dirs {
+                Some(data_dirs) => {
+                    cmd.env("STEPPE_XDG_DATA_DIRS", data_dirs);
+                    paths.extend(std::env::split_paths(data_dirs));
+                }
+                // what fish looks in when it's not set
+                None => paths.extend(["/usr/local/share", "/usr/share"].map(PathBuf::from)),
+            }
+            match std::env::join_paths(paths) {
+                Ok(joined) => cmd.env("XDG_DATA_DIRS", joined),
+                Err(err) => {
+                    eprintln!("could not add shell integration to XDG_DATA_DIRS: {err}");
+                    return false;
+                }
+            }
+            if login {
+                cmd.arg("-l");
+            }
+        }
+        _ => {
+            // pwsh wants -Login before anything else
+            if login {
+                cmd.arg("-Login");
+            }
+            let script = dir.join("steppe.ps1").display().to_string().replace('\'', "''");
+            cmd.args(["-NoExit", "-Command", &format!(". '{script}'")]);
+        }
+    }
+
+    true
+}
@@ -0,0 +1,143 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use deno_runtime::deno_core::{
+    anyhow::{anyhow, bail},
+    error::AnyError,
+    ModuleSpecifier,
+};
+
+use crate::get_config_dir;
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+    0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+    0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+    0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+    0xc67178f2,
+];
+
+/// SHA-256 of `data` as hex, which is what the lockfile keeps. It's here
+/// rather than from a crate since it's the only hash steppe needs.
+pub fn sha256(data: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    // a 1 bit, 0s up to 8 bytes short of a whole block, then the length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut schedule = [0u32; 64];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7) ^ schedule[i - 15].rotate_right(18) ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17) ^ schedule[i - 2].rotate_right(19) ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (constant, word) in ROUND_CONSTANTS.iter().zip(schedule) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*constant)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    state.iter().map(|word| format!("{word:08x}")).collect()
+}
+
+fn lockfile_path() -> PathBuf {
+    get_config_dir().join("modules.lock.json")
+}
+
+/// The hash of every remote module config.js or a plugin has imported, from
+/// `<config dir>/modules.lock.json`, so one that's changed since it was first
+/// imported (on the server, or in the cache) is refused rather than run.
+#[derive(Debug, Default)]
+pub struct Lockfile {
+    hashes: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    /// The lockfile as it's saved, or an empty one if there isn't one yet.
+    /// One that can't be read is an error, rather than quietly trusting everything again.
+    pub fn load() -> Result<Self, AnyError> {
+        let path = lockfile_path();
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => bail!("{}: {err}", path.display()),
+        };
+        let hashes = serde_json::from_slice(&contents).map_err(|err| anyhow!("{}: {err}", path.display()))?;
+
+        Ok(Self { hashes })
+    }
+
+    fn save(&self) -> Result<(), AnyError> {
+        let file = fs::File::create(lockfile_path())?;
+        serde_json::to_writer_pretty(file, &self.hashes)?;
+
+        Ok(())
+    }
+
+    /// Make sure `code` is what `url` was the first time, noting its hash if
+    /// this is the first time.
+    pub fn check(&mut self, url: &ModuleSpecifier, code: &str) -> Result<(), AnyError> {
+        let hash = sha256(code.as_bytes());
+
+        match self.hashes.get(url.as_str()) {
+            Some(locked) if *locked == hash => Ok(()),
+            Some(locked) => bail!(
+                "{url} isn't what it was when it was first imported (its sha256 was {locked}, now it's {hash}). \
+                 If that's expected, take it out of {} to trust it again",
+                lockfile_path().display()
+            ),
+            None => {
+                self.hashes.insert(url.to_string(), hash);
+                self.save()
+            }
+        }
+    }
+
+    /// Stop holding `url` to what it was, so whatever it is next time is trusted
+    pub fn forget(&mut self, url: &ModuleSpecifier) -> Result<(), AnyError> {
+        if self.hashes.remove(url.as_str()).is_some() {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+}
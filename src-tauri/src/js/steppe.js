@@ -0,0 +1,413 @@
+// the `steppe` global config.js talks to steppe through
+import {
+  op_steppe_add_command,
+  op_steppe_add_trigger,
+  op_steppe_bind_key,
+  op_steppe_extract_wallpaper_palette,
+  op_steppe_next_event,
+  op_steppe_next_output,
+  op_steppe_paste_from_buffer,
+  op_steppe_plugin_error,
+  op_steppe_set_allow_file_urls,
+  op_steppe_set_cell_spacing,
+  op_steppe_set_crash_reporting,
+  op_steppe_set_cursor_colors,
+  op_steppe_set_custom_url_opener,
+  op_steppe_set_expose_secrets_in_diagnostics,
+  op_steppe_set_focus_opacity,
+  op_steppe_set_focus_opacity_easing,
+  op_steppe_set_font_rendering,
+  op_steppe_set_line_height,
+  op_steppe_set_long_command_threshold,
+  op_steppe_set_mouse_report_mode,
+  op_steppe_set_output_batch_mode,
+  op_steppe_set_output_logging,
+  op_steppe_set_process_icon_map,
+  op_steppe_set_pty_line_disc,
+  op_steppe_set_renderer_backend,
+  op_steppe_set_scrollback_lines,
+  op_steppe_set_session_size_constraints,
+  op_steppe_set_session_startup_notification,
+  op_steppe_set_shell_profiles,
+  op_steppe_set_ssh_profiles,
+  op_steppe_set_startup_delay,
+  op_steppe_set_startup_sequences,
+  op_steppe_set_status,
+  op_steppe_set_terminal_notifications,
+  op_steppe_set_theme,
+  op_steppe_set_url_history_enabled,
+  op_steppe_set_url_open_mode,
+  op_steppe_set_write_rate_limit,
+  op_steppe_spawn_terminal,
+  op_steppe_start_gpu_monitoring,
+  op_steppe_wait_for_command,
+} from "ext:core/ops";
+
+const outputListeners = [];
+
+// `steppe.on` listeners, by the event they're for
+const EVENTS = ["terminalOpened", "terminalClosed", "commandFinished", "titleChanged"];
+const eventListeners = new Map(EVENTS.map((event) => [event, []]));
+let pumpingEvents = false;
+
+// keybindings bound to a function, by the id they're bound to as `script:<id>`
+const keybindingCallbacks = new Map();
+let nextKeybindingId = 0;
+
+// same for triggers
+const triggerCallbacks = new Map();
+let nextTriggerId = 0;
+
+// runs for as long as anything is listening, which also keeps the worker alive
+async function pumpOutput() {
+  while (true) {
+    const { terminalId, data } = await op_steppe_next_output();
+
+    for (const listener of outputListeners) {
+      try {
+        listener(terminalId, data);
+      } catch (error) {
+        console.error("error in onOutput listener:", error);
+      }
+    }
+  }
+}
+
+// same as pumpOutput, but started once and left running
+async function pumpEvents() {
+  pumpingEvents = true;
+
+  while (true) {
+    const event = await op_steppe_next_event();
+
+    for (const listener of eventListeners.get(event.event)) {
+      try {
+        await listener(event);
+      } catch (error) {
+        console.error(`error in ${event.event} listener:`, error);
+      }
+    }
+  }
+}
+
+globalThis.steppe = {
+  /** Set css variables on the page, e.g. `{ "--background": "#000" }` */
+  setTheme(vars) {
+    op_steppe_set_theme(vars);
+  },
+
+  /** Call `listener(terminalId, data)` with everything any terminal prints, until the returned function is called */
+  onOutput(listener) {
+    if (outputListeners.push(listener) === 1) {
+      pumpOutput();
+    }
+
+    return () => {
+      const index = outputListeners.indexOf(listener);
+      if (index !== -1) {
+        outputListeners.splice(index, 1);
+      }
+    };
+  },
+
+  /**
+   * Call `listener` with each `event` (`"terminalOpened"`, `"terminalClosed"`,
+   * `"commandFinished"` or `"titleChanged"`) and what it's about, until the
+   * returned function is called
+   */
+  on(event, listener) {
+    const listeners = eventListeners.get(event);
+    if (!listeners) {
+      throw new TypeError(`${event} isn't an event, it's one of ${EVENTS.join(", ")}`);
+    }
+
+    listeners.push(listener);
+    if (!pumpingEvents) {
+      pumpEvents();
+    }
+
+    return () => {
+      const index = listeners.indexOf(listener);
+      if (index !== -1) {
+        listeners.splice(index, 1);
+      }
+    };
+  },
+
+  /** Bind `key` (like `"ctrl+shift+t"`) to a named action, or a function to call */
+  bind(key, action) {
+    if (typeof action === "function") {
+      const id = nextKeybindingId++;
+      keybindingCallbacks.set(id, action);
+      action = `script:${id}`;
+    }
+
+    return op_steppe_bind_key(key, action);
+  },
+
+  /**
+   * Run `action` for every line of output matching `pattern`: a named action
+   * (like `"notify"`), or a function called with `(line, terminalId)`
+   */
+  addTrigger({ pattern, action }) {
+    const { source, flags } = pattern instanceof RegExp ? pattern : { source: String(pattern), flags: "" };
+
+    if (typeof action === "function") {
+      const id = nextTriggerId++;
+      triggerCallbacks.set(id, action);
+      action = `script:${id}`;
+    }
+
+    return op_steppe_add_trigger(source, flags, action);
+  },
+
+  /** Add `label` to the command palette, running a named action or a function */
+  addCommand({ label, action }) {
+    if (typeof action === "function") {
+      const id = nextKeybindingId++;
+      keybindingCallbacks.set(id, action);
+      action = `script:${id}`;
+    }
+
+    return op_steppe_add_command(label, action);
+  },
+
+  /**
+   * Show what `provider(terminalId)` returns (a string, or null for nothing)
+   * in each terminal's status bar as `name`, asking it again whenever a
+   * terminal opens, finishes a command or changes its title, until the
+   * returned function is called
+   */
+  addStatusProvider(name, provider) {
+    const terminals = new Set();
+
+    const update = async ({ terminalId }) => {
+      terminals.add(terminalId);
+      try {
+        const text = await provider(terminalId);
+        await op_steppe_set_status(terminalId, name, text == null ? null : String(text));
+      } catch (error) {
+        console.error(`error in status provider ${name}:`, error);
+      }
+    };
+
+    const unsubscribes = [
+      globalThis.steppe.on("terminalOpened", update),
+      globalThis.steppe.on("commandFinished", update),
+      globalThis.steppe.on("titleChanged", update),
+      globalThis.steppe.on("terminalClosed", ({ terminalId }) => terminals.delete(terminalId)),
+    ];
+
+    return () => {
+      unsubscribes.forEach((unsubscribe) => unsubscribe());
+      for (const terminalId of terminals) {
+        op_steppe_set_status(terminalId, name, null).catch(() => {});
+      }
+    };
+  },
+
+  /** Start a shell (or `{ profile }`, or in `{ cwd }`) in a new terminal, resolving to its id */
+  spawnTerminal(options) {
+    return op_steppe_spawn_terminal(options);
+  },
+
+  /** Set the pty's line discipline (`{ echo, canonicalMode, signalChars, minRead, timeout }`) for every terminal */
+  setPtyLineDisc(settings) {
+    return op_steppe_set_pty_line_disc(settings);
+  },
+
+  /** Open links with the system's handler (`"system"`), a browser (`"browser"`) or `setCustomUrlOpener`'s (`"custom"`) */
+  setUrlOpenMode(mode) {
+    return op_steppe_set_url_open_mode(mode);
+  },
+
+  /** Open links by running `command` with `args` and the url */
+  setCustomUrlOpener(command, args = []) {
+    return op_steppe_set_custom_url_opener(command, args);
+  },
+
+  setAllowFileUrls(allow) {
+    return op_steppe_set_allow_file_urls(allow);
+  },
+
+  setUrlHistoryEnabled(enabled) {
+    return op_steppe_set_url_history_enabled(enabled);
+  },
+
+  /** Upload crash reports to `endpoint`, or stop with `false` */
+  setCrashReporting(enabled, endpoint = null) {
+    return op_steppe_set_crash_reporting(enabled, endpoint);
+  },
+
+  setRendererBackend(backend) {
+    return op_steppe_set_renderer_backend(backend);
+  },
+
+  setSessionSizeConstraints(constraints) {
+    return op_steppe_set_session_size_constraints(constraints);
+  },
+
+  setExposeSecretsInDiagnostics(expose) {
+    return op_steppe_set_expose_secrets_in_diagnostics(expose);
+  },
+
+  setFontRendering(opts) {
+    return op_steppe_set_font_rendering(opts);
+  },
+
+  setStartupDelay(ms) {
+    return op_steppe_set_startup_delay(ms);
+  },
+
+  /** Throttle writes to terminals (`{ maxBytesPerSecond, burstBytes }`), or `null` to stop */
+  setWriteRateLimit(opts) {
+    return op_steppe_set_write_rate_limit(opts ?? null);
+  },
+
+  setStartupSequences(sequences) {
+    return op_steppe_set_startup_sequences(sequences);
+  },
+
+  /** Resolve once `cmd` with `args` succeeds, trying again until `timeoutMs` is up */
+  waitForCommand(cmd, args = [], timeoutMs) {
+    return op_steppe_wait_for_command(cmd, args, timeoutMs);
+  },
+
+  setCursorColors(fg, bg) {
+    return op_steppe_set_cursor_colors(fg, bg);
+  },
+
+  /** Bind `key` to pasting the buffer named `bufferName` (`"+"` for the clipboard) */
+  registerPasteFromBuffer(key, bufferName) {
+    return globalThis.steppe.bind(key, () => op_steppe_paste_from_buffer(bufferName));
+  },
+
+  startGpuMonitoring(intervalMs) {
+    return op_steppe_start_gpu_monitoring(intervalMs);
+  },
+
+  setLineHeight(value) {
+    return op_steppe_set_line_height(value);
+  },
+
+  setCellSpacing(horizontal, vertical) {
+    return op_steppe_set_cell_spacing(horizontal, vertical);
+  },
+
+  setSessionStartupNotification(opts) {
+    return op_steppe_set_session_startup_notification(opts);
+  },
+
+  setMouseReportMode(terminalId, mode) {
+    return op_steppe_set_mouse_report_mode(terminalId, mode);
+  },
+
+  setFocusOpacity(focused, unfocused, animationDurationMs = 0) {
+    return op_steppe_set_focus_opacity(focused, unfocused, animationDurationMs);
+  },
+
+  setFocusOpacityEasing(easing) {
+    return op_steppe_set_focus_opacity_easing(easing);
+  },
+
+  // an object's keys keep the order they were written in, which is the order they're tried in
+  setProcessIconMap(map) {
+    return op_steppe_set_process_icon_map(Object.entries(map));
+  },
+
+  setScrollbackLines(lines) {
+    return op_steppe_set_scrollback_lines(lines);
+  },
+
+  setOutputBatchMode(opts) {
+    return op_steppe_set_output_batch_mode(opts);
+  },
+
+  /** The wallpaper's most common colors, as hex strings */
+  extractWallpaperPalette() {
+    return op_steppe_extract_wallpaper_palette();
+  },
+
+  setSshProfiles(profiles) {
+    return op_steppe_set_ssh_profiles(profiles);
+  },
+
+  setShellProfiles(profiles) {
+    return op_steppe_set_shell_profiles(profiles);
+  },
+
+  /** How long a command has to run for, in milliseconds, to notify when it's done */
+  setLongCommandThreshold(ms) {
+    return op_steppe_set_long_command_threshold(ms);
+  },
+
+  setTerminalNotifications(terminalId, escapeSequences, bell) {
+    return op_steppe_set_terminal_notifications(terminalId, escapeSequences, bell);
+  },
+
+  /** Log every terminal opened from now on into its own file in `dir`, or stop for `null` */
+  setOutputLogging(dir, { stripAnsi, maxBytes } = {}) {
+    return op_steppe_set_output_logging(dir ?? null, stripAnsi ?? null, maxBytes ?? null);
+  },
+};
+
+// plugins, by file url. steppe calls into these (see plugins.rs) rather than
+// config.js or the plugins themselves
+const plugins = new Map();
+
+function reportPluginError(url, error) {
+  op_steppe_plugin_error(url, String(error?.stack ?? error));
+}
+
+globalThis.__steppePlugins = {
+  async activate(url) {
+    if (plugins.has(url)) {
+      return;
+    }
+
+    try {
+      const plugin = await import(url);
+      plugins.set(url, plugin);
+      await plugin.activate?.(globalThis.steppe);
+    } catch (error) {
+      reportPluginError(url, error);
+    }
+  },
+
+  async deactivate(url) {
+    const plugin = plugins.get(url);
+    plugins.delete(url);
+
+    try {
+      await plugin?.deactivate?.();
+    } catch (error) {
+      reportPluginError(url, error);
+    }
+  },
+
+  deactivateAll() {
+    for (const url of [...plugins.keys()]) {
+      this.deactivate(url);
+    }
+  },
+};
+
+globalThis.__steppeKeybindings = {
+  async run(id) {
+    try {
+      await keybindingCallbacks.get(id)?.();
+    } catch (error) {
+      console.error("error in keybinding:", error);
+    }
+  },
+};
+
+globalThis.__steppeTriggers = {
+  async run(id, terminalId, line) {
+    try {
+      await triggerCallbacks.get(id)?.(line, terminalId);
+    } catch (error) {
+      console.error("error in trigger:", error);
+    }
+  },
+};
@@ -0,0 +1,213 @@
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::{docker, error::SteppeError, spawn_in_terminal, AppState, ShellProcess};
+
+/// Which container to get a shell in, and where to find it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct K8sShell {
+    /// From kubeconfig, or whichever one's current if not given
+    pub context: Option<String>,
+    pub namespace: String,
+    pub pod: String,
+    /// The pod's default container if not given
+    pub container: Option<String>,
+}
+
+impl K8sShell {
+    /// `kubectl` pointed at the pod's context and namespace
+    fn kubectl_args(&self) -> Vec<String> {
+        let mut args = context_args(self.context.as_deref());
+        args.extend(["--namespace".to_string(), self.namespace.clone()]);
+        args
+    }
+
+    /// Everything after `kubectl exec` that picks out the container
+    fn exec_target(&self) -> Vec<String> {
+        let mut args = vec![self.pod.clone()];
+        if let Some(container) = &self.container {
+            args.extend(["--container".to_string(), container.clone()]);
+        }
+        args
+    }
+
+    async fn detect_shell(&self) -> Result<String, SteppeError> {
+        let mut args = self.kubectl_args();
+        args.push("exec".to_string());
+        args.extend(self.exec_target());
+        args.extend(["--", "sh", "-c", docker::DETECT_SHELL].map(String::from));
+
+        Ok(docker::detected_shell(&kubectl(&args).await?))
+    }
+
+    /// `kubectl exec` into the container, running `shell` there. Going through
+    /// kubectl means every auth plugin kubeconfig can use works here too.
+    fn command(&self, shell: &str) -> CommandBuilder {
+        let mut cmd = CommandBuilder::new("kubectl");
+        cmd.args(self.kubectl_args());
+        cmd.args(["exec", "--stdin", "--tty"]);
+        cmd.args(self.exec_target());
+        // there's no --env for exec, and the TERM steppe sets is only for kubectl
+        cmd.args(["--", "env", "TERM=xterm-256color", shell]);
+
+        cmd
+    }
+}
+
+/// A pod, from `kubectl get pods`
+#[derive(Debug, Clone, Serialize)]
+pub struct K8sPod {
+    pub namespace: String,
+    pub name: String,
+    /// `Running`, `Pending`, `Failed`, ...
+    pub phase: String,
+    pub containers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PodList {
+    items: Vec<Pod>,
+}
+
+#[derive(Deserialize)]
+struct Pod {
+    metadata: PodMetadata,
+    spec: PodSpec,
+    #[serde(default)]
+    status: PodStatus,
+}
+
+#[derive(Deserialize)]
+struct PodMetadata {
+    name: String,
+    namespace: String,
+}
+
+#[derive(Deserialize)]
+struct PodSpec {
+    containers: Vec<Container>,
+}
+
+#[derive(Deserialize)]
+struct Container {
+    name: String,
+}
+
+#[derive(Default, Deserialize)]
+struct PodStatus {
+    #[serde(default)]
+    phase: String,
+}
+
+fn context_args(context: Option<&str>) -> Vec<String> {
+    match context {
+        Some(context) => vec!["--context".to_string(), context.to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// Run `kubectl` with `args` and hand back what it printed, or what it
+/// complained about if it failed (no such context, cluster unreachable, ...)
+async fn kubectl(args: &[String]) -> Result<String, SteppeError> {
+    let output = tokio::process::Command::new("kubectl").args(args).output().await?;
+
+    if !output.status.success() {
+        return Err(SteppeError::Kubernetes(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The contexts in kubeconfig, by name
+#[tauri::command]
+pub async fn async_list_k8s_contexts() -> Result<Vec<String>, SteppeError> {
+    let output = kubectl(&["config", "get-contexts", "--output", "name"].map(String::from)).await?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// The namespaces in `context`, or the current context
+#[tauri::command]
+pub async fn async_list_k8s_namespaces(context: Option<String>) -> Result<Vec<String>, SteppeError> {
+    let mut args = context_args(context.as_deref());
+    args.extend(["get", "namespaces", "--output", "name"].map(String::from));
+
+    let output = kubectl(&args).await?;
+    Ok(output
+        .lines()
+        .map(|line| line.strip_prefix("namespace/").unwrap_or(line).to_string())
+        .collect())
+}
+
+/// The pods in `namespace` of `context`, or in every namespace if there's no
+/// `namespace`
+#[tauri::command]
+pub async fn async_list_k8s_pods(
+    context: Option<String>,
+    namespace: Option<String>,
+) -> Result<Vec<K8sPod>, SteppeError> {
+    let mut args = context_args(context.as_deref());
+    args.extend(["get", "pods", "--output", "json"].map(String::from));
+    match namespace {
+        Some(namespace) => args.extend(["--namespace".to_string(), namespace]),
+        None => args.push("--all-namespaces".to_string()),
+    }
+
+    let output = kubectl(&args).await?;
+    let pods: PodList = serde_json::from_str(&output).map_err(|err| SteppeError::Kubernetes(err.to_string()))?;
+
+    Ok(pods
+        .items
+        .into_iter()
+        .map(|pod| K8sPod {
+            namespace: pod.metadata.namespace,
+            name: pod.metadata.name,
+            phase: pod.status.phase,
+            containers: pod
+                .spec
+                .containers
+                .into_iter()
+                .map(|container| container.name)
+                .collect(),
+        })
+        .collect())
+}
+
+/// Like `async_create_shell`, but runs a shell inside `pod`'s `container`
+/// (or its default one) instead.
+#[tauri::command]
+pub async fn async_create_k8s_shell(
+    terminal_id: u32,
+    context: Option<String>,
+    namespace: String,
+    pod: String,
+    container: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let target = K8sShell {
+        context,
+        namespace,
+        pod,
+        container,
+    };
+    let shell = target.detect_shell().await?;
+
+    let process = ShellProcess {
+        program: "kubectl".to_string(),
+        shell_profile: None,
+        ssh_profile: None,
+        wsl_distro: None,
+        docker_container: None,
+        k8s_shell: Some(target.clone()),
+        pid: None,
+        init_command: None,
+    };
+
+    spawn_in_terminal(terminal_id, target.command(&shell), process, app, &state)
+        .await
+        .map_err(SteppeError::Pty)
+}
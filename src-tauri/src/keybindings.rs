@@ -0,0 +1,190 @@
+use std::sync::atomic::Ordering;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    config::WorkerCommand,
+    cwd, duplicate,
+    error::SteppeError,
+    export, monitor,
+    panes::{self, SplitDirection},
+    paste, reader, reset, spawn_terminal, tmux, window, AppState,
+};
+
+/// Actions bound to a js callback rather than a name, followed by the callback's id
+pub const SCRIPT_ACTION_PREFIX: &str = "script:";
+
+/// Modifiers, in the order they're written in a normalized key
+const MODIFIERS: [&str; 4] = ["ctrl", "alt", "shift", "meta"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keybinding {
+    /// Like `ctrl+shift+t`
+    pub key: String,
+    pub action: String,
+}
+
+/// Every keybinding config.js and plugins have registered. The frontend asks
+/// for these rather than keeping its own, so there's only the one list.
+#[derive(Debug, Default)]
+pub struct Keybindings {
+    bindings: Vec<Keybinding>,
+}
+
+#[derive(Clone, Serialize)]
+struct KeybindingAction {
+    action: String,
+    terminal_id: u32,
+}
+
+/// `Ctrl+Shift+T`, `shift+control+t` and so on all become `ctrl+shift+t`.
+fn normalize_key(key: &str) -> Result<String, SteppeError> {
+    let invalid = |reason: &str| SteppeError::InvalidConfig(format!("keybinding {key:?} {reason}"));
+
+    let mut modifiers = [false; MODIFIERS.len()];
+    let mut main_key = None;
+
+    for part in key.split('+').map(|part| part.trim().to_lowercase()) {
+        let modifier = match part.as_str() {
+            "ctrl" | "control" => Some(0),
+            "alt" | "option" => Some(1),
+            "shift" => Some(2),
+            "meta" | "cmd" | "super" => Some(3),
+            _ => None,
+        };
+
+        match modifier {
+            Some(index) => modifiers[index] = true,
+            None if part.is_empty() => return Err(invalid("has an empty key in it")),
+            None if main_key.is_some() => return Err(invalid("has more than one non-modifier key")),
+            None => main_key = Some(part),
+        }
+    }
+
+    let main_key = main_key.ok_or_else(|| invalid("is only modifiers"))?;
+
+    let mut parts: Vec<&str> = MODIFIERS
+        .iter()
+        .zip(modifiers)
+        .filter(|(_, held)| *held)
+        .map(|(name, _)| *name)
+        .collect();
+    parts.push(&main_key);
+
+    Ok(parts.join("+"))
+}
+
+impl Keybindings {
+    /// Bind `key` to `action`, replacing whatever it was bound to before.
+    pub fn bind(&mut self, key: &str, action: String) -> Result<(), SteppeError> {
+        let key = normalize_key(key)?;
+
+        self.bindings.retain(|binding| binding.key != key);
+        self.bindings.push(Keybinding { key, action });
+
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+}
+
+pub fn emit_changed(app: &AppHandle, keybindings: &Keybindings) -> tauri::Result<()> {
+    app.emit("keybindings-changed", keybindings.bindings.clone())
+}
+
+#[tauri::command]
+pub async fn async_get_keybindings(state: State<'_, AppState>) -> Result<Vec<Keybinding>, SteppeError> {
+    Ok(state.keybindings.lock().await.bindings.clone())
+}
+
+/// Do whatever `action` is, for terminal `terminal_id`. Js callbacks run in the
+/// config worker, a few actions are done right here, and the rest go to the
+/// frontend as `keybinding://action`.
+pub async fn invoke(action: String, terminal_id: u32, app: &AppHandle, state: &AppState) -> Result<(), SteppeError> {
+    if let Some(id) = action.strip_prefix(SCRIPT_ACTION_PREFIX) {
+        let id = id
+            .parse()
+            .map_err(|_| SteppeError::InvalidConfig(format!("{action} isn't a script action")))?;
+
+        // the worker's only gone if steppe is shutting down
+        let _ = state.worker_commands.send(WorkerCommand::RunKeybinding(id));
+        return Ok(());
+    }
+
+    match action.as_str() {
+        "paste" => paste::paste_clipboard(app, state, terminal_id).await?,
+        "copy_as_html" => export::copy_as_html(app, state, terminal_id).await?,
+        // like a new tab, it starts wherever the terminal it was opened from is
+        "new_terminal" => {
+            let cwd = match state.terminal(terminal_id).await {
+                Ok(terminal) => cwd::inherited_cwd(&terminal).await,
+                Err(_) => None,
+            };
+
+            spawn_terminal(app, None, cwd, &window::window_of(state, terminal_id).await).await?;
+        }
+        "new_window" => {
+            window::open(app, state).await?;
+        }
+        "split_horizontal" => {
+            panes::split(app, state, terminal_id, SplitDirection::Horizontal, None).await?;
+        }
+        "split_vertical" => {
+            panes::split(app, state, terminal_id, SplitDirection::Vertical, None).await?;
+        }
+        "duplicate_terminal" => {
+            duplicate::duplicate(app, state, terminal_id, None).await?;
+        }
+        "duplicate_split_horizontal" => {
+            duplicate::duplicate(app, state, terminal_id, Some(SplitDirection::Horizontal)).await?;
+        }
+        "duplicate_split_vertical" => {
+            duplicate::duplicate(app, state, terminal_id, Some(SplitDirection::Vertical)).await?;
+        }
+        "close_pane" => panes::close(app, state, terminal_id).await?,
+        "tmux_new_window" => tmux::new_window(state, terminal_id).await?,
+        "tmux_kill_window" => tmux::kill_window(state, terminal_id).await?,
+        // only the window it was pressed in should ask which container
+        "connect_container" => {
+            app.emit_to(window::window_of(state, terminal_id).await, "docker://pick", ())?;
+        }
+        "command_palette" => {
+            let label = window::window_of(state, terminal_id).await;
+            app.emit_to(label, "palette://open", terminal_id)?;
+        }
+        "toggle_output_pause" => {
+            let paused = state.terminal(terminal_id).await?.output_paused.load(Ordering::Acquire);
+            reader::set_paused(app, state, terminal_id, !paused).await?;
+        }
+        "toggle_monitor_activity" => {
+            let monitor = monitor::settings(state, terminal_id).await?;
+            monitor::set(app, state, terminal_id, !monitor.activity, monitor.silence_ms).await?;
+        }
+        "toggle_monitor_silence" => {
+            let monitor = monitor::settings(state, terminal_id).await?;
+            let silence_ms = match monitor.silence_ms {
+                Some(_) => None,
+                None => Some(monitor::DEFAULT_SILENCE_MS),
+            };
+            monitor::set(app, state, terminal_id, monitor.activity, silence_ms).await?;
+        }
+        "clear_scrollback" => reset::clear_scrollback(app, state, terminal_id).await?,
+        "hard_reset" => reset::hard_reset(app, state, terminal_id).await?,
+        _ => app.emit("keybinding://action", KeybindingAction { action, terminal_id })?,
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_invoke_action(
+    action: String,
+    terminal_id: u32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    invoke(action, terminal_id, &app, &state).await
+}
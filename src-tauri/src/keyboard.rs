@@ -0,0 +1,485 @@
+use std::{io::Write, sync::atomic::Ordering};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, input_broadcast, write_input, AppState, SubTerminal};
+
+// the kitty keyboard protocol's progressive enhancements, which programs push
+// with `CSI > flags u` and pop with `CSI < u`. The screen keeps the stack
+// (one for each of the main and alternate screens), these are its bits.
+pub const DISAMBIGUATE_ESCAPE_CODES: u8 = 1;
+pub const REPORT_EVENT_TYPES: u8 = 2;
+pub const REPORT_ALTERNATE_KEYS: u8 = 4;
+pub const REPORT_ALL_KEYS_AS_ESCAPE_CODES: u8 = 8;
+pub const REPORT_ASSOCIATED_TEXT: u8 = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyEventKind {
+    #[default]
+    Press,
+    Repeat,
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+    /// The command or windows key, kitty's `super`
+    pub meta: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+}
+
+impl KeyModifiers {
+    /// The modifiers' bits, without 1 added the way they're sent. The locks
+    /// only count for programs that asked for every key.
+    fn bits(&self, locks: bool) -> u32 {
+        let mut bits = 0;
+        for (held, bit) in [(self.shift, 1), (self.alt, 2), (self.ctrl, 4), (self.meta, 8)] {
+            if held {
+                bits |= bit;
+            }
+        }
+        if locks {
+            for (on, bit) in [(self.caps_lock, 64), (self.num_lock, 128)] {
+                if on {
+                    bits |= bit;
+                }
+            }
+        }
+
+        bits
+    }
+
+    /// Whether anything's held that makes a key mean something other than
+    /// what it types
+    fn any_besides_shift(&self) -> bool {
+        self.alt || self.ctrl || self.meta
+    }
+}
+
+/// A key going down, repeating or coming up, from the frontend
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyEvent {
+    /// `KeyboardEvent.key`: what the key types, or its name (`ArrowUp`, `F5`, ...)
+    pub key: String,
+    /// `KeyboardEvent.code`, the key on the keyboard, for what it'd type
+    /// without shift and which of a modifier key's two it is
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub modifiers: KeyModifiers,
+    #[serde(default)]
+    pub kind: KeyEventKind,
+}
+
+#[derive(Clone, Serialize)]
+struct KeyboardModeChanged {
+    terminal_id: u32,
+    flags: u8,
+}
+
+/// Keys that don't type anything: their number and the byte their escape
+/// code ends in, the kitty way
+fn functional(key: &str) -> Option<(u32, char)> {
+    Some(match key {
+        "Escape" => (27, 'u'),
+        "Enter" => (13, 'u'),
+        "Tab" => (9, 'u'),
+        "Backspace" => (127, 'u'),
+        "Insert" => (2, '~'),
+        "Delete" => (3, '~'),
+        "PageUp" => (5, '~'),
+        "PageDown" => (6, '~'),
+        "ArrowUp" => (1, 'A'),
+        "ArrowDown" => (1, 'B'),
+        "ArrowRight" => (1, 'C'),
+        "ArrowLeft" => (1, 'D'),
+        "Home" => (1, 'H'),
+        "End" => (1, 'F'),
+        "F1" => (1, 'P'),
+        "F2" => (1, 'Q'),
+        // `CSI R` is a cursor position report, so F3 is different
+        "F3" => (13, '~'),
+        "F4" => (1, 'S'),
+        "F5" => (15, '~'),
+        "F6" => (17, '~'),
+        "F7" => (18, '~'),
+        "F8" => (19, '~'),
+        "F9" => (20, '~'),
+        "F10" => (21, '~'),
+        "F11" => (23, '~'),
+        "F12" => (24, '~'),
+        _ => return None,
+    })
+}
+
+/// The number of a modifier key (or lock) itself, which is only sent to
+/// programs that asked for every key
+fn modifier_key(event: &KeyEvent) -> Option<u32> {
+    let right = event.code.as_deref().is_some_and(|code| code.ends_with("Right"));
+    let left_or_right = |left: u32| if right { left + 6 } else { left };
+
+    Some(match event.key.as_str() {
+        "CapsLock" => 57358,
+        "NumLock" => 57360,
+        "Shift" => left_or_right(57441),
+        "Control" => left_or_right(57442),
+        "Alt" => left_or_right(57443),
+        "Meta" => left_or_right(57444),
+        _ => return None,
+    })
+}
+
+/// The character a key types, if it types just the one
+fn typed(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// What the key `typed` is on types without shift, going by where it is
+/// for letters and digits, which is right for any layout
+fn unshifted(event: &KeyEvent, typed: char) -> char {
+    let code = event.code.as_deref().unwrap_or_default();
+    let from_code = code
+        .strip_prefix("Key")
+        .or_else(|| code.strip_prefix("Digit"))
+        .and_then(self::typed);
+
+    match from_code {
+        Some(c) => c.to_ascii_lowercase(),
+        None => typed.to_lowercase().next().unwrap_or(typed),
+    }
+}
+
+/// What ctrl and `c` together type
+fn control(c: char) -> Option<char> {
+    Some(match c {
+        'a'..='z' | 'A'..='Z' => (c.to_ascii_lowercase() as u8 - b'a' + 1) as char,
+        '@' | ' ' => '\0',
+        '[' => '\x1b',
+        '\\' => '\x1c',
+        ']' => '\x1d',
+        '^' => '\x1e',
+        '_' => '\x1f',
+        '?' => '\x7f',
+        _ => return None,
+    })
+}
+
+/// `event` the way xterm sends it, for programs that haven't asked for
+/// anything better. Releases aren't sent at all.
+fn legacy(event: &KeyEvent, app_cursor: bool) -> Option<String> {
+    if event.kind == KeyEventKind::Release {
+        return None;
+    }
+
+    let modifiers = &event.modifiers;
+    let mods = 1 + modifiers.bits(false);
+    let with_alt = |text: &str| {
+        if modifiers.alt {
+            format!("\x1b{text}")
+        } else {
+            text.to_string()
+        }
+    };
+
+    if let Some(c) = typed(&event.key) {
+        let c = if modifiers.ctrl { control(c).unwrap_or(c) } else { c };
+        return Some(with_alt(&c.to_string()));
+    }
+
+    let (number, end) = functional(&event.key)?;
+    Some(match (event.key.as_str(), end) {
+        ("Enter", _) => with_alt("\r"),
+        ("Tab", _) if modifiers.shift => "\x1b[Z".to_string(),
+        ("Tab", _) => with_alt("\t"),
+        ("Backspace", _) if modifiers.ctrl => with_alt("\x08"),
+        ("Backspace", _) => with_alt("\x7f"),
+        ("Escape", _) => with_alt("\x1b"),
+        ("F3", _) if mods == 1 => "\x1bOR".to_string(),
+        ("F3", _) => format!("\x1b[1;{mods}R"),
+        (_, '~') if mods == 1 => format!("\x1b[{number}~"),
+        (_, '~') => format!("\x1b[{number};{mods}~"),
+        (_, _) if mods > 1 => format!("\x1b[1;{mods}{end}"),
+        (_, 'P' | 'Q' | 'S') => format!("\x1bO{end}"),
+        (_, _) if app_cursor => format!("\x1bO{end}"),
+        (_, _) => format!("\x1b[{end}"),
+    })
+}
+
+/// `event` as `CSI number[:shifted];mods[:kind][;text] u` and friends, for
+/// a program that's pushed `flags`. Whatever they still leave to the
+/// legacy encoding (plain text, unmodified arrows, ...) goes that way.
+fn kitty(event: &KeyEvent, flags: u8, app_cursor: bool) -> Option<String> {
+    let all = flags & REPORT_ALL_KEYS_AS_ESCAPE_CODES != 0;
+    let release = event.kind == KeyEventKind::Release;
+    if release && flags & REPORT_EVENT_TYPES == 0 {
+        return None;
+    }
+
+    let modifiers = &event.modifiers;
+    let (number, shifted, end, text) = if let Some(number) = modifier_key(event) {
+        if !all {
+            return None;
+        }
+        (number, None, 'u', None)
+    } else if let Some((number, end)) = functional(&event.key) {
+        // only escape was ambiguous unmodified. Enter, tab and backspace
+        // staying as they were keeps a shell usable after a program that
+        // pushed flags crashes without popping them.
+        if !all && event.key != "Escape" && !modifiers.shift && !modifiers.any_besides_shift() {
+            return if release { None } else { legacy(event, app_cursor) };
+        }
+        (number, None, end, None)
+    } else {
+        let c = typed(&event.key)?;
+        if !all && !modifiers.any_besides_shift() {
+            return if release { None } else { Some(c.to_string()) };
+        }
+
+        let base = unshifted(event, c);
+        let shifted = (flags & REPORT_ALTERNATE_KEYS != 0 && modifiers.shift && c != base).then_some(c);
+        let text = (flags & REPORT_ASSOCIATED_TEXT != 0 && !release && !modifiers.any_besides_shift()).then_some(c);
+        (base as u32, shifted, 'u', text)
+    };
+
+    let mods = 1 + modifiers.bits(all);
+    let kind = match event.kind {
+        KeyEventKind::Press => None,
+        _ if flags & REPORT_EVENT_TYPES == 0 => None,
+        KeyEventKind::Repeat => Some(2),
+        KeyEventKind::Release => Some(3),
+    };
+
+    let mut sequence = String::from("\x1b[");
+    let mut params = number.to_string();
+    if let Some(shifted) = shifted {
+        params.push_str(&format!(":{}", shifted as u32));
+    }
+    if mods > 1 || kind.is_some() || text.is_some() {
+        params.push(';');
+        if mods > 1 || kind.is_some() {
+            params.push_str(&mods.to_string());
+        }
+        if let Some(kind) = kind {
+            params.push_str(&format!(":{kind}"));
+        }
+    }
+    if let Some(text) = text {
+        params.push_str(&format!(";{}", text as u32));
+    }
+    // `CSI A` and the like leave out the 1 when nothing comes after it
+    if end == 'u' || end == '~' || params != "1" {
+        sequence.push_str(&params);
+    }
+    sequence.push(end);
+
+    Some(sequence)
+}
+
+/// What to write to the pty for `event`, for a program that's pushed
+/// `flags` and is or isn't in application cursor mode, or `None` if it
+/// shouldn't hear about it
+pub fn encode(event: &KeyEvent, flags: u8, app_cursor: bool) -> Option<String> {
+    if flags == 0 {
+        legacy(event, app_cursor)
+    } else {
+        kitty(event, flags, app_cursor)
+    }
+}
+
+/// Send `terminal://keyboard-mode` when the program in terminal
+/// `terminal_id` pushes or pops flags, and answer it asking what they are
+/// (`CSI ? u`), which the screen doesn't do itself
+pub async fn track(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal, data: &str) {
+    let flags = terminal.screen.lock().await.keyboard_flags();
+
+    if data.contains("\x1b[?u") {
+        if let Err(err) = write!(terminal.writer.lock().await, "\x1b[?{flags}u") {
+            eprintln!("could not answer a keyboard mode query: {err}");
+        }
+    }
+
+    if terminal.keyboard_flags.swap(flags, Ordering::Relaxed) != flags {
+        if let Err(err) = app.emit("terminal://keyboard-mode", KeyboardModeChanged { terminal_id, flags }) {
+            eprintln!("could not send the keyboard mode: {err}");
+        }
+    }
+}
+
+/// Type `key_event` in terminal `terminal_id` (and whatever it's
+/// broadcasting to), encoded for what each program there has asked for.
+/// The frontend sends every key through here while the flags sent with
+/// `terminal://keyboard-mode` aren't 0, since xterm.js only knows the
+/// legacy encoding.
+#[tauri::command]
+pub async fn async_send_key(
+    terminal_id: u32,
+    key_event: KeyEvent,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    for target in input_broadcast::targets(&state, terminal_id).await {
+        let terminal = state.terminal(target).await?;
+        let (flags, app_cursor) = {
+            let screen = terminal.screen.lock().await;
+            (screen.keyboard_flags(), screen.app_cursor())
+        };
+
+        if let Some(data) = encode(&key_event, flags, app_cursor) {
+            write_input(&app, &state, target, &data).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The flags the program in terminal `terminal_id` has pushed. They're sent
+/// as `terminal://keyboard-mode` whenever that changes after.
+#[tauri::command]
+pub async fn async_get_keyboard_mode(terminal_id: u32, state: State<'_, AppState>) -> Result<u8, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let flags = terminal.keyboard_flags.load(Ordering::Relaxed);
+
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISAMBIGUATE: u8 = DISAMBIGUATE_ESCAPE_CODES;
+    const ALL_KEYS: u8 = DISAMBIGUATE_ESCAPE_CODES | REPORT_ALL_KEYS_AS_ESCAPE_CODES;
+
+    /// Flags, `key`, `code`, what's held (`+` between them) and what's sent for a press
+    const PRESSES: [(u8, &str, &str, &str, Option<&str>); 28] = [
+        // legacy
+        (0, "a", "KeyA", "", Some("a")),
+        (0, "a", "KeyA", "ctrl", Some("\x01")),
+        (0, "a", "KeyA", "alt", Some("\x1ba")),
+        (0, "[", "BracketLeft", "ctrl", Some("\x1b")),
+        (0, "Enter", "Enter", "", Some("\r")),
+        (0, "Tab", "Tab", "shift", Some("\x1b[Z")),
+        (0, "Backspace", "Backspace", "ctrl", Some("\x08")),
+        (0, "ArrowUp", "ArrowUp", "", Some("\x1b[A")),
+        (0, "ArrowUp", "ArrowUp", "ctrl", Some("\x1b[1;5A")),
+        (0, "F1", "F1", "", Some("\x1bOP")),
+        (0, "F3", "F3", "shift", Some("\x1b[1;2R")),
+        (0, "F5", "F5", "shift", Some("\x1b[15;2~")),
+        (0, "Shift", "ShiftLeft", "shift", None),
+        // disambiguated, which leaves plain text and unmodified keys alone
+        (DISAMBIGUATE, "a", "KeyA", "", Some("a")),
+        (DISAMBIGUATE, "A", "KeyA", "shift", Some("A")),
+        (DISAMBIGUATE, "a", "KeyA", "ctrl", Some("\x1b[97;5u")),
+        (DISAMBIGUATE, "Escape", "Escape", "", Some("\x1b[27u")),
+        (DISAMBIGUATE, "Enter", "Enter", "", Some("\r")),
+        (DISAMBIGUATE, "Enter", "Enter", "shift", Some("\x1b[13;2u")),
+        (DISAMBIGUATE, "ArrowUp", "ArrowUp", "", Some("\x1b[A")),
+        (DISAMBIGUATE, "ArrowUp", "ArrowUp", "ctrl+shift", Some("\x1b[1;6A")),
+        (DISAMBIGUATE, "Delete", "Delete", "alt", Some("\x1b[3;3~")),
+        (DISAMBIGUATE, "Control", "ControlLeft", "ctrl", None),
+        // every key, modifiers included
+        (ALL_KEYS, "a", "KeyA", "", Some("\x1b[97u")),
+        (ALL_KEYS, "A", "KeyA", "shift", Some("\x1b[97;2u")),
+        (ALL_KEYS, "ArrowUp", "ArrowUp", "", Some("\x1b[A")),
+        (ALL_KEYS, "Shift", "ShiftRight", "shift", Some("\x1b[57447;2u")),
+        (ALL_KEYS, "a", "KeyA", "capsLock", Some("\x1b[97;65u")),
+    ];
+
+    fn event(key: &str, code: &str, held: &str, kind: KeyEventKind) -> KeyEvent {
+        let held: Vec<&str> = held.split('+').collect();
+        KeyEvent {
+            key: key.to_string(),
+            code: Some(code.to_string()),
+            modifiers: KeyModifiers {
+                shift: held.contains(&"shift"),
+                alt: held.contains(&"alt"),
+                ctrl: held.contains(&"ctrl"),
+                meta: held.contains(&"meta"),
+                caps_lock: held.contains(&"capsLock"),
+                num_lock: held.contains(&"numLock"),
+            },
+            kind,
+        }
+    }
+
+    #[test]
+    fn encodes_presses() {
+        for (flags, key, code, held, sent) in PRESSES {
+            let event = event(key, code, held, KeyEventKind::Press);
+            assert_eq!(
+                encode(&event, flags, false).as_deref(),
+                sent,
+                "{held}+{key} with flags {flags}"
+            );
+        }
+    }
+
+    #[test]
+    fn encodes_cursor_keys_for_application_mode() {
+        let up = event("ArrowUp", "ArrowUp", "", KeyEventKind::Press);
+        assert_eq!(encode(&up, 0, true).as_deref(), Some("\x1bOA"));
+        assert_eq!(encode(&up, DISAMBIGUATE, true).as_deref(), Some("\x1bOA"));
+
+        let ctrl_up = event("ArrowUp", "ArrowUp", "ctrl", KeyEventKind::Press);
+        assert_eq!(encode(&ctrl_up, 0, true).as_deref(), Some("\x1b[1;5A"));
+    }
+
+    #[test]
+    fn encodes_event_types() {
+        let types = DISAMBIGUATE | REPORT_EVENT_TYPES;
+        let ctrl_a = |kind| event("a", "KeyA", "ctrl", kind);
+
+        assert_eq!(encode(&ctrl_a(KeyEventKind::Release), 0, false), None);
+        assert_eq!(encode(&ctrl_a(KeyEventKind::Release), DISAMBIGUATE, false), None);
+        assert_eq!(
+            encode(&ctrl_a(KeyEventKind::Press), types, false).as_deref(),
+            Some("\x1b[97;5u")
+        );
+        assert_eq!(
+            encode(&ctrl_a(KeyEventKind::Repeat), types, false).as_deref(),
+            Some("\x1b[97;5:2u")
+        );
+        assert_eq!(
+            encode(&ctrl_a(KeyEventKind::Release), types, false).as_deref(),
+            Some("\x1b[97;5:3u")
+        );
+
+        let escape = event("Escape", "Escape", "", KeyEventKind::Release);
+        assert_eq!(encode(&escape, types, false).as_deref(), Some("\x1b[27;1:3u"));
+        // what's left to the legacy encoding has no releases
+        let enter = event("Enter", "Enter", "", KeyEventKind::Release);
+        assert_eq!(encode(&enter, types, false), None);
+    }
+
+    #[test]
+    fn encodes_alternate_keys_and_text() {
+        let shift_a = event("A", "KeyA", "shift", KeyEventKind::Press);
+        let alternates = ALL_KEYS | REPORT_ALTERNATE_KEYS;
+        assert_eq!(encode(&shift_a, alternates, false).as_deref(), Some("\x1b[97:65;2u"));
+
+        let text = ALL_KEYS | REPORT_ASSOCIATED_TEXT;
+        assert_eq!(encode(&shift_a, text, false).as_deref(), Some("\x1b[97;2;65u"));
+        let a = event("a", "KeyA", "", KeyEventKind::Press);
+        assert_eq!(encode(&a, text, false).as_deref(), Some("\x1b[97;;97u"));
+        // ctrl+a doesn't type anything
+        let ctrl_a = event("a", "KeyA", "ctrl", KeyEventKind::Press);
+        assert_eq!(encode(&ctrl_a, text, false).as_deref(), Some("\x1b[97;5u"));
+    }
+
+    #[test]
+    fn uses_the_key_without_shift_on_any_layout() {
+        // shift+2 on a us layout, and the key where q is on qwerty on azerty
+        let at = event("@", "Digit2", "shift+ctrl", KeyEventKind::Press);
+        assert_eq!(encode(&at, DISAMBIGUATE, false).as_deref(), Some("\x1b[50;6u"));
+        let a = event("a", "KeyQ", "ctrl", KeyEventKind::Press);
+        assert_eq!(encode(&a, DISAMBIGUATE, false).as_deref(), Some("\x1b[113;5u"));
+    }
+}
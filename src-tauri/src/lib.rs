@@ -1,108 +1,874 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use deno_runtime::deno_core::{ModuleSpecifier, FsModuleLoader};
-use deno_runtime::deno_fs::RealFs;
-use deno_runtime::deno_permissions::PermissionsContainer;
-use deno_runtime::permissions::RuntimePermissionDescriptorParser;
-use deno_runtime::worker::{MainWorker, WorkerOptions, WorkerServiceOptions};
-use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySize};
+mod accessibility;
+mod appearance;
+mod asciicast;
+mod askpass;
+mod buffers;
+mod cli;
+mod clipboard;
+mod command_history;
+mod config;
+mod config_editor;
+mod config_permissions;
+#[cfg(test)]
+mod config_types;
+mod connections;
+mod context_menu;
+mod crash;
+mod cursor;
+mod cwd;
+mod dec_modes;
+mod detach;
+mod diagnostics;
+mod docker;
+mod duplicate;
+mod environment;
+mod error;
+mod event_log;
+mod export;
+mod file_drop;
+mod file_transfer;
+mod foreground;
+mod gpu;
+mod graphics;
+mod identity;
+mod input_broadcast;
+mod input_recording;
+mod integrity;
+mod integration_scripts;
+mod k8s;
+mod keybindings;
+mod keyboard;
+mod links;
+mod module_loader;
+mod monitor;
+mod mouse;
+mod notifications;
+mod on_exit;
+mod opacity;
+mod output_batch;
+mod output_log;
+mod palette;
+mod panes;
+mod paste;
+mod playback;
+mod plugins;
+mod port_forward;
+mod process_icons;
+mod process_info;
+mod pty_backend;
+mod quake;
+mod reader;
+mod redact;
+mod remote;
+mod renderer;
+mod reset;
+mod screen;
+mod scrollback;
+mod scrollback_spill;
+mod search;
+mod secure_input;
+mod selection;
+mod serial;
+mod serve;
+mod session;
+mod settings;
+mod shell_integration;
+mod shell_profile;
+mod shells;
+mod signal;
+mod size;
+mod ssh;
+mod ssh_agent;
+mod startup;
+mod startup_layout;
+mod startup_notification;
+mod statusline;
+mod steppe_api;
+mod suggestions;
+mod tabs;
+mod tasks;
+mod telnet;
+mod termios;
+mod themes;
+mod title;
+mod tmux;
+mod tray;
+mod triggers;
+mod url_history;
+mod url_opener;
+mod wallpaper;
+mod watch;
+mod window;
+mod write_limit;
+mod wsl;
+mod zip;
+
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, PtyPair, PtySize};
 use std::fs::{create_dir_all, File};
 use std::{
-    io::{BufRead, BufReader, Read, Write}, path::Path, process::exit, sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    }, thread::{self}, rc::Rc, path::PathBuf
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Write, path::Path, sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, OnceLock,
+    }, thread::{self}, path::PathBuf
+};
+
+use serde::Serialize;
+use tokio::sync::{
+    broadcast,
+    mpsc::{unbounded_channel, UnboundedSender},
+    watch,
 };
+use tauri::{
+    async_runtime::{self, Mutex as AsyncMutex},
+    ipc::{Channel, InvokeResponseBody},
+    AppHandle, Emitter, Manager, State,
+};
+use tauri_plugin_global_shortcut::Shortcut;
+
+use appearance::{CellMetrics, FontRenderingOptions};
+use asciicast::Recording;
+use command_history::CommandHistory;
+use context_menu::ContextMenuContext;
+use cursor::CursorColors;
+use dec_modes::DecModeScanner;
+use duplicate::StartedWith;
+use buffers::Buffers;
+use cli::LaunchOptions;
+use config::WorkerCommand;
+use connections::ConnectionBook;
+use error::SteppeError;
+use event_log::{EventLogs, SessionEventKind};
+use foreground::ForegroundProcess;
+use input_recording::InputRecorder;
+use k8s::K8sShell;
+use keybindings::Keybindings;
+use module_loader::ModuleInfo;
+use monitor::Monitor;
+use mouse::MouseReportMode;
+use notifications::NotificationSettings;
+use on_exit::{OnExit, Respawn};
+use opacity::FocusOpacity;
+use output_batch::OutputBatch;
+use output_log::{OutputLog, OutputLogSettings};
+use palette::PaletteCommands;
+use panes::Layout;
+use playback::Playback;
+use plugins::Plugins;
+use port_forward::PortForward;
+use process_icons::ProcessIcons;
+use redact::Redactor;
+use remote::RemoteListener;
+use renderer::RendererBackend;
+use screen::Screen;
+use scrollback::Scrollback;
+use scrollback_spill::SpillOptions;
+use search::SearchSession;
+use session::SavedSession;
+use settings::Settings;
+use shell_integration::ShellIntegration;
+use shell_profile::ShellProfile;
+use size::SizeConstraints;
+use ssh::SshProfile;
+use startup::TerminalSequence;
+use startup_notification::StartupNotification;
+use statusline::StatusLine;
+use steppe_api::{ScriptEvent, ScriptOutput};
+use termios::TermiosSettings;
+use tabs::Tabs;
+use tasks::TaskTerminals;
+use telnet::Telnet;
+use themes::Theme;
+use tmux::TmuxSession;
+use triggers::{LineBuffer, Triggers};
+use url_history::UrlHistory;
+use url_opener::UrlOpener;
+use wallpaper::CachedPalette;
+use watch::Watch;
+use write_limit::WriteLimiter;
 
-use tauri::{async_runtime::Mutex as AsyncMutex, State};
+/// The shell started in a terminal
+#[derive(Clone)]
+struct ShellProcess {
+    program: String,
+    /// Set when this was started from a profile in config.js
+    shell_profile: Option<String>,
+    /// Set when this is an ssh connection rather than a local shell
+    ssh_profile: Option<String>,
+    wsl_distro: Option<String>,
+    /// Set when this is a shell inside a docker container, by id or name
+    docker_container: Option<String>,
+    /// Set when this is a shell inside a kubernetes pod
+    k8s_shell: Option<K8sShell>,
+    pid: Option<u32>,
+    /// Typed into the shell as soon as it's started, like a profile's `initCommand`
+    init_command: Option<String>,
+}
 
+/// A pty, and whether a shell has been started in it yet
 struct SubTerminal {
     pty_pair: Arc<AsyncMutex<PtyPair>>,
     writer: Arc<AsyncMutex<Box<dyn Write + Send>>>,
-    reader: Arc<AsyncMutex<BufReader<Box<dyn Read + Send>>>>,
     has_terminal: AtomicBool,
+    shell: OnceLock<ShellProcess>,
+    /// What the shell was started with, for duplicating this terminal
+    started_with: OnceLock<StartedWith>,
+    /// What's changed in the shell's environment since it started, as last
+    /// seen, see `environment::snapshot`
+    environment: Mutex<Option<BTreeMap<String, String>>>,
+    /// Open over this terminal's ssh connection, if it is one
+    port_forwards: AsyncMutex<Vec<PortForward>>,
+    /// Output sent to the frontend that xterm hasn't written yet
+    unacked_bytes: Mutex<usize>,
+    output_acked: Condvar,
+    /// Set while the user has output paused, see `async_pause_output`
+    output_paused: AtomicBool,
+    scrollback: AsyncMutex<Scrollback>,
+    screen: AsyncMutex<Screen>,
+    trigger_lines: AsyncMutex<LineBuffer>,
+    shell_integration: AsyncMutex<ShellIntegration>,
+    notifications: NotificationSettings,
+    /// Where this terminal's being recorded to, if anywhere
+    recording: AsyncMutex<Option<Recording>>,
+    /// Set when this plays back a recording rather than running a shell
+    playback: OnceLock<Playback>,
+    /// Set when this is a telnet connection rather than a shell, for what
+    /// goes to the host besides typing
+    telnet: OnceLock<Arc<Telnet>>,
+    /// The file this terminal's output is being logged to, if any
+    output_log: AsyncMutex<Option<OutputLog>>,
+    /// Watches output for the program switching terminal modes
+    dec_modes: AsyncMutex<DecModeScanner>,
+    /// Mouse reporting modes the program has turned on
+    mouse_report_mode: AsyncMutex<MouseReportMode>,
+    /// The find bar's search, kept up to date as output comes in
+    search: AsyncMutex<Option<SearchSession>>,
+    /// Whether it looks like a password's being typed, see `secure_input::check`
+    secure_input: AtomicBool,
+    /// Files that rerun a command when they change, see `watch::async_set_watch`
+    watch: AsyncMutex<Option<Watch>>,
+    /// Output for clients attached with `steppe attach`, see `remote`
+    remote_output: broadcast::Sender<String>,
+    /// The screen's lines as of the last accessible snapshot, to tell what's
+    /// changed since, see `accessibility::async_get_accessible_snapshot`
+    accessible_lines: AsyncMutex<Vec<String>>,
+    /// What the status bar shows for this terminal, see `statusline`
+    status_line: AsyncMutex<StatusLine>,
+    /// Whether it's the terminal the user's looking at, as the frontend says
+    focused: AtomicBool,
+    /// Watching for it to start or stop printing, see `monitor`
+    monitor: Mutex<Monitor>,
+    /// The kitty keyboard protocol flags last sent with `terminal://keyboard-mode`
+    keyboard_flags: AtomicU8,
+    /// Set while its output thread's crashed, until it's started again
+    reader_dead: AtomicBool,
+    /// Set once that's crashed too many times in a row, when its output
+    /// stops being handled at all, see `reader::spawn_reader`
+    corrupted: AtomicBool,
+}
+
+impl SubTerminal {
+    fn open(scrollback_lines: usize, spill: Option<SpillOptions>, size: PtySize) -> Result<Self, SteppeError> {
+        let pty_pair = native_pty_system().openpty(size).map_err(|err| SteppeError::Pty(err.to_string()))?;
+
+        let writer = pty_pair.master.take_writer().map_err(|err| SteppeError::Pty(err.to_string()))?;
+
+        Ok(Self {
+            pty_pair: Arc::new(AsyncMutex::new(pty_pair)),
+            writer: Arc::new(AsyncMutex::new(writer)),
+            has_terminal: AtomicBool::new(false),
+            shell: OnceLock::new(),
+            started_with: OnceLock::new(),
+            environment: Mutex::new(None),
+            port_forwards: AsyncMutex::new(Vec::new()),
+            unacked_bytes: Mutex::new(0),
+            output_acked: Condvar::new(),
+            output_paused: AtomicBool::new(false),
+            scrollback: AsyncMutex::new(Scrollback::new(scrollback_lines, spill)),
+            screen: AsyncMutex::new(Screen::new(size.rows, size.cols)),
+            trigger_lines: AsyncMutex::new(LineBuffer::default()),
+            shell_integration: AsyncMutex::new(ShellIntegration::default()),
+            notifications: NotificationSettings::default(),
+            recording: AsyncMutex::new(None),
+            playback: OnceLock::new(),
+            telnet: OnceLock::new(),
+            output_log: AsyncMutex::new(None),
+            dec_modes: AsyncMutex::new(DecModeScanner::default()),
+            mouse_report_mode: AsyncMutex::new(MouseReportMode::default()),
+            search: AsyncMutex::new(None),
+            secure_input: AtomicBool::new(false),
+            watch: AsyncMutex::new(None),
+            remote_output: broadcast::channel(remote::OUTPUT_CHANNEL_CAPACITY).0,
+            accessible_lines: AsyncMutex::new(Vec::new()),
+            status_line: AsyncMutex::new(StatusLine::default()),
+            focused: AtomicBool::new(false),
+            monitor: Mutex::new(Monitor::default()),
+            keyboard_flags: AtomicU8::new(0),
+            reader_dead: AtomicBool::new(false),
+            corrupted: AtomicBool::new(false),
+        })
+    }
+}
+
+/// The terminal that features without a terminal id of their own (startup
+/// notifications, batching, the cursor's inverse video, ...) follow
+const SESSION_ID: u32 = 0;
+
+fn check_session(session_id: u32) -> Result<(), SteppeError> {
+    if session_id == SESSION_ID {
+        Ok(())
+    } else {
+        Err(SteppeError::SessionNotFound(session_id))
+    }
 }
 
 struct AppState {
-    pty_pair: Arc<AsyncMutex<PtyPair>>,
-    writer: Arc<AsyncMutex<Box<dyn Write + Send>>>,
-    reader: Arc<AsyncMutex<BufReader<Box<dyn Read + Send>>>>,
-    has_terminal: AtomicBool,
+    /// Every open terminal, by the id the frontend gave it
+    terminals: AsyncMutex<HashMap<u32, Arc<SubTerminal>>>,
+    /// Where the backend starts looking for an id for a terminal it opens
+    /// itself, past any it's seen, see `AppState::open_new_terminal`
+    next_terminal_id: AtomicU32,
+    /// `None` leaves the pty with the system defaults
+    line_discipline: AsyncMutex<Option<TermiosSettings>>,
+    url_opener: AsyncMutex<UrlOpener>,
+    renderer_backend: AsyncMutex<RendererBackend>,
+    size_constraints: AsyncMutex<SizeConstraints>,
+    expose_secrets_in_diagnostics: AtomicBool,
+    startup_delay_ms: AtomicU64,
+    startup_sequences: AsyncMutex<Vec<TerminalSequence>>,
+    /// Whether the shell has printed anything yet, and so been sent the startup sequences
+    startup_sequences_sent: AtomicBool,
+    /// What the last context menu was opened on
+    context_menu: AsyncMutex<ContextMenuContext>,
+    font_rendering: AsyncMutex<FontRenderingOptions>,
+    /// Colors configured for the block cursor
+    cursor_colors: AsyncMutex<CursorColors>,
+    /// Whether the screen is in reverse video (DECSCNM)
+    inverse_video: AtomicBool,
+    /// Vim style named buffers, `+` and `*` aren't stored here
+    buffers: AsyncMutex<Buffers>,
+    /// The task emitting `gpu-memory-updated`, if monitoring is on
+    gpu_monitor: AsyncMutex<Option<async_runtime::JoinHandle<()>>>,
+    cell_metrics: AsyncMutex<CellMetrics>,
+    startup_notification: AsyncMutex<StartupNotification>,
+    event_log: AsyncMutex<EventLogs>,
+    wallpaper_palette: AsyncMutex<CachedPalette>,
+    /// What the user has typed since recording started, if it's recording
+    input_recording: AsyncMutex<Option<InputRecorder>>,
+    /// Pty output being held back while batch mode is on
+    output_batch: AsyncMutex<OutputBatch>,
+    /// Terminals that get whatever's typed into any of them
+    broadcast: AsyncMutex<BTreeSet<u32>>,
+    /// The hotkey that drops down the quake window, if one's bound
+    quake_shortcut: AsyncMutex<Option<Shortcut>>,
+    /// Where `remoteAttach` has steppe listening, see `remote::configure`
+    remote_listener: Mutex<Option<RemoteListener>>,
+    /// What's taken out of logs, recordings, history and exports, see `redact`
+    redactor: Mutex<Arc<Redactor>>,
+    url_history: AsyncMutex<UrlHistory>,
+    /// What the user is running in each terminal, as of the last poll
+    foreground_process: AsyncMutex<HashMap<u32, ForegroundProcess>>,
+    /// Which icon a tab shows for what's running in it
+    process_icons: AsyncMutex<ProcessIcons>,
+    focus_opacity: AsyncMutex<FocusOpacity>,
+    /// `None` lets writes through as fast as they come
+    write_limiter: AsyncMutex<Option<WriteLimiter>>,
+    /// How many lines of scrollback new terminals keep
+    scrollback_lines: AtomicUsize,
+    /// Whether new terminals spill scrollback past that to disk
+    scrollback_spill: Mutex<Option<SpillOptions>>,
+    /// The most output each `terminal://output` gathers up, see `reader::spawn_reader`
+    coalesce_bytes: AtomicUsize,
+    /// How often a terminal that keeps printing sends its output
+    coalesce_interval_ms: AtomicU64,
+    /// Where each terminal's output goes once its frontend's subscribed, see `reader::emit_output`
+    output_channels: AsyncMutex<HashMap<u32, Channel<InvokeResponseBody>>>,
+    /// The biggest image a program can show, see `graphics::Graphics::split`
+    max_image_bytes: AtomicUsize,
+    /// Whether to save open terminals on exit and bring them back next time
+    restore_session: AtomicBool,
+    /// Whether closing the window leaves steppe running in the background
+    detach_on_close: AtomicBool,
+    /// Whether closing the last window leaves steppe running in the tray
+    keep_alive_in_tray: AtomicBool,
+    /// Whether this is `steppe serve`, which runs with no window at all
+    serving: AtomicBool,
+    ssh_profiles: AsyncMutex<Vec<SshProfile>>,
+    /// Hosts saved from the frontend, see `connections::ConnectionBook`
+    connections: AsyncMutex<ConnectionBook>,
+    shell_profiles: AsyncMutex<Vec<ShellProfile>>,
+    /// Output for `steppe.onOutput` in config.js
+    script_output: broadcast::Sender<ScriptOutput>,
+    /// Terminals opening, closing and so on, for `steppe.on` in config.js
+    script_events: broadcast::Sender<ScriptEvent>,
+    plugins: AsyncMutex<Plugins>,
+    keybindings: AsyncMutex<Keybindings>,
+    /// The colors config.js picked, if it picked any
+    theme: AsyncMutex<Option<Theme>>,
+    /// Output patterns config.js is watching for
+    triggers: AsyncMutex<Triggers>,
+    /// What config.js and plugins added to the command palette
+    palette_commands: AsyncMutex<PaletteCommands>,
+    /// Where each of config.js's tasks last ran, see `tasks::run`
+    task_terminals: AsyncMutex<TaskTerminals>,
+    /// What the startup layout opened in the main window, once it has
+    startup_session: AsyncMutex<Option<SavedSession>>,
+    /// How long a command runs before its end is worth a `command://finished`
+    long_command_ms: AtomicU64,
+    /// Whether the window title follows the main terminal's
+    window_title_from_terminal: AtomicBool,
+    /// Whether programs can copy to the clipboard with OSC 52
+    clipboard_write: AtomicBool,
+    /// Whether programs can read the clipboard with OSC 52
+    clipboard_read: AtomicBool,
+    /// Where new terminals log their output, if they do
+    output_logging: AsyncMutex<Option<OutputLogSettings>>,
+    /// Commands run in any terminal, for searching back through
+    command_history: AsyncMutex<CommandHistory>,
+    /// Whether closing the window asks first when something's still running
+    confirm_close: AtomicBool,
+    /// Set once the frontend's asked, so the next close goes through
+    close_confirmed: AtomicBool,
+    /// What steppe was started with, until the main terminal's picked it up
+    launch: AsyncMutex<LaunchOptions>,
+    /// Set when the main terminal runs a `-e` command without `--hold`, so steppe quits with it
+    quit_with_session: AtomicBool,
+    /// Which window each terminal's in, for the ones that aren't in the main one
+    terminal_windows: AsyncMutex<HashMap<u32, String>>,
+    /// Tab order and names, see `tabs::async_list_tabs`
+    tabs: AsyncMutex<Tabs>,
+    /// Each split window's panes, by window label
+    layouts: AsyncMutex<HashMap<String, Layout>>,
+    /// The terminal last focused in each window, by its label, which files
+    /// dropped on the window go to
+    focused_terminals: AsyncMutex<HashMap<String, u32>>,
+    /// The terminal each window last raised a command notification for, to
+    /// focus when the window next is
+    pending_focus: AsyncMutex<HashMap<String, u32>>,
+    /// `tmux -CC` running in a terminal, by that terminal's id
+    tmux: AsyncMutex<HashMap<u32, TmuxSession>>,
+    /// What the settings file and config.js add up to
+    settings: AsyncMutex<Settings>,
+    /// Set once `settings` has been put in effect the first time
+    settings_loaded: watch::Sender<bool>,
+    /// To the thread running config.js, for anything that needs js run
+    worker_commands: UnboundedSender<WorkerCommand>,
+    /// What config.js and the plugins have loaded since the last reload
+    module_graph: Mutex<Vec<ModuleInfo>>,
 }
 
-#[tauri::command]
-async fn async_create_shell(state: State<'_, AppState>) -> Result<(), String> {
-    if state.has_terminal.load(Ordering::Acquire) {
+impl AppState {
+    /// Terminal `id`, handed out so the registry isn't held onto while it's in use
+    async fn terminal(&self, id: u32) -> Result<Arc<SubTerminal>, SteppeError> {
+        self.terminals.lock().await.get(&id).cloned().ok_or(SteppeError::SessionNotFound(id))
+    }
+
+    /// Terminal `id`, opening a pty for it if this is the first the backend's
+    /// heard of it. The frontend sizes the pty before it starts the shell.
+    async fn open_terminal(&self, id: u32) -> Result<Arc<SubTerminal>, SteppeError> {
+        let size = self.first_size().await;
+
+        let mut terminals = self.terminals.lock().await;
+        if let Some(terminal) = terminals.get(&id) {
+            return Ok(terminal.clone());
+        }
+        // so the backend doesn't go on to hand it out again
+        self.next_terminal_id.fetch_max(id.saturating_add(1), Ordering::Relaxed);
+
+        self.insert_terminal(&mut terminals, id, size).await
+    }
+
+    /// A new terminal, under an id no other has. It's picked under the same
+    /// lock the terminal's added under, so nothing else can take it in between.
+    async fn open_new_terminal(&self) -> Result<(u32, Arc<SubTerminal>), SteppeError> {
+        let size = self.first_size().await;
+
+        let mut terminals = self.terminals.lock().await;
+        let id = loop {
+            let id = self.next_terminal_id.fetch_add(1, Ordering::Relaxed);
+            if !terminals.contains_key(&id) {
+                break id;
+            }
+        };
+
+        Ok((id, self.insert_terminal(&mut terminals, id, size).await?))
+    }
+
+    /// Programs that ask before the first resize get the pixels to go with 80x24
+    async fn first_size(&self) -> PtySize {
+        let (pixel_width, pixel_height) = self.cell_metrics.lock().await.pixel_size(24, 80);
+
+        PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width,
+            pixel_height,
+        }
+    }
+
+    async fn insert_terminal(
+        &self,
+        terminals: &mut HashMap<u32, Arc<SubTerminal>>,
+        id: u32,
+        size: PtySize,
+    ) -> Result<Arc<SubTerminal>, SteppeError> {
+        let scrollback_lines = self.scrollback_lines.load(Ordering::Relaxed);
+        let spill = self.scrollback_spill.lock().unwrap_or_else(|err| err.into_inner()).clone();
+        let terminal = Arc::new(SubTerminal::open(scrollback_lines, spill, size)?);
+        terminals.insert(id, terminal.clone());
+        output_log::start_configured(self, id, &terminal).await;
+
+        Ok(terminal)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct TerminalExited {
+    terminal_id: u32,
+    code: u32,
+    /// What the backend did about it
+    on_exit: OnExit,
+}
+
+/// Forget about a terminal whose shell is gone, then close it, start it
+/// again or leave it be, going by the `onExit` setting. Only terminals with
+/// a `respawn` (ones that ran a command) can be started again.
+async fn terminal_exited(app: &AppHandle, terminal_id: u32, code: u32, mut respawn: Option<Respawn>) {
+    let state = app.state::<AppState>();
+    let mut on_exit = on_exit::policy(&state, code, respawn.as_ref()).await;
+
+    if let Ok(terminal) = state.terminal(terminal_id).await {
+        on_exit::before_removal(app, &state, terminal_id, &terminal, code, on_exit, respawn.as_mut()).await;
+    }
+
+    // a paused reader would never get to the end of the output and finish
+    if let Some(terminal) = state.terminals.lock().await.remove(&terminal_id) {
+        terminal.set_output_paused(false);
+    }
+    crash::ACTIVE_SESSIONS.fetch_sub(1, Ordering::Relaxed);
+    event_log::record(&state, terminal_id, SessionEventKind::ShellExited { code: code as i32 }, None).await;
+    steppe_api::emit_event(
+        &state,
+        ScriptEvent::TerminalClosed {
+            terminal_id,
+            exit_code: code,
+        },
+    );
+
+    if terminal_id == SESSION_ID && state.quit_with_session.swap(false, Ordering::Relaxed) {
+        app.exit(code as i32);
+        return;
+    }
+
+    if let (OnExit::Restart, Some(respawn)) = (on_exit, respawn) {
+        if let Err(err) = on_exit::restart(app, &state, terminal_id, respawn).await {
+            eprintln!("could not restart shell: {err}");
+            on_exit = OnExit::Hold;
+        }
+    }
+
+    if let Err(err) = app.emit("terminal://exited", TerminalExited { terminal_id, code, on_exit }) {
+        eprintln!("could not send terminal exit: {err}");
+    }
+
+    if on_exit == OnExit::Close {
+        if let Err(err) = on_exit::close(app, &state, terminal_id).await {
+            eprintln!("could not close tab: {err}");
+        }
+    }
+}
+
+/// Run `cmd` in terminal `terminal_id`, unless something's running there already.
+async fn spawn_in_terminal(
+    terminal_id: u32,
+    mut cmd: CommandBuilder,
+    mut process: ShellProcess,
+    app: AppHandle,
+    state: &AppState,
+) -> Result<(), String> {
+    let terminal = state.open_terminal(terminal_id).await.map_err(|err| err.to_string())?;
+    if terminal.has_terminal.load(Ordering::Acquire) {
         return Ok(());
     }
 
-    #[cfg(target_os = "windows")]
-    let mut cmd = CommandBuilder::new("powershell.exe");
+    if terminal_id == SESSION_ID {
+        startup_notification::session_created(state).await;
+    }
+    event_log::record(state, terminal_id, SessionEventKind::Created, None).await;
+    startup::startup_delay(state).await;
 
-    #[cfg(not(target_os = "windows"))]
-    let mut cmd = {
-        let path = std::env::var("SHELL").map_err(|_| "Could not grab preferred shell from $SHELL")?;
-        CommandBuilder::new(path)
+    // the msys shells (git bash and friends) read it on windows, and the native ones ignore it
+    cmd.env("TERM", identity::term(state, process.ssh_profile.is_some()).await);
+
+    let pty_pair = terminal.pty_pair.lock().await;
+    let respawn = Respawn::new(cmd.clone(), process.clone());
+    let _ = terminal.started_with.set(StartedWith::new(cmd.clone(), process.clone()));
+
+    // anything that can fail goes before the shell's started, so it's never left
+    // running with nothing reading or waiting on it
+    if let Some(settings) = &*state.line_discipline.lock().await {
+        termios::apply(pty_pair.master.as_ref(), settings).map_err(|err| err.to_string())?;
+    }
+    let output = pty_pair.master.try_clone_reader().map_err(|err| err.to_string())?;
+
+    let mut child = match pty_pair.slave.spawn_command(cmd) {
+        Ok(child) => child,
+        Err(err) => {
+            event_log::record(state, terminal_id, SessionEventKind::Error(err.to_string()), None).await;
+            return Err(err.to_string());
+        }
     };
 
-    // add the $TERM env variable
+    let pid = child.process_id();
+    process.pid = pid;
+    let init_command = process.init_command.take();
+    let _ = terminal.shell.set(process);
+    event_log::record(state, terminal_id, SessionEventKind::ShellSpawned, pid.map(|pid| format!("pid {pid}"))).await;
 
-    #[cfg(target_os = "windows")]
-    cmd.env("TERM", "cygwin");
+    // with nothing reading it the shell's no use, so it's ended (and not
+    // started again), which closes the terminal the usual way
+    let respawn = match reader::spawn_reader(app.clone(), terminal_id, terminal.clone(), output) {
+        Ok(_) => Some(respawn),
+        Err(err) => {
+            eprintln!("could not read from terminal {terminal_id}: {err}");
+            event_log::record(state, terminal_id, SessionEventKind::Error(err.to_string()), None).await;
+            if let Err(err) = child.kill() {
+                eprintln!("could not end the shell: {err}");
+            }
+            None
+        }
+    };
 
-    #[cfg(not(target_os = "windows"))]
-    cmd.env("TERM", "xterm-256color");
+    // the pty holds onto it until the shell's ready to read
+    if let Some(init_command) = init_command {
+        if let Err(err) = write!(terminal.writer.lock().await, "{init_command}\r") {
+            eprintln!("could not run init command: {err}");
+        }
+    }
 
-    let mut child = state
-        .pty_pair
-        .lock()
-        .await
-        .slave
-        .spawn_command(cmd)
-        .map_err(|err| err.to_string())?;
+    terminal.has_terminal.store(true, Ordering::Release);
+    crash::ACTIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+
+    if let Some(process) = terminal.shell.get() {
+        steppe_api::emit_event(
+            state,
+            ScriptEvent::TerminalOpened {
+                terminal_id,
+                program: process.program.clone(),
+                profile: process.shell_profile.clone(),
+                ssh_profile: process.ssh_profile.clone(),
+                pid,
+            },
+        );
+    }
+    statusline::spawn_refresh(&app, terminal_id);
 
     thread::spawn(move || {
-        let status = child.wait().unwrap();
-        exit(status.exit_code() as i32)
+        let code = match child.wait() {
+            Ok(status) => status.exit_code(),
+            Err(err) => {
+                eprintln!("could not wait for the shell to exit: {err}");
+                1
+            }
+        };
+        async_runtime::block_on(terminal_exited(&app, terminal_id, code, respawn));
     });
 
-    state.has_terminal.store(true, Ordering::Release);
-
     Ok(())
 }
 
+/// Start a shell in terminal `terminal_id`. `shell` and `cwd` default to
+/// `$SHELL` and wherever steppe was started, and are mainly for restoring
+/// sessions, as is `environment`, which is set on top of everything else.
+/// With `wsl_distro`, the shell is that distro's instead (windows only). The
+/// first main terminal runs whatever steppe was launched with instead, if anything.
 #[tauri::command]
-async fn async_write_to_pty(data: &str, state: State<'_, AppState>) -> Result<(), ()> {
-    write!(state.writer.lock().await, "{}", data).map_err(|_| ())
+async fn async_create_shell(
+    terminal_id: u32,
+    shell: Option<String>,
+    cwd: Option<PathBuf>,
+    wsl_distro: Option<String>,
+    environment: Option<BTreeMap<String, String>>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let launch = if terminal_id == SESSION_ID {
+        cli::take(&state).await
+    } else {
+        LaunchOptions::default()
+    };
+    let cwd = launch.working_directory.clone().or(cwd);
+
+    if let Some((cmd, process)) = launch.command(cwd.as_deref()) {
+        state.quit_with_session.store(!launch.hold, Ordering::Relaxed);
+        return spawn_in_terminal(terminal_id, cmd, process, app, &state).await;
+    }
+
+    let settings = state.settings.lock().await.clone();
+    let shell = shell.or(settings.default_shell);
+
+    if let Some(distro) = wsl_distro {
+        let cmd = wsl::command(&distro, cwd.as_deref()).map_err(|err| err.to_string())?;
+        let process = ShellProcess {
+            program: "wsl.exe".to_string(),
+            shell_profile: None,
+            ssh_profile: None,
+            wsl_distro: Some(distro),
+            docker_container: None,
+            k8s_shell: None,
+            pid: None,
+            init_command: None,
+        };
+
+        return spawn_in_terminal(terminal_id, cmd, process, app, &state).await;
+    }
+
+    #[cfg(target_os = "windows")]
+    let program = shell.unwrap_or_else(shells::default_shell);
+
+    #[cfg(not(target_os = "windows"))]
+    let program = match shell {
+        Some(shell) => shell,
+        None => std::env::var("SHELL").map_err(|_| "Could not grab preferred shell from $SHELL")?,
+    };
+
+    let mut cmd = CommandBuilder::new(&program);
+    let login = cfg!(not(target_os = "windows")) && settings.login_shell.unwrap_or(cfg!(target_os = "macos"));
+    let shell_args = settings.shell_args.unwrap_or_default();
+    // shellArgs could well clash with what injecting adds, so they're taken as opting out
+    let injected = settings.shell_integration.unwrap_or(true)
+        && shell_args.is_empty()
+        && integration_scripts::inject(&mut cmd, &program, login);
+    if login && !injected {
+        cmd.arg("-l");
+    }
+    cmd.args(shell_args);
+    for (key, value) in settings.shell_env.unwrap_or_default() {
+        cmd.env(key, value);
+    }
+    for (key, value) in environment.unwrap_or_default() {
+        cmd.env(key, value);
+    }
+    if let Some(cwd) = cwd {
+        cmd.cwd(cwd);
+    }
+
+    let process = ShellProcess {
+        program,
+        shell_profile: None,
+        ssh_profile: None,
+        wsl_distro: None,
+        docker_container: None,
+        k8s_shell: None,
+        pid: None,
+        init_command: settings.init_command,
+    };
+
+    spawn_in_terminal(terminal_id, cmd, process, app, &state).await
 }
 
-#[tauri::command]
-async fn async_read_from_pty(state: State<'_, AppState>) -> Result<Option<String>, ()> {
-    let mut reader = state.reader.lock().await;
-    let data = {
-        // Read all available text
-        let data = reader.fill_buf().map_err(|_| ())?;
-
-        // Send te data to the webview if necessary
-        if data.len() > 0 {
-            std::str::from_utf8(data)
-                .map(|v| Some(v.to_string()))
-                .map_err(|_| ())?
-        } else {
-            None
+/// An id no other terminal has, taken by opening its pty
+async fn next_terminal_id(state: &AppState) -> Result<u32, SteppeError> {
+    Ok(state.open_new_terminal().await?.0)
+}
+
+/// Start a shell (or `profile`) in a terminal of its own for window `window`,
+/// in `cwd` if given. Returns its id.
+async fn start_terminal(
+    app: &AppHandle,
+    profile: Option<String>,
+    cwd: Option<PathBuf>,
+    window: &str,
+) -> Result<u32, SteppeError> {
+    let terminal_id = next_terminal_id(&app.state()).await?;
+    window::assign(&app.state(), terminal_id, window).await;
+
+    match profile {
+        Some(name) => {
+            shell_profile::async_create_shell_with_profile(terminal_id, name, app.clone(), app.state()).await?
         }
-    };
+        None => async_create_shell(terminal_id, None, cwd, None, None, app.clone(), app.state())
+            .await
+            .map_err(SteppeError::Pty)?,
+    }
+
+    Ok(terminal_id)
+}
 
-    if let Some(data) = &data {
-        reader.consume(data.len());
+/// Like `start_terminal`, and let window `window` know there's a new
+/// terminal to show.
+async fn spawn_terminal(
+    app: &AppHandle,
+    profile: Option<String>,
+    cwd: Option<PathBuf>,
+    window: &str,
+) -> Result<u32, SteppeError> {
+    let terminal_id = start_terminal(app, profile, cwd, window).await?;
+    app.emit_to(window, "terminal://spawned", terminal_id)?;
+
+    Ok(terminal_id)
+}
+
+/// Send `data` to terminal `terminal_id` as if it were typed, and record it
+/// wherever input's being recorded, unless it looks like a password.
+async fn write_input(app: &AppHandle, state: &AppState, terminal_id: u32, data: &str) -> Result<(), SteppeError> {
+    let terminal = state.open_terminal(terminal_id).await?;
+    // nothing it prints would show up
+    if terminal.corrupted.load(Ordering::Relaxed) {
+        return Err(SteppeError::Corrupted(terminal_id));
     }
 
-    Ok(data)
+    write_limit::throttle(state, data.len()).await;
+    write!(terminal.writer.lock().await, "{}", data)?;
+
+    if secure_input::check(app, terminal_id, &terminal).await {
+        return Ok(());
+    }
+    asciicast::record(state, terminal_id, &terminal, asciicast::Event::Input, data).await;
+
+    if terminal_id == SESSION_ID {
+        input_recording::record(state, data).await;
+    }
+
+    Ok(())
 }
 
 #[tauri::command]
-async fn async_resize_pty(rows: u16, cols: u16, state: State<'_, AppState>) -> Result<(), ()> {
-    state
+async fn async_write_to_pty(
+    terminal_id: u32,
+    data: &str,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), ()> {
+    for target in input_broadcast::targets(&state, terminal_id).await {
+        write_input(&app, &state, target, data).await.map_err(|_| ())?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn async_resize_pty(
+    terminal_id: u32,
+    rows: u16,
+    cols: u16,
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
+    state: State<'_, AppState>,
+) -> Result<(u16, u16), ()> {
+    let terminal = state.open_terminal(terminal_id).await.map_err(|_| ())?;
+    let requested = (rows, cols);
+    let (rows, cols) = state.size_constraints.lock().await.apply(rows, cols);
+
+    let (pixel_width, pixel_height) = match (pixel_width, pixel_height) {
+        // the frontend measured the size it asked for, which only holds if that's what it got
+        (Some(width), Some(height)) if requested == (rows, cols) => (width, height),
+        _ => state.cell_metrics.lock().await.pixel_size(rows, cols),
+    };
+
+    terminal
         .pty_pair
         .lock()
         .await
@@ -110,93 +876,433 @@ async fn async_resize_pty(rows: u16, cols: u16, state: State<'_, AppState>) -> R
         .resize(PtySize {
             rows,
             cols,
-            ..Default::default()
+            pixel_width,
+            pixel_height,
         })
-        .map_err(|_| ())
+        .map_err(|_| ())?;
+    terminal.screen.lock().await.resize(rows, cols);
+    tmux::resized(&state, terminal_id, rows, cols).await;
+    telnet::resized(&terminal, rows, cols);
+    let dimensions = format!("{cols}x{rows}");
+    asciicast::record(&state, terminal_id, &terminal, asciicast::Event::Resize, &dimensions).await;
+
+    event_log::record(&state, terminal_id, SessionEventKind::Resized { rows, cols }, None).await;
+
+    // let the frontend know if it got something other than what it asked for
+    Ok((rows, cols))
 }
 
+#[tauri::command]
+async fn async_set_pty_line_disc(
+    settings: TermiosSettings,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    // not even kept for later, or every shell started after would fail to
+    termios::check_supported()?;
+
+    for terminal in state.terminals.lock().await.values() {
+        termios::apply(terminal.pty_pair.lock().await.master.as_ref(), &settings)?;
+    }
+    *state.line_discipline.lock().await = Some(settings);
+
+    Ok(())
+}
+
+/// Where config.js and everything else steppe keeps lives: `$STEPPE_CONFIG_DIR`,
+/// then `$XDG_CONFIG_HOME/steppe`, then the platform's own config directory
+/// (`~/.config`, `~/Library/Application Support` or `%APPDATA%`).
 fn get_config_dir() -> PathBuf {
-    Path::new(&std::env::var("XDG_CONFIG_HOME").unwrap_or(
-        Path::new(&std::env::var("HOME").unwrap()).join(".config").to_string_lossy().to_string()
-    )).join("steppe")
+    static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+    CONFIG_DIR
+        .get_or_init(|| {
+            if let Some(dir) = std::env::var_os("STEPPE_CONFIG_DIR").filter(|dir| !dir.is_empty()) {
+                return PathBuf::from(dir);
+            }
+
+            if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+                return Path::new(&dir).join("steppe");
+            }
+
+            // steppe used ~/.config everywhere before, so keep using it where it's already set up
+            let legacy = dirs::home_dir().map(|home| home.join(".config").join("steppe"));
+            if let Some(legacy) = legacy.filter(|legacy| legacy.is_dir()) {
+                return legacy;
+            }
+
+            dirs::config_dir()
+                // no home to speak of, but steppe still has to put config.js somewhere
+                .unwrap_or_else(std::env::temp_dir)
+                .join("steppe")
+        })
+        .clone()
+}
+
+/// Where steppe's config is, for showing or opening in the ui
+#[tauri::command]
+async fn async_get_config_dir() -> Result<PathBuf, SteppeError> {
+    Ok(get_config_dir())
 }
 
+/// `config.ts` if there is one, otherwise `config.js`
 fn get_config_path() -> PathBuf {
+    let typescript = get_config_dir().join("config.ts");
+    if typescript.exists() {
+        return typescript;
+    }
+
     get_config_dir().join("config.js")
 }
 
-fn write_default_config(path: &PathBuf) {
+fn write_default_config(path: &PathBuf) -> std::io::Result<()> {
     if let Some(parent) = path.parent() {
-        create_dir_all(parent).unwrap();
+        create_dir_all(parent)?;
     }
 
-    File::create_new(path).unwrap().write(b"export {}").unwrap();
+    File::create_new(path)?.write_all(b"export {}")
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let path = get_config_path();
-    if !path.exists() {
-        write_default_config(&path);
-    }
-
-    // deno boilerplate from https://github.com/denoland/deno/blob/main/runtime/examples/extension/main.rs
-    let main_module = ModuleSpecifier::from_file_path(get_config_path()).unwrap();
-
-    let fs = Arc::new(RealFs);
-
-    let permission_desc_parser =
-        Arc::new(RuntimePermissionDescriptorParser::new(fs.clone()));
-
-    let mut worker = MainWorker::bootstrap_from_options(
-        main_module.clone(),
-        WorkerServiceOptions {
-            module_loader: Rc::new(FsModuleLoader),
-            permissions: PermissionsContainer::allow_all(permission_desc_parser),
-            blob_store: Default::default(),
-            broadcast_channel: Default::default(),
-            feature_checker: Default::default(),
-            node_services: Default::default(),
-            npm_process_state_provider: Default::default(),
-            root_cert_store_provider: Default::default(),
-            shared_array_buffer_store: Default::default(),
-            compiled_wasm_module_store: Default::default(),
-            v8_code_cache: Default::default(),
-            fs,
-        },
-        WorkerOptions {
-            ..Default::default()
-        },
-    );
+    // ssh runs steppe again as its askpass program, which only asks the steppe already running
+    if let Some(code) = askpass::run_if_asked() {
+        std::process::exit(code);
+    }
+    // `steppe attach <id>` is a client of the steppe already running, not a steppe of its own
+    if let Some(code) = remote::run_if_attaching() {
+        std::process::exit(code);
+    }
 
-    let pty_system = native_pty_system();
+    crash::install_panic_hook();
 
-    let pty_pair = pty_system
-        .openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .unwrap();
+    let path = get_config_path();
+    if !path.exists() {
+        if let Err(err) = write_default_config(&path) {
+            crash::exit_on_startup_error(
+                format!("could not create {}: {err}", path.display()),
+                crash::CONFIG_DIR_HINT,
+            );
+        }
+    }
 
-    let reader = pty_pair.master.try_clone_reader().unwrap();
-    let writer = pty_pair.master.take_writer().unwrap();
+    let launch = LaunchOptions::parse(std::env::args().skip(1));
+    // everything `steppe serve` needs is checked up front, rather than leaving it serving nothing
+    let serving = serve::options_if_serving()
+        .map(|options| options.unwrap_or_else(|err| crash::exit_on_startup_error(err, serve::USAGE)));
+    let is_serving = serving.is_some();
+    let (worker_commands, worker_receiver) = unbounded_channel();
 
     tauri::Builder::default()
+        // has to come first, so a second launch never gets as far as opening a window
+        .plugin(tauri_plugin_single_instance::init(detach::handle_second_instance))
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(quake::handle_shortcut)
+                .build(),
+        )
+        .setup(move |app| {
+            config::spawn_worker(app.handle().clone(), worker_receiver);
+            foreground::spawn_tracker(app.handle().clone());
+            tray::create(app.handle())?;
+            remote::listen_locally(app.handle());
+            // the window's only made here, so `steppe serve` never has one
+            match serving {
+                Some(options) => {
+                    if let Err(err) = serve::start(app.handle(), options) {
+                        crash::exit_on_startup_error(err, serve::USAGE);
+                    }
+                }
+                None => detach::attach(app.handle())?,
+            }
+            Ok(())
+        })
+        .on_menu_event(context_menu::handle_menu_event)
+        .on_window_event(|window, event| {
+            opacity::handle_window_event(window, event);
+            window::handle_window_event(window, event);
+            file_drop::handle_window_event(window, event);
+            notifications::handle_window_event(window, event);
+            if quake::hide_instead_of_close(window, event) || foreground::close_blocked(window, event) {
+                return;
+            }
+            session::handle_window_event(window, event);
+        })
         .manage(AppState {
-            pty_pair: Arc::new(AsyncMutex::new(pty_pair)),
-            writer: Arc::new(AsyncMutex::new(writer)),
-            reader: Arc::new(AsyncMutex::new(BufReader::new(reader))),
-            has_terminal: AtomicBool::new(false),
+            terminals: AsyncMutex::new(HashMap::new()),
+            // the main window's own terminal is the frontend's to open
+            next_terminal_id: AtomicU32::new(SESSION_ID + 1),
+            line_discipline: AsyncMutex::new(None),
+            url_opener: AsyncMutex::new(UrlOpener::default()),
+            renderer_backend: AsyncMutex::new(RendererBackend::default()),
+            size_constraints: AsyncMutex::new(SizeConstraints::default()),
+            expose_secrets_in_diagnostics: AtomicBool::new(false),
+            startup_delay_ms: AtomicU64::new(0),
+            startup_sequences: AsyncMutex::new(Vec::new()),
+            startup_sequences_sent: AtomicBool::new(false),
+            context_menu: AsyncMutex::new(ContextMenuContext::default()),
+            font_rendering: AsyncMutex::new(FontRenderingOptions::default()),
+            cursor_colors: AsyncMutex::new(CursorColors::default()),
+            inverse_video: AtomicBool::new(false),
+            buffers: AsyncMutex::new(Buffers::default()),
+            gpu_monitor: AsyncMutex::new(None),
+            cell_metrics: AsyncMutex::new(CellMetrics::default()),
+            startup_notification: AsyncMutex::new(StartupNotification::default()),
+            event_log: AsyncMutex::new(EventLogs::default()),
+            wallpaper_palette: AsyncMutex::new(None),
+            input_recording: AsyncMutex::new(None),
+            output_batch: AsyncMutex::new(OutputBatch::default()),
+            broadcast: AsyncMutex::new(BTreeSet::new()),
+            quake_shortcut: AsyncMutex::new(None),
+            remote_listener: Mutex::new(None),
+            redactor: Mutex::new(Arc::new(Redactor::default())),
+            url_history: AsyncMutex::new(UrlHistory::load()),
+            foreground_process: AsyncMutex::new(HashMap::new()),
+            process_icons: AsyncMutex::new(ProcessIcons::default()),
+            focus_opacity: AsyncMutex::new(FocusOpacity::default()),
+            write_limiter: AsyncMutex::new(None),
+            scrollback_lines: AtomicUsize::new(scrollback::DEFAULT_SCROLLBACK_LINES),
+            scrollback_spill: Mutex::new(None),
+            coalesce_bytes: AtomicUsize::new(reader::DEFAULT_COALESCE_BYTES),
+            coalesce_interval_ms: AtomicU64::new(reader::DEFAULT_COALESCE_INTERVAL_MS),
+            output_channels: AsyncMutex::new(HashMap::new()),
+            max_image_bytes: AtomicUsize::new(graphics::DEFAULT_MAX_IMAGE_BYTES),
+            restore_session: AtomicBool::new(true),
+            detach_on_close: AtomicBool::new(false),
+            keep_alive_in_tray: AtomicBool::new(false),
+            serving: AtomicBool::new(is_serving),
+            ssh_profiles: AsyncMutex::new(Vec::new()),
+            connections: AsyncMutex::new(ConnectionBook::load()),
+            shell_profiles: AsyncMutex::new(Vec::new()),
+            script_output: broadcast::channel(steppe_api::OUTPUT_CHANNEL_CAPACITY).0,
+            script_events: broadcast::channel(steppe_api::EVENT_CHANNEL_CAPACITY).0,
+            plugins: AsyncMutex::new(Plugins::load()),
+            keybindings: AsyncMutex::new(Keybindings::default()),
+            theme: AsyncMutex::new(None),
+            triggers: AsyncMutex::new(Triggers::default()),
+            palette_commands: AsyncMutex::new(PaletteCommands::default()),
+            task_terminals: AsyncMutex::new(TaskTerminals::default()),
+            startup_session: AsyncMutex::new(None),
+            long_command_ms: AtomicU64::new(shell_integration::DEFAULT_LONG_COMMAND_MS),
+            window_title_from_terminal: AtomicBool::new(true),
+            clipboard_write: AtomicBool::new(true),
+            clipboard_read: AtomicBool::new(false),
+            output_logging: AsyncMutex::new(None),
+            command_history: AsyncMutex::new(CommandHistory::default()),
+            confirm_close: AtomicBool::new(true),
+            close_confirmed: AtomicBool::new(false),
+            launch: AsyncMutex::new(launch),
+            quit_with_session: AtomicBool::new(false),
+            terminal_windows: AsyncMutex::new(HashMap::new()),
+            tabs: AsyncMutex::new(Tabs::default()),
+            layouts: AsyncMutex::new(HashMap::new()),
+            focused_terminals: AsyncMutex::new(HashMap::new()),
+            pending_focus: AsyncMutex::new(HashMap::new()),
+            tmux: AsyncMutex::new(HashMap::new()),
+            settings: AsyncMutex::new(Settings::default()),
+            settings_loaded: watch::channel(false).0,
+            worker_commands,
+            module_graph: Mutex::new(Vec::new()),
         })
         .invoke_handler(tauri::generate_handler![
             async_write_to_pty,
+            paste::async_paste,
+            input_broadcast::async_set_broadcast,
+            input_broadcast::async_get_broadcast,
             async_resize_pty,
             async_create_shell,
-            async_read_from_pty
+            async_set_pty_line_disc,
+            async_get_config_dir,
+            config_editor::async_read_config_source,
+            config_editor::async_write_config_source,
+            config_editor::async_get_config_schema,
+            settings::async_get_effective_config,
+            url_opener::async_open_url,
+            url_opener::async_set_url_open_mode,
+            url_opener::async_set_custom_url_opener,
+            url_opener::async_set_allow_file_urls,
+            crash::async_set_crash_reporting,
+            renderer::async_get_renderer_backend,
+            renderer::async_set_renderer_backend,
+            size::async_set_session_size_constraints,
+            diagnostics::async_get_app_state_summary,
+            diagnostics::async_set_expose_secrets_in_diagnostics,
+            diagnostics::async_create_diagnostic_bundle,
+            startup::async_set_startup_delay,
+            startup::async_wait_for_command,
+            startup::async_set_startup_sequences,
+            context_menu::async_show_context_menu,
+            appearance::async_get_font_rendering,
+            appearance::async_set_font_rendering,
+            cursor::async_get_cursor_colors,
+            cursor::async_set_cursor_colors,
+            buffers::async_set_named_buffer,
+            buffers::async_get_named_buffer,
+            buffers::async_paste_from_buffer,
+            gpu::async_get_gpu_memory_usage,
+            gpu::async_start_gpu_monitoring,
+            gpu::async_stop_gpu_monitoring,
+            appearance::async_get_cell_metrics,
+            appearance::async_set_line_height,
+            appearance::async_set_cell_spacing,
+            appearance::async_report_cell_size,
+            startup_notification::async_set_session_startup_notification,
+            startup_notification::async_measure_shell_startup_time,
+            event_log::async_get_session_event_log,
+            event_log::async_clear_session_event_log,
+            event_log::async_get_global_event_log,
+            mouse::async_get_mouse_report_mode,
+            mouse::async_set_mouse_report_mode,
+            mouse::async_send_mouse_event,
+            wallpaper::async_extract_wallpaper_palette,
+            input_recording::async_start_input_recording,
+            input_recording::async_stop_input_recording,
+            output_batch::async_set_output_batch_mode,
+            output_batch::async_flush_output_buffer,
+            url_history::async_get_url_history,
+            url_history::async_clear_url_history,
+            url_history::async_search_url_history,
+            url_history::async_set_url_history_enabled,
+            opacity::async_set_focus_opacity,
+            opacity::async_set_focus_opacity_easing,
+            write_limit::async_set_write_rate_limit,
+            reader::async_ack_terminal_output,
+            reader::async_subscribe_output,
+            reader::async_unsubscribe_output,
+            reader::async_benchmark_output,
+            reader::async_pause_output,
+            reader::async_resume_output,
+            scrollback::async_set_scrollback_lines,
+            scrollback::async_get_scrollback,
+            reset::async_clear_scrollback,
+            reset::async_hard_reset,
+            scrollback::async_search_terminal,
+            screen::async_get_screen_snapshot,
+            screen::async_get_screen_diff,
+            screen::async_get_link_at,
+            accessibility::async_get_accessible_snapshot,
+            pty_backend::async_get_pty_backend_info,
+            monitor::async_set_terminal_focused,
+            monitor::async_set_terminal_monitor,
+            monitor::async_get_terminal_monitor,
+            keyboard::async_send_key,
+            keyboard::async_get_keyboard_mode,
+            statusline::async_get_status_line,
+            search::async_begin_search,
+            search::async_next_match,
+            search::async_prev_match,
+            search::async_end_search,
+            secure_input::async_get_secure_input,
+            selection::async_start_selection,
+            selection::async_update_selection,
+            selection::async_clear_selection,
+            selection::async_get_selection_text,
+            selection::async_copy_selection,
+            links::async_list_links,
+            links::async_open_link_at,
+            export::async_export_terminal,
+            session::async_set_restore_session,
+            session::async_restore_session,
+            scrollback::async_get_terminal_replay,
+            detach::async_set_detach_on_close,
+            ssh::async_set_ssh_profiles,
+            ssh::async_list_ssh_profiles,
+            connections::async_list_connections,
+            connections::async_add_connection,
+            connections::async_update_connection,
+            connections::async_remove_connection,
+            connections::async_rename_connection_folder,
+            connections::async_connect,
+            askpass::async_answer_ssh_prompt,
+            ssh_agent::async_list_ssh_agent_keys,
+            ssh_agent::async_add_ssh_key,
+            ssh::async_create_ssh,
+            file_transfer::async_upload_file,
+            file_transfer::async_download_remote_file,
+            port_forward::async_open_port_forward,
+            port_forward::async_list_port_forwards,
+            port_forward::async_close_port_forward,
+            serial::async_list_serial_ports,
+            serial::async_create_serial,
+            wsl::async_list_wsl_distros,
+            docker::async_list_docker_containers,
+            docker::async_create_docker_exec,
+            docker::async_open_docker_tab,
+            k8s::async_list_k8s_contexts,
+            k8s::async_list_k8s_namespaces,
+            k8s::async_list_k8s_pods,
+            k8s::async_create_k8s_shell,
+            tmux::async_tmux_new_window,
+            tmux::async_tmux_kill_window,
+            tmux::async_tmux_split_pane,
+            tmux::async_tmux_detach,
+            shell_profile::async_set_shell_profiles,
+            shell_profile::async_list_profiles,
+            shell_profile::async_create_shell_with_profile,
+            shells::async_list_available_shells,
+            plugins::async_list_plugins,
+            plugins::async_enable_plugin,
+            plugins::async_disable_plugin,
+            module_loader::async_get_deno_module_graph,
+            module_loader::async_force_reload_module,
+            keybindings::async_get_keybindings,
+            keybindings::async_invoke_action,
+            themes::async_get_theme,
+            themes::async_list_builtin_themes,
+            shell_integration::async_scroll_to_prompt,
+            shell_integration::async_get_last_command_output,
+            shell_integration::async_set_long_command_threshold,
+            cwd::async_get_cwd,
+            title::async_get_title,
+            title::async_set_window_title_from_terminal,
+            clipboard::async_set_allow_clipboard_write,
+            clipboard::async_set_allow_clipboard_read,
+            notifications::async_set_terminal_notifications,
+            asciicast::async_start_recording,
+            asciicast::async_stop_recording,
+            playback::async_create_playback,
+            playback::async_set_playback_paused,
+            playback::async_set_playback_speed,
+            playback::async_seek_playback,
+            playback::async_get_playback,
+            playback::async_stop_playback,
+            output_log::async_set_output_logging,
+            output_log::async_start_output_log,
+            output_log::async_stop_output_log,
+            command_history::async_search_command_history,
+            command_history::async_clear_command_history,
+            command_history::async_set_command_history_enabled,
+            suggestions::async_get_suggestions,
+            palette::async_list_palette_items,
+            palette::async_run_palette_item,
+            tasks::async_list_tasks,
+            tasks::async_run_task,
+            watch::async_set_watch,
+            watch::async_clear_watch,
+            watch::async_get_watch,
+            foreground::async_get_foreground_process,
+            process_icons::async_set_process_icon_map,
+            process_icons::async_get_process_icons,
+            process_info::async_get_terminal_info,
+            foreground::async_confirm_close,
+            foreground::async_set_confirm_close,
+            signal::async_signal_pty,
+            window::async_new_window,
+            window::async_list_window_terminals,
+            tabs::async_create_tab,
+            tabs::async_close_tab,
+            tabs::async_list_tabs,
+            tabs::async_rename_tab,
+            tabs::async_move_tab,
+            panes::async_get_layout,
+            panes::async_split_pane,
+            duplicate::async_duplicate_terminal,
+            panes::async_resize_pane,
+            panes::async_focus_pane,
+            panes::async_close_pane
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .unwrap_or_else(|err| crash::exit_on_startup_error(err, crash::WINDOW_HINT))
+        .run(detach::handle_run_event);
 }
@@ -0,0 +1,385 @@
+use std::path::{Path, PathBuf};
+
+use portable_pty::CommandBuilder;
+use regex::{Captures, Match, Regex};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    cwd, error::SteppeError, next_terminal_id, screen::ScreenLine, spawn_in_terminal, url_opener, window, AppState,
+    ShellProcess,
+};
+
+const DEFAULT_URL_PATTERN: &str = r#"(?:https?|ftp|file)://[^\s<>"'`]+"#;
+
+/// Absolute, `~/`, `./` and `../` paths, relative ones with a slash in them,
+/// and bare file names with an extension, each with an optional `:line:column`
+const DEFAULT_PATH_PATTERN: &str = r"(?P<path>(?:~|\.{1,2})?/[\w.@+-]+(?:/[\w.@+-]+)*|[\w.@+-]+(?:/[\w.@+-]+)+|[\w@+-]+\.[A-Za-z0-9]+)(?::(?P<line>\d+)(?::(?P<column>\d+))?)?";
+
+/// What counts as a link in a terminal's output, in place of the built-in patterns
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct LinkPatterns {
+    pub url: Option<String>,
+    /// Named groups `path`, `line` and `column` pick out the parts of a match,
+    /// otherwise the whole match is the path
+    pub path: Option<String>,
+    /// Links of kinds of their own, which win over urls and paths
+    pub rules: Vec<LinkRule>,
+}
+
+/// A kind of link the built-in patterns don't know, like `JIRA-\d+` for the
+/// issue tracker or `src/(.+):(\d+)` for the editor. `url`, `path`, `line`
+/// and `column` get `$0`, `$1`, `${name}` and the like filled in from the
+/// match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct LinkRule {
+    /// Sent along with the links it finds, for the frontend to style them by
+    pub name: String,
+    pub pattern: String,
+    /// The url its links open
+    pub url: Option<String>,
+    /// Or the file they open in the editor, at `line`
+    pub path: Option<String>,
+    pub line: Option<String>,
+    pub column: Option<String>,
+}
+
+/// `LinkPatterns` with its regexes compiled
+struct Compiled {
+    url: Regex,
+    path: Regex,
+    rules: Vec<(LinkRule, Regex)>,
+}
+
+impl LinkPatterns {
+    pub fn validate(&self) -> Result<(), String> {
+        self.compile().map(|_| ())
+    }
+
+    fn compile(&self) -> Result<Compiled, String> {
+        let compile = |name: &str, pattern: Option<&str>, default: &str| {
+            Regex::new(pattern.unwrap_or(default)).map_err(|err| format!("linkPatterns.{name} isn't a regex: {err}"))
+        };
+
+        let mut rules = Vec::with_capacity(self.rules.len());
+        for (i, rule) in self.rules.iter().enumerate() {
+            let name = format!("rules[{i}]");
+            if rule.name.is_empty() {
+                return Err(format!("linkPatterns.{name} needs a name"));
+            }
+            if rule.url.is_some() == rule.path.is_some() {
+                return Err(format!("linkPatterns.{name} needs one of url and path"));
+            }
+            if rule.url.is_some() && (rule.line.is_some() || rule.column.is_some()) {
+                return Err(format!("linkPatterns.{name} only takes a line and column with a path"));
+            }
+
+            rules.push((rule.clone(), compile(&name, Some(rule.pattern.as_str()), "")?));
+        }
+
+        Ok(Compiled {
+            url: compile("url", self.url.as_deref(), DEFAULT_URL_PATTERN)?,
+            path: compile("path", self.path.as_deref(), DEFAULT_PATH_PATTERN)?,
+            rules,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Url,
+    Path,
+}
+
+/// A url or path on the screen, and the cells it covers
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectedLink {
+    pub kind: LinkKind,
+    /// What's on the screen
+    pub text: String,
+    /// The url, or the path with `~` and the terminal's directory filled in
+    pub target: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The name of the rule that found it, for a link from `rules`
+    pub rule: Option<String>,
+    pub start_row: i32,
+    pub start_col: usize,
+    /// The row the link ends on, which is further down if it wrapped
+    pub end_row: i32,
+    /// Just past the last cell of the link
+    pub end_col: usize,
+}
+
+impl DetectedLink {
+    fn covers(&self, row: i32, col: usize) -> bool {
+        (self.start_row, self.start_col) <= (row, col) && (row, col) < (self.end_row, self.end_col)
+    }
+}
+
+/// A url without the punctuation around it in prose, like a full stop or
+/// the closing bracket of a parenthetical
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+        let unbalanced = trimmed.ends_with(')') && trimmed.matches(')').count() > trimmed.matches('(').count();
+        let trimmed = if unbalanced {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// `path` as it is on disk, if it's there at all. Relative paths are only
+/// any good with a directory to look in.
+fn resolve(path: &str, cwd: Option<&Path>) -> Option<PathBuf> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()?.join(rest),
+        None => PathBuf::from(path),
+    };
+    let path = if path.is_absolute() { path } else { cwd?.join(path) };
+
+    path.exists().then_some(path)
+}
+
+/// `template` with the parts of `captures` it names filled in
+fn expand(captures: &Captures, template: &str) -> String {
+    let mut expanded = String::new();
+    captures.expand(template, &mut expanded);
+    expanded
+}
+
+/// The links in `line`. Rules win over urls, and urls over any paths inside
+/// them.
+fn detect(line: &ScreenLine, patterns: &Compiled, cwd: Option<&Path>) -> Vec<DetectedLink> {
+    let text = &line.text;
+    let link = |kind, start: usize, end: usize, target: String, line_number, column, rule: Option<&str>| {
+        let (start_row, start_col) = line.cells[text[..start].chars().count()];
+        let (end_row, last_col) = line.cells[text[..end].chars().count() - 1];
+
+        DetectedLink {
+            kind,
+            text: text[start..end].to_string(),
+            target,
+            line: line_number,
+            column,
+            rule: rule.map(str::to_string),
+            start_row,
+            start_col,
+            end_row,
+            end_col: last_col + 1,
+        }
+    };
+
+    let mut links = Vec::new();
+    let mut taken: Vec<(usize, usize)> = Vec::new();
+    let overlaps = |taken: &[(usize, usize)], found: &Match| {
+        taken
+            .iter()
+            .any(|(start, end)| found.start() < *end && *start < found.end())
+    };
+
+    for (rule, pattern) in &patterns.rules {
+        for captures in pattern.captures_iter(text) {
+            let Some(whole) = captures.get(0).filter(|whole| !whole.is_empty()) else {
+                continue;
+            };
+            if overlaps(&taken, &whole) {
+                continue;
+            }
+
+            let number = |template: &Option<String>| {
+                template
+                    .as_deref()
+                    .and_then(|template| expand(&captures, template).parse().ok())
+            };
+            let (kind, target) = match (&rule.url, &rule.path) {
+                (Some(url), _) => (LinkKind::Url, expand(&captures, url)),
+                (None, Some(path)) => match resolve(&expand(&captures, path), cwd) {
+                    Some(target) => (LinkKind::Path, target.to_string_lossy().into_owned()),
+                    None => continue,
+                },
+                (None, None) => continue,
+            };
+
+            taken.push((whole.start(), whole.end()));
+            links.push(link(
+                kind,
+                whole.start(),
+                whole.end(),
+                target,
+                number(&rule.line),
+                number(&rule.column),
+                Some(&rule.name),
+            ));
+        }
+    }
+
+    for found in patterns.url.find_iter(text) {
+        if overlaps(&taken, &found) {
+            continue;
+        }
+        taken.push((found.start(), found.end()));
+
+        let url = trim_url(found.as_str());
+        if !url.is_empty() {
+            let end = found.start() + url.len();
+            links.push(link(
+                LinkKind::Url,
+                found.start(),
+                end,
+                url.to_string(),
+                None,
+                None,
+                None,
+            ));
+        }
+    }
+
+    for captures in patterns.path.captures_iter(text) {
+        let Some(whole) = captures.get(0).filter(|whole| !whole.is_empty()) else {
+            continue;
+        };
+        if overlaps(&taken, &whole) {
+            continue;
+        }
+
+        let path = captures.name("path").unwrap_or(whole);
+        let Some(target) = resolve(path.as_str(), cwd) else {
+            continue;
+        };
+        let number = |name| captures.name(name).and_then(|number| number.as_str().parse().ok());
+
+        links.push(link(
+            LinkKind::Path,
+            whole.start(),
+            whole.end(),
+            target.to_string_lossy().into_owned(),
+            number("line"),
+            number("column"),
+            None,
+        ));
+    }
+
+    links
+}
+
+/// The links on terminal `terminal_id`'s screen, top to bottom
+async fn links(state: &AppState, terminal_id: u32) -> Result<Vec<DetectedLink>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let patterns = state.settings.lock().await.link_patterns.clone().unwrap_or_default();
+    let patterns = patterns.compile().map_err(SteppeError::InvalidConfig)?;
+    // relative paths are only worth looking for where the shell is on this machine
+    let cwd = cwd::inherited_cwd(&terminal).await;
+
+    let lines = terminal.screen.lock().await.lines();
+    let mut links: Vec<DetectedLink> = lines
+        .iter()
+        .flat_map(|line| detect(line, &patterns, cwd.as_deref()))
+        .collect();
+    links.sort_by_key(|link| (link.start_row, link.start_col));
+
+    Ok(links)
+}
+
+/// `$VISUAL` or `$EDITOR` in a tab next to terminal `terminal_id`, opened at
+/// `line` the way most terminal editors take it (`+line`)
+async fn open_in_editor(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    editor: &str,
+    path: &Path,
+    line: Option<u32>,
+) -> Result<(), SteppeError> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(editor);
+
+    let mut cmd = CommandBuilder::new(program);
+    cmd.args(words);
+    if let Some(line) = line {
+        cmd.arg(format!("+{line}"));
+    }
+    cmd.arg(path);
+    if let Some(dir) = path.parent() {
+        cmd.cwd(dir);
+    }
+
+    let process = ShellProcess {
+        program: program.to_string(),
+        shell_profile: None,
+        ssh_profile: None,
+        wsl_distro: None,
+        docker_container: None,
+        k8s_shell: None,
+        pid: None,
+        init_command: None,
+    };
+
+    let label = window::window_of(state, terminal_id).await;
+    let editor_id = next_terminal_id(state).await?;
+    window::assign(state, editor_id, &label).await;
+
+    spawn_in_terminal(editor_id, cmd, process, app.clone(), state)
+        .await
+        .map_err(SteppeError::Pty)?;
+    app.emit_to(&label, "terminal://spawned", editor_id)?;
+
+    Ok(())
+}
+
+/// Urls go to the url opener, files to the editor, and directories (or files
+/// when there's no editor) to whatever the system opens them with
+async fn open(app: &AppHandle, state: &AppState, terminal_id: u32, link: &DetectedLink) -> Result<(), SteppeError> {
+    if link.kind == LinkKind::Url {
+        return url_opener::open_url(app, state, terminal_id, &link.target).await;
+    }
+
+    let path = Path::new(&link.target);
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty());
+
+    match editor {
+        Some(editor) if path.is_file() => open_in_editor(app, state, terminal_id, &editor, path, link.line).await,
+        _ => Ok(open::that_detached(path)?),
+    }
+}
+
+/// The urls and paths on terminal `terminal_id`'s screen, for picking one
+/// with the keyboard
+#[tauri::command]
+pub async fn async_list_links(terminal_id: u32, state: State<'_, AppState>) -> Result<Vec<DetectedLink>, SteppeError> {
+    links(&state, terminal_id).await
+}
+
+/// Open the url or path at a cell, for ctrl+click. `row` counts from the top
+/// of the screen. Returns whether there was one there.
+#[tauri::command]
+pub async fn async_open_link_at(
+    terminal_id: u32,
+    row: i32,
+    col: usize,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, SteppeError> {
+    let links = links(&state, terminal_id).await?;
+    let Some(link) = links.iter().find(|link| link.covers(row, col)) else {
+        return Ok(false);
+    };
+
+    open(&app, &state, terminal_id, link).await?;
+    Ok(true)
+}
@@ -0,0 +1,256 @@
+use std::{cell::RefCell, fs, io, path::PathBuf, sync::MutexGuard};
+
+use deno_ast::{EmitOptions, MediaType, ParseParams, TranspileOptions};
+use deno_runtime::deno_core::{
+    anyhow::{anyhow, bail},
+    error::AnyError,
+    resolve_import, ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType,
+    RequestedModuleType, ResolutionKind,
+};
+
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+
+use crate::{config::WorkerCommand, error::SteppeError, get_config_dir, integrity::Lockfile, AppState};
+
+/// `FsModuleLoader`, but typescript gets its types stripped on the way in, so
+/// config.ts and plugins can be written in it. Nothing gets type checked.
+///
+/// `https://` imports work too, like deno, so a plugin can be shared as a
+/// url. They're kept in `<config dir>/modules` after the first time, so later
+/// loads work offline and get the same thing, and checked against
+/// `modules.lock.json` either way. Fetching them doesn't need the `net`
+/// permission, which is only for what the modules do themselves. `npm:`
+/// imports are refused, since there's no npm resolver to run them the way
+/// node would.
+pub struct TsModuleLoader {
+    lockfile: RefCell<Lockfile>,
+    /// Every module loaded goes in `state.module_graph`
+    app: AppHandle,
+}
+
+impl TsModuleLoader {
+    pub fn new(app: AppHandle) -> Result<Self, AnyError> {
+        Ok(Self {
+            lockfile: RefCell::new(Lockfile::load()?),
+            app,
+        })
+    }
+}
+
+/// A module config.js or a plugin has loaded, in the order they were
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModuleInfo {
+    pub specifier: String,
+    /// What deno took it for, like `TypeScript` or `Json`
+    pub kind: String,
+    /// Where it was read from, which for a remote module is where it's cached
+    pub local_path: Option<String>,
+    /// Of its source, before any types were stripped
+    pub size_bytes: usize,
+}
+
+/// Where the module at `url` is kept, with each part of its path (and the
+/// query, if it has one) made safe to be a file name
+fn cache_path(url: &ModuleSpecifier) -> PathBuf {
+    let safe = |part: &str| -> String {
+        part.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-._@".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+
+    let mut path = get_config_dir()
+        .join("modules")
+        .join(safe(url.host_str().unwrap_or_default()));
+    let mut segments: Vec<String> = url.path_segments().into_iter().flatten().map(safe).collect();
+    match segments.last_mut() {
+        Some(last) if !last.is_empty() => {}
+        Some(last) => *last = "index".to_string(),
+        None => segments.push("index".to_string()),
+    }
+    if let (Some(query), Some(last)) = (url.query(), segments.last_mut()) {
+        last.push_str(&format!("__{}", safe(query)));
+    }
+    path.extend(segments);
+
+    path
+}
+
+/// The module at `url`, from the cache if it's been fetched before
+fn fetch(url: &ModuleSpecifier, lockfile: &mut Lockfile) -> Result<String, AnyError> {
+    let path = cache_path(url);
+    if let Ok(code) = fs::read_to_string(&path) {
+        lockfile.check(url, &code)?;
+        return Ok(code);
+    }
+
+    let code = ureq::get(url.as_str())
+        .call()
+        .map_err(|err| anyhow!("could not fetch {url}: {err}"))?
+        .into_string()?;
+    // before it's cached, so something that fails the check isn't kept around
+    lockfile.check(url, &code)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, &code)?;
+
+    Ok(code)
+}
+
+fn load(specifier: &ModuleSpecifier, lockfile: &RefCell<Lockfile>) -> Result<(ModuleSource, ModuleInfo), AnyError> {
+    let (code, media_type, local_path) = match specifier.scheme() {
+        "https" => {
+            let code = fetch(specifier, &mut lockfile.borrow_mut())?;
+            // most cdns leave the extension off, and what comes back is javascript
+            let media_type = match MediaType::from_specifier(specifier) {
+                MediaType::Unknown => MediaType::JavaScript,
+                media_type => media_type,
+            };
+            (code, media_type, cache_path(specifier))
+        }
+        _ => {
+            let path = specifier
+                .to_file_path()
+                .map_err(|_| anyhow!("{specifier} isn't a file, only file:// and https:// imports work"))?;
+            (fs::read_to_string(&path)?, MediaType::from_path(&path), path)
+        }
+    };
+    let info = ModuleInfo {
+        specifier: specifier.to_string(),
+        kind: media_type.to_string(),
+        local_path: Some(local_path.display().to_string()),
+        size_bytes: code.len(),
+    };
+
+    let (module_type, transpile) = match media_type {
+        MediaType::JavaScript | MediaType::Mjs | MediaType::Cjs => (ModuleType::JavaScript, false),
+        MediaType::Jsx
+        | MediaType::TypeScript
+        | MediaType::Mts
+        | MediaType::Cts
+        | MediaType::Dts
+        | MediaType::Dmts
+        | MediaType::Dcts
+        | MediaType::Tsx => (ModuleType::JavaScript, true),
+        MediaType::Json => (ModuleType::Json, false),
+        _ => bail!("can't import {specifier}, it isn't js, ts or json"),
+    };
+
+    let code = if transpile {
+        let parsed = deno_ast::parse_module(ParseParams {
+            specifier: specifier.clone(),
+            text: code.into(),
+            media_type,
+            capture_tokens: false,
+            scope_analysis: false,
+            maybe_syntax: None,
+        })?;
+
+        let transpiled = parsed.transpile(&TranspileOptions::default(), &EmitOptions::default())?;
+        String::from_utf8(transpiled.into_source().source)?
+    } else {
+        code
+    };
+
+    let source = ModuleSource::new(module_type, ModuleSourceCode::String(code.into()), specifier, None);
+
+    Ok((source, info))
+}
+
+impl ModuleLoader for TsModuleLoader {
+    fn resolve(&self, specifier: &str, referrer: &str, _kind: ResolutionKind) -> Result<ModuleSpecifier, AnyError> {
+        if specifier.starts_with("npm:") {
+            bail!("can't import {specifier}, npm packages aren't supported, only file:// and https:// imports are");
+        }
+
+        let resolved = resolve_import(specifier, referrer)?;
+        // a module from the internet doesn't get to read whatever it likes off this machine
+        if resolved.scheme() == "file" && referrer.starts_with("https:") {
+            bail!("{referrer} can't import {resolved}, remote modules can only import other remote modules");
+        }
+
+        Ok(resolved)
+    }
+
+    fn load(
+        &self,
+        specifier: &ModuleSpecifier,
+        _maybe_referrer: Option<&ModuleSpecifier>,
+        _is_dyn_import: bool,
+        _requested_module_type: RequestedModuleType,
+    ) -> ModuleLoadResponse {
+        let loaded = load(specifier, &self.lockfile).map(|(source, info)| {
+            graph(&self.app.state::<AppState>()).push(info);
+            source
+        });
+
+        ModuleLoadResponse::Sync(loaded)
+    }
+}
+
+/// What's been loaded since config.js was last reloaded
+pub fn graph(state: &AppState) -> MutexGuard<'_, Vec<ModuleInfo>> {
+    state.module_graph.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Send `module-graph-updated` if the modules loaded aren't `previous`
+/// anymore, returning what they are now
+pub fn emit_graph_changes(app: &AppHandle, previous: &[ModuleInfo]) -> Vec<ModuleInfo> {
+    let current = graph(&app.state::<AppState>()).clone();
+    if current != previous {
+        if let Err(err) = app.emit("module-graph-updated", &current) {
+            eprintln!("could not send the module graph: {err}");
+        }
+    }
+
+    current
+}
+
+/// Every module config.js and the plugins have loaded since it was last
+/// reloaded, config.js first.
+#[tauri::command]
+pub async fn async_get_deno_module_graph(state: State<'_, AppState>) -> Result<Vec<ModuleInfo>, SteppeError> {
+    Ok(graph(&state).clone())
+}
+
+/// Forget the module at `specifier`, and reload config.js so it's loaded
+/// again. A remote one's fetched again, and trusted as whatever it is now.
+#[tauri::command]
+pub async fn async_force_reload_module(specifier: String, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let loaded = graph(&state).iter().any(|module| module.specifier == specifier);
+    if !loaded {
+        return Err(SteppeError::ModuleNotLoaded(specifier));
+    }
+
+    let specifier = ModuleSpecifier::parse(&specifier).map_err(|err| SteppeError::InvalidUrl(err.to_string()))?;
+    // local modules are read again on every reload anyway
+    if specifier.scheme() == "https" {
+        async_runtime::spawn_blocking(move || forget(&specifier)).await??;
+    }
+
+    // the worker's only gone if steppe is shutting down
+    let _ = state.worker_commands.send(WorkerCommand::Reload);
+
+    Ok(())
+}
+
+/// Take the module at `url` out of the cache and the lockfile
+fn forget(url: &ModuleSpecifier) -> Result<(), SteppeError> {
+    match fs::remove_file(cache_path(url)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+
+    let mut lockfile = Lockfile::load().map_err(|err| SteppeError::InvalidConfig(err.to_string()))?;
+    lockfile
+        .forget(url)
+        .map_err(|err| SteppeError::InvalidConfig(err.to_string()))
+}
@@ -0,0 +1,234 @@
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+
+use crate::{error::SteppeError, notifications, window, AppState, SubTerminal};
+
+/// How long `toggle_monitor_silence` waits for a terminal to go quiet
+pub const DEFAULT_SILENCE_MS: u64 = 30_000;
+
+/// What a terminal's being watched for, like tmux's `monitor-activity` and
+/// `monitor-silence`. Either is only sent for a terminal that isn't focused,
+/// and once until it's focused again (or prints again, for silence).
+#[derive(Debug, Default)]
+pub struct Monitor {
+    activity: bool,
+    silence: Option<Duration>,
+    last_output: Option<Instant>,
+    activity_sent: bool,
+    silence_sent: bool,
+    /// Bumped whenever `silence` is set, so a watch started for an older
+    /// setting knows to stop
+    generation: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorSettings {
+    pub activity: bool,
+    pub silence_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+struct Activity {
+    terminal_id: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct Silence {
+    terminal_id: u32,
+    threshold_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct Focused {
+    terminal_id: u32,
+}
+
+/// Note that terminal `terminal_id` just printed, sending
+/// `terminal://activity` if it's being watched for that
+pub async fn output(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal) {
+    let send = {
+        let mut monitor = terminal.monitor.lock().unwrap_or_else(|err| err.into_inner());
+        monitor.last_output = Some(Instant::now());
+        monitor.silence_sent = false;
+
+        let send = monitor.activity && !monitor.activity_sent && !terminal.focused.load(Ordering::Relaxed);
+        monitor.activity_sent |= send;
+        send
+    };
+    if !send {
+        return;
+    }
+
+    if let Err(err) = app.emit("terminal://activity", Activity { terminal_id }) {
+        eprintln!("could not send activity: {err}");
+    }
+    if let Err(err) = notifications::monitor(app, terminal, "started printing").await {
+        eprintln!("could not notify about activity: {err}");
+    }
+}
+
+/// How long until terminal `terminal_id` would have been quiet long enough,
+/// or `None` once the watch for `generation` is over
+fn until_silent(terminal: &SubTerminal, generation: u64) -> Option<(Duration, Duration)> {
+    let monitor = terminal.monitor.lock().unwrap_or_else(|err| err.into_inner());
+    let threshold = monitor.silence.filter(|_| monitor.generation == generation)?;
+    let quiet_for = monitor.last_output.map_or(Duration::ZERO, |last| last.elapsed());
+
+    Some((threshold, threshold.saturating_sub(quiet_for)))
+}
+
+/// Wait for terminal `terminal_id` to stop printing for as long as its
+/// silence setting says, sending `terminal://silence` each time it does,
+/// until the setting changes or the terminal closes
+fn watch_silence(app: AppHandle, terminal_id: u32, generation: u64) {
+    async_runtime::spawn(async move {
+        loop {
+            let Ok(terminal) = app.state::<AppState>().terminal(terminal_id).await else {
+                return;
+            };
+            let Some((threshold, wait)) = until_silent(&terminal, generation) else {
+                return;
+            };
+            if !wait.is_zero() {
+                drop(terminal);
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+
+            let send = {
+                let mut monitor = terminal.monitor.lock().unwrap_or_else(|err| err.into_inner());
+                let send = !monitor.silence_sent && !terminal.focused.load(Ordering::Relaxed);
+                monitor.silence_sent = true;
+                send
+            };
+            if send {
+                let threshold_ms = threshold.as_millis() as u64;
+                if let Err(err) = app.emit(
+                    "terminal://silence",
+                    Silence {
+                        terminal_id,
+                        threshold_ms,
+                    },
+                ) {
+                    eprintln!("could not send silence: {err}");
+                }
+                if let Err(err) = notifications::monitor(&app, &terminal, "stopped printing").await {
+                    eprintln!("could not notify about silence: {err}");
+                }
+            }
+
+            // nothing more to say until it prints again
+            drop(terminal);
+            tokio::time::sleep(threshold).await;
+        }
+    });
+}
+
+/// Watch terminal `terminal_id` for starting to print (`activity`), and for
+/// going `silence_ms` without printing, or stop for `false` and `None`
+pub async fn set(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    activity: bool,
+    silence_ms: Option<u64>,
+) -> Result<(), SteppeError> {
+    if silence_ms == Some(0) {
+        return Err(SteppeError::InvalidConfig(
+            "silence has to be longer than 0ms".to_string(),
+        ));
+    }
+
+    let terminal = state.terminal(terminal_id).await?;
+    let generation = {
+        let mut monitor = terminal.monitor.lock().unwrap_or_else(|err| err.into_inner());
+        monitor.activity = activity;
+        monitor.activity_sent = false;
+
+        let silence = silence_ms.map(Duration::from_millis);
+        if silence == monitor.silence {
+            // already being watched for, if it's set
+            None
+        } else {
+            monitor.silence = silence;
+            monitor.silence_sent = false;
+            monitor.generation += 1;
+            // counting from now, for a terminal that hasn't printed anything yet
+            monitor.last_output.get_or_insert_with(Instant::now);
+
+            silence.is_some().then_some(monitor.generation)
+        }
+    };
+
+    if let Some(generation) = generation {
+        watch_silence(app.clone(), terminal_id, generation);
+    }
+
+    Ok(())
+}
+
+pub async fn settings(state: &AppState, terminal_id: u32) -> Result<MonitorSettings, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let monitor = terminal.monitor.lock().unwrap_or_else(|err| err.into_inner());
+
+    Ok(MonitorSettings {
+        activity: monitor.activity,
+        silence_ms: monitor.silence.map(|silence| silence.as_millis() as u64),
+    })
+}
+
+/// Which terminal the user's looking at, which activity and silence aren't
+/// sent for (and files dropped on its window go to). Focusing one sends
+/// `terminal://focused`, for the tab bar to clear whatever it was showing
+/// for it.
+#[tauri::command]
+pub async fn async_set_terminal_focused(
+    terminal_id: u32,
+    focused: bool,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    terminal.focused.store(focused, Ordering::Relaxed);
+
+    if focused {
+        let label = window::window_of(&state, terminal_id).await;
+        state.focused_terminals.lock().await.insert(label, terminal_id);
+
+        terminal
+            .monitor
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .activity_sent = false;
+        app.emit("terminal://focused", Focused { terminal_id })?;
+    }
+
+    Ok(())
+}
+
+/// Send `terminal://activity` when terminal `terminal_id` starts printing
+/// and `terminal://silence` when it's gone `silence_ms` without, while it
+/// isn't focused
+#[tauri::command]
+pub async fn async_set_terminal_monitor(
+    terminal_id: u32,
+    activity: bool,
+    silence_ms: Option<u64>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    set(&app, &state, terminal_id, activity, silence_ms).await
+}
+
+#[tauri::command]
+pub async fn async_get_terminal_monitor(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<MonitorSettings, SteppeError> {
+    settings(&state, terminal_id).await
+}
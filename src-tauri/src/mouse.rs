@@ -0,0 +1,322 @@
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+/// A mouse reporting mode, as set with `CSI ? N h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MouseMode {
+    X10,
+    Normal,
+    ButtonEvent,
+    AnyEvent,
+    Sgr,
+    Urxvt,
+    Pixel,
+}
+
+impl MouseMode {
+    fn code(self) -> u16 {
+        match self {
+            Self::X10 => 9,
+            Self::Normal => 1000,
+            Self::ButtonEvent => 1002,
+            Self::AnyEvent => 1003,
+            Self::Sgr => 1006,
+            Self::Urxvt => 1015,
+            Self::Pixel => 1016,
+        }
+    }
+}
+
+/// Which mouse reporting modes the program in the pty has turned on.
+/// Tracking modes (x10 through any event) pick which events get reported,
+/// the rest pick how they're encoded.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MouseReportMode {
+    pub x10: bool,
+    pub normal: bool,
+    pub button_event: bool,
+    pub any_event: bool,
+    pub sgr: bool,
+    pub urxvt: bool,
+    pub pixel: bool,
+}
+
+impl MouseReportMode {
+    /// Update from a DEC private mode change, ignoring modes that aren't about the mouse
+    pub fn set(&mut self, code: u16, enabled: bool) {
+        let mode = match code {
+            9 => &mut self.x10,
+            1000 => &mut self.normal,
+            1002 => &mut self.button_event,
+            1003 => &mut self.any_event,
+            1006 => &mut self.sgr,
+            1015 => &mut self.urxvt,
+            1016 => &mut self.pixel,
+            _ => return,
+        };
+
+        *mode = enabled;
+    }
+
+    fn reports(&self, event: &MouseEvent) -> bool {
+        match event.kind {
+            MouseEventKind::Press => self.x10 || self.normal || self.button_event || self.any_event,
+            MouseEventKind::Release => self.normal || self.button_event || self.any_event,
+            MouseEventKind::Move if event.button.is_some() => self.button_event || self.any_event,
+            MouseEventKind::Move => self.any_event,
+        }
+    }
+
+    /// The bytes a program expects for `event`, or `None` if it didn't ask for this kind of event.
+    fn encode(&self, event: &MouseEvent) -> Option<Vec<u8>> {
+        if !self.reports(event) {
+            return None;
+        }
+
+        let button = match event.button {
+            Some(MouseButton::Left) => 0,
+            Some(MouseButton::Middle) => 1,
+            Some(MouseButton::Right) => 2,
+            Some(MouseButton::WheelUp) => 64,
+            Some(MouseButton::WheelDown) => 65,
+            None => 3,
+        };
+
+        let mut code = button;
+        // x10 doesn't report modifiers
+        if !self.x10 || self.normal || self.button_event || self.any_event {
+            let modifiers = event.modifiers;
+            code |= u32::from(modifiers.shift) * 4 | u32::from(modifiers.alt) * 8 | u32::from(modifiers.ctrl) * 16;
+        }
+        if event.kind == MouseEventKind::Move {
+            code |= 32;
+        }
+
+        // coordinates are 1-based on the wire
+        let (x, y) = (event.col + 1, event.row + 1);
+
+        if self.sgr || self.pixel {
+            let end = if event.kind == MouseEventKind::Release { 'm' } else { 'M' };
+            return Some(format!("\x1b[<{code};{x};{y}{end}").into_bytes());
+        }
+
+        // the legacy encodings can't say which button was released
+        if event.kind == MouseEventKind::Release {
+            code = (code & !0b11) | 3;
+        }
+
+        if self.urxvt {
+            return Some(format!("\x1b[{};{x};{y}M", code + 32).into_bytes());
+        }
+
+        // one byte each, so anything past 223 can't be reported
+        let byte = |value: u32| u8::try_from(value + 32).ok();
+        Some(vec![0x1b, b'[', b'M', byte(code)?, byte(x)?, byte(y)?])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MouseEventKind {
+    Press,
+    Release,
+    Move,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+/// Which keys were held down along with the mouse
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+/// A mouse event over the terminal, in cells (or pixels, with pixel reporting on)
+#[derive(Debug, Clone, Copy)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    /// `None` for moves without a button held down
+    pub button: Option<MouseButton>,
+    pub col: u32,
+    pub row: u32,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Clone, Serialize)]
+struct InjectOutput {
+    terminal_id: u32,
+    data: String,
+}
+
+/// Which mouse reporting modes the program in terminal `terminal_id` has on
+#[tauri::command]
+pub async fn async_get_mouse_report_mode(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<MouseReportMode, SteppeError> {
+    Ok(*state.terminal(terminal_id).await?.mouse_report_mode.lock().await)
+}
+
+/// Turn a mode on as if the program had asked for it. The sequence goes to
+/// the terminal rather than the program, so xterm.js starts reporting too.
+#[tauri::command]
+pub async fn async_set_mouse_report_mode(
+    terminal_id: u32,
+    mode: MouseMode,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    terminal.mouse_report_mode.lock().await.set(mode.code(), true);
+
+    app.emit(
+        "inject-output",
+        InjectOutput {
+            terminal_id,
+            data: format!("\x1b[?{}h", mode.code()),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Report a mouse event at cell `x`, `y` (counting from 0, or pixels if the
+/// program asked for pixel reporting) to the program in terminal
+/// `terminal_id`, encoded however it asked for: SGR, urxvt or the original
+/// bytes. Does nothing if it didn't ask for this kind of event.
+#[tauri::command]
+pub async fn async_send_mouse_event(
+    terminal_id: u32,
+    button: Option<MouseButton>,
+    x: u32,
+    y: u32,
+    modifiers: Option<Modifiers>,
+    kind: MouseEventKind,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let event = MouseEvent {
+        kind,
+        button,
+        col: x,
+        row: y,
+        modifiers: modifiers.unwrap_or_default(),
+    };
+
+    let bytes = terminal.mouse_report_mode.lock().await.encode(&event);
+    if let Some(bytes) = bytes {
+        terminal.writer.lock().await.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MouseButton::*;
+    use MouseEventKind::*;
+
+    /// The modes that are on, what happened at which cell with what held, and the bytes sent for it
+    type Case = (
+        &'static [u16],
+        MouseEventKind,
+        Option<MouseButton>,
+        (u32, u32),
+        &'static str,
+        Option<&'static [u8]>,
+    );
+
+    const EVENTS: [Case; 16] = [
+        // x10 only reports presses, without modifiers
+        (&[9], Press, Some(Left), (0, 0), "", Some(b"\x1b[M !!")),
+        (&[9], Press, Some(Right), (1, 2), "shift", Some(b"\x1b[M\"\"#")),
+        (&[9], Release, Some(Left), (0, 0), "", None),
+        // normal tracking, in the original bytes
+        (&[1000], Press, Some(Middle), (0, 0), "ctrl", Some(b"\x1b[M1!!")),
+        (&[1000], Release, Some(Right), (0, 0), "", Some(b"\x1b[M#!!")),
+        (&[1000], Press, Some(WheelUp), (0, 0), "", Some(b"\x1b[M`!!")),
+        (&[1000], Move, Some(Left), (0, 0), "", None),
+        (&[1000], Press, Some(Left), (222, 0), "", Some(b"\x1b[M \xff!")),
+        // too far out for a byte
+        (&[1000], Press, Some(Left), (223, 0), "", None),
+        // sgr, which says which button was released and has no limit
+        (&[1000, 1006], Press, Some(Left), (9, 4), "", Some(b"\x1b[<0;10;5M")),
+        (&[1000, 1006], Release, Some(Right), (9, 4), "", Some(b"\x1b[<2;10;5m")),
+        (&[1000, 1006], Press, Some(Left), (0, 0), "alt", Some(b"\x1b[<8;1;1M")),
+        (&[1000, 1006], Press, Some(Left), (300, 0), "", Some(b"\x1b[<0;301;1M")),
+        // moves, with a button held for button events and without for any event
+        (&[1002, 1006], Move, Some(Left), (2, 2), "", Some(b"\x1b[<32;3;3M")),
+        (&[1002, 1006], Move, None, (2, 2), "", None),
+        (&[1003, 1006], Move, None, (2, 2), "", Some(b"\x1b[<35;3;3M")),
+    ];
+
+    fn modes(codes: &[u16]) -> MouseReportMode {
+        let mut mode = MouseReportMode::default();
+        for &code in codes {
+            mode.set(code, true);
+        }
+        mode
+    }
+
+    fn event(kind: MouseEventKind, button: Option<MouseButton>, (col, row): (u32, u32), held: &str) -> MouseEvent {
+        MouseEvent {
+            kind,
+            button,
+            col,
+            row,
+            modifiers: Modifiers {
+                shift: held == "shift",
+                alt: held == "alt",
+                ctrl: held == "ctrl",
+            },
+        }
+    }
+
+    #[test]
+    fn encodes_events() {
+        for (codes, kind, button, at, held, sent) in EVENTS {
+            let encoded = modes(codes).encode(&event(kind, button, at, held));
+            assert_eq!(encoded.as_deref(), sent, "{kind:?} {button:?} with {codes:?}");
+        }
+    }
+
+    #[test]
+    fn encodes_urxvt() {
+        let release = event(Release, Some(Left), (299, 0), "");
+        assert_eq!(
+            modes(&[1000, 1015]).encode(&release).as_deref(),
+            Some(&b"\x1b[35;300;1M"[..])
+        );
+    }
+
+    #[test]
+    fn reports_nothing_until_asked() {
+        let press = event(Press, Some(Left), (0, 0), "");
+        // the encoding alone doesn't turn reporting on
+        let mut mode = modes(&[1006, 25]);
+        assert_eq!(mode.encode(&press), None);
+
+        mode.set(1000, true);
+        assert!(mode.encode(&press).is_some());
+        mode.set(1000, false);
+        assert_eq!(mode.encode(&press), None);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State, Window, WindowEvent};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{error::SteppeError, shell_integration::FinishedCommand, window, AppState, SubTerminal};
+
+/// How long a command has to run for `commandNotifications` to say it's done
+pub const DEFAULT_COMMAND_NOTIFICATION_MS: u64 = 10_000;
+
+/// What a terminal is allowed to raise desktop notifications for. They only
+/// show up while the window isn't focused, since otherwise it's right there.
+#[derive(Debug)]
+pub struct NotificationSettings {
+    /// OSC 9 and OSC 777, which a program only sends when it means to
+    escape_sequences: AtomicBool,
+    /// BEL, which plenty of things ring for no good reason, so it's off by default
+    bell: AtomicBool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            escape_sequences: AtomicBool::new(true),
+            bell: AtomicBool::new(false),
+        }
+    }
+}
+
+/// A desktop notification when a command (as marked by OSC 133) that ran a
+/// while finishes in a terminal that isn't focused
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct CommandNotificationOptions {
+    /// How long the command has to have run, `DEFAULT_COMMAND_NOTIFICATION_MS`
+    /// if it's unset
+    pub min_duration_ms: Option<u64>,
+    /// Only for commands that failed
+    pub only_failures: bool,
+    /// Regexes for commands not to notify about, like editors and pagers
+    /// that are only ever closed by hand
+    pub exclude: Vec<String>,
+}
+
+impl CommandNotificationOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        RegexSet::new(&self.exclude).map_err(|err| format!("commandNotifications.exclude: {err}"))?;
+
+        Ok(())
+    }
+
+    fn applies_to(&self, command: &FinishedCommand) -> bool {
+        let min_duration = Duration::from_millis(self.min_duration_ms.unwrap_or(DEFAULT_COMMAND_NOTIFICATION_MS));
+        if command.duration < min_duration {
+            return false;
+        }
+
+        if self.only_failures && matches!(command.exit_code, Some(0) | None) {
+            return false;
+        }
+
+        // checked when the settings were loaded
+        let excluded = RegexSet::new(&self.exclude).unwrap_or_else(|_| RegexSet::empty());
+        !command.command.as_deref().is_some_and(|text| excluded.is_match(text))
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct FocusRequested {
+    terminal_id: u32,
+}
+
+fn window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+fn show(app: &AppHandle, title: &str, body: &str) -> Result<(), SteppeError> {
+    app.notification().builder().title(title).body(body).show()?;
+    Ok(())
+}
+
+/// OSC 9 (`9;body`) or OSC 777 (`777;notify;title;body`)
+pub async fn notify(
+    app: &AppHandle,
+    terminal: &SubTerminal,
+    title: Option<String>,
+    body: &str,
+) -> Result<(), SteppeError> {
+    if !terminal.notifications.escape_sequences.load(Ordering::Relaxed) || window_focused(app) {
+        return Ok(());
+    }
+
+    let title = match title {
+        Some(title) => title,
+        None => terminal_title(terminal).await,
+    };
+
+    show(app, &title, body)
+}
+
+/// The terminal rang its bell (once or more, they're not told apart).
+pub async fn bell(app: &AppHandle, terminal: &SubTerminal) -> Result<(), SteppeError> {
+    if !terminal.notifications.bell.load(Ordering::Relaxed) || window_focused(app) {
+        return Ok(());
+    }
+
+    show(app, &terminal_title(terminal).await, "the bell rang")
+}
+
+/// A terminal being monitored started or stopped printing, see `monitor`
+pub async fn monitor(app: &AppHandle, terminal: &SubTerminal, body: &str) -> Result<(), SteppeError> {
+    if window_focused(app) {
+        return Ok(());
+    }
+
+    show(app, &terminal_title(terminal).await, body)
+}
+
+/// `duration` the way a person would say it, to the second
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+/// `command` finished in terminal `terminal_id`, which is worth a
+/// notification if `commandNotifications` is set, the command's one it
+/// applies to, and the terminal (or its window) isn't focused. Clicking a
+/// desktop notification only brings the app forward, so the terminal's
+/// focused once its window is.
+pub async fn command_finished(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    terminal: &SubTerminal,
+    command: &FinishedCommand,
+) -> Result<(), SteppeError> {
+    let Some(options) = state.settings.lock().await.command_notifications.clone() else {
+        return Ok(());
+    };
+    if !options.applies_to(command) {
+        return Ok(());
+    }
+
+    let label = window::window_of(state, terminal_id).await;
+    let window_focused = app
+        .get_webview_window(&label)
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false);
+    if window_focused && terminal.focused.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let outcome = match command.exit_code {
+        Some(0) | None => "finished".to_string(),
+        Some(code) => format!("failed with exit code {code}"),
+    };
+    let body = format!(
+        "{} {outcome} after {}",
+        command.command.as_deref().unwrap_or("a command"),
+        format_duration(command.duration)
+    );
+
+    show(app, &terminal_title(terminal).await, &body)?;
+    state.pending_focus.lock().await.insert(label, terminal_id);
+
+    Ok(())
+}
+
+/// A window coming forward after a command's notification, which is most
+/// likely from clicking it, sends `terminal://focus-requested` for the
+/// terminal it was about
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if !matches!(event, WindowEvent::Focused(true)) {
+        return;
+    }
+
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let Some(terminal_id) = state.pending_focus.lock().await.remove(&label) else {
+            return;
+        };
+
+        if let Err(err) = app.emit_to(&label, "terminal://focus-requested", FocusRequested { terminal_id }) {
+            eprintln!("could not focus terminal {terminal_id}: {err}");
+        }
+    });
+}
+
+async fn terminal_title(terminal: &SubTerminal) -> String {
+    let shell_integration = terminal.shell_integration.lock().await;
+    shell_integration.title().unwrap_or("steppe").to_string()
+}
+
+/// Which desktop notifications terminal `terminal_id` can raise while the
+/// window's in the background.
+#[tauri::command]
+pub async fn async_set_terminal_notifications(
+    terminal_id: u32,
+    escape_sequences: bool,
+    bell: bool,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    terminal.notifications.escape_sequences.store(escape_sequences, Ordering::Relaxed);
+    terminal.notifications.bell.store(bell, Ordering::Relaxed);
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use portable_pty::{CommandBuilder, PtySize};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::{
+    cwd, environment, error::SteppeError, reader, spawn_in_terminal, tabs, window, AppState, ShellProcess, SubTerminal,
+};
+
+/// A shell that dies sooner than this after starting is held rather than
+/// restarted, so one that can't start at all doesn't restart forever
+const MIN_RUN: Duration = Duration::from_secs(1);
+
+/// What happens to a terminal once its shell is gone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnExit {
+    /// Take the tab away
+    Close,
+    /// Start it again in the same directory, unless it exited cleanly (like
+    /// with `exit`), which closes it
+    Restart,
+    /// Keep the terminal and what it printed, with a line saying how it ended
+    #[default]
+    Hold,
+}
+
+/// What a terminal's shell was started with, to start it again
+pub struct Respawn {
+    cmd: CommandBuilder,
+    process: ShellProcess,
+    started: Instant,
+    /// The old pty's size, so the new shell starts out fitting the terminal
+    size: Option<PtySize>,
+}
+
+impl Respawn {
+    pub fn new(cmd: CommandBuilder, process: ShellProcess) -> Self {
+        Self {
+            cmd,
+            process,
+            started: Instant::now(),
+            size: None,
+        }
+    }
+
+    /// Pick up where `terminal`'s shell was, what it had changed in its
+    /// environment and how big it was, which all go with it
+    async fn remember(&mut self, state: &AppState, terminal: &SubTerminal) {
+        if let Some(cwd) = cwd::inherited_cwd(terminal).await {
+            self.cmd.cwd(cwd);
+        }
+        for (key, value) in environment::changes(state, terminal).await.unwrap_or_default() {
+            self.cmd.env(key, value);
+        }
+        self.size = terminal.pty_pair.lock().await.master.get_size().ok();
+    }
+}
+
+/// What to do about a shell that exited with `code`, going by the `onExit` setting
+pub async fn policy(state: &AppState, code: u32, respawn: Option<&Respawn>) -> OnExit {
+    match (state.settings.lock().await.on_exit.unwrap_or_default(), respawn) {
+        (OnExit::Restart, _) if code == 0 => OnExit::Close,
+        // serial ports, tmux panes and playback have nothing to start again
+        (OnExit::Restart, None) => OnExit::Hold,
+        (OnExit::Restart, Some(respawn)) if respawn.started.elapsed() < MIN_RUN => OnExit::Hold,
+        (on_exit, _) => on_exit,
+    }
+}
+
+/// Let `terminal` know its shell is gone before it's forgotten about, with a
+/// line in its output unless it's about to close anyway
+pub async fn before_removal(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    terminal: &SubTerminal,
+    code: u32,
+    on_exit: OnExit,
+    respawn: Option<&mut Respawn>,
+) {
+    if let (OnExit::Restart, Some(respawn)) = (on_exit, respawn) {
+        respawn.remember(state, terminal).await;
+    }
+
+    if on_exit != OnExit::Close {
+        let banner = format!("\r\n[process exited with code {code}]\r\n");
+        if let Err(err) = reader::emit_output(app, state, terminal_id, banner.clone().into_bytes(), banner).await {
+            eprintln!("could not send exit code: {err}");
+        }
+    }
+}
+
+/// Start `respawn` again in a fresh pty for terminal `terminal_id`, the same
+/// size as the old one
+pub async fn restart(app: &AppHandle, state: &AppState, terminal_id: u32, respawn: Respawn) -> Result<(), SteppeError> {
+    let terminal = state.open_terminal(terminal_id).await?;
+    if let Some(size) = respawn.size {
+        terminal
+            .pty_pair
+            .lock()
+            .await
+            .master
+            .resize(size)
+            .map_err(|err| SteppeError::Pty(err.to_string()))?;
+        terminal.screen.lock().await.resize(size.rows, size.cols);
+    }
+
+    spawn_in_terminal(terminal_id, respawn.cmd, respawn.process, app.clone(), state)
+        .await
+        .map_err(SteppeError::Pty)
+}
+
+/// Take terminal `terminal_id`'s tab away, the way closing it would once its
+/// shell had hung up
+pub async fn close(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let label = window::window_of(state, terminal_id).await;
+
+    app.emit_to(&label, "tab://closed", terminal_id)?;
+    tabs::emit_changed(app, state, &label).await
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State, Window, WindowEvent};
+
+use crate::{error::SteppeError, AppState};
+
+/// About 60fps
+const FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// How far along the animation should look, `t` being how far along it is in time
+    fn apply(self, t: f64) -> f64 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(2),
+            Self::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Self::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0,
+        }
+    }
+}
+
+/// Window opacity when focused and unfocused. Webviews can't set their
+/// window's opacity, so this is applied to the page through `--window-opacity`
+/// and the window itself is transparent.
+#[derive(Debug)]
+pub struct FocusOpacity {
+    focused: f64,
+    unfocused: f64,
+    duration: Duration,
+    easing: Easing,
+    /// Where the last animation got to
+    current: f64,
+    animation: Option<async_runtime::JoinHandle<()>>,
+}
+
+impl Default for FocusOpacity {
+    fn default() -> Self {
+        Self {
+            focused: 1.0,
+            unfocused: 1.0,
+            duration: Duration::from_millis(150),
+            easing: Easing::default(),
+            current: 1.0,
+            animation: None,
+        }
+    }
+}
+
+impl FocusOpacity {
+    /// Back to the default opacities and fade, leaving the window wherever it's got to
+    pub fn reset(&mut self) {
+        let defaults = Self::default();
+        self.focused = defaults.focused;
+        self.unfocused = defaults.unfocused;
+        self.duration = defaults.duration;
+        self.easing = defaults.easing;
+    }
+}
+
+fn emit_opacity(app: &AppHandle, opacity: f64) -> tauri::Result<()> {
+    app.emit("css-vars-changed", HashMap::from([("--window-opacity", opacity.to_string())]))
+}
+
+/// Fade from wherever the window is now to the opacity for `focused`,
+/// cutting off any fade that's still going.
+async fn animate(app: AppHandle, focused: bool) {
+    let state = app.state::<AppState>();
+    let mut opacity = state.focus_opacity.lock().await;
+
+    if let Some(previous) = opacity.animation.take() {
+        previous.abort();
+    }
+
+    let from = opacity.current;
+    let to = if focused { opacity.focused } else { opacity.unfocused };
+    if from == to {
+        return;
+    }
+
+    let (duration, easing) = (opacity.duration, opacity.easing);
+    // `state` borrows from `app`, so the task needs its own handle
+    let app = app.clone();
+    opacity.animation = Some(async_runtime::spawn(async move {
+        let started = Instant::now();
+        let mut interval = tokio::time::interval(FRAME_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let t = if duration.is_zero() {
+                1.0
+            } else {
+                (started.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0)
+            };
+            let value = from + (to - from) * easing.apply(t);
+
+            app.state::<AppState>().focus_opacity.lock().await.current = value;
+            if emit_opacity(&app, value).is_err() || t >= 1.0 {
+                break;
+            }
+        }
+    }));
+}
+
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::Focused(focused) = event {
+        async_runtime::spawn(animate(window.app_handle().clone(), *focused));
+    }
+}
+
+fn check_opacity(value: f64) -> Result<(), SteppeError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(SteppeError::InvalidConfig(format!("opacity {value} isn't between 0 and 1")))
+    }
+}
+
+#[tauri::command]
+pub async fn async_set_focus_opacity(
+    focused: f64,
+    unfocused: f64,
+    animation_duration_ms: u64,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    check_opacity(focused)?;
+    check_opacity(unfocused)?;
+
+    let mut opacity = state.focus_opacity.lock().await;
+    opacity.focused = focused;
+    opacity.unfocused = unfocused;
+    opacity.duration = Duration::from_millis(animation_duration_ms);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_focus_opacity_easing(easing: Easing, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.focus_opacity.lock().await.easing = easing;
+    Ok(())
+}
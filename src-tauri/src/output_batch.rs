@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager, State};
+
+use crate::{check_session, error::SteppeError, reader, AppState, SESSION_ID};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchModeOptions {
+    pub enabled: bool,
+    /// Send the batch once this many lines have built up
+    pub max_line_buffer_size: usize,
+    /// Send whatever's built up this long after the first of it came in
+    pub flush_interval_ms: u64,
+}
+
+impl Default for BatchModeOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_line_buffer_size: 1000,
+            flush_interval_ms: 50,
+        }
+    }
+}
+
+/// Pty output held back so the frontend renders it in a few big writes
+/// rather than lots of small ones.
+#[derive(Debug, Default)]
+pub struct OutputBatch {
+    options: BatchModeOptions,
+    /// As the pty wrote it, for the output channel
+    raw: Vec<u8>,
+    buffer: String,
+    lines: usize,
+    /// Whether a task is already waiting to flush this batch
+    flush_scheduled: bool,
+}
+
+impl OutputBatch {
+    fn take(&mut self) -> Option<(Vec<u8>, String)> {
+        self.lines = 0;
+        self.flush_scheduled = false;
+
+        let raw = std::mem::take(&mut self.raw);
+        let buffer = std::mem::take(&mut self.buffer);
+        Some((raw, buffer)).filter(|(raw, _)| !raw.is_empty())
+    }
+}
+
+/// Emit whatever's batched up, rather than waiting for more output.
+async fn emit_flush(app: &AppHandle, state: &AppState, batch: &mut OutputBatch) -> Result<(), SteppeError> {
+    if let Some((raw, data)) = batch.take() {
+        reader::emit_output(app, state, SESSION_ID, raw, data).await?;
+    }
+
+    Ok(())
+}
+
+/// Add output to the batch, `raw` as the pty wrote it and `data` the same
+/// decoded, returning what should go to the frontend now, if anything.
+pub async fn push(app: &AppHandle, state: &AppState, raw: Vec<u8>, data: String) -> Option<(Vec<u8>, String)> {
+    let mut batch = state.output_batch.lock().await;
+    if !batch.options.enabled {
+        return Some((raw, data));
+    }
+
+    batch.lines += data.matches('\n').count();
+    batch.raw.extend_from_slice(&raw);
+    batch.buffer.push_str(&data);
+
+    if batch.lines >= batch.options.max_line_buffer_size {
+        return batch.take();
+    }
+
+    // the interval can't be checked when output comes in, since a quiet pty
+    // would strand the rest of the batch
+    if !batch.flush_scheduled {
+        batch.flush_scheduled = true;
+
+        let app = app.clone();
+        let interval = Duration::from_millis(batch.options.flush_interval_ms);
+        async_runtime::spawn(async move {
+            tokio::time::sleep(interval).await;
+
+            let state = app.state::<AppState>();
+            let mut batch = state.output_batch.lock().await;
+
+            // a full batch may have gone out already
+            if batch.flush_scheduled {
+                if let Err(err) = emit_flush(&app, &state, &mut batch).await {
+                    eprintln!("could not flush output: {err}");
+                }
+            }
+        });
+    }
+
+    None
+}
+
+#[tauri::command]
+pub async fn async_set_output_batch_mode(
+    opts: BatchModeOptions,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if opts.enabled && (opts.max_line_buffer_size == 0 || opts.flush_interval_ms == 0) {
+        return Err(SteppeError::InvalidConfig(
+            "batch mode needs a line buffer size and flush interval above 0".into(),
+        ));
+    }
+
+    let mut batch = state.output_batch.lock().await;
+    batch.options = opts;
+
+    // don't leave anything behind when batching gets turned off
+    if !opts.enabled {
+        emit_flush(&app, &state, &mut batch).await?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_flush_output_buffer(
+    session_id: u32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    check_session(session_id)?;
+    emit_flush(&app, &state, &mut *state.output_batch.lock().await).await
+}
@@ -0,0 +1,239 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tauri::State;
+
+use crate::{
+    error::SteppeError,
+    redact::{self, Redactor},
+    scrollback::visible_text,
+    AppState, SubTerminal,
+};
+
+/// How big a log gets before it's rotated out
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many rotated logs (`.1` being the newest) are kept around
+const ROTATED_LOGS: u32 = 5;
+
+/// A line that long is written out as it is rather than waiting on a newline forever
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Where every terminal's output gets logged from the start, set from config.js
+#[derive(Debug, Clone)]
+pub struct OutputLogSettings {
+    dir: PathBuf,
+    strip_ansi: bool,
+    max_bytes: u64,
+}
+
+/// A terminal's output being written to a log file a line at a time, each
+/// with the time it finished printing.
+#[derive(Debug)]
+pub struct OutputLog {
+    path: PathBuf,
+    file: BufWriter<File>,
+    /// How big the current file is
+    written: u64,
+    max_bytes: u64,
+    strip_ansi: bool,
+    /// The end of the output that isn't a whole line yet
+    partial: String,
+}
+
+fn unix_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// `path` with `.n` on the end
+fn rotated(path: &Path, n: u32) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+impl OutputLog {
+    /// Append to the log at `path`, making its directory if it needs to.
+    fn open(path: PathBuf, strip_ansi: bool, max_bytes: u64) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file: BufWriter::new(file),
+            written,
+            max_bytes,
+            strip_ansi,
+            partial: String::new(),
+        })
+    }
+
+    /// Move `path` to `path.1`, `path.1` to `path.2` and so on, dropping the
+    /// oldest, and start a fresh file.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        for n in (1..ROTATED_LOGS).rev() {
+            let from = rotated(&self.path, n);
+            if from.exists() {
+                fs::rename(from, rotated(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))?;
+
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.written = 0;
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str, redactor: &Redactor) -> io::Result<()> {
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+        }
+
+        let line = if self.strip_ansi {
+            visible_text(line)
+        } else {
+            line.to_string()
+        };
+
+        let line = redactor.redact(&line);
+
+        let millis = unix_millis();
+        let entry = format!("[{}.{:03}] {line}\n", millis / 1000, millis % 1000);
+        self.file.write_all(entry.as_bytes())?;
+        self.written += entry.len() as u64;
+
+        Ok(())
+    }
+
+    fn push(&mut self, data: &str, redactor: &Redactor) -> io::Result<()> {
+        let mut rest = data;
+
+        while let Some(end) = rest.find('\n') {
+            self.partial.push_str(&rest[..end]);
+            let line = std::mem::take(&mut self.partial);
+            self.write_line(&line, redactor)?;
+            rest = &rest[end + 1..];
+        }
+
+        self.partial.push_str(rest);
+        if self.partial.len() > MAX_LINE_BYTES {
+            let line = std::mem::take(&mut self.partial);
+            self.write_line(&line, redactor)?;
+        }
+
+        // it's an audit trail, so it shouldn't sit in memory waiting on a crash
+        self.file.flush()
+    }
+
+    /// Write out whatever's left and close the log
+    fn finish(mut self, redactor: &Redactor) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.write_line(&line, redactor)?;
+        }
+
+        self.file.flush()
+    }
+}
+
+/// Add output to terminal's log, if it's being logged, with any secrets in
+/// it redacted.
+pub async fn record(state: &AppState, terminal: &SubTerminal, data: &str) {
+    let mut log = terminal.output_log.lock().await;
+    let Some(current) = &mut *log else {
+        return;
+    };
+
+    if let Err(err) = current.push(data, &redact::current(state)) {
+        eprintln!("could not log terminal output, stopping: {err}");
+        *log = None;
+    }
+}
+
+/// Start logging a terminal that was just opened, if config.js asked for every terminal to be.
+pub async fn start_configured(state: &AppState, terminal_id: u32, terminal: &SubTerminal) {
+    let Some(settings) = state.output_logging.lock().await.clone() else {
+        return;
+    };
+
+    let path = settings
+        .dir
+        .join(format!("terminal-{terminal_id}-{}.log", unix_millis() / 1000));
+    match OutputLog::open(path, settings.strip_ansi, settings.max_bytes) {
+        Ok(log) => *terminal.output_log.lock().await = Some(log),
+        Err(err) => eprintln!("could not start logging terminal output: {err}"),
+    }
+}
+
+/// Log every terminal opened from now on into its own file in `dir`, or stop
+/// for `None`. Terminals that are already open are left as they are.
+#[tauri::command]
+pub async fn async_set_output_logging(
+    dir: Option<PathBuf>,
+    strip_ansi: Option<bool>,
+    max_bytes: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    *state.output_logging.lock().await = dir.map(|dir| OutputLogSettings {
+        dir,
+        strip_ansi: strip_ansi.unwrap_or(false),
+        max_bytes: max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+    });
+
+    Ok(())
+}
+
+/// Append terminal `terminal_id`'s output to the log at `path` from now on,
+/// rotating it once it's over `max_bytes`. Any log that was already going is
+/// closed first.
+#[tauri::command]
+pub async fn async_start_output_log(
+    terminal_id: u32,
+    path: PathBuf,
+    strip_ansi: Option<bool>,
+    max_bytes: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let log = OutputLog::open(
+        path,
+        strip_ansi.unwrap_or(false),
+        max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+    )?;
+
+    if let Some(previous) = terminal.output_log.lock().await.replace(log) {
+        previous.finish(&redact::current(&state))?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_stop_output_log(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let log = terminal
+        .output_log
+        .lock()
+        .await
+        .take()
+        .ok_or(SteppeError::NotLoggingOutput(terminal_id))?;
+
+    log.finish(&redact::current(&state))?;
+
+    Ok(())
+}
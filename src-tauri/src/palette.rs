@@ -0,0 +1,213 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+use crate::{error::SteppeError, keybindings, next_terminal_id, spawn_terminal, ssh, tasks, window, AppState};
+
+/// How many of the directories commands were last run in show up
+const RECENT_DIRS: usize = 10;
+
+/// Actions anyone might want without a keybinding for them, and what the
+/// palette calls them
+const ACTIONS: [(&str, &str); 20] = [
+    ("new_terminal", "new tab"),
+    ("new_window", "new window"),
+    ("split_horizontal", "split horizontally"),
+    ("split_vertical", "split vertically"),
+    ("duplicate_terminal", "duplicate terminal"),
+    ("duplicate_split_horizontal", "split horizontally with the same shell"),
+    ("duplicate_split_vertical", "split vertically with the same shell"),
+    ("close_pane", "close pane"),
+    ("copy", "copy"),
+    ("copy_as_html", "copy as html"),
+    ("paste", "paste"),
+    ("select_all", "select all"),
+    ("clear_scrollback", "clear scrollback"),
+    ("hard_reset", "reset terminal"),
+    ("scroll_to_top", "scroll to top"),
+    ("scroll_to_bottom", "scroll to bottom"),
+    ("previous_prompt", "jump to previous prompt"),
+    ("next_prompt", "jump to next prompt"),
+    ("select_last_output", "select the last command's output"),
+    ("hint_mode", "open a link on screen"),
+    ("toggle_output_pause", "pause or resume output"),
+    ("toggle_monitor_activity", "notify when this starts printing"),
+    ("toggle_monitor_silence", "notify when this stops printing"),
+    ("connect_container", "connect to container"),
+];
+
+/// Something `steppe.addCommand` put in the palette
+#[derive(Debug)]
+struct PaletteCommand {
+    label: String,
+    /// Same as a keybinding's action
+    action: String,
+}
+
+/// What config.js and plugins added to the palette
+#[derive(Debug, Default)]
+pub struct PaletteCommands {
+    commands: Vec<PaletteCommand>,
+}
+
+impl PaletteCommands {
+    pub fn add(&mut self, label: String, action: String) {
+        self.commands.push(PaletteCommand { label, action });
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaletteKind {
+    Action,
+    Profile,
+    Ssh,
+    Directory,
+    /// One of config.js's tasks
+    Task,
+    /// From `steppe.addCommand`
+    Script,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaletteItem {
+    /// What to hand `async_run_palette_item`
+    pub id: String,
+    pub label: String,
+    pub kind: PaletteKind,
+}
+
+impl PaletteItem {
+    fn new(kind: PaletteKind, id: String, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            kind,
+        }
+    }
+}
+
+/// Open a tab in window `label` connected to the ssh profile `name`
+pub async fn open_ssh_tab(app: &AppHandle, state: &AppState, name: String, label: &str) -> Result<(), SteppeError> {
+    let terminal_id = next_terminal_id(state).await?;
+    window::assign(state, terminal_id, label).await;
+
+    ssh::async_create_ssh(terminal_id, name, app.clone(), app.state()).await?;
+    app.emit_to(label, "terminal://spawned", terminal_id)?;
+
+    Ok(())
+}
+
+/// Everything the command palette can do right now: steppe's own actions,
+/// what config.js and plugins added, a tab for each profile and saved
+/// connection, config.js's tasks, and a tab in each directory commands were
+/// run in lately.
+#[tauri::command]
+pub async fn async_list_palette_items(state: State<'_, AppState>) -> Result<Vec<PaletteItem>, SteppeError> {
+    let mut items: Vec<PaletteItem> = ACTIONS
+        .iter()
+        .map(|(action, label)| PaletteItem::new(PaletteKind::Action, format!("action:{action}"), *label))
+        .collect();
+
+    let commands = state.palette_commands.lock().await;
+    items.extend(commands.commands.iter().enumerate().map(|(index, command)| {
+        PaletteItem::new(PaletteKind::Script, format!("script:{index}"), command.label.clone())
+    }));
+    drop(commands);
+
+    for profile in state.shell_profiles.lock().await.iter() {
+        let label = format!("new tab: {}", profile.name);
+        items.push(PaletteItem::new(
+            PaletteKind::Profile,
+            format!("profile:{}", profile.name),
+            label,
+        ));
+    }
+
+    for profile in state.ssh_profiles.lock().await.iter() {
+        let label = format!("connect: {}", profile.name);
+        items.push(PaletteItem::new(
+            PaletteKind::Ssh,
+            format!("ssh:{}", profile.name),
+            label,
+        ));
+    }
+
+    for name in state.connections.lock().await.names() {
+        if items.iter().any(|item| item.id == format!("ssh:{name}")) {
+            continue;
+        }
+        let label = format!("connect: {name}");
+        items.push(PaletteItem::new(PaletteKind::Ssh, format!("ssh:{name}"), label));
+    }
+
+    for task in state.settings.lock().await.tasks.iter().flatten() {
+        let label = format!("run task: {}", task.name);
+        items.push(PaletteItem::new(
+            PaletteKind::Task,
+            format!("task:{}", task.name),
+            label,
+        ));
+    }
+
+    // no history is no reason for the rest of the palette not to work
+    let database = state.command_history.lock().await.database.clone();
+    match database.recent_dirs(RECENT_DIRS).await {
+        Ok(dirs) => items.extend(dirs.into_iter().map(|dir| {
+            let label = format!("new tab in {}", dir.display());
+            PaletteItem::new(PaletteKind::Directory, format!("cwd:{}", dir.display()), label)
+        })),
+        Err(err) => eprintln!("could not read recent directories: {err}"),
+    }
+
+    Ok(items)
+}
+
+/// Do what the palette item `id` does, for terminal `terminal_id` in the
+/// calling window.
+#[tauri::command]
+pub async fn async_run_palette_item(
+    id: String,
+    terminal_id: u32,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let not_found = || SteppeError::PaletteItemNotFound(id.clone());
+    let (kind, rest) = id.split_once(':').ok_or_else(not_found)?;
+
+    match kind {
+        "action" => keybindings::invoke(rest.to_string(), terminal_id, &app, &state).await?,
+        "script" => {
+            let index: usize = rest.parse().map_err(|_| not_found())?;
+            let action = state
+                .palette_commands
+                .lock()
+                .await
+                .commands
+                .get(index)
+                .map(|command| command.action.clone())
+                .ok_or_else(not_found)?;
+
+            keybindings::invoke(action, terminal_id, &app, &state).await?;
+        }
+        "profile" => {
+            spawn_terminal(&app, Some(rest.to_string()), None, window.label()).await?;
+        }
+        "ssh" => open_ssh_tab(&app, &state, rest.to_string(), window.label()).await?,
+        "task" => {
+            tasks::run(&app, &state, rest, window.label()).await?;
+        }
+        "cwd" => {
+            spawn_terminal(&app, None, Some(PathBuf::from(rest)), window.label()).await?;
+        }
+        _ => return Err(not_found()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State, Window};
+
+use crate::{cwd, error::SteppeError, spawn_terminal, tabs, window, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitDirection {
+    /// One above the other, with a horizontal line between them
+    Horizontal,
+    /// Side by side, with a vertical line between them
+    Vertical,
+}
+
+/// A window's panes, as a tree of splits with a terminal at each leaf
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Pane {
+    #[serde(rename_all = "camelCase")]
+    Terminal { terminal_id: u32 },
+    #[serde(rename_all = "camelCase")]
+    Split {
+        direction: SplitDirection,
+        /// How much of the split `first` gets, above 0 and below 1
+        ratio: f64,
+        first: Box<Pane>,
+        second: Box<Pane>,
+    },
+}
+
+impl Pane {
+    fn is_terminal(&self, id: u32) -> bool {
+        matches!(self, Self::Terminal { terminal_id } if *terminal_id == id)
+    }
+
+    fn contains(&self, id: u32) -> bool {
+        match self {
+            Self::Terminal { terminal_id } => *terminal_id == id,
+            Self::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    /// The terminal in the top left, for focusing once the focused one's gone
+    fn first_terminal(&self) -> u32 {
+        match self {
+            Self::Terminal { terminal_id } => *terminal_id,
+            Self::Split { first, .. } => first.first_terminal(),
+        }
+    }
+
+    /// Split terminal `id`'s pane in half, with `new` going below or to the right
+    fn split(&mut self, id: u32, direction: SplitDirection, new: u32) -> bool {
+        match self {
+            Self::Terminal { terminal_id } if *terminal_id == id => {
+                *self = Self::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Self::Terminal { terminal_id: id }),
+                    second: Box::new(Self::Terminal { terminal_id: new }),
+                };
+                true
+            }
+            Self::Terminal { .. } => false,
+            Self::Split { first, second, .. } => first.split(id, direction, new) || second.split(id, direction, new),
+        }
+    }
+
+    /// The ratio of the split terminal `id`'s pane is directly in, and whether
+    /// it's that split's `first`
+    fn ratio_of(&mut self, id: u32) -> Option<(&mut f64, bool)> {
+        match self {
+            Self::Terminal { .. } => None,
+            Self::Split {
+                ratio, first, second, ..
+            } => {
+                if first.is_terminal(id) {
+                    return Some((ratio, true));
+                }
+                if second.is_terminal(id) {
+                    return Some((ratio, false));
+                }
+
+                first.ratio_of(id).or_else(|| second.ratio_of(id))
+            }
+        }
+    }
+
+    /// The tree without terminal `id`'s pane, whose sibling takes up the
+    /// whole split instead. `None` if that was the only pane.
+    fn remove(self, id: u32) -> Option<Self> {
+        match self {
+            Self::Terminal { terminal_id } if terminal_id == id => None,
+            Self::Terminal { .. } => Some(self),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => match (first.remove(id), second.remove(id)) {
+                (Some(first), Some(second)) => Some(Self::Split {
+                    direction,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(only), None) | (None, Some(only)) => Some(only),
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+/// A window's panes and which one has focus. Windows nobody's split yet
+/// don't have one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Layout {
+    pub root: Pane,
+    pub focused: u32,
+}
+
+/// The layout of window `label`, if it's been split
+pub async fn layout(state: &AppState, label: &str) -> Option<Layout> {
+    state.layouts.lock().await.get(label).cloned()
+}
+
+/// Use `layout` for window `label`, e.g. when a session's restored
+pub async fn set_layout(state: &AppState, label: &str, layout: Layout) {
+    state.layouts.lock().await.insert(label.to_string(), layout);
+}
+
+pub async fn emit_changed(app: &AppHandle, state: &AppState, label: &str) -> Result<(), SteppeError> {
+    app.emit_to(label, "layout://changed", layout(state, label).await)?;
+    Ok(())
+}
+
+/// Split terminal `terminal_id`'s pane, starting `profile` or the default
+/// shell (in the same directory) in the new half, which gets focus. Returns
+/// the new terminal's id.
+pub async fn split(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    direction: SplitDirection,
+    profile: Option<String>,
+) -> Result<u32, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let label = window::window_of(state, terminal_id).await;
+    check_splittable(state, &label, terminal_id).await?;
+
+    let cwd = match profile {
+        // profiles have a directory of their own
+        Some(_) => None,
+        None => cwd::inherited_cwd(&terminal).await,
+    };
+    let new = spawn_terminal(app, profile, cwd, &label).await?;
+    insert(app, state, &label, terminal_id, direction, new).await?;
+
+    Ok(new)
+}
+
+/// Whether terminal `terminal_id`, in window `label`, has a pane to split
+pub async fn check_splittable(state: &AppState, label: &str, terminal_id: u32) -> Result<(), SteppeError> {
+    // the window's other tabs aren't in its panes, so there's nothing of theirs to split
+    if let Some(layout) = layout(state, label).await {
+        if !layout.root.contains(terminal_id) {
+            return Err(SteppeError::NotInPane(terminal_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Split terminal `terminal_id`'s pane in window `label`, putting terminal
+/// `new` in the new half and focusing it
+pub async fn insert(
+    app: &AppHandle,
+    state: &AppState,
+    label: &str,
+    terminal_id: u32,
+    direction: SplitDirection,
+    new: u32,
+) -> Result<(), SteppeError> {
+    {
+        let mut layouts = state.layouts.lock().await;
+        let layout = layouts.entry(label.to_string()).or_insert_with(|| Layout {
+            root: Pane::Terminal { terminal_id },
+            focused: terminal_id,
+        });
+
+        if !layout.root.split(terminal_id, direction, new) {
+            return Err(SteppeError::NotInPane(terminal_id));
+        }
+        layout.focused = new;
+    }
+
+    emit_changed(app, state, label).await
+}
+
+/// Close terminal `terminal_id`'s pane and its tab with it, focusing what's left
+pub async fn close(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let label = window::window_of(state, terminal_id).await;
+
+    {
+        let mut layouts = state.layouts.lock().await;
+        if let Some(layout) = layouts.remove(&label) {
+            match layout.root.remove(terminal_id) {
+                // down to one pane, which is no split at all
+                Some(Pane::Terminal { .. }) | None => {}
+                Some(root) => {
+                    let focused = if root.contains(layout.focused) {
+                        layout.focused
+                    } else {
+                        root.first_terminal()
+                    };
+                    layouts.insert(label.clone(), Layout { root, focused });
+                }
+            }
+        }
+    }
+
+    tabs::close(app, state, terminal_id).await?;
+    emit_changed(app, state, &label).await
+}
+
+#[tauri::command]
+pub async fn async_get_layout(window: Window, state: State<'_, AppState>) -> Result<Option<Layout>, SteppeError> {
+    Ok(layout(&state, window.label()).await)
+}
+
+#[tauri::command]
+pub async fn async_split_pane(
+    terminal_id: u32,
+    direction: SplitDirection,
+    profile: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<u32, SteppeError> {
+    split(&app, &state, terminal_id, direction, profile).await
+}
+
+/// Give terminal `terminal_id`'s pane `ratio` of the split it's in, with the
+/// other side getting the rest
+#[tauri::command]
+pub async fn async_resize_pane(
+    terminal_id: u32,
+    ratio: f64,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if !(ratio > 0.0 && ratio < 1.0) {
+        return Err(SteppeError::InvalidPaneRatio(ratio));
+    }
+
+    let label = window::window_of(&state, terminal_id).await;
+
+    {
+        let mut layouts = state.layouts.lock().await;
+        let layout = layouts.get_mut(&label).ok_or(SteppeError::NotInPane(terminal_id))?;
+
+        let (split_ratio, first) = layout
+            .root
+            .ratio_of(terminal_id)
+            .ok_or(SteppeError::NotInPane(terminal_id))?;
+        *split_ratio = if first { ratio } else { 1.0 - ratio };
+    }
+
+    emit_changed(&app, &state, &label).await
+}
+
+#[tauri::command]
+pub async fn async_focus_pane(terminal_id: u32, app: AppHandle, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let label = window::window_of(&state, terminal_id).await;
+
+    {
+        let mut layouts = state.layouts.lock().await;
+        let layout = layouts
+            .get_mut(&label)
+            .filter(|layout| layout.root.contains(terminal_id))
+            .ok_or(SteppeError::NotInPane(terminal_id))?;
+        layout.focused = terminal_id;
+    }
+
+    emit_changed(&app, &state, &label).await
+}
+
+#[tauri::command]
+pub async fn async_close_pane(terminal_id: u32, app: AppHandle, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    close(&app, &state, terminal_id).await
+}
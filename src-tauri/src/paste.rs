@@ -0,0 +1,103 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{error::SteppeError, input_broadcast, write_input, AppState};
+
+/// Pastes bigger than this ask first, even on one line
+const CONFIRM_BYTES: usize = 64 * 1024;
+
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PasteResult {
+    Pasted,
+    /// Nothing was sent. `paste://confirm` went out too, and pasting again
+    /// with `confirmed` sends it anyway.
+    NeedsConfirmation {
+        lines: usize,
+        bytes: usize,
+    },
+}
+
+#[derive(Clone, Serialize)]
+struct PasteConfirm {
+    terminal_id: u32,
+    text: String,
+    lines: usize,
+    bytes: usize,
+}
+
+/// `text` as a terminal types it: newlines as carriage returns, like enter
+/// sends, and without the control characters a paste could use to do more
+/// than type, like escape sequences (which includes ending a bracketed
+/// paste early). Tabs are left in.
+pub fn sanitize(text: &str) -> String {
+    text.replace("\r\n", "\r")
+        .replace('\n', "\r")
+        .chars()
+        .filter(|&c| c == '\t' || c == '\r' || !c.is_control())
+        .collect()
+}
+
+/// Paste `text` into terminal `terminal_id`, unless it's the kind of paste
+/// that should be confirmed first: more than one line (which runs as soon as
+/// it's pasted) or a lot of text.
+pub async fn paste(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    text: &str,
+    confirmed: bool,
+) -> Result<PasteResult, SteppeError> {
+    let text = sanitize(text);
+    let lines = text.split_terminator('\r').count();
+    let bytes = text.len();
+
+    if !confirmed && (text.contains('\r') || bytes > CONFIRM_BYTES) {
+        app.emit(
+            "paste://confirm",
+            PasteConfirm {
+                terminal_id,
+                text,
+                lines,
+                bytes,
+            },
+        )?;
+        return Ok(PasteResult::NeedsConfirmation { lines, bytes });
+    }
+
+    for target in input_broadcast::targets(state, terminal_id).await {
+        let bracketed = state.terminal(target).await?.screen.lock().await.bracketed_paste();
+        let data = if bracketed {
+            format!("{PASTE_START}{text}{PASTE_END}")
+        } else {
+            text.clone()
+        };
+
+        write_input(app, state, target, &data).await?;
+    }
+
+    Ok(PasteResult::Pasted)
+}
+
+/// Paste what's on the clipboard, for the paste keybinding and menu item
+pub async fn paste_clipboard(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let text = app.clipboard().read_text()?;
+    paste(app, state, terminal_id, &text, false).await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_paste(
+    terminal_id: u32,
+    text: String,
+    confirmed: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<PasteResult, SteppeError> {
+    paste(&app, &state, terminal_id, &text, confirmed.unwrap_or(false)).await
+}
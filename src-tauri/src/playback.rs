@@ -0,0 +1,317 @@
+use std::{
+    path::PathBuf,
+    sync::{atomic::Ordering, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+
+use crate::{
+    crash,
+    error::SteppeError,
+    event_log::{self, SessionEventKind},
+    reader, scrollback, terminal_exited, AppState, SubTerminal,
+};
+
+/// Raw logs don't say when anything was printed, so they play back a line at a time at this pace
+const RAW_LINE_INTERVAL_SECS: f64 = 0.02;
+
+/// RIS, to start the screen over when seeking backwards
+const RESET: &str = "\x1bc";
+
+#[derive(Deserialize)]
+struct AsciicastHeader {
+    version: u8,
+}
+
+/// Output from a recording, and how far into it it was printed
+#[derive(Debug)]
+struct Frame {
+    time: f64,
+    data: String,
+}
+
+#[derive(Debug)]
+struct Controls {
+    /// Seconds into the recording
+    position: f64,
+    paused: bool,
+    speed: f64,
+    /// Where to jump to, once the player gets to it
+    seek_to: Option<f64>,
+    stopped: bool,
+}
+
+/// What a playback terminal's player is told to do, by way of the commands below
+#[derive(Debug)]
+pub struct Playback {
+    controls: Mutex<Controls>,
+    changed: Condvar,
+    /// How long the whole recording is, in seconds
+    duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackStatus {
+    position: f64,
+    duration: f64,
+    paused: bool,
+    speed: f64,
+}
+
+impl Playback {
+    fn controls(&self) -> MutexGuard<'_, Controls> {
+        self.controls.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Change the controls and wake the player up to look at them
+    fn update(&self, change: impl FnOnce(&mut Controls)) {
+        change(&mut self.controls());
+        self.changed.notify_all();
+    }
+}
+
+/// The output in an asciicast v2 file, or a raw log of pty output one line at a time.
+fn load(contents: &[u8]) -> Result<Vec<Frame>, SteppeError> {
+    let contents = String::from_utf8_lossy(contents);
+
+    let is_asciicast = contents
+        .lines()
+        .next()
+        .and_then(|line| serde_json::from_str::<AsciicastHeader>(line).ok())
+        .is_some_and(|header| header.version == 2);
+
+    if !is_asciicast {
+        return Ok(contents
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(line, data)| Frame {
+                time: line as f64 * RAW_LINE_INTERVAL_SECS,
+                data: data.to_string(),
+            })
+            .collect());
+    }
+
+    let mut frames = Vec::new();
+    for (line, event) in contents.lines().enumerate().skip(1) {
+        if event.trim().is_empty() {
+            continue;
+        }
+
+        let (time, code, data): (f64, String, String) = serde_json::from_str(event)
+            .map_err(|err| SteppeError::InvalidRecording(format!("line {}: {err}", line + 1)))?;
+
+        // input shows up in the output anyways, and resizes are up to whoever's watching
+        if code == "o" {
+            frames.push(Frame { time, data });
+        }
+    }
+
+    Ok(frames)
+}
+
+fn check_speed(speed: f64) -> Result<f64, SteppeError> {
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(SteppeError::InvalidConfig(format!("{speed} isn't a playback speed")))
+    }
+}
+
+/// Send a recording through the same output pipeline a shell's goes through,
+/// until playback's stopped.
+fn play(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal, playback: &Playback, frames: &[Frame]) {
+    let mut next = 0;
+    let mut controls = playback.controls();
+
+    while !controls.stopped {
+        if let Some(target) = controls.seek_to.take() {
+            let rewind = target < controls.position;
+            if rewind {
+                next = 0;
+            }
+
+            let mut data = if rewind { RESET.to_string() } else { String::new() };
+            while let Some(frame) = frames.get(next).filter(|frame| frame.time <= target) {
+                data.push_str(&frame.data);
+                next += 1;
+            }
+
+            controls.position = target;
+            drop(controls);
+
+            if rewind {
+                let state = app.state::<AppState>();
+                let _ = async_runtime::block_on(scrollback::clear(&state, terminal_id));
+            }
+            async_runtime::block_on(reader::handle_output(app, terminal_id, terminal, data));
+            terminal.wait_for_frontend();
+
+            controls = playback.controls();
+            continue;
+        }
+
+        let Some(frame) = frames.get(next) else {
+            // stay around at the end, for seeking back
+            controls.paused = true;
+            if let Err(err) = app.emit("playback://finished", terminal_id) {
+                eprintln!("could not send playback end: {err}");
+            }
+
+            controls = playback.changed.wait(controls).unwrap_or_else(|err| err.into_inner());
+            continue;
+        };
+
+        if controls.paused {
+            controls = playback.changed.wait(controls).unwrap_or_else(|err| err.into_inner());
+            continue;
+        }
+
+        let speed = controls.speed;
+        let wait = (frame.time - controls.position).max(0.0) / speed;
+        if wait > 0.0 {
+            let started = Instant::now();
+            let (guard, timeout) = playback
+                .changed
+                .wait_timeout(controls, Duration::from_secs_f64(wait))
+                .unwrap_or_else(|err| err.into_inner());
+            controls = guard;
+
+            // woken up early to pause, seek or change speed
+            if !timeout.timed_out() {
+                controls.position = (controls.position + started.elapsed().as_secs_f64() * speed).min(frame.time);
+                continue;
+            }
+        }
+
+        controls.position = frame.time;
+        next += 1;
+        drop(controls);
+
+        async_runtime::block_on(reader::handle_output(app, terminal_id, terminal, frame.data.clone()));
+        terminal.wait_for_frontend();
+
+        controls = playback.controls();
+    }
+}
+
+/// Like `async_create_shell`, but terminal `terminal_id` plays back a recording
+/// from an asciicast v2 file or a raw log, at `speed` times as fast as it was
+/// printed (1 by default).
+#[tauri::command]
+pub async fn async_create_playback(
+    terminal_id: u32,
+    path: PathBuf,
+    speed: Option<f64>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let speed = check_speed(speed.unwrap_or(1.0))?;
+    let frames = load(&tokio::fs::read(&path).await?)?;
+
+    let terminal = state.open_terminal(terminal_id).await?;
+    if terminal.has_terminal.load(Ordering::Acquire) {
+        return Ok(());
+    }
+
+    let source = path.display().to_string();
+    event_log::record(&state, terminal_id, SessionEventKind::Created, Some(source)).await;
+
+    let playback = Playback {
+        controls: Mutex::new(Controls {
+            position: 0.0,
+            paused: false,
+            speed,
+            seek_to: None,
+            stopped: false,
+        }),
+        changed: Condvar::new(),
+        duration: frames.last().map_or(0.0, |frame| frame.time),
+    };
+    if terminal.playback.set(playback).is_err() {
+        return Ok(());
+    }
+
+    // nothing's on the other end to type to
+    *terminal.writer.lock().await = Box::new(std::io::sink());
+
+    terminal.has_terminal.store(true, Ordering::Release);
+    crash::ACTIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+
+    thread::spawn(move || {
+        if let Some(playback) = terminal.playback.get() {
+            play(&app, terminal_id, &terminal, playback, &frames);
+        }
+        async_runtime::block_on(terminal_exited(&app, terminal_id, 0, None));
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_playback_paused(
+    terminal_id: u32,
+    paused: bool,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let playback = terminal.playback.get().ok_or(SteppeError::NotPlayback(terminal_id))?;
+    playback.update(|controls| controls.paused = paused);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_playback_speed(
+    terminal_id: u32,
+    speed: f64,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let speed = check_speed(speed)?;
+    let terminal = state.terminal(terminal_id).await?;
+    let playback = terminal.playback.get().ok_or(SteppeError::NotPlayback(terminal_id))?;
+    playback.update(|controls| controls.speed = speed);
+
+    Ok(())
+}
+
+/// Jump to `position` seconds into the recording
+#[tauri::command]
+pub async fn async_seek_playback(
+    terminal_id: u32,
+    position: f64,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let playback = terminal.playback.get().ok_or(SteppeError::NotPlayback(terminal_id))?;
+    let position = position.clamp(0.0, playback.duration);
+    playback.update(|controls| controls.seek_to = Some(position));
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_get_playback(terminal_id: u32, state: State<'_, AppState>) -> Result<PlaybackStatus, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let playback = terminal.playback.get().ok_or(SteppeError::NotPlayback(terminal_id))?;
+    let controls = playback.controls();
+
+    Ok(PlaybackStatus {
+        position: controls.seek_to.unwrap_or(controls.position),
+        duration: playback.duration,
+        paused: controls.paused,
+        speed: controls.speed,
+    })
+}
+
+/// Stop playing back, which closes the terminal the way a shell exiting would
+#[tauri::command]
+pub async fn async_stop_playback(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let playback = terminal.playback.get().ok_or(SteppeError::NotPlayback(terminal_id))?;
+    playback.update(|controls| controls.stopped = true);
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use deno_runtime::{deno_core::ModuleSpecifier, worker::MainWorker};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::{config::WorkerCommand, error::SteppeError, get_config_dir, AppState};
+
+/// Extra modules in `<config dir>/plugins`, each run alongside config.js with
+/// the same `steppe` global. They can export `activate(steppe)` and
+/// `deactivate()` to be told when they're turned on and off.
+#[derive(Debug)]
+pub struct Plugins {
+    /// File names, kept in `<config dir>/disabled_plugins.json`
+    disabled: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub enabled: bool,
+}
+
+fn plugins_dir() -> PathBuf {
+    get_config_dir().join("plugins")
+}
+
+fn disabled_path() -> PathBuf {
+    get_config_dir().join("disabled_plugins.json")
+}
+
+impl Plugins {
+    /// Pick up which plugins were turned off last time.
+    pub fn load() -> Self {
+        let disabled = fs::read(disabled_path())
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+
+        Self { disabled }
+    }
+
+    fn save(&self) -> Result<(), SteppeError> {
+        let file = File::create(disabled_path())?;
+        serde_json::to_writer(file, &self.disabled).map_err(std::io::Error::from)?;
+
+        Ok(())
+    }
+
+    fn list(&self) -> Vec<PluginInfo> {
+        let Ok(entries) = fs::read_dir(plugins_dir()) else {
+            return Vec::new();
+        };
+
+        let mut plugins: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file() && path.extension().is_some_and(|extension| extension == "js" || extension == "ts")
+            })
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().to_string();
+                Some(PluginInfo {
+                    enabled: !self.disabled.contains(&name),
+                    name,
+                    path,
+                })
+            })
+            .collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+        plugins
+    }
+
+    fn find(&self, name: &str) -> Result<PluginInfo, SteppeError> {
+        self.list()
+            .into_iter()
+            .find(|plugin| plugin.name == name)
+            .ok_or_else(|| SteppeError::PluginNotFound(name.to_string()))
+    }
+}
+
+/// Call into the `steppe.js` side of the plugin machinery, which does the
+/// importing and reports errors back as `plugin://error`.
+fn call(worker: &mut MainWorker, function: &str, path: &Path) {
+    let Ok(url) = ModuleSpecifier::from_file_path(path) else {
+        return;
+    };
+
+    // the url is json-quoted so it's a valid js string whatever's in the path
+    let script = format!("globalThis.__steppePlugins.{function}({})", serde_json::json!(url.as_str()));
+    if let Err(err) = worker.execute_script("steppe:plugins", script.into()) {
+        eprintln!("could not {function} plugin {}: {err}", path.display());
+    }
+}
+
+pub fn activate(worker: &mut MainWorker, path: &Path) {
+    call(worker, "activate", path);
+}
+
+pub fn deactivate(worker: &mut MainWorker, path: &Path) {
+    call(worker, "deactivate", path);
+}
+
+/// Start every enabled plugin in a freshly started config worker.
+pub async fn activate_enabled(app: &AppHandle, worker: &mut MainWorker) {
+    let plugins = app.state::<AppState>().plugins.lock().await.list();
+
+    for plugin in plugins.into_iter().filter(|plugin| plugin.enabled) {
+        activate(worker, &plugin.path);
+    }
+}
+
+/// Let every plugin clean up before its worker goes away.
+pub fn deactivate_all(worker: &mut MainWorker) {
+    if let Err(err) = worker.execute_script("steppe:plugins", "globalThis.__steppePlugins.deactivateAll()".into()) {
+        eprintln!("could not deactivate plugins: {err}");
+    }
+}
+
+#[tauri::command]
+pub async fn async_list_plugins(state: State<'_, AppState>) -> Result<Vec<PluginInfo>, SteppeError> {
+    Ok(state.plugins.lock().await.list())
+}
+
+#[tauri::command]
+pub async fn async_enable_plugin(name: String, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let mut plugins = state.plugins.lock().await;
+    let plugin = plugins.find(&name)?;
+    if plugin.enabled {
+        return Ok(());
+    }
+
+    plugins.disabled.remove(&name);
+    plugins.save()?;
+
+    // the worker's only gone if steppe is shutting down
+    let _ = state.worker_commands.send(WorkerCommand::ActivatePlugin(plugin.path));
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_disable_plugin(name: String, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let mut plugins = state.plugins.lock().await;
+    let plugin = plugins.find(&name)?;
+    if !plugin.enabled {
+        return Ok(());
+    }
+
+    plugins.disabled.insert(name);
+    plugins.save()?;
+
+    let _ = state.worker_commands.send(WorkerCommand::DeactivatePlugin(plugin.path));
+
+    Ok(())
+}
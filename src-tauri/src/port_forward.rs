@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{error::SteppeError, ssh, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardKind {
+    /// `-L`, a local port that reaches somewhere from the host
+    Local,
+    /// `-R`, a port on the host that reaches somewhere from here
+    Remote,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortForward {
+    pub kind: ForwardKind,
+    /// Defaults to localhost on whichever end is listening
+    pub bind_address: Option<String>,
+    pub bind_port: u16,
+    pub host: String,
+    pub port: u16,
+}
+
+impl PortForward {
+    pub fn flag(&self) -> &'static str {
+        match self.kind {
+            ForwardKind::Local => "-L",
+            ForwardKind::Remote => "-R",
+        }
+    }
+
+    /// `[bind_address:]bind_port:host:port`, the way ssh takes it
+    pub fn spec(&self) -> String {
+        match &self.bind_address {
+            Some(address) => format!("{address}:{}:{}:{}", self.bind_port, self.host, self.port),
+            None => format!("{}:{}:{}", self.bind_port, self.host, self.port),
+        }
+    }
+}
+
+/// Ask the ssh connection terminal `terminal_id` is running to `forward` or
+/// `cancel` a forward, through its control socket.
+async fn control(state: &AppState, terminal_id: u32, operation: &str, forward: &PortForward) -> Result<(), SteppeError> {
+    let profile = ssh::terminal_profile(state, terminal_id).await?;
+    // mosh's ssh is gone once it's logged in, and mosh itself only carries the terminal
+    if profile.mosh {
+        return Err(SteppeError::PortForward(format!("{} is connected with mosh", profile.name)));
+    }
+
+    let output = tokio::process::Command::new("ssh")
+        .args(["-O", operation, forward.flag(), &forward.spec()])
+        .args(profile.connection_args())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(SteppeError::PortForward(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_open_port_forward(
+    terminal_id: u32,
+    forward: PortForward,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut forwards = terminal.port_forwards.lock().await;
+    if forwards.contains(&forward) {
+        return Ok(());
+    }
+
+    control(&state, terminal_id, "forward", &forward).await?;
+    forwards.push(forward);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_list_port_forwards(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Vec<PortForward>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let forwards = terminal.port_forwards.lock().await.clone();
+
+    Ok(forwards)
+}
+
+#[tauri::command]
+pub async fn async_close_port_forward(
+    terminal_id: u32,
+    forward: PortForward,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut forwards = terminal.port_forwards.lock().await;
+    let Some(index) = forwards.iter().position(|open| *open == forward) else {
+        return Ok(());
+    };
+
+    control(&state, terminal_id, "cancel", &forward).await?;
+    forwards.remove(index);
+
+    Ok(())
+}
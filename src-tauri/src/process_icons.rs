@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+/// The icons a tab can show for what's running in it, from `icons/processes`
+const ICONS: [(&str, &str); 8] = [
+    ("docker", include_str!("../icons/processes/docker.svg")),
+    ("editor", include_str!("../icons/processes/editor.svg")),
+    ("git", include_str!("../icons/processes/git.svg")),
+    ("monitor", include_str!("../icons/processes/monitor.svg")),
+    ("node", include_str!("../icons/processes/node.svg")),
+    ("python", include_str!("../icons/processes/python.svg")),
+    ("rust", include_str!("../icons/processes/rust.svg")),
+    ("ssh", include_str!("../icons/processes/ssh.svg")),
+];
+
+/// Which icon goes with which program, from `steppe.setProcessIconMap`. The
+/// patterns are kept in the order they were given, since the first one that
+/// matches wins.
+#[derive(Debug, Default)]
+pub struct ProcessIcons {
+    patterns: Vec<(String, String)>,
+}
+
+impl ProcessIcons {
+    /// The icon for the program `name`, or `""` if no pattern matches it
+    pub fn icon_for(&self, name: &str) -> &str {
+        self.patterns
+            .iter()
+            .find(|(pattern, _)| matches(pattern, name))
+            .map_or("", |(_, icon)| icon)
+    }
+}
+
+/// Whether `name` is `pattern`, where each `*` in it stands for anything
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[derive(Clone, Serialize)]
+pub struct SessionProcessChanged {
+    session_id: u32,
+    process_name: String,
+    /// From [`async_get_process_icons`], or empty when there's no icon for it
+    icon_name: String,
+}
+
+/// Let the frontend know terminal `terminal_id` is running `name` now, and
+/// which icon that gets
+pub fn emit_changed(app: &AppHandle, icons: &ProcessIcons, terminal_id: u32, name: &str) {
+    let changed = SessionProcessChanged {
+        session_id: terminal_id,
+        process_name: name.to_string(),
+        icon_name: icons.icon_for(name).to_string(),
+    };
+
+    if let Err(err) = app.emit("session-process-changed", changed) {
+        eprintln!("could not send process icon: {err}");
+    }
+}
+
+/// Show icon `icon` for programs matching each pattern, first match first.
+/// What's running now gets its icon again straight away.
+#[tauri::command]
+pub async fn async_set_process_icon_map(
+    map: Vec<(String, String)>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if let Some((_, icon)) = map.iter().find(|(_, icon)| !ICONS.iter().any(|(name, _)| name == icon)) {
+        let names: Vec<_> = ICONS.iter().map(|(name, _)| *name).collect();
+        return Err(SteppeError::InvalidConfig(format!(
+            "there's no {icon:?} process icon, only {}",
+            names.join(", ")
+        )));
+    }
+
+    state.process_icons.lock().await.patterns = map;
+
+    // in the same order as `foreground::spawn_tracker` takes them
+    let foreground = state.foreground_process.lock().await;
+    let icons = state.process_icons.lock().await;
+    for (terminal_id, process) in foreground.iter() {
+        emit_changed(&app, &icons, *terminal_id, &process.name);
+    }
+
+    Ok(())
+}
+
+/// Every icon by name, as `data:` urls to use as an `<img>`'s `src`.
+#[tauri::command]
+pub async fn async_get_process_icons() -> Result<HashMap<String, String>, SteppeError> {
+    Ok(ICONS
+        .iter()
+        .map(|(name, svg)| {
+            let url = format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg));
+            (name.to_string(), url)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        assert!(matches("nvim", "nvim"));
+        assert!(!matches("nvim", "nvim-qt"));
+        assert!(matches("nvim*", "nvim-qt"));
+        assert!(matches("*vim", "nvim"));
+        assert!(matches("py*3*", "python3.12"));
+        assert!(!matches("py*3*", "python2"));
+        assert!(matches("*", "anything"));
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let icons = ProcessIcons {
+            patterns: vec![
+                ("git".to_string(), "git".to_string()),
+                ("*".to_string(), "monitor".to_string()),
+            ],
+        };
+
+        assert_eq!(icons.icon_for("git"), "git");
+        assert_eq!(icons.icon_for("top"), "monitor");
+        assert_eq!(ProcessIcons::default().icon_for("git"), "");
+    }
+}
@@ -0,0 +1,212 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::{
+    cwd,
+    error::SteppeError,
+    foreground::{self, ForegroundProcess},
+    AppState, SubTerminal,
+};
+
+/// How deep `tree` goes, so a fork bomb (or a pid reused mid-walk) can't keep it going
+const MAX_DEPTH: usize = 16;
+
+/// A process and everything it started that's still running
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub name: String,
+    pub children: Vec<ProcessNode>,
+}
+
+/// What the os says about a terminal's shell, for when the shell doesn't say
+/// itself over OSC 7 and 133
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalInfo {
+    pub pid: Option<u32>,
+    pub program: Option<String>,
+    pub cwd: Option<PathBuf>,
+    pub foreground: Option<ForegroundProcess>,
+    /// The shell and everything running under it
+    pub processes: Option<ProcessNode>,
+}
+
+#[cfg(target_os = "linux")]
+pub fn cwd(pid: u32) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{pid}/cwd")).ok()
+}
+
+#[cfg(target_os = "macos")]
+pub fn cwd(pid: u32) -> Option<PathBuf> {
+    libproc::libproc::proc_pid::pidcwd(pid as i32).ok()
+}
+
+/// Windows only keeps it in the process's own memory
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn cwd(_pid: u32) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+pub fn name(pid: u32) -> Option<String> {
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(comm.trim_end().to_string())
+}
+
+#[cfg(target_os = "macos")]
+pub fn name(pid: u32) -> Option<String> {
+    libproc::libproc::proc_pid::name(pid as i32).ok()
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+pub fn name(pid: u32) -> Option<String> {
+    // no procfs, so ask ps
+    let output = std::process::Command::new("ps")
+        .args(["-o", "comm=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+
+    let name = String::from_utf8_lossy(&output.stdout);
+    let name = name.trim();
+    let name = name.rsplit('/').next().unwrap_or(name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(windows)]
+pub fn name(pid: u32) -> Option<String> {
+    windows_processes(&format!("ProcessId={pid}"))
+        .into_iter()
+        .next()
+        .map(|(_, name)| name)
+}
+
+/// The processes `pid` started, with their names
+#[cfg(target_os = "linux")]
+fn children(pid: u32) -> Vec<(u32, String)> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|child| {
+            let stat = std::fs::read_to_string(format!("/proc/{child}/stat")).ok()?;
+            // `pid (comm) state ppid ...`, where comm can have spaces and brackets of its own
+            let (comm, rest) = stat.split_once(" (")?.1.rsplit_once(") ")?;
+            let ppid: u32 = rest.split(' ').nth(1)?.parse().ok()?;
+
+            (ppid == pid).then(|| (child, comm.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn children(pid: u32) -> Vec<(u32, String)> {
+    use libproc::processes::{pids_by_type, ProcFilter};
+
+    pids_by_type(ProcFilter::ByParentProcess { ppid: pid })
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|child| Some((child, name(child)?)))
+        .collect()
+}
+
+#[cfg(windows)]
+fn children(pid: u32) -> Vec<(u32, String)> {
+    windows_processes(&format!("ParentProcessId={pid}"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn children(_pid: u32) -> Vec<(u32, String)> {
+    Vec::new()
+}
+
+/// Pids and names of the processes matching a WMI `filter`, by way of
+/// powershell since there's no procfs to read
+#[cfg(windows)]
+fn windows_processes(filter: &str) -> Vec<(u32, String)> {
+    let script = format!(
+        "Get-CimInstance Win32_Process -Filter '{filter}' | ForEach-Object {{ \"$($_.ProcessId) $($_.Name)\" }}"
+    );
+    let Ok(output) = std::process::Command::new("powershell.exe")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, name) = line.trim().split_once(' ')?;
+            Some((pid.parse().ok()?, name.to_string()))
+        })
+        .collect()
+}
+
+fn subtree(pid: u32, name: String, depth: usize) -> ProcessNode {
+    let mut children: Vec<ProcessNode> = if depth < MAX_DEPTH {
+        children(pid)
+            .into_iter()
+            .map(|(child, name)| subtree(child, name, depth + 1))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    // oldest first, as near as pids can say
+    children.sort_by_key(|child| child.pid);
+
+    ProcessNode { pid, name, children }
+}
+
+/// `pid` and everything under it, or `None` if it's gone
+pub fn tree(pid: u32) -> Option<ProcessNode> {
+    Some(subtree(pid, name(pid)?, 0))
+}
+
+/// What's running in the shell in `terminal`, going by its process tree: the
+/// newest thing the shell started, or whatever that started in turn. Only a
+/// guess, since a program can start things in the background too.
+pub fn running(terminal: &SubTerminal) -> Option<ForegroundProcess> {
+    let mut process = tree(terminal.shell.get()?.pid?)?.children.pop()?;
+    while let Some(child) = process.children.pop() {
+        process = child;
+    }
+
+    Some(ForegroundProcess {
+        pid: process.pid,
+        name: process.name,
+    })
+}
+
+/// What the os knows about terminal `terminal_id`'s shell: where it is and
+/// what it's running, whether or not the shell reports either.
+#[tauri::command]
+pub async fn async_get_terminal_info(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<TerminalInfo, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let shell = terminal.shell.get();
+    let pid = shell.and_then(|shell| shell.pid);
+
+    // walking the tree means reading a file per process, or running powershell
+    let processes = match pid {
+        Some(pid) => tauri::async_runtime::spawn_blocking(move || tree(pid))
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    };
+
+    Ok(TerminalInfo {
+        pid,
+        program: shell.map(|shell| shell.program.clone()),
+        cwd: cwd::terminal_cwd(&terminal).await,
+        foreground: foreground::foreground_process(&terminal).await,
+        processes,
+    })
+}
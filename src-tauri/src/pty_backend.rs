@@ -0,0 +1,62 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::{error::SteppeError, identity, AppState};
+
+/// What every shell's told the terminal is, unless `terminalIdentity.term`
+/// says otherwise. ConPTY translates to and from xterm's sequences too, so
+/// it's the same on windows: `cygwin` would have msys programs (git bash,
+/// vim from it, ...) drawing for a console that isn't there.
+pub const TERM: &str = "xterm-256color";
+
+/// What kind of pty terminals run in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PtyBackend {
+    /// A unix pty, from `openpty`
+    Unix,
+    /// Windows' pseudo console, which is the only one portable-pty has
+    Conpty,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyBackendInfo {
+    pub backend: PtyBackend,
+    /// Windows' build number, which decides what ConPTY gets wrong: xterm.js
+    /// works around its resizes by it, see `windowsPty` in Terminal.svelte
+    pub windows_build: Option<u64>,
+    /// What `TERM` is set to
+    pub term: String,
+}
+
+fn windows_build() -> Option<u64> {
+    if !cfg!(windows) {
+        return None;
+    }
+
+    // windows 10 and 11 are both 10.0, the build's what tells them apart
+    match os_info::get().version() {
+        os_info::Version::Semantic(_, _, build) => Some(*build),
+        _ => None,
+    }
+}
+
+pub async fn info(state: &AppState) -> PtyBackendInfo {
+    PtyBackendInfo {
+        backend: if cfg!(windows) {
+            PtyBackend::Conpty
+        } else {
+            PtyBackend::Unix
+        },
+        windows_build: windows_build(),
+        term: identity::term(state, false).await,
+    }
+}
+
+/// Which pty terminals run in, for diagnostics and for the frontend to know
+/// which of ConPTY's quirks to work around.
+#[tauri::command]
+pub async fn async_get_pty_backend_info(state: State<'_, AppState>) -> Result<PtyBackendInfo, SteppeError> {
+    Ok(info(&state).await)
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewWindow, Window, WindowEvent};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::{error::SteppeError, window, AppState};
+
+/// The dropdown window's label
+pub const QUAKE_WINDOW: &str = "quake";
+
+/// A terminal window that drops down from the top of the screen on a global
+/// hotkey, and goes away again (shells and all still running) on the next
+/// press. Turned on by setting `quake` at all.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct QuakeOptions {
+    /// Like `ctrl+backquote` or `F12`, which works even when steppe isn't focused
+    pub hotkey: String,
+    /// How much of the monitor's height it takes up, above 0 and up to 1
+    pub height: f64,
+    /// Which monitor it drops down on, counting from 0, rather than the primary one
+    pub monitor: Option<usize>,
+}
+
+impl Default for QuakeOptions {
+    fn default() -> Self {
+        Self {
+            hotkey: "F12".to_string(),
+            height: 0.4,
+            monitor: None,
+        }
+    }
+}
+
+impl QuakeOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        let height = self.height;
+        if !(height > 0.0 && height <= 1.0) {
+            return Err(format!("quake.height has to be above 0 and up to 1, not {height}"));
+        }
+
+        self.shortcut()?;
+
+        Ok(())
+    }
+
+    fn shortcut(&self) -> Result<Shortcut, String> {
+        self.hotkey
+            .parse()
+            .map_err(|err| format!("quake.hotkey {:?} isn't a hotkey: {err}", self.hotkey))
+    }
+}
+
+/// Bind the hotkey in `options`, in place of whatever was bound before, or
+/// just unbind it for `None`. The window itself stays open either way.
+pub async fn configure(app: &AppHandle, state: &AppState, options: Option<&QuakeOptions>) -> Result<(), SteppeError> {
+    let shortcut = options
+        .map(QuakeOptions::shortcut)
+        .transpose()
+        .map_err(SteppeError::InvalidConfig)?;
+
+    let mut bound = state.quake_shortcut.lock().await;
+    if *bound == shortcut {
+        return Ok(());
+    }
+
+    if let Some(old) = bound.take() {
+        app.global_shortcut().unregister(old)?;
+    }
+    if let Some(shortcut) = shortcut {
+        app.global_shortcut().register(shortcut)?;
+        *bound = Some(shortcut);
+    }
+
+    Ok(())
+}
+
+/// Fill the top `options.height` of the configured monitor, and bring the window up
+fn drop_down(window: &WebviewWindow, options: &QuakeOptions) -> Result<(), SteppeError> {
+    let monitor = match options.monitor {
+        Some(index) => window.available_monitors()?.into_iter().nth(index),
+        None => window.primary_monitor()?,
+    };
+
+    if let Some(monitor) = monitor {
+        let (position, size) = (monitor.position(), monitor.size());
+        let height = (f64::from(size.height) * options.height).round() as u32;
+
+        window.set_size(PhysicalSize::new(size.width, height))?;
+        window.set_position(PhysicalPosition::new(position.x, position.y))?;
+    }
+
+    window.show()?;
+    window.set_focus()?;
+
+    Ok(())
+}
+
+/// Show the dropdown window, opening it the first time, or hide it if it's
+/// already up and focused
+pub async fn toggle(app: &AppHandle, state: &AppState) -> Result<(), SteppeError> {
+    let Some(options) = state.settings.lock().await.quake.clone() else {
+        return Ok(());
+    };
+
+    let window = match app.get_webview_window(QUAKE_WINDOW) {
+        Some(window) if window.is_visible()? && window.is_focused()? => return Ok(window.hide()?),
+        Some(window) => window,
+        None => {
+            let mut config = window::main_config(app)?;
+            config.label = QUAKE_WINDOW.to_string();
+            config.decorations = false;
+            config.always_on_top = true;
+            config.skip_taskbar = true;
+            // shown once it's been moved into place
+            config.visible = false;
+
+            window::build(app, state, &config).await?
+        }
+    };
+
+    drop_down(&window, &options)
+}
+
+/// For the global shortcut plugin, which calls this for every hotkey steppe
+/// has registered. The quake one is the only one so far.
+pub fn handle_shortcut(app: &AppHandle, _shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = toggle(&app, &app.state::<AppState>()).await {
+            eprintln!("could not toggle the quake window: {err}");
+        }
+    });
+}
+
+/// Closing the dropdown window only hides it, so its shells keep running
+/// until the next time it drops down. Returns whether it was hidden instead.
+pub fn hide_instead_of_close(window: &Window, event: &WindowEvent) -> bool {
+    let WindowEvent::CloseRequested { api, .. } = event else {
+        return false;
+    };
+    if window.label() != QUAKE_WINDOW {
+        return false;
+    }
+
+    api.prevent_close();
+    if let Err(err) = window.hide() {
+        eprintln!("could not hide the quake window: {err}");
+    }
+
+    true
+}
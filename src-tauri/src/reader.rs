@@ -0,0 +1,658 @@
+use std::{
+    io::Read,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tauri::{
+    async_runtime,
+    ipc::{Channel, InvokeResponseBody},
+    AppHandle, Emitter, Manager, State,
+};
+
+use crate::{
+    asciicast, crash, cursor,
+    error::SteppeError,
+    event_log::{self, SessionEventKind},
+    graphics::{Chunk, Graphics},
+    keyboard, monitor, output_batch, output_log, search, secure_input, shell_integration, startup,
+    startup_notification,
+    steppe_api::ScriptOutput,
+    tmux::{self, ControlScanner, Part},
+    triggers, AppState, SubTerminal, SESSION_ID,
+};
+
+/// Stop reading once the frontend is this far behind
+const HIGH_WATER_BYTES: usize = 1024 * 1024;
+
+/// The most output that goes into one `terminal://output`, unless config says otherwise
+pub const DEFAULT_COALESCE_BYTES: usize = 16 * 1024;
+
+/// How often a terminal that's printing nonstop sends output, unless config says otherwise
+pub const DEFAULT_COALESCE_INTERVAL_MS: u64 = 8;
+
+/// Reads the read thread can get ahead of the emitting one by, which is
+/// what keeps the frontend's backpressure reaching the pty
+const READ_AHEAD: usize = 16;
+
+/// If the frontend hasn't caught up by now it probably never will (a reload
+/// drops everything in flight), so start over rather than hang the shell
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a terminal's output thread waits to be started again after it crashes
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// How many times in a row it can crash before the terminal's given up on
+const MAX_CRASHES: u32 = 3;
+
+/// What output threads' names start with. A panic on one doesn't take steppe
+/// down with it, see `crash::install_panic_hook`.
+pub const OUTPUT_THREAD_PREFIX: &str = "pty-output-";
+
+/// What `async_benchmark_output` prints, over and over: a bit of color and
+/// a lot of plain text, like most output
+const BENCHMARK_LINE: &str = "\x1b[32mok\x1b[0m the quick brown fox jumps over the lazy dog 0123456789\r\n";
+
+/// Turns pty output into text, even when a read ends partway through a
+/// character or the program writes something that isn't utf-8 at all.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    /// The start of a character whose remaining bytes haven't been read yet
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, bytes: Vec<u8>) -> String {
+        let input = if self.pending.is_empty() {
+            bytes
+        } else {
+            let mut input = std::mem::take(&mut self.pending);
+            input.extend_from_slice(&bytes);
+            input
+        };
+
+        // nearly every read is valid utf-8 from end to end, and then it's the string as it is
+        let input = match String::from_utf8(input) {
+            Ok(text) => return text,
+            Err(err) => err.into_bytes(),
+        };
+
+        let mut text = String::with_capacity(input.len());
+        let mut rest = input.as_slice();
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    // everything up to `valid_up_to` was checked just now
+                    text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+
+                    match err.error_len() {
+                        // bytes that can never be utf-8, show them the way xterm would
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[len..];
+                        }
+                        // cut off by the end of the read, the rest is still coming
+                        None => {
+                            self.pending = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        text
+    }
+
+    /// What's left once there's nothing more to read: the start of a
+    /// character that never got finished, which comes out as one U+FFFD.
+    pub fn finish(&mut self) -> String {
+        if std::mem::take(&mut self.pending).is_empty() {
+            String::new()
+        } else {
+            char::REPLACEMENT_CHARACTER.to_string()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OutputBenchmark {
+    bytes: usize,
+    elapsed_ms: u64,
+    bytes_per_second: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct OutputPaused {
+    terminal_id: u32,
+    paused: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ReaderCrashed {
+    session_id: u32,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+struct SessionCorrupted {
+    session_id: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct TerminalOutput {
+    terminal_id: u32,
+    data: String,
+    /// What to pass back to `async_ack_terminal_output` once it's been written
+    bytes: usize,
+}
+
+impl SubTerminal {
+    /// Block until the frontend has written enough of what it's been sent.
+    pub fn wait_for_frontend(&self) {
+        self.wait_until_unacked(HIGH_WATER_BYTES);
+    }
+
+    /// Block until no more than `bytes` of what the frontend's been sent
+    /// hasn't been written yet
+    fn wait_until_unacked(&self, bytes: usize) {
+        let unacked = self.unacked_bytes.lock().unwrap_or_else(|err| err.into_inner());
+        let (mut unacked, timeout) = self
+            .output_acked
+            .wait_timeout_while(unacked, ACK_TIMEOUT, |unacked| *unacked > bytes)
+            .unwrap_or_else(|err| err.into_inner());
+
+        if timeout.timed_out() {
+            *unacked = 0;
+        }
+    }
+
+    /// Block for as long as output's paused. The pty fills up in the
+    /// meantime, and then whatever's writing to it blocks too.
+    fn wait_while_paused(&self) {
+        let unacked = self.unacked_bytes.lock().unwrap_or_else(|err| err.into_inner());
+        let _unacked = self
+            .output_acked
+            .wait_while(unacked, |_| self.output_paused.load(Ordering::Acquire))
+            .unwrap_or_else(|err| err.into_inner());
+    }
+
+    pub fn set_output_paused(&self, paused: bool) {
+        // under the lock, so the reader can't check the flag and then miss the wakeup
+        let _unacked = self.unacked_bytes.lock().unwrap_or_else(|err| err.into_inner());
+        self.output_paused.store(paused, Ordering::Release);
+        self.output_acked.notify_all();
+    }
+}
+
+/// Stop or start reading terminal `terminal_id`'s output, and let the frontend know.
+pub async fn set_paused(app: &AppHandle, state: &AppState, terminal_id: u32, paused: bool) -> Result<(), SteppeError> {
+    state.terminal(terminal_id).await?.set_output_paused(paused);
+    let kind = if paused {
+        SessionEventKind::Suspended
+    } else {
+        SessionEventKind::Resumed
+    };
+    event_log::record(state, terminal_id, kind, None).await;
+    app.emit("terminal://output-paused", OutputPaused { terminal_id, paused })?;
+
+    Ok(())
+}
+
+/// Send output to the frontend, counting it against the terminal's backpressure limit.
+/// `raw` is the output as the pty wrote it, and `data` the same decoded.
+///
+/// Once the frontend's subscribed with `async_subscribe_output`, `raw` goes
+/// over that channel untouched, which skips escaping it into json and back
+/// on every read, and leaves decoding it to xterm. Until then `data` goes
+/// out as a `terminal://output` event.
+pub async fn emit_output(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    raw: Vec<u8>,
+    data: String,
+) -> tauri::Result<()> {
+    let channels = state.output_channels.lock().await;
+    let channel = channels.get(&terminal_id);
+    let bytes = if channel.is_some() { raw.len() } else { data.len() };
+    if let Ok(terminal) = state.terminal(terminal_id).await {
+        *terminal.unacked_bytes.lock().unwrap_or_else(|err| err.into_inner()) += bytes;
+    }
+
+    if let Some(channel) = channel {
+        return channel.send(InvokeResponseBody::Raw(raw));
+    }
+    drop(channels);
+
+    app.emit("terminal://output", TerminalOutput { terminal_id, data, bytes })
+}
+
+/// Keep track of the terminal modes the backend cares about
+async fn track_dec_modes(app: &AppHandle, state: &AppState, terminal_id: u32, terminal: &SubTerminal, data: &str) {
+    let mut changes = Vec::new();
+    terminal
+        .dec_modes
+        .lock()
+        .await
+        .scan(data.as_bytes(), |mode, enabled| changes.push((mode, enabled)));
+
+    for (mode, enabled) in changes {
+        terminal.mouse_report_mode.lock().await.set(mode, enabled);
+
+        // the cursor colors are the main terminal's
+        if mode == cursor::INVERSE_VIDEO_MODE && terminal_id == SESSION_ID {
+            if let Err(err) = cursor::set_inverse_video(app, state, enabled).await {
+                eprintln!("could not update cursor colors: {err}");
+            }
+        }
+    }
+}
+
+/// Handle output that's text to begin with, rather than read from the pty
+pub async fn handle_output(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal, data: String) {
+    handle_pty_output(app, terminal_id, terminal, data.clone().into_bytes(), data).await;
+}
+
+/// Handle a read from terminal `terminal_id`'s pty, `raw` as the pty wrote it
+/// and `data` the same decoded, which is what everything but the frontend
+/// goes by.
+async fn handle_pty_output(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal, raw: Vec<u8>, data: String) {
+    let state = app.state::<AppState>();
+    let mut scrollback = terminal.scrollback.lock().await;
+    scrollback.push(&data);
+    // under the same lock, so a client attaching gets each bit in its replay or sent, never both
+    if terminal.remote_output.receiver_count() > 0 {
+        let _ = terminal.remote_output.send(data.clone());
+    }
+    drop(scrollback);
+    search::scan_output(app, terminal_id, terminal).await;
+    terminal.screen.lock().await.advance(data.as_bytes());
+    secure_input::check(app, terminal_id, terminal).await;
+    keyboard::track(app, terminal_id, terminal, &data).await;
+    asciicast::record(&state, terminal_id, terminal, asciicast::Event::Output, &data).await;
+    output_log::record(&state, terminal, &data).await;
+    monitor::output(app, terminal_id, terminal).await;
+    shell_integration::scan_output(app, &state, terminal_id, terminal, &data).await;
+    triggers::scan_output(app, &state, terminal_id, &mut *terminal.trigger_lines.lock().await, &data).await;
+
+    // cloning every read isn't worth it when config.js isn't listening
+    if state.script_output.receiver_count() > 0 {
+        let _ = state.script_output.send(ScriptOutput {
+            terminal_id,
+            data: data.clone(),
+        });
+    }
+
+    track_dec_modes(app, &state, terminal_id, terminal, &data).await;
+
+    let output = if terminal_id == SESSION_ID {
+        startup::send_startup_sequences(&state).await;
+
+        if let Err(err) = startup_notification::scan_output(app, &state, data.as_bytes()).await {
+            eprintln!("could not send startup notification: {err}");
+        }
+
+        output_batch::push(app, &state, raw, data).await
+    } else {
+        Some((raw, data))
+    };
+
+    if let Some((raw, data)) = output {
+        if let Err(err) = emit_output(app, &state, terminal_id, raw, data).await {
+            eprintln!("could not send terminal output: {err}");
+        }
+    }
+}
+
+/// Read from the pty until the shell goes away, emitting `terminal://output` as it comes in.
+///
+/// Output right after a quiet spell goes out straight away, so typing stays
+/// snappy, but a terminal that keeps printing (`yes`, `cat` on something
+/// big) gets its reads gathered up into at most one event per coalescing
+/// interval, rather than thousands of tiny ones a second.
+///
+/// Each terminal gets two threads of its own, neither on the async runtime:
+/// one does nothing but read, and hands what it read to the other over a
+/// channel that holds `READ_AHEAD` reads. The other handles it and waits on
+/// the frontend, so a terminal the frontend's behind on only ever holds up
+/// its own reads (and once the channel's full, its own pty). It starts over
+/// if it panics, see `spawn_output`.
+///
+/// Returns the reading thread, which finishes once the pty's closed, or an
+/// error if either thread couldn't be started.
+pub fn spawn_reader(
+    app: AppHandle,
+    terminal_id: u32,
+    terminal: Arc<SubTerminal>,
+    mut reader: Box<dyn Read + Send>,
+) -> Result<thread::JoinHandle<()>, SteppeError> {
+    let (reads, pending) = mpsc::sync_channel::<Vec<u8>>(READ_AHEAD);
+
+    let reading = spawn_named(format!("pty-reader-{terminal_id}"), move || {
+        let mut buf = [0; 8192];
+
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => len,
+            };
+
+            if reads.send(buf[..len].to_vec()).is_err() {
+                break;
+            }
+        }
+    })?;
+
+    spawn_output(app, terminal_id, terminal, pending, 0)?;
+
+    Ok(reading)
+}
+
+/// Handle what's read on a thread of its own, which starts over (after
+/// `RESTART_DELAY`) if it panics, with `crashes` the times in a row it
+/// already has. What it keeps track of between reads starts over too, since
+/// a panic could've left that half-updated. Past `MAX_CRASHES`, the
+/// terminal's marked as corrupted and its output's dropped from then on, so
+/// the shell doesn't block on a full pty.
+fn spawn_output(
+    app: AppHandle,
+    terminal_id: u32,
+    terminal: Arc<SubTerminal>,
+    reads: Receiver<Vec<u8>>,
+    mut crashes: u32,
+) -> Result<(), SteppeError> {
+    spawn_named(format!("{OUTPUT_THREAD_PREFIX}{terminal_id}"), move || {
+        let handled = panic::catch_unwind(AssertUnwindSafe(|| {
+            handle_reads(&app, terminal_id, &terminal, &reads, &mut crashes)
+        }));
+        let Err(panic) = handled else {
+            return;
+        };
+
+        crashes += 1;
+        let error = crash::panic_message(&*panic);
+        eprintln!("terminal {terminal_id}'s output crashed: {error}");
+        terminal.reader_dead.store(true, Ordering::Relaxed);
+        let crashed = ReaderCrashed {
+            session_id: terminal_id,
+            error,
+        };
+        if let Err(err) = app.emit("session-reader-crashed", crashed) {
+            eprintln!("could not send reader crash: {err}");
+        }
+
+        if crashes >= MAX_CRASHES {
+            terminal.corrupted.store(true, Ordering::Relaxed);
+            let corrupted = SessionCorrupted {
+                session_id: terminal_id,
+            };
+            if let Err(err) = app.emit("session-corrupted", corrupted) {
+                eprintln!("could not send session corruption: {err}");
+            }
+
+            while reads.recv().is_ok() {}
+            return;
+        }
+
+        thread::sleep(RESTART_DELAY);
+        terminal.reader_dead.store(false, Ordering::Relaxed);
+        let (app, terminal) = (app.clone(), terminal.clone());
+        if let Err(err) = spawn_output(app, terminal_id, terminal, reads, crashes) {
+            eprintln!("could not restart terminal {terminal_id}'s output: {err}");
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Handle each lot of output until the reads stop, setting `crashes` back to
+/// 0 after each one that's handled
+fn handle_reads(
+    app: &AppHandle,
+    terminal_id: u32,
+    terminal: &SubTerminal,
+    reads: &Receiver<Vec<u8>>,
+    crashes: &mut u32,
+) {
+    let mut tmux = ControlScanner::default();
+    let mut graphics = Graphics::default();
+    let mut decoder = Utf8Decoder::default();
+    let mut last_emit: Option<Instant> = None;
+
+    while let Some(bytes) = coalesce(app, reads, last_emit) {
+        // tmux control mode isn't for the screen at all, so it goes first
+        for part in tmux.split(&bytes) {
+            let Part::Output(bytes) = part else {
+                if let Err(err) = async_runtime::block_on(tmux::handle(app, terminal_id, part)) {
+                    eprintln!("could not handle tmux in terminal {terminal_id}: {err}");
+                }
+                continue;
+            };
+
+            // images have to come out before decoding, which would mangle
+            // anything in them that isn't utf-8
+            let max_image_bytes = app.state::<AppState>().max_image_bytes.load(Ordering::Relaxed);
+            for chunk in graphics.split(&bytes, max_image_bytes) {
+                let Chunk::Output(output) = chunk else {
+                    let handled = graphics.handle(app, terminal_id, terminal, chunk);
+                    if let Err(err) = async_runtime::block_on(handled) {
+                        eprintln!("could not handle an image in terminal {terminal_id}: {err}");
+                    }
+                    continue;
+                };
+
+                // the frontend still gets any half a character the decoder's holding on to
+                if !output.is_empty() {
+                    let data = decoder.decode(output.clone());
+                    async_runtime::block_on(handle_pty_output(app, terminal_id, terminal, output, data));
+                }
+            }
+        }
+        last_emit = Some(Instant::now());
+        *crashes = 0;
+
+        terminal.wait_for_frontend();
+        terminal.wait_while_paused();
+    }
+
+    // the pty closed partway through a character. its bytes already went
+    // to any output channel as they were read, so only the text's left
+    let data = decoder.finish();
+    if !data.is_empty() {
+        async_runtime::block_on(handle_pty_output(app, terminal_id, terminal, Vec::new(), data));
+    }
+}
+
+/// `thread::spawn`, but named, so a stuck one shows up as whose it is
+fn spawn_named(name: String, f: impl FnOnce() + Send + 'static) -> Result<thread::JoinHandle<()>, SteppeError> {
+    Ok(thread::Builder::new().name(name).spawn(f)?)
+}
+
+/// The next lot of output to handle, or `None` once the reads have stopped
+/// and there's nothing left
+fn coalesce(app: &AppHandle, reads: &Receiver<Vec<u8>>, last_emit: Option<Instant>) -> Option<Vec<u8>> {
+    let mut bytes = reads.recv().ok()?;
+
+    let state = app.state::<AppState>();
+    let max_bytes = state.coalesce_bytes.load(Ordering::Relaxed);
+    let interval = Duration::from_millis(state.coalesce_interval_ms.load(Ordering::Relaxed));
+    let deadline = last_emit.map_or_else(Instant::now, |last_emit| last_emit + interval);
+
+    while bytes.len() < max_bytes {
+        let more = match deadline.checked_duration_since(Instant::now()) {
+            Some(wait) => reads.recv_timeout(wait).ok(),
+            // past the deadline, so only what's already been read gets added
+            None => reads.try_recv().ok(),
+        };
+
+        // out of time, or the reads have stopped, but what's here still needs handling
+        let Some(more) = more else {
+            break;
+        };
+        bytes.extend_from_slice(&more);
+    }
+
+    Some(bytes)
+}
+
+/// Send terminal `terminal_id`'s output to `channel` from now on, as raw
+/// bytes rather than `terminal://output` events. It lasts through the shell
+/// restarting, until `async_unsubscribe_output`.
+#[tauri::command]
+pub async fn async_subscribe_output(
+    terminal_id: u32,
+    channel: Channel<InvokeResponseBody>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    state.output_channels.lock().await.insert(terminal_id, channel);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_unsubscribe_output(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.output_channels.lock().await.remove(&terminal_id);
+
+    Ok(())
+}
+
+/// Push `megabytes` of made-up output through terminal `terminal_id` the
+/// way reads from its pty go, and time how long until the frontend's
+/// written all of it. It's for checking the output path hasn't got any
+/// slower, and it does fill the terminal with junk.
+#[tauri::command]
+pub async fn async_benchmark_output(
+    terminal_id: u32,
+    megabytes: usize,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OutputBenchmark, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let coalesce_bytes = state.coalesce_bytes.load(Ordering::Relaxed);
+    let chunk = BENCHMARK_LINE.repeat((coalesce_bytes / BENCHMARK_LINE.len()).max(1));
+    let total = megabytes * 1024 * 1024;
+
+    let start = Instant::now();
+    let mut bytes = 0;
+    while bytes < total {
+        handle_output(&app, terminal_id, &terminal, chunk.clone()).await;
+        bytes += chunk.len();
+
+        // the same backpressure a real read gets, off the runtime since it blocks
+        let waiting = Arc::clone(&terminal);
+        async_runtime::spawn_blocking(move || waiting.wait_for_frontend()).await?;
+    }
+    let waiting = Arc::clone(&terminal);
+    async_runtime::spawn_blocking(move || waiting.wait_until_unacked(0)).await?;
+    let elapsed = start.elapsed();
+
+    Ok(OutputBenchmark {
+        bytes,
+        elapsed_ms: elapsed.as_millis() as u64,
+        bytes_per_second: bytes as f64 / elapsed.as_secs_f64(),
+    })
+}
+
+/// Let the reader know the frontend got through `bytes` of output.
+#[tauri::command]
+pub async fn async_ack_terminal_output(
+    terminal_id: u32,
+    bytes: usize,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+
+    let mut unacked = terminal.unacked_bytes.lock().unwrap_or_else(|err| err.into_inner());
+    *unacked = unacked.saturating_sub(bytes);
+    terminal.output_acked.notify_all();
+
+    Ok(())
+}
+
+/// Stop reading terminal `terminal_id`'s output until `async_resume_output`,
+/// which stops the program writing it too once the pty's full.
+#[tauri::command]
+pub async fn async_pause_output(
+    terminal_id: u32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    set_paused(&app, &state, terminal_id, true).await
+}
+
+#[tauri::command]
+pub async fn async_resume_output(
+    terminal_id: u32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    set_paused(&app, &state, terminal_id, false).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode `bytes` as reads split at `at`, then finish
+    fn decode_split(bytes: &[u8], at: usize) -> String {
+        let mut decoder = Utf8Decoder::default();
+        let (first, second) = bytes.split_at(at);
+        let mut text = decoder.decode(first.to_vec());
+        text.push_str(&decoder.decode(second.to_vec()));
+        text.push_str(&decoder.finish());
+        text
+    }
+
+    #[test]
+    fn joins_characters_split_across_reads() {
+        for text in ["é", "€", "🦀", "a€b🦀c"] {
+            for at in 0..=text.len() {
+                assert_eq!(decode_split(text.as_bytes(), at), text, "split at {at}");
+            }
+        }
+    }
+
+    #[test]
+    fn holds_on_to_the_start_of_a_character() {
+        let mut decoder = Utf8Decoder::default();
+
+        assert_eq!(decoder.decode(b"ab\xf0\x9f".to_vec()), "ab");
+        assert_eq!(decoder.decode(b"\xa6".to_vec()), "");
+        assert_eq!(decoder.decode(b"\x80cd".to_vec()), "🦀cd");
+        assert_eq!(decoder.finish(), "");
+    }
+
+    #[test]
+    fn replaces_invalid_bytes() {
+        let mut decoder = Utf8Decoder::default();
+
+        assert_eq!(decoder.decode(b"a\xffb\x80c".to_vec()), "a\u{fffd}b\u{fffd}c");
+        // a character that's cut off by something else
+        assert_eq!(decoder.decode(b"\xe2\x82x".to_vec()), "\u{fffd}x");
+    }
+
+    #[test]
+    fn flushes_what_is_pending_at_the_end() {
+        let mut decoder = Utf8Decoder::default();
+
+        assert_eq!(decoder.decode(b"ok\xe2\x82".to_vec()), "ok");
+        assert_eq!(decoder.finish(), "\u{fffd}");
+        // and starts over afterwards
+        assert_eq!(decoder.finish(), "");
+        assert_eq!(decoder.decode(b"\xac".to_vec()), "\u{fffd}");
+    }
+}
@@ -0,0 +1,660 @@
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager};
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    time::timeout,
+};
+
+use crate::{async_resize_pty, error::SteppeError, reader::Utf8Decoder, write_input, AppState};
+
+/// Output an attached client can fall behind by before it misses some
+pub const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
+
+/// The biggest frame either side accepts, so a bad length can't ask for all the memory there is
+pub const MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+/// The biggest frame a client can send before its hello's been checked, which
+/// is only ever a terminal id and a token
+pub const MAX_HELLO_BYTES: usize = 4 * 1024;
+
+/// How much of the scrollback goes in each frame when a client attaches
+pub const REPLAY_CHUNK_BYTES: usize = 64 * 1024;
+
+/// How many clients a listener serves at once, counting ones that haven't
+/// said hello yet, since each gets threads of its own
+pub const MAX_CLIENTS: usize = 16;
+
+/// How long a client has to say hello before it's hung up on
+pub const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a client's output is checked on for it having hung up, while
+/// the terminal's quiet
+const HANGUP_POLL: Duration = Duration::from_secs(1);
+
+/// How long waking a replaced listener's accept loop can take
+const WAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// ctrl+\, like dtach: typed at `steppe attach`, it lets go of the terminal
+/// rather than sending it on
+const DETACH_KEY: u8 = 0x1c;
+
+/// Where `steppe attach` reaches remote steppes from elsewhere on the
+/// network, by setting `remoteAttach`. Steppes on this machine always listen
+/// on `attach.sock` in the config dir, which only this user can open.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RemoteAttachOptions {
+    /// Like `0.0.0.0:7681`
+    pub listen: String,
+    /// What clients have to give to attach, since anything that can reach
+    /// `listen` can try. Nothing's encrypted, so it's only for networks
+    /// that are trusted, or the far end of an ssh tunnel.
+    pub token: String,
+}
+
+impl RemoteAttachOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        self.listen
+            .parse::<SocketAddr>()
+            .map_err(|err| format!("remoteAttach.listen {:?} isn't an address and port: {err}", self.listen))?;
+
+        if self.token.chars().count() < 16 {
+            return Err("remoteAttach.token has to be at least 16 characters".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// The tcp listener `remoteAttach` started, if it's set
+pub struct RemoteListener {
+    options: RemoteAttachOptions,
+    /// What it's bound to, which tells its accept loop whether it's been replaced
+    addr: SocketAddr,
+}
+
+/// What the first frame from a client holds
+#[derive(Serialize, Deserialize)]
+pub struct Hello {
+    /// `None` starts a new terminal, which only `steppe serve` does
+    pub terminal_id: Option<u32>,
+    /// Only checked over tcp
+    pub token: Option<String>,
+}
+
+/// One message either way: a kind byte, the payload's length as a
+/// big-endian u32, and then the payload.
+pub enum Frame {
+    /// The first thing a client sends, as json
+    Hello(Hello),
+    /// Typed at the client
+    Input(Vec<u8>),
+    /// The client's terminal changed size, as big-endian u16 rows and columns
+    Resize { rows: u16, cols: u16 },
+    /// Printed by the terminal. The first one's its scrollback so far.
+    Output(Vec<u8>),
+    /// Why the server's hanging up, before it does
+    Error(String),
+    /// The terminal's gone, so there's nothing more to come
+    Exit,
+}
+
+impl Frame {
+    fn kind(&self) -> u8 {
+        match self {
+            Frame::Hello(_) => 0,
+            Frame::Input(_) => 1,
+            Frame::Resize { .. } => 2,
+            Frame::Output(_) => 3,
+            Frame::Error(_) => 4,
+            Frame::Exit => 5,
+        }
+    }
+
+    /// The next frame, or `None` if the other end hung up in between frames.
+    /// One with more than `max_bytes` of payload is an error.
+    pub fn read(reader: &mut impl Read, max_bytes: usize) -> io::Result<Option<Frame>> {
+        let mut header = [0; 5];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+
+        let [kind, length @ ..] = header;
+        let length = u32::from_be_bytes(length) as usize;
+        if length > max_bytes {
+            return Err(io::Error::other(format!("a {length} byte frame is too big")));
+        }
+        let mut payload = vec![0; length];
+        reader.read_exact(&mut payload)?;
+
+        let frame = match (kind, payload.as_slice()) {
+            (0, _) => Frame::Hello(serde_json::from_slice(&payload)?),
+            (1, _) => Frame::Input(payload),
+            (2, &[rows_high, rows_low, cols_high, cols_low]) => Frame::Resize {
+                rows: u16::from_be_bytes([rows_high, rows_low]),
+                cols: u16::from_be_bytes([cols_high, cols_low]),
+            },
+            (2, _) => return Err(io::Error::other("a resize frame has to be 4 bytes")),
+            (3, _) => Frame::Output(payload),
+            (4, _) => Frame::Error(String::from_utf8_lossy(&payload).into_owned()),
+            (5, _) => Frame::Exit,
+            _ => return Err(io::Error::other(format!("unknown frame kind {kind}"))),
+        };
+
+        Ok(Some(frame))
+    }
+
+    /// Write the whole frame at once, so frames from different threads can't interleave
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let payload = match self {
+            Frame::Hello(hello) => serde_json::to_vec(hello)?,
+            Frame::Input(data) | Frame::Output(data) => data.clone(),
+            Frame::Resize { rows, cols } => [rows.to_be_bytes(), cols.to_be_bytes()].concat(),
+            Frame::Error(message) => message.clone().into_bytes(),
+            Frame::Exit => Vec::new(),
+        };
+
+        let mut frame = Vec::with_capacity(5 + payload.len());
+        frame.push(self.kind());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+
+        writer.write_all(&frame)?;
+        writer.flush()
+    }
+}
+
+/// A unix socket or tcp connection, which attaching works the same over
+trait Stream: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn shutdown(&self) -> io::Result<()>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        TcpStream::shutdown(self, std::net::Shutdown::Both)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        std::os::unix::net::UnixStream::shutdown(self, std::net::Shutdown::Both)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Compare tokens in the same time however much of them matches, so how
+/// long a wrong one takes to refuse doesn't give the right one away
+pub fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |differences, (given, expected)| differences | (given ^ expected))
+            == 0
+}
+
+/// Terminal `terminal_id`'s scrollback so far, and everything it prints after that
+pub fn subscribe(app: &AppHandle, terminal_id: u32) -> Result<(String, Receiver<String>), SteppeError> {
+    let state = app.state::<AppState>();
+    async_runtime::block_on(async {
+        let terminal = state.terminal(terminal_id).await?;
+        // under the scrollback's lock, so each bit of output is either in the replay or sent, never both
+        let scrollback = terminal.scrollback.lock().await;
+        Ok((scrollback.replay(), terminal.remote_output.subscribe()))
+    })
+}
+
+/// Send terminal `terminal_id`'s output to `stream` until it's gone or
+/// `hung_up` is set
+fn forward_output<S: Stream>(
+    app: &AppHandle,
+    terminal_id: u32,
+    mut stream: S,
+    hung_up: &AtomicBool,
+) -> Result<(), SteppeError> {
+    let (replay, mut output) = subscribe(app, terminal_id)?;
+    // a bit at a time, since there can be more of it than fits in a frame
+    for chunk in replay.as_bytes().chunks(REPLAY_CHUNK_BYTES) {
+        Frame::Output(chunk.to_vec()).write(&mut stream)?;
+    }
+
+    while !hung_up.load(Ordering::Relaxed) {
+        let data = match async_runtime::block_on(timeout(HANGUP_POLL, output.recv())) {
+            Ok(Ok(data)) => data,
+            // the client fell behind, and only misses some of the output
+            Ok(Err(RecvError::Lagged(_))) => continue,
+            Ok(Err(RecvError::Closed)) => {
+                Frame::Exit.write(&mut stream)?;
+                // so the input side stops waiting on a client that doesn't hang up
+                let _ = stream.shutdown();
+                break;
+            }
+            // quiet for a while, which is when to check on the client
+            Err(_) => continue,
+        };
+        Frame::Output(data.into_bytes()).write(&mut stream)?;
+    }
+
+    Ok(())
+}
+
+/// Type what a client sent into terminal `terminal_id`, or size it the way
+/// the client asks. Anything else a client sends is ignored.
+pub fn apply(app: &AppHandle, terminal_id: u32, frame: Frame, decoder: &mut Utf8Decoder) -> Result<(), SteppeError> {
+    match frame {
+        Frame::Input(data) => {
+            let input = decoder.decode(data);
+            async_runtime::block_on(write_input(app, &app.state(), terminal_id, &input))?;
+        }
+        Frame::Resize { rows, cols } => {
+            let resize = async_resize_pty(terminal_id, rows, cols, None, None, app.state());
+            if async_runtime::block_on(resize).is_err() {
+                eprintln!("could not resize terminal {terminal_id} for an attached client");
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Apply what the client sends to terminal `terminal_id` until the client hangs up
+fn forward_input<S: Stream>(app: &AppHandle, terminal_id: u32, mut stream: S) -> Result<(), SteppeError> {
+    let mut decoder = Utf8Decoder::default();
+    while let Some(frame) = Frame::read(&mut stream, MAX_FRAME_BYTES)? {
+        apply(app, terminal_id, frame, &mut decoder)?;
+    }
+
+    Ok(())
+}
+
+/// Attach the client on `stream` to the terminal its hello asks for, once
+/// it's given `token` (if there is one)
+fn serve<S: Stream>(app: &AppHandle, mut stream: S, token: Option<&str>) -> Result<(), SteppeError> {
+    stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+    let Some(Frame::Hello(hello)) = Frame::read(&mut stream, MAX_HELLO_BYTES)? else {
+        return Ok(());
+    };
+    stream.set_read_timeout(None)?;
+
+    if let Some(token) = token {
+        if !hello.token.is_some_and(|given| same_token(&given, token)) {
+            Frame::Error("wrong token".to_string()).write(&mut stream)?;
+            return Ok(());
+        }
+    }
+
+    let Some(terminal_id) = hello.terminal_id else {
+        Frame::Error("steppe attach needs a terminal id".to_string()).write(&mut stream)?;
+        return Ok(());
+    };
+    if let Err(err) = async_runtime::block_on(app.state::<AppState>().terminal(terminal_id)) {
+        Frame::Error(err.to_string()).write(&mut stream)?;
+        return Ok(());
+    }
+
+    let hung_up = Arc::new(AtomicBool::new(false));
+    let output = {
+        let (app, stream, hung_up) = (app.clone(), stream.try_clone()?, hung_up.clone());
+        thread::spawn(move || {
+            if let Err(err) = forward_output(&app, terminal_id, stream, &hung_up) {
+                eprintln!("could not send output to an attached client: {err}");
+            }
+        })
+    };
+
+    let result = forward_input(app, terminal_id, stream.try_clone()?);
+    hung_up.store(true, Ordering::Relaxed);
+    // the output side's done with it too, whether the client or the terminal went first
+    let _ = stream.shutdown();
+    let _ = output.join();
+
+    result
+}
+
+/// Serve each client `accept` comes back with on a thread of its own, along
+/// with the token it has to give, until it comes back with `None`. Past
+/// `MAX_CLIENTS` at once, they're turned away.
+fn spawn_server<S: Stream>(app: &AppHandle, mut accept: impl FnMut() -> Option<(S, Option<String>)> + Send + 'static) {
+    let app = app.clone();
+    let clients = Arc::new(AtomicUsize::new(0));
+    let spawned = thread::Builder::new().name("remote-attach".to_string()).spawn(move || {
+        while let Some((mut stream, token)) = accept() {
+            if clients.fetch_add(1, Ordering::Relaxed) >= MAX_CLIENTS {
+                clients.fetch_sub(1, Ordering::Relaxed);
+                let _ = Frame::Error("too many clients attached".to_string()).write(&mut stream);
+                continue;
+            }
+
+            let (app, clients) = (app.clone(), clients.clone());
+            thread::spawn(move || {
+                if let Err(err) = serve(&app, stream, token.as_deref()) {
+                    eprintln!("could not serve an attached client: {err}");
+                }
+                clients.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    });
+
+    if let Err(err) = spawned {
+        eprintln!("could not start the remote attach server: {err}");
+    }
+}
+
+/// Where steppes on this machine listen for `steppe attach`
+#[cfg(unix)]
+fn socket_path() -> std::path::PathBuf {
+    crate::get_config_dir().join("attach.sock")
+}
+
+/// Listen on `attach.sock` for `steppe attach` on this machine. The socket's
+/// only this user's, so there's no token.
+#[cfg(unix)]
+pub fn listen_locally(app: &AppHandle) {
+    use std::{
+        fs,
+        os::unix::{fs::PermissionsExt, net::UnixListener},
+    };
+
+    let path = socket_path();
+    // left over from a steppe that didn't get to clean up, since only one runs at a time
+    let _ = fs::remove_file(&path);
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("could not listen on {}: {err}", path.display());
+            return;
+        }
+    };
+    if let Err(err) = fs::set_permissions(&path, fs::Permissions::from_mode(0o600)) {
+        eprintln!("could not make {} private: {err}", path.display());
+        return;
+    }
+
+    spawn_server(app, move || Some((listener.accept().ok()?.0, None)));
+}
+
+#[cfg(not(unix))]
+pub fn listen_locally(_app: &AppHandle) {}
+
+/// Connect to `addr` so its accept loop wakes up, and sees it's been replaced
+fn wake(addr: SocketAddr) {
+    let ip = match addr {
+        SocketAddr::V4(addr) if addr.ip().is_unspecified() => Ipv4Addr::LOCALHOST.into(),
+        SocketAddr::V6(addr) if addr.ip().is_unspecified() => Ipv6Addr::LOCALHOST.into(),
+        _ => addr.ip(),
+    };
+    let _ = TcpStream::connect_timeout(&SocketAddr::new(ip, addr.port()), WAKE_TIMEOUT);
+}
+
+/// Listen where `options` says, in place of wherever `remoteAttach` said
+/// before, or stop listening over tcp at all for `None`.
+pub fn configure(app: &AppHandle, state: &AppState, options: Option<&RemoteAttachOptions>) -> Result<(), SteppeError> {
+    let mut current = state.remote_listener.lock().unwrap_or_else(|err| err.into_inner());
+    if current.as_ref().map(|listener| &listener.options) == options {
+        return Ok(());
+    }
+
+    // only the token changed, which the accept loop picks up by itself
+    if let (Some(listener), Some(options)) = (current.as_mut(), options) {
+        if listener.options.listen == options.listen {
+            listener.options = options.clone();
+            return Ok(());
+        }
+    }
+
+    if let Some(old) = current.take() {
+        wake(old.addr);
+    }
+    let Some(options) = options else {
+        return Ok(());
+    };
+
+    let listener = TcpListener::bind(&options.listen)
+        .map_err(|err| SteppeError::RemoteAttach(format!("could not listen on {}: {err}", options.listen)))?;
+    let addr = listener.local_addr()?;
+    *current = Some(RemoteListener {
+        options: options.clone(),
+        addr,
+    });
+
+    let app_handle = app.clone();
+    spawn_server(app, move || {
+        let stream = listener.accept().ok()?.0;
+        let state = app_handle.state::<AppState>();
+        let current = state.remote_listener.lock().unwrap_or_else(|err| err.into_inner());
+        match current.as_ref() {
+            Some(current) if current.addr == addr => Some((stream, Some(current.options.token.clone()))),
+            // replaced, which is what woke it up
+            _ => None,
+        }
+    });
+
+    Ok(())
+}
+
+/// Put this terminal in raw mode until it's dropped, so every key goes
+/// through to the attached terminal as it's typed
+#[cfg(unix)]
+struct RawMode(nix::sys::termios::Termios);
+
+#[cfg(unix)]
+impl RawMode {
+    fn enter() -> io::Result<Self> {
+        use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+
+        let original = tcgetattr(io::stdin()).map_err(io::Error::from)?;
+        let mut raw = original.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(io::stdin(), SetArg::TCSAFLUSH, &raw).map_err(io::Error::from)?;
+
+        Ok(Self(original))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = nix::sys::termios::tcsetattr(io::stdin(), nix::sys::termios::SetArg::TCSAFLUSH, &self.0);
+    }
+}
+
+/// Set by SIGWINCH, for `send_resizes` to pick up
+#[cfg(unix)]
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_resize(_: nix::libc::c_int) {
+    RESIZED.store(true, Ordering::Relaxed);
+}
+
+/// This terminal's size, as `stty` sees it
+#[cfg(unix)]
+fn terminal_size() -> Option<Frame> {
+    let output = std::process::Command::new("stty")
+        .arg("size")
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .ok()?;
+    let size = String::from_utf8(output.stdout).ok()?;
+    let (rows, cols) = size.trim().split_once(' ')?;
+
+    Some(Frame::Resize {
+        rows: rows.parse().ok()?,
+        cols: cols.parse().ok()?,
+    })
+}
+
+/// Size the attached terminal like this one, now and every time this one's resized
+#[cfg(unix)]
+fn send_resizes<S: Stream>(writer: Arc<Mutex<S>>) {
+    use nix::sys::signal::{signal, SigHandler, Signal};
+
+    // the handler only stores to an atomic
+    if let Err(err) = unsafe { signal(Signal::SIGWINCH, SigHandler::Handler(on_resize)) } {
+        eprintln!("steppe attach: could not watch for resizes: {err}");
+    }
+    RESIZED.store(true, Ordering::Relaxed);
+
+    thread::spawn(move || loop {
+        if RESIZED.swap(false, Ordering::Relaxed) {
+            let Some(size) = terminal_size() else {
+                continue;
+            };
+            let mut writer = writer.lock().unwrap_or_else(|err| err.into_inner());
+            if size.write(&mut *writer).is_err() {
+                return;
+            }
+        }
+        thread::sleep(Duration::from_millis(100));
+    });
+}
+
+/// Send what's typed here until the detach key, then hang up
+fn send_input<S: Stream>(writer: Arc<Mutex<S>>) {
+    let mut stdin = io::stdin().lock();
+    let mut buffer = [0; 4096];
+
+    loop {
+        let read = match stdin.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let input = &buffer[..read];
+        let detach_at = input.iter().position(|&byte| byte == DETACH_KEY);
+
+        let input = &input[..detach_at.unwrap_or(input.len())];
+        let mut writer = writer.lock().unwrap_or_else(|err| err.into_inner());
+        if !input.is_empty() && Frame::Input(input.to_vec()).write(&mut *writer).is_err() {
+            break;
+        }
+        if detach_at.is_some() {
+            break;
+        }
+    }
+
+    let _ = writer.lock().unwrap_or_else(|err| err.into_inner()).shutdown();
+}
+
+/// How an attach ended
+enum Ending {
+    Detached,
+    Exited,
+}
+
+/// Mirror the terminal `hello` asks for into this one, and send it what's
+/// typed here, until it exits or the detach key's pressed
+fn attach<S: Stream>(mut stream: S, hello: Hello) -> io::Result<Ending> {
+    Frame::Hello(hello).write(&mut stream)?;
+
+    #[cfg(unix)]
+    let _raw_mode = RawMode::enter()?;
+
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    #[cfg(unix)]
+    send_resizes(writer.clone());
+    thread::spawn(move || send_input(writer));
+
+    let mut stdout = io::stdout().lock();
+    while let Some(frame) = Frame::read(&mut stream, MAX_FRAME_BYTES)? {
+        match frame {
+            Frame::Output(data) => {
+                stdout.write_all(&data)?;
+                stdout.flush()?;
+            }
+            Frame::Error(message) => return Err(io::Error::other(message)),
+            Frame::Exit => return Ok(Ending::Exited),
+            _ => {}
+        }
+    }
+
+    Ok(Ending::Detached)
+}
+
+/// Connect to the steppe at `remote`, or the one running here for `None`
+fn connect(hello: Hello, remote: Option<&str>) -> io::Result<Ending> {
+    match remote {
+        Some(remote) => attach(TcpStream::connect(remote)?, hello),
+        #[cfg(unix)]
+        None => attach(std::os::unix::net::UnixStream::connect(socket_path())?, hello),
+        #[cfg(not(unix))]
+        None => Err(io::Error::other(
+            "attaching on this machine needs --remote and remoteAttach",
+        )),
+    }
+}
+
+/// For `steppe attach <id> [--remote <host:port>] [--token <token>]`,
+/// attach to terminal `id` right here and return the exit code to quit with,
+/// before anything else about steppe starts. The token can be given as
+/// `STEPPE_ATTACH_TOKEN` instead, to keep it out of the process list. `None`
+/// for any other launch, including plain `steppe attach`.
+pub fn run_if_attaching() -> Option<i32> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() != Some("attach") {
+        return None;
+    }
+    let terminal_id: u32 = args.next()?.parse().ok()?;
+
+    let mut remote = None;
+    let mut token = std::env::var("STEPPE_ATTACH_TOKEN").ok();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--remote" => remote = args.next(),
+            "--token" => token = args.next(),
+            _ => eprintln!("ignoring unknown argument {arg:?}"),
+        }
+    }
+
+    let hello = Hello {
+        terminal_id: Some(terminal_id),
+        token,
+    };
+    // raw mode's over by the time these print, so they need their own line
+    Some(match connect(hello, remote.as_deref()) {
+        Ok(Ending::Detached) => {
+            eprintln!("\r\n[detached from terminal {terminal_id}]");
+            0
+        }
+        Ok(Ending::Exited) => {
+            eprintln!("\r\n[terminal {terminal_id} exited]");
+            0
+        }
+        Err(err) => {
+            eprintln!("\r\nsteppe attach: {err}");
+            1
+        }
+    })
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, AppState};
+
+/// Which xterm.js renderer the frontend should use. This is purely a hint
+/// for the frontend, the backend doesn't render anything itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RendererBackend {
+    #[default]
+    Webgl2,
+    Canvas2d,
+    Dom,
+}
+
+#[derive(Clone, Serialize)]
+struct RendererBackendChanged {
+    backend: RendererBackend,
+}
+
+#[tauri::command]
+pub async fn async_get_renderer_backend(state: State<'_, AppState>) -> Result<RendererBackend, SteppeError> {
+    Ok(*state.renderer_backend.lock().await)
+}
+
+#[tauri::command]
+pub async fn async_set_renderer_backend(
+    backend: RendererBackend,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if backend == RendererBackend::Dom {
+        eprintln!("warning: the dom renderer is a lot slower than webgl2 or canvas2d");
+    }
+
+    let mut current = state.renderer_backend.lock().await;
+    if *current != backend {
+        *current = backend;
+        app.emit("renderer-backend-changed", RendererBackendChanged { backend })?;
+    }
+
+    Ok(())
+}
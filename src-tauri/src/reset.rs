@@ -0,0 +1,51 @@
+use tauri::{AppHandle, State};
+
+use crate::{cursor, error::SteppeError, mouse::MouseReportMode, reader, scrollback, AppState, SESSION_ID};
+
+/// Erase saved lines (ED 3), which both the backend's screen and xterm.js
+/// take as letting go of their scrollback, leaving what's on screen
+const CLEAR_SCROLLBACK: &str = "\x1b[3J";
+
+/// Full reset (RIS): back to how the terminal started, scrollback and all
+const HARD_RESET: &str = "\x1bc";
+
+/// Let go of terminal `terminal_id`'s scrollback everywhere it's kept: the
+/// lines kept for replays and searches (and spilled to disk), the backend's
+/// screen, and xterm.js's, by sending it through like it was output.
+pub async fn clear_scrollback(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    reader::handle_output(app, terminal_id, &terminal, CLEAR_SCROLLBACK.to_string()).await;
+
+    scrollback::clear(state, terminal_id).await
+}
+
+/// Reset terminal `terminal_id` like it had just been opened, for when a
+/// program left it in a mess (the alternate screen, mouse reporting, a
+/// charset that turns everything into line drawing, ...). The shell's left
+/// alone.
+pub async fn hard_reset(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    reader::handle_output(app, terminal_id, &terminal, HARD_RESET.to_string()).await;
+    // the modes are only tracked as they're set and unset, which a reset doesn't do one by one
+    *terminal.mouse_report_mode.lock().await = MouseReportMode::default();
+    // the cursor colors are the main terminal's
+    if terminal_id == SESSION_ID {
+        cursor::set_inverse_video(app, state, false).await?;
+    }
+
+    scrollback::clear(state, terminal_id).await
+}
+
+#[tauri::command]
+pub async fn async_clear_scrollback(
+    terminal_id: u32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    clear_scrollback(&app, &state, terminal_id).await
+}
+
+#[tauri::command]
+pub async fn async_hard_reset(terminal_id: u32, app: AppHandle, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    hard_reset(&app, &state, terminal_id).await
+}
@@ -0,0 +1,327 @@
+use alacritty_terminal::{
+    event::VoidListener,
+    grid::{Dimensions, Grid},
+    index::{Column, Line, Point, Side},
+    selection::{Selection, SelectionType},
+    term::{
+        cell::{Cell, Flags},
+        Config, Term, TermMode,
+    },
+    vte::ansi::Processor,
+};
+use serde::Serialize;
+use tauri::State;
+
+use crate::{error::SteppeError, keyboard, AppState};
+
+struct ScreenSize {
+    rows: usize,
+    cols: usize,
+}
+
+impl Dimensions for ScreenSize {
+    fn total_lines(&self) -> usize {
+        self.rows
+    }
+
+    fn screen_lines(&self) -> usize {
+        self.rows
+    }
+
+    fn columns(&self) -> usize {
+        self.cols
+    }
+}
+
+/// A line of text on the screen, which can carry on over a few rows where it wrapped
+#[derive(Default)]
+pub struct ScreenLine {
+    pub text: String,
+    /// The `(row, col)` each of `text`'s characters is in
+    pub cells: Vec<(i32, usize)>,
+}
+
+/// What's on the terminal's screen, kept up to date by running pty output
+/// through the same kind of parser a native terminal would.
+pub struct Screen {
+    term: Term<VoidListener>,
+    parser: Processor,
+    /// The rows as of the last snapshot or diff, to diff against
+    last_rows: Vec<String>,
+}
+
+impl Screen {
+    pub fn new(rows: u16, cols: u16) -> Self {
+        let size = ScreenSize {
+            rows: rows.into(),
+            cols: cols.into(),
+        };
+
+        Self {
+            term: Term::new(
+                Config {
+                    kitty_keyboard: true,
+                    ..Config::default()
+                },
+                &size,
+                VoidListener,
+            ),
+            parser: Processor::new(),
+            last_rows: Vec::new(),
+        }
+    }
+
+    pub fn advance(&mut self, data: &[u8]) {
+        self.parser.advance(&mut self.term, data);
+    }
+
+    /// The screen and what's scrolled off it, styles and all
+    pub fn grid(&self) -> &Grid<Cell> {
+        self.term.grid()
+    }
+
+    /// Whether the program's asked for pastes wrapped in `\x1b[200~`/`\x1b[201~`
+    pub fn bracketed_paste(&self) -> bool {
+        self.term.mode().contains(TermMode::BRACKETED_PASTE)
+    }
+
+    /// Whether a full-screen program has switched to the alternate screen
+    pub fn alternate_screen(&self) -> bool {
+        self.term.mode().contains(TermMode::ALT_SCREEN)
+    }
+
+    /// The kitty keyboard protocol flags the program's pushed on this screen
+    pub fn keyboard_flags(&self) -> u8 {
+        let mode = self.term.mode();
+        [
+            (TermMode::DISAMBIGUATE_ESC_CODES, keyboard::DISAMBIGUATE_ESCAPE_CODES),
+            (TermMode::REPORT_EVENT_TYPES, keyboard::REPORT_EVENT_TYPES),
+            (TermMode::REPORT_ALTERNATE_KEYS, keyboard::REPORT_ALTERNATE_KEYS),
+            (
+                TermMode::REPORT_ALL_KEYS_AS_ESC,
+                keyboard::REPORT_ALL_KEYS_AS_ESCAPE_CODES,
+            ),
+            (TermMode::REPORT_ASSOCIATED_TEXT, keyboard::REPORT_ASSOCIATED_TEXT),
+        ]
+        .into_iter()
+        .filter(|(mode_flag, _)| mode.contains(*mode_flag))
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+
+    /// Whether arrow keys should be sent as `SS3 A` rather than `CSI A`
+    pub fn app_cursor(&self) -> bool {
+        self.term.mode().contains(TermMode::APP_CURSOR)
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        self.term.resize(ScreenSize {
+            rows: rows.into(),
+            cols: cols.into(),
+        });
+    }
+
+    fn rows(&self) -> Vec<String> {
+        let grid = self.term.grid();
+
+        (0..grid.screen_lines())
+            .map(|line| {
+                let row = &grid[Line(line as i32)];
+                let text: String = (0..grid.columns()).map(|col| row[Column(col)].c).collect();
+                text.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    /// The lines on the screen, with rows that wrapped joined back together
+    pub fn lines(&self) -> Vec<ScreenLine> {
+        let grid = self.term.grid();
+        let spacers = Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER;
+
+        let mut lines = Vec::new();
+        let mut line = ScreenLine::default();
+        for row in 0..grid.screen_lines() as i32 {
+            let cells = &grid[Line(row)];
+            for col in 0..grid.columns() {
+                let cell = &cells[Column(col)];
+                if !cell.flags.intersects(spacers) {
+                    line.text.push(cell.c);
+                    line.cells.push((row, col));
+                }
+            }
+
+            let wrapped = cells[Column(grid.columns() - 1)].flags.contains(Flags::WRAPLINE);
+            if !wrapped {
+                lines.push(std::mem::take(&mut line));
+            }
+        }
+        if !line.cells.is_empty() {
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    /// The cell at `row`, `col`, moved onto the screen or scrollback if it's off the edge
+    fn clamp_point(&self, row: i32, col: usize) -> Point {
+        let grid = self.term.grid();
+        let row = row.clamp(-(grid.history_size() as i32), grid.screen_lines() as i32 - 1);
+        let col = col.min(grid.columns() - 1);
+
+        Point::new(Line(row), Column(col))
+    }
+
+    /// Start selecting from `side` of the cell at `row`, `col`, dropping any
+    /// selection there was
+    pub fn start_selection(&mut self, ty: SelectionType, row: i32, col: usize, side: Side) {
+        let point = self.clamp_point(row, col);
+        self.term.selection = Some(Selection::new(ty, point, side));
+    }
+
+    /// Stretch the selection to `side` of the cell at `row`, `col`
+    pub fn update_selection(&mut self, row: i32, col: usize, side: Side) {
+        let point = self.clamp_point(row, col);
+        if let Some(selection) = &mut self.term.selection {
+            selection.update(point, side);
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.term.selection = None;
+    }
+
+    /// What's selected, with wrapped rows joined back up and wide characters
+    /// counted once
+    pub fn selection_text(&self) -> Option<String> {
+        self.term.selection_to_string()
+    }
+
+    /// What's on the cursor's row, up to the cursor
+    pub fn cursor_line(&self) -> String {
+        let grid = self.term.grid();
+        let point = grid.cursor.point;
+        let row = &grid[point.line];
+
+        (0..point.column.0.min(grid.columns()))
+            .map(|col| row[Column(col)].c)
+            .collect()
+    }
+
+    /// The cursor's row and column on the screen
+    pub fn cursor(&self) -> (usize, usize) {
+        let point = self.term.grid().cursor.point;
+        (point.line.0.max(0) as usize, point.column.0)
+    }
+
+    /// The OSC 8 link covering the cell at `row`, `col`, and the rest of the
+    /// cells on that row it covers
+    fn link_at(&self, row: i32, col: usize) -> Option<ScreenLink> {
+        let grid = self.term.grid();
+        let history = grid.history_size() as i32;
+        if row < -history || row >= grid.screen_lines() as i32 || col >= grid.columns() {
+            return None;
+        }
+
+        let cells = &grid[Line(row)];
+        let link = cells[Column(col)].hyperlink()?;
+        let is_link = |col: &usize| cells[Column(*col)].hyperlink().as_ref() == Some(&link);
+
+        let start_col = (0..col).rev().take_while(is_link).last().unwrap_or(col);
+        let end_col = (col..grid.columns()).take_while(is_link).last().unwrap_or(col) + 1;
+
+        Some(ScreenLink {
+            uri: link.uri().to_string(),
+            row,
+            start_col,
+            end_col,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScreenSnapshot {
+    rows: usize,
+    cols: usize,
+    /// Each row's text, without trailing blanks
+    lines: Vec<String>,
+    cursor_row: usize,
+    cursor_col: usize,
+}
+
+#[derive(Serialize)]
+pub struct ScreenDiff {
+    /// `(row, text)` for every row that changed since the last snapshot or diff
+    changed: Vec<(usize, String)>,
+    cursor_row: usize,
+    cursor_col: usize,
+}
+
+#[derive(Serialize)]
+pub struct ScreenLink {
+    uri: String,
+    /// Counting from the top of the screen, and negative going up into the scrollback
+    row: i32,
+    start_col: usize,
+    /// Just past the last cell of the link
+    end_col: usize,
+}
+
+/// The whole screen, e.g. to redraw a terminal that's being reattached.
+#[tauri::command]
+pub async fn async_get_screen_snapshot(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<ScreenSnapshot, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut screen = terminal.screen.lock().await;
+
+    let lines = screen.rows();
+    let (cursor_row, cursor_col) = screen.cursor();
+    screen.last_rows = lines.clone();
+
+    Ok(ScreenSnapshot {
+        rows: screen.term.grid().screen_lines(),
+        cols: screen.term.grid().columns(),
+        lines,
+        cursor_row,
+        cursor_col,
+    })
+}
+
+/// Just the rows that changed since the screen was last asked for.
+#[tauri::command]
+pub async fn async_get_screen_diff(terminal_id: u32, state: State<'_, AppState>) -> Result<ScreenDiff, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut screen = terminal.screen.lock().await;
+
+    let rows = screen.rows();
+    let changed = rows
+        .iter()
+        .enumerate()
+        .filter(|(row, text)| screen.last_rows.get(*row) != Some(*text))
+        .map(|(row, text)| (row, text.clone()))
+        .collect();
+
+    let (cursor_row, cursor_col) = screen.cursor();
+    screen.last_rows = rows;
+
+    Ok(ScreenDiff {
+        changed,
+        cursor_row,
+        cursor_col,
+    })
+}
+
+/// The OSC 8 link at a cell, for the frontend to open on ctrl+click. `row`
+/// counts from the top of the screen, and goes negative into the scrollback.
+#[tauri::command]
+pub async fn async_get_link_at(
+    terminal_id: u32,
+    row: i32,
+    col: usize,
+    state: State<'_, AppState>,
+) -> Result<Option<ScreenLink>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let screen = terminal.screen.lock().await;
+
+    Ok(screen.link_at(row, col))
+}
@@ -0,0 +1,524 @@
+use std::{
+    collections::VecDeque,
+    sync::{atomic::Ordering, Arc},
+};
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use tauri::{async_runtime, State};
+
+use crate::{
+    error::SteppeError,
+    scrollback_spill::{Spill, SpillOptions, SpillReader},
+    AppState, SubTerminal,
+};
+
+pub const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+/// The longest a line gets before it's broken in two, for programs that never
+/// print a newline (full-screen ones, progress bars redrawn with `\r`)
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Output the terminal has printed, as it came out of the pty (escape
+/// sequences and all), so it can be searched, exported or replayed into a
+/// fresh xterm without the webview keeping it around.
+#[derive(Debug)]
+pub struct Scrollback {
+    lines: VecDeque<String>,
+    /// The line still being printed
+    partial: String,
+    max_lines: usize,
+    /// Whether lines past `max_lines` go to disk rather than being let go of
+    spill_options: Option<SpillOptions>,
+    /// The lines that did, older than `lines`, once there's been any
+    spill: Option<Spill>,
+    /// How many lines have been let go of, so lines keep their numbers as old ones go
+    dropped: usize,
+}
+
+impl Scrollback {
+    pub fn new(max_lines: usize, spill_options: Option<SpillOptions>) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            partial: String::new(),
+            max_lines,
+            spill_options,
+            spill: None,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, data: &str) {
+        let mut rest = data;
+        while let Some(end) = rest.find('\n') {
+            self.push_partial(&rest[..end]);
+            let mut line = std::mem::take(&mut self.partial);
+            if line.ends_with('\r') {
+                line.pop();
+            }
+
+            self.push_line(line);
+            rest = &rest[end + 1..];
+        }
+
+        self.push_partial(rest);
+    }
+
+    /// Add `data` to the line still being printed, breaking it off as a line
+    /// of its own whenever it gets to `MAX_LINE_BYTES`
+    fn push_partial(&mut self, mut data: &str) {
+        while self.partial.len() + data.len() > MAX_LINE_BYTES {
+            let mut split = MAX_LINE_BYTES - self.partial.len();
+            while !data.is_char_boundary(split) {
+                split -= 1;
+            }
+
+            self.partial.push_str(&data[..split]);
+            let line = std::mem::take(&mut self.partial);
+            self.push_line(line);
+            data = &data[split..];
+        }
+
+        self.partial.push_str(data);
+    }
+
+    fn push_line(&mut self, line: String) {
+        if self.max_lines == 0 {
+            self.evict(line);
+            return;
+        }
+
+        if self.lines.len() == self.max_lines {
+            if let Some(oldest) = self.lines.pop_front() {
+                self.evict(oldest);
+            }
+        }
+
+        self.lines.push_back(line);
+    }
+
+    /// Spill `line`, which doesn't fit in memory anymore, or let go of it
+    fn evict(&mut self, line: String) {
+        let Some(options) = &self.spill_options else {
+            self.dropped += 1;
+            return;
+        };
+
+        if self.spill.is_none() {
+            match Spill::new(options) {
+                Ok(spill) => self.spill = Some(spill),
+                Err(err) => {
+                    eprintln!("could not spill scrollback to disk: {err}");
+                    self.spill_options = None;
+                    self.dropped += 1;
+                    return;
+                }
+            }
+        }
+
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        match spill.push(line) {
+            Ok(dropped) => self.dropped += dropped,
+            Err(err) => {
+                // what's there can't be trusted anymore, so it goes too
+                eprintln!("could not spill scrollback to disk: {err}");
+                self.dropped += spill.len();
+                self.spill = None;
+                self.spill_options = None;
+            }
+        }
+    }
+
+    /// How many lines are on disk
+    fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, Spill::len)
+    }
+
+    /// The lines still kept from line `from` up to (but not including) `to`,
+    /// the line still being printed included, counting every line the
+    /// terminal's printed. Spilled ones are only read back from disk when
+    /// they're visited, so that can happen without holding this.
+    pub fn range(&self, from: usize, to: usize) -> ScrollbackRange {
+        let from = from.max(self.dropped);
+        let in_memory = self.dropped + self.spilled();
+
+        let spill = self.spill.as_ref().and_then(|spill| {
+            let (start, end) = (from - self.dropped, to.min(in_memory).saturating_sub(self.dropped));
+            match spill.reader(start, end) {
+                Ok(reader) => Some((self.dropped, reader)),
+                Err(err) => {
+                    eprintln!("could not read spilled scrollback: {err}");
+                    None
+                }
+            }
+        });
+
+        let first = from.max(in_memory);
+        let lines = (first..to.min(self.current_line() + 1))
+            .map(|number| {
+                let index = number - in_memory;
+                self.lines.get(index).unwrap_or(&self.partial).clone()
+            })
+            .collect();
+
+        ScrollbackRange { spill, first, lines }
+    }
+
+    /// Call `f` with each line [`Scrollback::range`] would have
+    pub fn visit(&self, from: usize, to: usize, mut f: impl FnMut(usize, &str)) {
+        self.range(from, to).visit(&mut f);
+    }
+
+    /// Lines `from` up to (but not including) `to`, as far as they're still
+    /// kept, without the line still being printed
+    pub fn lines_between(&self, from: usize, to: usize) -> Vec<String> {
+        self.range(from, to.min(self.current_line())).lines()
+    }
+
+    /// Everything in memory, including the line still being printed, as one
+    /// string of output. Spilled lines are left out, since there could be
+    /// more of them than is worth putting on screen.
+    pub fn replay(&self) -> String {
+        let mut replay = String::new();
+        for line in &self.lines {
+            replay.push_str(line);
+            replay.push_str("\r\n");
+        }
+        replay.push_str(&self.partial);
+
+        replay
+    }
+
+    /// The oldest line kept, counting every line the terminal's printed
+    pub fn first_line(&self) -> usize {
+        self.dropped
+    }
+
+    /// The line still being printed, counting the same way
+    pub fn current_line(&self) -> usize {
+        self.dropped + self.spilled() + self.lines.len()
+    }
+
+    fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines;
+
+        let excess = self.lines.len().saturating_sub(max_lines);
+        for line in self.lines.drain(..excess).collect::<Vec<_>>() {
+            self.evict(line);
+        }
+    }
+
+    fn set_spill_options(&mut self, options: Option<SpillOptions>) {
+        match (&mut self.spill, &options) {
+            (Some(spill), Some(options)) => match spill.set_options(options) {
+                Ok(dropped) => self.dropped += dropped,
+                Err(err) => eprintln!("could not trim spilled scrollback: {err}"),
+            },
+            (Some(_), None) => {
+                self.dropped += self.spilled();
+                self.spill = None;
+            }
+            (None, _) => {}
+        }
+
+        self.spill_options = options;
+    }
+
+    fn clear(&mut self) {
+        self.dropped += self.spilled() + self.lines.len();
+        self.lines.clear();
+
+        if let Some(spill) = &mut self.spill {
+            if let Err(err) = spill.clear() {
+                eprintln!("could not clear spilled scrollback: {err}");
+            }
+        }
+    }
+}
+
+/// Lines copied out of a [`Scrollback`], with the spilled ones left to be
+/// read back from disk
+#[derive(Debug)]
+pub struct ScrollbackRange {
+    /// With how many lines were let go of before the spill's first one
+    spill: Option<(usize, SpillReader)>,
+    /// The number of the first line in `lines`
+    first: usize,
+    lines: Vec<String>,
+}
+
+impl ScrollbackRange {
+    /// Call `f` with each line and its number, reading spilled ones back a
+    /// block at a time, which blocks
+    pub fn visit(&self, f: &mut impl FnMut(usize, &str)) {
+        if let Some((dropped, reader)) = &self.spill {
+            if let Err(err) = reader.visit(&mut |index, line| f(dropped + index, line)) {
+                eprintln!("could not read spilled scrollback: {err}");
+            }
+        }
+
+        for (number, line) in (self.first..).zip(&self.lines) {
+            f(number, line);
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.visit(&mut |_, line| lines.push(line.to_string()));
+
+        lines
+    }
+}
+
+/// Run `f` on `range` where reading it back from disk won't hold up the runtime
+pub async fn read<T: Send + 'static>(
+    range: ScrollbackRange,
+    f: impl FnOnce(&ScrollbackRange) -> T + Send + 'static,
+) -> Result<T, SteppeError> {
+    Ok(async_runtime::spawn_blocking(move || f(&range)).await?)
+}
+
+/// What a line of output looks like on screen, without the escape sequences
+/// and control characters that were mixed in with it.
+pub fn visible_text(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            if !c.is_control() || c == '\t' {
+                text.push(c);
+            }
+            continue;
+        }
+
+        match chars.next() {
+            // CSI, up to a final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, up to BEL or ST
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // everything else is a single character after the escape
+            _ => {}
+        }
+    }
+
+    text
+}
+
+#[derive(Serialize)]
+pub struct SearchMatch {
+    /// Counting from the oldest line kept, like [`async_get_scrollback`]
+    line: usize,
+    /// In characters, not bytes
+    column: usize,
+    length: usize,
+}
+
+#[derive(Serialize)]
+pub struct ScrollbackRegion {
+    /// How many lines there are altogether, to page through them
+    total_lines: usize,
+    lines: Vec<String>,
+}
+
+/// The terminals open now, to go through without holding `state.terminals`
+async fn open_terminals(state: &AppState) -> Vec<Arc<SubTerminal>> {
+    state.terminals.lock().await.values().cloned().collect()
+}
+
+/// Run `f` on each terminal's scrollback where spilling to disk won't hold
+/// up the runtime
+async fn update_each(terminals: Vec<Arc<SubTerminal>>, f: impl Fn(&mut Scrollback) + Send + 'static) {
+    let result = async_runtime::spawn_blocking(move || {
+        for terminal in terminals {
+            f(&mut terminal.scrollback.blocking_lock());
+        }
+    })
+    .await;
+
+    if let Err(err) = result {
+        eprintln!("could not update scrollback: {err}");
+    }
+}
+
+/// How many lines each terminal keeps, dropping anything past that from the ones that are already open.
+pub async fn set_max_lines(state: &AppState, lines: usize) {
+    state.scrollback_lines.store(lines, Ordering::Relaxed);
+
+    update_each(open_terminals(state).await, move |scrollback| {
+        scrollback.set_max_lines(lines)
+    })
+    .await;
+}
+
+#[tauri::command]
+pub async fn async_set_scrollback_lines(lines: usize, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    set_max_lines(&state, lines).await;
+
+    Ok(())
+}
+
+/// Whether lines past each terminal's scrollback limit spill to disk, for
+/// the ones already open too. Turning it off lets go of what's spilled.
+pub async fn configure_spill(state: &AppState, options: Option<SpillOptions>) {
+    *state.scrollback_spill.lock().unwrap_or_else(|err| err.into_inner()) = options.clone();
+
+    update_each(open_terminals(state).await, move |scrollback| {
+        scrollback.set_spill_options(options.clone())
+    })
+    .await;
+}
+
+/// `count` lines starting at `start`, counting from the oldest line kept,
+/// spilled ones included. The line still being printed isn't included.
+#[tauri::command]
+pub async fn async_get_scrollback(
+    terminal_id: u32,
+    start: usize,
+    count: usize,
+    state: State<'_, AppState>,
+) -> Result<ScrollbackRegion, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let (total_lines, range) = {
+        let scrollback = terminal.scrollback.lock().await;
+        let first_line = scrollback.first_line();
+        let from = first_line.saturating_add(start);
+        let to = from.saturating_add(count).min(scrollback.current_line());
+
+        (scrollback.current_line() - first_line, scrollback.range(from, to))
+    };
+
+    Ok(ScrollbackRegion {
+        total_lines,
+        lines: read(range, ScrollbackRange::lines).await?,
+    })
+}
+
+/// Everything kept, including the line still being printed, to write into a
+/// fresh xterm so it looks like it was there all along (e.g. after attaching).
+#[tauri::command]
+pub async fn async_get_terminal_replay(terminal_id: u32, state: State<'_, AppState>) -> Result<String, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let replay = terminal.scrollback.lock().await.replay();
+
+    Ok(replay)
+}
+
+/// Let go of the lines kept here, but not what's on screen: see
+/// `reset::clear_scrollback` for that
+pub async fn clear(state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    terminal.scrollback.lock().await.clear();
+
+    Ok(())
+}
+
+/// What to look for: `query` as it is, or as a regex
+pub fn search_pattern(query: &str, regex: bool, case_sensitive: bool) -> Result<Regex, SteppeError> {
+    let pattern = if regex { query.to_string() } else { regex::escape(query) };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|err| SteppeError::InvalidConfig(err.to_string()))
+}
+
+/// Where `pattern` is in `line` as it looks on screen, as `(column, length)`
+/// in characters
+pub fn find_in_line(pattern: &Regex, line: &str) -> Vec<(usize, usize)> {
+    let text = visible_text(line);
+
+    pattern
+        .find_iter(&text)
+        .filter(|found| !found.is_empty())
+        .map(|found| {
+            let column = text[..found.start()].chars().count();
+            (column, found.as_str().chars().count())
+        })
+        .collect()
+}
+
+/// Find `query` in the scrollback and the line still being printed, as
+/// they'd look on screen.
+#[tauri::command]
+pub async fn async_search_terminal(
+    id: u32,
+    query: String,
+    regex: bool,
+    case_sensitive: bool,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchMatch>, SteppeError> {
+    let pattern = search_pattern(&query, regex, case_sensitive)?;
+
+    let terminal = state.terminal(id).await?;
+    let (first_line, range) = {
+        let scrollback = terminal.scrollback.lock().await;
+        let first_line = scrollback.first_line();
+
+        (first_line, scrollback.range(first_line, scrollback.current_line() + 1))
+    };
+
+    read(range, move |range| {
+        let mut matches = Vec::new();
+        range.visit(&mut |number, text| {
+            for (column, length) in find_in_line(&pattern, text) {
+                let line = number - first_line;
+                matches.push(SearchMatch { line, column, length });
+            }
+        });
+
+        matches
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines() {
+        let mut scrollback = Scrollback::new(10, None);
+        scrollback.push("one\r\ntw");
+        scrollback.push("o\nthree");
+
+        assert_eq!(scrollback.lines_between(0, 10), ["one", "two"]);
+        assert_eq!(scrollback.replay(), "one\r\ntwo\r\nthree");
+    }
+
+    #[test]
+    fn breaks_lines_that_never_end() {
+        let mut scrollback = Scrollback::new(10, None);
+        for _ in 0..MAX_LINE_BYTES {
+            scrollback.push("\r\u{e9}");
+        }
+
+        let lines = scrollback.lines_between(0, 10);
+        assert!(lines.len() >= 2);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_BYTES));
+        assert!(scrollback.partial.len() <= MAX_LINE_BYTES);
+    }
+
+    #[test]
+    fn keeps_only_max_lines() {
+        let mut scrollback = Scrollback::new(2, None);
+        scrollback.push("a\nb\nc\n");
+
+        assert_eq!(scrollback.first_line(), 1);
+        assert_eq!(scrollback.lines_between(0, 10), ["b", "c"]);
+    }
+}
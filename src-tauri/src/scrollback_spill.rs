@@ -0,0 +1,323 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+/// Lines are compressed together in blocks of about this much text, so
+/// reading one back only means decompressing its block
+const BLOCK_BYTES: usize = 64 * 1024;
+
+/// Spill files need names no other terminal's has had, even one that's
+/// been closed, or compacted into a new file
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Keeping scrollback past `scrollbackLines` on disk rather than letting it
+/// go, for long-running sessions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct SpillOptions {
+    /// How many lines to keep on disk, or no limit when it's unset. What's
+    /// kept can go over by a block's worth, since blocks go a whole one at
+    /// a time.
+    pub max_lines: Option<usize>,
+}
+
+impl SpillOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_lines == Some(0) {
+            return Err("scrollbackSpill.maxLines has to be above 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a block is in the file
+#[derive(Debug, Clone)]
+struct Block {
+    offset: u64,
+    len: u64,
+    lines: usize,
+}
+
+/// Scrollback lines that didn't fit in memory, oldest first, in a
+/// compressed temp file that's deleted along with them
+#[derive(Debug)]
+pub struct Spill {
+    path: PathBuf,
+    file: File,
+    blocks: VecDeque<Block>,
+    /// Lines waiting to fill a block, after the blocks' lines
+    pending: VecDeque<String>,
+    pending_bytes: usize,
+    /// Where the next block goes
+    end: u64,
+    /// How much of the start of the file is blocks that were let go of
+    dead: u64,
+    /// In the blocks and pending
+    lines: usize,
+    max_lines: Option<usize>,
+}
+
+/// Where spill files go: somewhere only this user can get at, since they're
+/// terminal output that hasn't been redacted. That's the runtime dir where
+/// there is one, and the cache dir otherwise, never the shared temp dir.
+fn spill_dir() -> io::Result<PathBuf> {
+    let dir = dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no runtime or cache directory"))?
+        .join("steppe-scrollback");
+
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+
+    // it could already be there with a looser umask's permissions
+    #[cfg(unix)]
+    fs::set_permissions(&dir, std::os::unix::fs::PermissionsExt::from_mode(0o700))?;
+
+    Ok(dir)
+}
+
+fn new_file() -> io::Result<(PathBuf, File)> {
+    let dir = spill_dir()?;
+
+    let name = format!("{}-{}.z", std::process::id(), NEXT_FILE.fetch_add(1, Ordering::Relaxed));
+    let path = dir.join(name);
+    let mut options = File::options();
+    options.read(true).write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    // so it can be deleted while it's open, like on unix
+    #[cfg(windows)]
+    std::os::windows::fs::OpenOptionsExt::share_mode(&mut options, 0x7);
+    let file = options.open(&path)?;
+
+    Ok((path, file))
+}
+
+impl Spill {
+    pub fn new(options: &SpillOptions) -> io::Result<Self> {
+        let (path, file) = new_file()?;
+
+        Ok(Self {
+            path,
+            file,
+            blocks: VecDeque::new(),
+            pending: VecDeque::new(),
+            pending_bytes: 0,
+            end: 0,
+            dead: 0,
+            lines: 0,
+            max_lines: options.max_lines,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines
+    }
+
+    /// Add `line` after the others, returning how many of the oldest had to
+    /// go to stay under the limit
+    pub fn push(&mut self, line: String) -> io::Result<usize> {
+        self.pending_bytes += line.len() + 1;
+        self.pending.push_back(line);
+        self.lines += 1;
+
+        if self.pending_bytes >= BLOCK_BYTES {
+            self.write_block()?;
+        }
+
+        self.trim()
+    }
+
+    pub fn set_options(&mut self, options: &SpillOptions) -> io::Result<usize> {
+        self.max_lines = options.max_lines;
+        self.trim()
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        for line in &self.pending {
+            encoder.write_all(line.as_bytes())?;
+            encoder.write_all(b"\n")?;
+        }
+        let compressed = encoder.finish()?;
+
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&compressed)?;
+
+        self.blocks.push_back(Block {
+            offset: self.end,
+            len: compressed.len() as u64,
+            lines: self.pending.len(),
+        });
+        self.end += compressed.len() as u64;
+        self.pending.clear();
+        self.pending_bytes = 0;
+
+        Ok(())
+    }
+
+    /// Let go of the oldest lines past `max_lines`, a block at a time
+    fn trim(&mut self) -> io::Result<usize> {
+        let Some(max_lines) = self.max_lines else {
+            return Ok(0);
+        };
+
+        let before = self.lines;
+        while let Some(block) = self.blocks.front() {
+            if self.lines - block.lines < max_lines {
+                break;
+            }
+            self.lines -= block.lines;
+            self.dead += block.len;
+            self.blocks.pop_front();
+        }
+        // lines that haven't made a block yet go one at a time
+        while self.blocks.is_empty() && self.lines > max_lines {
+            let Some(line) = self.pending.pop_front() else {
+                break;
+            };
+            self.pending_bytes -= line.len() + 1;
+            self.lines -= 1;
+        }
+
+        // once most of the file is lines that are gone, what's left moves to a new one
+        if self.dead > 0 && self.dead >= self.end - self.dead {
+            self.compact()?;
+        }
+
+        Ok(before - self.lines)
+    }
+
+    fn compact(&mut self) -> io::Result<()> {
+        let (path, mut file) = new_file()?;
+
+        self.file.seek(SeekFrom::Start(self.dead))?;
+        io::copy(&mut (&self.file).take(self.end - self.dead), &mut file)?;
+
+        for block in &mut self.blocks {
+            block.offset -= self.dead;
+        }
+        self.end -= self.dead;
+        self.dead = 0;
+
+        let old_path = std::mem::replace(&mut self.path, path);
+        self.file = file;
+        fs::remove_file(old_path)
+    }
+
+    /// What's needed to read lines `start` up to `end` (counting from the
+    /// oldest one here) without this: where their blocks are, with a handle
+    /// of its own on the file, and the ones that haven't made a block yet
+    pub fn reader(&self, start: usize, end: usize) -> io::Result<SpillReader> {
+        let mut blocks = Vec::new();
+        let mut first = 0;
+        for block in &self.blocks {
+            let last = first + block.lines;
+            if first >= end {
+                break;
+            }
+            if last > start {
+                blocks.push((first, block.clone()));
+            }
+            first = last;
+        }
+
+        let pending = (first..)
+            .zip(&self.pending)
+            .skip(start.saturating_sub(first))
+            .take_while(|(index, _)| *index < end)
+            .map(|(index, line)| (index, line.clone()))
+            .collect();
+
+        // a handle of its own, so reading doesn't move this one's position
+        let file = if blocks.is_empty() {
+            None
+        } else {
+            Some(File::open(&self.path)?)
+        };
+
+        Ok(SpillReader {
+            file,
+            blocks,
+            pending,
+            start,
+            end,
+        })
+    }
+
+    /// Let go of every line, keeping the file for what spills next
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.blocks.clear();
+        self.pending.clear();
+        self.pending_bytes = 0;
+        self.end = 0;
+        self.dead = 0;
+        self.lines = 0;
+
+        self.file.set_len(0)
+    }
+}
+
+/// Spilled lines copied out of a [`Spill`] to be read back from disk
+/// somewhere it's fine to block
+#[derive(Debug)]
+pub struct SpillReader {
+    file: Option<File>,
+    /// With the index of their first line
+    blocks: Vec<(usize, Block)>,
+    pending: Vec<(usize, String)>,
+    start: usize,
+    end: usize,
+}
+
+impl SpillReader {
+    /// Call `f` with each line, only reading the blocks they're in
+    pub fn visit(&self, f: &mut impl FnMut(usize, &str)) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            for (first, block) in &self.blocks {
+                let text = read_block(file, block)?;
+                for (index, line) in (*first..).zip(text.split_terminator('\n')) {
+                    if (self.start..self.end).contains(&index) {
+                        f(index, line);
+                    }
+                }
+            }
+        }
+
+        for (index, line) in &self.pending {
+            f(*index, line);
+        }
+
+        Ok(())
+    }
+}
+
+fn read_block(mut file: &File, block: &Block) -> io::Result<String> {
+    let mut compressed = vec![0; block.len as usize];
+    file.seek(SeekFrom::Start(block.offset))?;
+    file.read_exact(&mut compressed)?;
+
+    let mut text = String::new();
+    ZlibDecoder::new(&compressed[..]).read_to_string(&mut text)?;
+
+    Ok(text)
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            eprintln!("could not delete spilled scrollback {}: {err}", self.path.display());
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use regex::Regex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    error::SteppeError,
+    scrollback::{self, Scrollback, ScrollbackRange},
+    AppState, SubTerminal,
+};
+
+/// A match, by the line it's on counting every line the terminal's printed
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LiveMatch {
+    line: usize,
+    column: usize,
+    length: usize,
+}
+
+/// A search that keeps up with output as it's printed, for a find bar
+#[derive(Debug)]
+pub struct SearchSession {
+    pattern: Regex,
+    /// Oldest first
+    matches: Vec<LiveMatch>,
+    /// The first line that could still change since the last scan, which is
+    /// the one that was being printed
+    scanned_to: usize,
+    /// The match last jumped to, as an index into `matches`
+    current: Option<usize>,
+}
+
+impl SearchSession {
+    /// Scan `range`, which goes up to line `scanned_to`, the one that was
+    /// being printed
+    fn new(pattern: Regex, range: &ScrollbackRange, scanned_to: usize) -> Self {
+        let mut session = Self {
+            pattern,
+            matches: Vec::new(),
+            scanned_to,
+            current: None,
+        };
+        range.visit(&mut |line, text| session.scan_line(line, text));
+
+        session
+    }
+
+    fn scan_line(&mut self, line: usize, text: &str) {
+        let found = scrollback::find_in_line(&self.pattern, text);
+        self.matches.extend(
+            found
+                .into_iter()
+                .map(|(column, length)| LiveMatch { line, column, length }),
+        );
+    }
+
+    /// Catch up with what's been printed since the last scan, and drop
+    /// matches on lines that have left the scrollback. Returns whether the
+    /// matches changed.
+    fn update(&mut self, scrollback: &Scrollback) -> bool {
+        let first_line = scrollback.first_line();
+        let dropped = self.matches.partition_point(|found| found.line < first_line);
+        self.matches.drain(..dropped);
+        self.current = self.current.and_then(|current| current.checked_sub(dropped));
+
+        // the line that was being printed last time has likely got longer
+        let from = self.scanned_to.max(first_line);
+        let kept = self.matches.partition_point(|found| found.line < from);
+        let stale = self.matches.split_off(kept);
+
+        scrollback.visit(from, scrollback.current_line() + 1, |line, text| {
+            self.scan_line(line, text)
+        });
+        self.scanned_to = scrollback.current_line();
+
+        if self.current.is_some_and(|current| current >= self.matches.len()) {
+            self.current = None;
+        }
+
+        dropped > 0 || self.matches[kept..] != stale[..]
+    }
+
+    /// Move to the match after (or before) the current one, going round at
+    /// the ends. The first move goes to the newest match.
+    fn step(&mut self, forward: bool, current_line: usize) -> Option<MatchPosition> {
+        let last = self.matches.len().checked_sub(1)?;
+        let index = match self.current {
+            None => last,
+            Some(current) if forward => (current + 1) % self.matches.len(),
+            Some(current) => current.checked_sub(1).unwrap_or(last),
+        };
+        self.current = Some(index);
+
+        let found = self.matches[index];
+        Some(MatchPosition {
+            index,
+            total: self.matches.len(),
+            lines_up: current_line - found.line,
+            column: found.column,
+            length: found.length,
+        })
+    }
+}
+
+/// Where a match is, for the frontend to scroll to and highlight
+#[derive(Serialize)]
+pub struct MatchPosition {
+    /// Which match it is, counting from the oldest
+    index: usize,
+    total: usize,
+    /// How far up from the line being printed it is, like `async_scroll_to_prompt`
+    lines_up: usize,
+    /// In characters, not bytes
+    column: usize,
+    length: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct MatchesChanged {
+    terminal_id: u32,
+    total: usize,
+    current: Option<usize>,
+}
+
+fn emit_changed(app: &AppHandle, terminal_id: u32, session: &SearchSession) -> tauri::Result<()> {
+    app.emit(
+        "search://matches-changed",
+        MatchesChanged {
+            terminal_id,
+            total: session.matches.len(),
+            current: session.current,
+        },
+    )
+}
+
+/// Bring terminal `terminal_id`'s search up to date with what it just
+/// printed, sending `search://matches-changed` if that changed anything.
+pub async fn scan_output(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal) {
+    let mut search = terminal.search.lock().await;
+    let Some(session) = search.as_mut() else {
+        return;
+    };
+
+    if session.update(&terminal.scrollback.lock().await) {
+        if let Err(err) = emit_changed(app, terminal_id, session) {
+            eprintln!("could not send search matches: {err}");
+        }
+    }
+}
+
+/// Search terminal `terminal_id` for `query`, and keep searching what it
+/// prints until `async_end_search`. Returns how many matches there are so
+/// far; `search://matches-changed` says when that changes.
+#[tauri::command]
+pub async fn async_begin_search(
+    terminal_id: u32,
+    query: String,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<usize, SteppeError> {
+    let pattern = scrollback::search_pattern(&query, regex.unwrap_or(false), case_sensitive.unwrap_or(false))?;
+    let terminal = state.terminal(terminal_id).await?;
+
+    // what's there now is scanned off the runtime (spilled lines are read
+    // back from disk), and the session catches up on what's printed meanwhile
+    let (range, scanned_to) = {
+        let scrollback = terminal.scrollback.lock().await;
+        let scanned_to = scrollback.current_line();
+
+        (scrollback.range(scrollback.first_line(), scanned_to + 1), scanned_to)
+    };
+    let mut session = scrollback::read(range, move |range| SearchSession::new(pattern, range, scanned_to)).await?;
+
+    let mut search = terminal.search.lock().await;
+    session.update(&terminal.scrollback.lock().await);
+    let total = session.matches.len();
+    *search = Some(session);
+
+    Ok(total)
+}
+
+/// Move terminal `terminal_id`'s search on to the next match down, or
+/// `None` if there aren't any.
+#[tauri::command]
+pub async fn async_next_match(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Option<MatchPosition>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut search = terminal.search.lock().await;
+    let current_line = terminal.scrollback.lock().await.current_line();
+
+    Ok(search.as_mut().and_then(|session| session.step(true, current_line)))
+}
+
+/// Same as [`async_next_match`], but going up.
+#[tauri::command]
+pub async fn async_prev_match(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Option<MatchPosition>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut search = terminal.search.lock().await;
+    let current_line = terminal.scrollback.lock().await.current_line();
+
+    Ok(search.as_mut().and_then(|session| session.step(false, current_line)))
+}
+
+#[tauri::command]
+pub async fn async_end_search(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    *state.terminal(terminal_id).await?.search.lock().await = None;
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::sync::{atomic::Ordering, OnceLock};
+
+use regex::Regex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{error::SteppeError, termios, AppState, SubTerminal};
+
+/// What a prompt for something secret ends with, like `Password:` or
+/// `[sudo] password for leo: `
+fn password_prompt() -> &'static Regex {
+    static PROMPT: OnceLock<Regex> = OnceLock::new();
+
+    PROMPT.get_or_init(|| {
+        Regex::new(r"(?i)(password|passphrase|passcode|\bpin\b)[^:]*:\s*$").expect("the password prompt regex is valid")
+    })
+}
+
+#[derive(Clone, Serialize)]
+struct SecureInputChanged {
+    terminal_id: u32,
+    secure: bool,
+}
+
+/// Whether what's typed into `terminal` right now is probably a secret.
+///
+/// A program reading a password turns echo off but leaves the pty reading a
+/// line at a time, which nothing else does: shells and full screen programs
+/// turn both off. Over ssh the pty only ever looks like the latter, so then
+/// it's a secret if the line the cursor's on looks like a password prompt.
+async fn detect(terminal: &SubTerminal) -> bool {
+    let modes = termios::echo_modes(terminal.pty_pair.lock().await.master.as_ref());
+    match modes {
+        Some((false, true)) => true,
+        Some((true, _)) => false,
+        // no echo, or no telling (windows), so it's down to the prompt
+        Some((false, false)) | None => password_prompt().is_match(&terminal.screen.lock().await.cursor_line()),
+    }
+}
+
+/// Check whether terminal `terminal_id` is taking a secret, sending
+/// `terminal://secure-input` when that changes. Returns whether it is, for
+/// input that's about to be recorded.
+pub async fn check(app: &AppHandle, terminal_id: u32, terminal: &SubTerminal) -> bool {
+    let secure = detect(terminal).await;
+
+    if terminal.secure_input.swap(secure, Ordering::Relaxed) != secure {
+        if let Err(err) = app.emit("terminal://secure-input", SecureInputChanged { terminal_id, secure }) {
+            eprintln!("could not send secure input: {err}");
+        }
+    }
+
+    secure
+}
+
+/// Whether terminal `terminal_id` looks like it's taking a password, in
+/// which case what's typed isn't recorded anywhere.
+#[tauri::command]
+pub async fn async_get_secure_input(terminal_id: u32, state: State<'_, AppState>) -> Result<bool, SteppeError> {
+    Ok(state.terminal(terminal_id).await?.secure_input.load(Ordering::Relaxed))
+}
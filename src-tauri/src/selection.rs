@@ -0,0 +1,126 @@
+use alacritty_terminal::{index::Side, selection::SelectionType};
+use serde::Deserialize;
+use tauri::{AppHandle, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{error::SteppeError, AppState};
+
+/// How a selection grows as it's dragged out
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionMode {
+    /// Cell by cell, like a single click and drag
+    #[default]
+    Character,
+    /// A word at a time, like a double click
+    Word,
+    /// Whole lines, wrapped rows and all, like a triple click
+    Line,
+    /// A block of columns, like alt and drag
+    Rectangular,
+}
+
+impl From<SelectionMode> for SelectionType {
+    fn from(mode: SelectionMode) -> Self {
+        match mode {
+            SelectionMode::Character => Self::Simple,
+            SelectionMode::Word => Self::Semantic,
+            SelectionMode::Line => Self::Lines,
+            SelectionMode::Rectangular => Self::Block,
+        }
+    }
+}
+
+/// Which half of a cell the mouse is over, which decides whether that
+/// cell's in the selection
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellSide {
+    #[default]
+    Left,
+    Right,
+}
+
+impl From<CellSide> for Side {
+    fn from(side: CellSide) -> Self {
+        match side {
+            CellSide::Left => Self::Left,
+            CellSide::Right => Self::Right,
+        }
+    }
+}
+
+/// Start a selection in terminal `terminal_id` at `row`, `col`, replacing
+/// the one there was. `row` counts from the top of the screen, and goes
+/// negative into the scrollback.
+#[tauri::command]
+pub async fn async_start_selection(
+    terminal_id: u32,
+    mode: Option<SelectionMode>,
+    row: i32,
+    col: usize,
+    side: Option<CellSide>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let ty = mode.unwrap_or_default().into();
+    terminal
+        .screen
+        .lock()
+        .await
+        .start_selection(ty, row, col, side.unwrap_or_default().into());
+
+    Ok(())
+}
+
+/// Drag terminal `terminal_id`'s selection out to `row`, `col`.
+#[tauri::command]
+pub async fn async_update_selection(
+    terminal_id: u32,
+    row: i32,
+    col: usize,
+    side: Option<CellSide>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    terminal
+        .screen
+        .lock()
+        .await
+        .update_selection(row, col, side.unwrap_or_default().into());
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_clear_selection(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.terminal(terminal_id).await?.screen.lock().await.clear_selection();
+
+    Ok(())
+}
+
+/// What's selected in terminal `terminal_id`, or `None` if nothing is.
+#[tauri::command]
+pub async fn async_get_selection_text(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SteppeError> {
+    Ok(state.terminal(terminal_id).await?.screen.lock().await.selection_text())
+}
+
+/// Put what's selected in terminal `terminal_id` on the clipboard. Returns
+/// whether there was anything to copy.
+#[tauri::command]
+pub async fn async_copy_selection(
+    terminal_id: u32,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, SteppeError> {
+    let text = state.terminal(terminal_id).await?.screen.lock().await.selection_text();
+    let Some(text) = text.filter(|text| !text.is_empty()) else {
+        return Ok(false);
+    };
+
+    app.clipboard().write_text(text)?;
+    Ok(true)
+}
@@ -0,0 +1,152 @@
+use std::{
+    io::{self, Read},
+    sync::atomic::Ordering,
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serialport::{DataBits, FlowControl, Parity, SerialPort, SerialPortType, StopBits};
+use tauri::{async_runtime, AppHandle, State};
+
+use crate::{
+    crash,
+    error::SteppeError,
+    event_log::{self, SessionEventKind},
+    reader, terminal_exited, AppState,
+};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialParity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialFlowControl {
+    #[default]
+    None,
+    Software,
+    Hardware,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SerialPortEntry {
+    pub name: String,
+    /// What's on the other end, for ports that say (usb ones mostly)
+    pub description: Option<String>,
+}
+
+/// Serial ports time out reads rather than blocking, which the reader would
+/// take as the port going away, so keep waiting through those.
+struct SerialReader(Box<dyn SerialPort>);
+
+impl Read for SerialReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.0.read(buf) {
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn async_list_serial_ports() -> Result<Vec<SerialPortEntry>, SteppeError> {
+    let ports = serialport::available_ports()?;
+
+    Ok(ports
+        .into_iter()
+        .map(|port| SerialPortEntry {
+            name: port.port_name,
+            description: match port.port_type {
+                SerialPortType::UsbPort(usb) => usb.product.or(usb.manufacturer),
+                SerialPortType::BluetoothPort => Some("bluetooth".to_string()),
+                SerialPortType::PciPort | SerialPortType::Unknown => None,
+            },
+        })
+        .collect())
+}
+
+/// Like `async_create_shell`, but terminal `terminal_id` talks to a serial
+/// port instead. Defaults to 8N1 with no flow control.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn async_create_serial(
+    terminal_id: u32,
+    port: String,
+    baud: u32,
+    parity: Option<SerialParity>,
+    data_bits: Option<u8>,
+    stop_bits: Option<u8>,
+    flow_control: Option<SerialFlowControl>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let data_bits = match data_bits.unwrap_or(8) {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        8 => DataBits::Eight,
+        bits => return Err(SteppeError::InvalidConfig(format!("{bits} data bits, expected 5 to 8"))),
+    };
+
+    let stop_bits = match stop_bits.unwrap_or(1) {
+        1 => StopBits::One,
+        2 => StopBits::Two,
+        bits => return Err(SteppeError::InvalidConfig(format!("{bits} stop bits, expected 1 or 2"))),
+    };
+
+    let parity = match parity.unwrap_or_default() {
+        SerialParity::None => Parity::None,
+        SerialParity::Odd => Parity::Odd,
+        SerialParity::Even => Parity::Even,
+    };
+
+    let flow_control = match flow_control.unwrap_or_default() {
+        SerialFlowControl::None => FlowControl::None,
+        SerialFlowControl::Software => FlowControl::Software,
+        SerialFlowControl::Hardware => FlowControl::Hardware,
+    };
+
+    let terminal = state.open_terminal(terminal_id).await?;
+    if terminal.has_terminal.load(Ordering::Acquire) {
+        return Ok(());
+    }
+
+    event_log::record(&state, terminal_id, SessionEventKind::Created, Some(port.clone())).await;
+
+    let serial = serialport::new(&port, baud)
+        .data_bits(data_bits)
+        .stop_bits(stop_bits)
+        .parity(parity)
+        .flow_control(flow_control)
+        .timeout(Duration::from_secs(1))
+        .open()?;
+
+    // the terminal keeps its pty, it just never gets a shell. no `shell` is
+    // set either, so sessions don't try to restore the port as a shell
+    *terminal.writer.lock().await = Box::new(serial.try_clone()?);
+    let reader = reader::spawn_reader(
+        app.clone(),
+        terminal_id,
+        terminal.clone(),
+        Box::new(SerialReader(serial)),
+    )?;
+
+    terminal.has_terminal.store(true, Ordering::Release);
+    crash::ACTIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+
+    // the reader stops once the port goes away, e.g. the board was unplugged
+    thread::spawn(move || {
+        let _ = reader.join();
+        async_runtime::block_on(terminal_exited(&app, terminal_id, 0, None));
+    });
+
+    Ok(())
+}
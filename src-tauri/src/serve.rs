@@ -0,0 +1,256 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use rustls::{crypto::ring, ServerConfig, ServerConnection, StreamOwned};
+use tauri::{async_runtime, AppHandle};
+use tokio::sync::broadcast::error::TryRecvError;
+use tungstenite::{Message, WebSocket};
+
+use crate::{
+    error::SteppeError,
+    reader::Utf8Decoder,
+    remote::{self, Frame, Hello, HELLO_TIMEOUT, MAX_CLIENTS, MAX_FRAME_BYTES, MAX_HELLO_BYTES, REPLAY_CHUNK_BYTES},
+    start_terminal,
+    window::MAIN_WINDOW,
+};
+
+/// What `steppe serve` says when it can't start
+pub const USAGE: &str = "usage: steppe serve --listen <address:port> --cert <cert.pem> --key <key.pem>, \
+    with the token in STEPPE_SERVE_TOKEN (or --token)";
+
+/// How long waiting on a client goes before its terminal's output gets a turn
+const POLL: Duration = Duration::from_millis(20);
+
+/// A client's websocket, over tls
+type Socket = WebSocket<StreamOwned<ServerConnection, TcpStream>>;
+
+/// Where `steppe serve` listens, and what clients have to give
+pub struct ServeOptions {
+    listen: SocketAddr,
+    token: String,
+    tls: Arc<ServerConfig>,
+}
+
+impl ServeOptions {
+    /// Check what `steppe serve` was given, all of which it needs
+    fn new(
+        listen: Option<String>,
+        cert: Option<String>,
+        key: Option<String>,
+        token: Option<String>,
+    ) -> Result<Self, String> {
+        let listen = listen.ok_or("steppe serve needs --listen")?;
+        let listen = listen
+            .parse()
+            .map_err(|err| format!("--listen {listen:?} isn't an address and port: {err}"))?;
+        let (Some(cert), Some(key)) = (cert, key) else {
+            return Err("steppe serve needs --cert and --key, since it only serves over tls".to_string());
+        };
+        let token = token.ok_or("steppe serve needs a token")?;
+        if token.chars().count() < 16 {
+            return Err("the token has to be at least 16 characters".to_string());
+        }
+
+        Ok(Self {
+            listen,
+            token,
+            tls: Arc::new(load_tls(Path::new(&cert), Path::new(&key))?),
+        })
+    }
+}
+
+/// The tls setup for the certificate chain in `cert` and the private key in `key`, both pem
+fn load_tls(cert: &Path, key: &Path) -> Result<ServerConfig, String> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|err| format!("could not open {}: {err}", path.display()))
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("could not read {}: {err}", cert.display()))?;
+    if certs.is_empty() {
+        return Err(format!("there's no certificate in {}", cert.display()));
+    }
+    let key = rustls_pemfile::private_key(&mut open(key)?)
+        .map_err(|err| format!("could not read {}: {err}", key.display()))?
+        .ok_or_else(|| format!("there's no private key in {}", key.display()))?;
+
+    ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|err| format!("could not set up tls: {err}"))
+}
+
+/// For `steppe serve --listen <address:port> --cert <pem> --key <pem> [--token <token>]`,
+/// what to serve with, once the certificate's loaded. The token can be given as
+/// `STEPPE_SERVE_TOKEN` instead, to keep it out of the process list. `None`
+/// for any other launch.
+pub fn options_if_serving() -> Option<Result<ServeOptions, String>> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() != Some("serve") {
+        return None;
+    }
+
+    let (mut listen, mut cert, mut key) = (None, None, None);
+    let mut token = std::env::var("STEPPE_SERVE_TOKEN").ok();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next(),
+            "--cert" => cert = args.next(),
+            "--key" => key = args.next(),
+            "--token" => token = args.next(),
+            _ => eprintln!("ignoring unknown argument {arg:?}"),
+        }
+    }
+
+    Some(ServeOptions::new(listen, cert, key, token))
+}
+
+fn socket_error(err: tungstenite::Error) -> SteppeError {
+    SteppeError::Serve(err.to_string())
+}
+
+/// Send `frame` as a binary message of its own
+fn send(socket: &mut Socket, frame: Frame) -> Result<(), SteppeError> {
+    let mut data = Vec::new();
+    frame.write(&mut data)?;
+    socket.send(Message::binary(data)).map_err(socket_error)
+}
+
+/// What a client sent, as of waiting for as long as its stream's read timeout
+enum Received {
+    Frame(Frame),
+    Nothing,
+    HungUp,
+}
+
+/// The next frame from the client, if it sends one in time. Each binary
+/// message holds one frame, the same as `steppe attach` sends.
+fn receive(socket: &mut Socket, max_bytes: usize) -> Result<Received, SteppeError> {
+    match socket.read() {
+        Ok(Message::Binary(data)) => match Frame::read(&mut data.as_slice(), max_bytes)? {
+            Some(frame) => Ok(Received::Frame(frame)),
+            None => Ok(Received::Nothing),
+        },
+        Ok(Message::Close(_)) => Ok(Received::HungUp),
+        // pings are answered by the socket itself
+        Ok(_) => Ok(Received::Nothing),
+        Err(tungstenite::Error::Io(err))
+            if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) =>
+        {
+            Ok(Received::Nothing)
+        }
+        Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => Ok(Received::HungUp),
+        Err(err) => Err(socket_error(err)),
+    }
+}
+
+/// Send the client one last frame, and hang up on it
+fn hang_up(mut socket: Socket, frame: Frame) -> Result<(), SteppeError> {
+    send(&mut socket, frame)?;
+    let _ = socket.close(None);
+    let _ = socket.flush();
+
+    Ok(())
+}
+
+/// Attach the client on `stream` to the terminal its hello asks for, or a
+/// new one, once it's given `options`' token. Everything's on this one
+/// thread, taking turns between the client and the terminal, since the
+/// socket can't be split between threads the way `steppe attach`'s can.
+fn serve(app: &AppHandle, stream: TcpStream, options: &ServeOptions) -> Result<(), SteppeError> {
+    stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+    let connection = ServerConnection::new(options.tls.clone()).map_err(|err| SteppeError::Serve(err.to_string()))?;
+    let mut socket =
+        tungstenite::accept(StreamOwned::new(connection, stream)).map_err(|err| SteppeError::Serve(err.to_string()))?;
+
+    // anything else, a timeout included, is a client that isn't going to say hello
+    let Received::Frame(Frame::Hello(Hello { terminal_id, token })) = receive(&mut socket, MAX_HELLO_BYTES)? else {
+        return Ok(());
+    };
+    if !token.is_some_and(|given| remote::same_token(&given, &options.token)) {
+        return hang_up(socket, Frame::Error("wrong token".to_string()));
+    }
+
+    let terminal_id = match terminal_id {
+        Some(terminal_id) => terminal_id,
+        None => match async_runtime::block_on(start_terminal(app, None, None, MAIN_WINDOW)) {
+            Ok(terminal_id) => terminal_id,
+            Err(err) => return hang_up(socket, Frame::Error(err.to_string())),
+        },
+    };
+    let (replay, mut output) = match remote::subscribe(app, terminal_id) {
+        Ok(subscribed) => subscribed,
+        Err(err) => return hang_up(socket, Frame::Error(err.to_string())),
+    };
+
+    // a bit at a time, since there can be more of it than fits in a frame
+    for chunk in replay.as_bytes().chunks(REPLAY_CHUNK_BYTES) {
+        send(&mut socket, Frame::Output(chunk.to_vec()))?;
+    }
+
+    socket.get_ref().get_ref().set_read_timeout(Some(POLL))?;
+    let mut decoder = Utf8Decoder::default();
+    loop {
+        match receive(&mut socket, MAX_FRAME_BYTES)? {
+            Received::Frame(frame) => remote::apply(app, terminal_id, frame, &mut decoder)?,
+            Received::Nothing => {}
+            Received::HungUp => return Ok(()),
+        }
+
+        loop {
+            match output.try_recv() {
+                Ok(data) => send(&mut socket, Frame::Output(data.into_bytes()))?,
+                Err(TryRecvError::Empty) => break,
+                // the client fell behind, and only misses some of the output
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Closed) => return hang_up(socket, Frame::Exit),
+            }
+        }
+    }
+}
+
+/// Serve terminals over websockets, for `steppe serve`, on a thread of
+/// its own and each client on one of theirs. Past `MAX_CLIENTS` at once,
+/// they're hung up on.
+pub fn start(app: &AppHandle, options: ServeOptions) -> Result<(), SteppeError> {
+    let listener = TcpListener::bind(options.listen)
+        .map_err(|err| SteppeError::Serve(format!("could not listen on {}: {err}", options.listen)))?;
+
+    let app = app.clone();
+    let options = Arc::new(options);
+    let clients = Arc::new(AtomicUsize::new(0));
+    thread::Builder::new().name("serve".to_string()).spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if clients.fetch_add(1, Ordering::Relaxed) >= MAX_CLIENTS {
+                clients.fetch_sub(1, Ordering::Relaxed);
+                continue;
+            }
+
+            let (app, options, clients) = (app.clone(), options.clone(), clients.clone());
+            thread::spawn(move || {
+                if let Err(err) = serve(&app, stream, &options) {
+                    eprintln!("could not serve a websocket client: {err}");
+                }
+                clients.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    })?;
+
+    Ok(())
+}
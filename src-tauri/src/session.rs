@@ -0,0 +1,162 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::PathBuf,
+    sync::atomic::Ordering,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager, PhysicalSize, State, Window, WindowEvent};
+
+use crate::{
+    cwd, environment,
+    error::SteppeError,
+    get_config_dir,
+    k8s::K8sShell,
+    panes::{self, Layout},
+    settings, startup_layout, tabs,
+    window::MAIN_WINDOW,
+    AppState,
+};
+
+/// Enough about a terminal to start it again where it left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTerminal {
+    pub terminal_id: u32,
+    pub shell: String,
+    /// For terminals started from a profile, which one
+    pub shell_profile: Option<String>,
+    /// For ssh connections, which profile to connect with again
+    pub ssh_profile: Option<String>,
+    pub wsl_distro: Option<String>,
+    /// For shells in a docker container, which container to exec into again
+    pub docker_container: Option<String>,
+    /// For shells in a kubernetes pod, which container to exec into again
+    pub k8s_shell: Option<K8sShell>,
+    /// Where the shell was when steppe closed, if that can be found out
+    pub cwd: Option<PathBuf>,
+    /// What the shell had changed in its environment, see `environmentSnapshot`
+    #[serde(default)]
+    pub environment: Option<BTreeMap<String, String>>,
+}
+
+/// What was open when steppe last closed, kept in `<config dir>/session.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    /// In tab order
+    pub terminals: Vec<SavedTerminal>,
+    pub window_width: u32,
+    pub window_height: u32,
+    /// How the window was split, if it was
+    #[serde(default)]
+    pub layout: Option<Layout>,
+}
+
+fn session_path() -> PathBuf {
+    get_config_dir().join("session.json")
+}
+
+/// Terminal `terminal_id` as it is now, or `None` if nothing's running in it
+pub async fn saved_terminal(state: &AppState, terminal_id: u32) -> Option<SavedTerminal> {
+    let terminal = state.terminal(terminal_id).await.ok()?;
+    let shell = terminal.shell.get()?;
+
+    Some(SavedTerminal {
+        terminal_id,
+        shell: shell.program.clone(),
+        shell_profile: shell.shell_profile.clone(),
+        ssh_profile: shell.ssh_profile.clone(),
+        wsl_distro: shell.wsl_distro.clone(),
+        docker_container: shell.docker_container.clone(),
+        k8s_shell: shell.k8s_shell.clone(),
+        cwd: cwd::terminal_cwd(&terminal).await,
+        environment: environment::changes(state, &terminal).await,
+    })
+}
+
+async fn save(window: &Window) -> Result<(), SteppeError> {
+    let state = window.state::<AppState>();
+
+    let mut terminals = Vec::new();
+    for terminal_id in tabs::ordered(&state, window.label()).await {
+        terminals.extend(saved_terminal(&state, terminal_id).await);
+    }
+
+    let size = window.inner_size()?;
+    let session = SavedSession {
+        terminals,
+        window_width: size.width,
+        window_height: size.height,
+        layout: panes::layout(&state, window.label()).await,
+    };
+
+    let file = File::create(session_path())?;
+    serde_json::to_writer(file, &session).map_err(std::io::Error::from)?;
+
+    Ok(())
+}
+
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    // only the main window comes back next time
+    if !matches!(event, WindowEvent::CloseRequested { .. }) || window.label() != MAIN_WINDOW {
+        return;
+    }
+
+    if !window.state::<AppState>().restore_session.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // the app can be gone by the time a spawned task gets to run
+    if let Err(err) = async_runtime::block_on(save(window)) {
+        eprintln!("could not save session: {err}");
+    }
+}
+
+#[tauri::command]
+pub async fn async_set_restore_session(enabled: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.restore_session.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Put the window back the way it was and hand back the terminals to reopen,
+/// or `None` if there's nothing to restore. With a `startupLayout`, it's that
+/// that's opened instead.
+#[tauri::command]
+pub async fn async_restore_session(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<SavedSession>, SteppeError> {
+    // launching with a command is asking for that, not last time's terminals
+    if !state.launch.lock().await.command.is_empty() {
+        return Ok(None);
+    }
+
+    // a startup layout wins over last time, and it's in config.js, which is still starting up
+    settings::wait_until_loaded(&state).await;
+    if let Some(session) = startup_layout::restore(&app, &state).await? {
+        return Ok(Some(session));
+    }
+
+    if !state.restore_session.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    // a missing or unreadable session just means starting fresh
+    let Some(session) = fs::read(session_path())
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<SavedSession>(&contents).ok())
+    else {
+        return Ok(None);
+    };
+
+    if let Some(window) = app.get_webview_window("main") {
+        window.set_size(PhysicalSize::new(session.window_width, session.window_height))?;
+    }
+
+    // the terminals come back under the same ids, so the panes still line up
+    if let Some(layout) = session.layout.clone() {
+        panes::set_layout(&state, MAIN_WINDOW, layout).await;
+    }
+
+    Ok(Some(session))
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, State};
+
+use crate::{
+    config::ConfigError,
+    environment::EnvironmentOptions,
+    error::SteppeError,
+    file_drop::FileDropOptions,
+    get_config_dir, graphics,
+    identity::{self, IdentityOptions},
+    links::LinkPatterns,
+    notifications::CommandNotificationOptions,
+    on_exit::OnExit,
+    quake::{self, QuakeOptions},
+    reader,
+    redact::{self, RedactionOptions},
+    remote::{self, RemoteAttachOptions},
+    scrollback,
+    scrollback_spill::SpillOptions,
+    startup_layout::{self, StartupWindow},
+    tasks::{self, Task},
+    themes::{self, ThemeSource},
+    AppState,
+};
+
+/// How long `wait_until_loaded` waits on a config.js that's slow to start,
+/// like one that waits on a command of its own
+const LOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Simple settings that don't need any js, from `<config dir>/settings.toml`
+/// or `settings.json` (the toml one, if there's both). They go by the same
+/// names as config.js exports, and an export always wins over the file.
+/// Anything neither sets is left to steppe's defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Settings {
+    pub font_size: Option<f64>,
+    /// For terminals opened from now on
+    pub scrollback_lines: Option<usize>,
+    /// The most output sent to the frontend at once, in bytes
+    pub output_coalesce_bytes: Option<usize>,
+    /// How often, in milliseconds, a terminal that keeps printing sends its output
+    pub output_coalesce_ms: Option<u64>,
+    /// Started in place of `$SHELL`, or powershell on windows. See
+    /// `async_list_available_shells` for what's installed.
+    pub default_shell: Option<String>,
+    /// Start the default shell with `-l`, so it reads `.zprofile` and the
+    /// like. Defaults to on for macos, like its other terminals, and does
+    /// nothing on windows.
+    pub login_shell: Option<bool>,
+    /// Passed to the default shell, e.g. `["--norc"]`
+    pub shell_args: Option<Vec<String>>,
+    /// Load steppe's OSC 7 and 133 scripts into the default shell as it
+    /// starts, for bash, zsh, fish and powershell. On by default, and
+    /// `shellArgs` turns it off too.
+    pub shell_integration: Option<bool>,
+    /// Set for the default shell on top of steppe's own environment
+    pub shell_env: Option<HashMap<String, String>>,
+    /// Typed into the default shell once it's started
+    pub init_command: Option<String>,
+    /// A built-in theme's name, or a whole theme
+    pub theme: Option<ThemeSource>,
+    /// A dropdown window on a global hotkey
+    pub quake: Option<QuakeOptions>,
+    /// Keep running in the tray once the last window's closed
+    pub keep_alive_in_tray: Option<bool>,
+    /// The biggest image (sixel, kitty or iterm) a program can show, in bytes
+    pub max_image_bytes: Option<usize>,
+    /// Regexes for the urls and paths that ctrl+click and hint mode find in output
+    pub link_patterns: Option<LinkPatterns>,
+    /// What happens to a terminal once its shell exits
+    pub on_exit: Option<OnExit>,
+    /// Commands to run by name in terminals of their own, see `tasks::run`
+    pub tasks: Option<Vec<Task>>,
+    /// The windows, tabs and splits to open at launch, in place of the last
+    /// session's. The first window is the main one.
+    pub startup_layout: Option<Vec<StartupWindow>>,
+    /// Let `steppe attach` in from elsewhere on the network, not only this machine
+    pub remote_attach: Option<RemoteAttachOptions>,
+    /// Secrets to keep out of output logs, recordings, the command history
+    /// and exports
+    pub redaction: Option<RedactionOptions>,
+    /// How the paths of files dropped on a terminal go in
+    pub file_drop: Option<FileDropOptions>,
+    /// Keep scrollback past `scrollback_lines` in a compressed temp file
+    /// rather than letting it go
+    pub scrollback_spill: Option<SpillOptions>,
+    /// Remember what's changed in shells' environments, for restarting them
+    /// and restoring the session
+    pub environment_snapshot: Option<EnvironmentOptions>,
+    /// Notify when a command that ran a while finishes in the background
+    pub command_notifications: Option<CommandNotificationOptions>,
+    /// What steppe says it is in `TERM` and to programs that ask
+    pub terminal_identity: Option<IdentityOptions>,
+    /// Let programs put text on the clipboard with OSC 52. On by default.
+    pub allow_clipboard_write: Option<bool>,
+    /// Let programs read the clipboard with OSC 52. Off by default, since
+    /// anything that can print to a terminal could ask, ssh'd-into hosts included.
+    pub allow_clipboard_read: Option<bool>,
+    /// Save finished commands to the searchable history. On by default, and
+    /// turning it off keeps what's already saved.
+    pub command_history: Option<bool>,
+    /// Ask before closing a window with something other than a shell still
+    /// running in it. On by default.
+    pub confirm_close: Option<bool>,
+    /// Save the main window's terminals on exit and open them again next
+    /// launch. On by default.
+    pub restore_session: Option<bool>,
+    /// Keep the shells running with no window once the last one's closed,
+    /// for `steppe attach` to bring back
+    pub detach_on_close: Option<bool>,
+    /// Follow the main terminal's title (OSC 0 and 2) in the window's. On
+    /// by default.
+    pub window_title_from_terminal: Option<bool>,
+}
+
+impl Settings {
+    /// These settings, with anything `over` sets taking their place
+    pub fn merge(self, over: Settings) -> Settings {
+        Settings {
+            font_size: over.font_size.or(self.font_size),
+            scrollback_lines: over.scrollback_lines.or(self.scrollback_lines),
+            output_coalesce_bytes: over.output_coalesce_bytes.or(self.output_coalesce_bytes),
+            output_coalesce_ms: over.output_coalesce_ms.or(self.output_coalesce_ms),
+            default_shell: over.default_shell.or(self.default_shell),
+            login_shell: over.login_shell.or(self.login_shell),
+            shell_args: over.shell_args.or(self.shell_args),
+            shell_integration: over.shell_integration.or(self.shell_integration),
+            shell_env: over.shell_env.or(self.shell_env),
+            init_command: over.init_command.or(self.init_command),
+            theme: over.theme.or(self.theme),
+            quake: over.quake.or(self.quake),
+            keep_alive_in_tray: over.keep_alive_in_tray.or(self.keep_alive_in_tray),
+            max_image_bytes: over.max_image_bytes.or(self.max_image_bytes),
+            link_patterns: over.link_patterns.or(self.link_patterns),
+            on_exit: over.on_exit.or(self.on_exit),
+            tasks: over.tasks.or(self.tasks),
+            startup_layout: over.startup_layout.or(self.startup_layout),
+            remote_attach: over.remote_attach.or(self.remote_attach),
+            redaction: over.redaction.or(self.redaction),
+            file_drop: over.file_drop.or(self.file_drop),
+            scrollback_spill: over.scrollback_spill.or(self.scrollback_spill),
+            environment_snapshot: over.environment_snapshot.or(self.environment_snapshot),
+            command_notifications: over.command_notifications.or(self.command_notifications),
+            terminal_identity: over.terminal_identity.or(self.terminal_identity),
+            allow_clipboard_write: over.allow_clipboard_write.or(self.allow_clipboard_write),
+            allow_clipboard_read: over.allow_clipboard_read.or(self.allow_clipboard_read),
+            command_history: over.command_history.or(self.command_history),
+            confirm_close: over.confirm_close.or(self.confirm_close),
+            restore_session: over.restore_session.or(self.restore_session),
+            detach_on_close: over.detach_on_close.or(self.detach_on_close),
+            window_title_from_terminal: over.window_title_from_terminal.or(self.window_title_from_terminal),
+        }
+    }
+
+    /// Check the values themselves, now that serde's checked their types
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(size) = self.font_size {
+            if !size.is_finite() || size <= 0.0 {
+                return Err(format!("fontSize has to be a positive number, not {size}"));
+            }
+        }
+
+        if self.output_coalesce_bytes == Some(0) {
+            return Err("outputCoalesceBytes has to be above 0".to_string());
+        }
+
+        if self.max_image_bytes == Some(0) {
+            return Err("maxImageBytes has to be above 0".to_string());
+        }
+
+        if self
+            .default_shell
+            .as_deref()
+            .is_some_and(|shell| shell.trim().is_empty())
+        {
+            return Err("defaultShell can't be empty".to_string());
+        }
+
+        if let Some(theme) = &self.theme {
+            theme.clone().resolve().map_err(|err| err.to_string())?;
+        }
+
+        if let Some(quake) = &self.quake {
+            quake.validate()?;
+        }
+
+        if let Some(patterns) = &self.link_patterns {
+            patterns.validate()?;
+        }
+
+        if let Some(tasks) = &self.tasks {
+            tasks::validate(tasks)?;
+        }
+
+        if let Some(windows) = &self.startup_layout {
+            startup_layout::validate(windows)?;
+        }
+
+        if let Some(remote_attach) = &self.remote_attach {
+            remote_attach.validate()?;
+        }
+
+        if let Some(redaction) = &self.redaction {
+            redaction.validate()?;
+        }
+
+        if let Some(spill) = &self.scrollback_spill {
+            spill.validate()?;
+        }
+
+        if let Some(environment) = &self.environment_snapshot {
+            environment.validate()?;
+        }
+
+        if let Some(notifications) = &self.command_notifications {
+            notifications.validate()?;
+        }
+
+        if let Some(identity) = &self.terminal_identity {
+            identity.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The 1-based line and column of byte `offset` in `contents`
+fn position(contents: &str, offset: usize) -> (usize, usize) {
+    let before = contents.get(..offset).unwrap_or(contents);
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+    (line, column)
+}
+
+fn toml_path() -> PathBuf {
+    get_config_dir().join("settings.toml")
+}
+
+fn json_path() -> PathBuf {
+    get_config_dir().join("settings.json")
+}
+
+/// Whether `path` is a settings file, so changing it reloads the config
+pub fn is_settings_file(path: &Path) -> bool {
+    path == toml_path() || path == json_path()
+}
+
+/// What the settings file says, or nothing if there isn't one.
+pub fn load() -> Result<Settings, ConfigError> {
+    let path = match (toml_path(), json_path()) {
+        (toml, _) if toml.exists() => toml,
+        (_, json) if json.exists() => json,
+        _ => return Ok(Settings::default()),
+    };
+    let contents = fs::read_to_string(&path).map_err(|err| ConfigError::new(&path, err.to_string()))?;
+
+    let settings: Settings = if path == toml_path() {
+        toml::from_str(&contents).map_err(|err| {
+            let error = ConfigError::new(&path, err.message());
+            match err.span() {
+                Some(span) => {
+                    let (line, column) = position(&contents, span.start);
+                    error.at(line, column)
+                }
+                None => error,
+            }
+        })?
+    } else {
+        serde_json::from_str(&contents).map_err(|err| {
+            // serde_json puts the position in its message too, so it's left off here
+            let message = err.to_string();
+            let message = message.split(" at line ").next().unwrap_or(&message);
+            ConfigError::new(&path, message).at(err.line(), err.column())
+        })?
+    };
+
+    settings
+        .validate()
+        .map_err(|message| ConfigError::new(&path, message))?;
+
+    Ok(settings)
+}
+
+/// Put `settings` in effect, sending `settings://changed` with them for the
+/// frontend's part (the font size).
+pub async fn apply(app: &AppHandle, state: &AppState, settings: Settings) -> Result<(), SteppeError> {
+    let theme = settings.theme.clone().map(ThemeSource::resolve).transpose()?;
+    themes::set(app, state, theme).await?;
+
+    // what `setScrollbackLines` left it at otherwise, which is the default
+    // unless it was called since config.js was last reset
+    let lines = settings
+        .scrollback_lines
+        .unwrap_or_else(|| state.scrollback_lines.load(Ordering::Relaxed));
+    scrollback::set_max_lines(state, lines).await;
+    scrollback::configure_spill(state, settings.scrollback_spill.clone()).await;
+
+    let coalesce_bytes = settings.output_coalesce_bytes.unwrap_or(reader::DEFAULT_COALESCE_BYTES);
+    state.coalesce_bytes.store(coalesce_bytes, Ordering::Relaxed);
+    let coalesce_ms = settings
+        .output_coalesce_ms
+        .unwrap_or(reader::DEFAULT_COALESCE_INTERVAL_MS);
+    state.coalesce_interval_ms.store(coalesce_ms, Ordering::Relaxed);
+
+    let max_image_bytes = settings.max_image_bytes.unwrap_or(graphics::DEFAULT_MAX_IMAGE_BYTES);
+    state.max_image_bytes.store(max_image_bytes, Ordering::Relaxed);
+
+    quake::configure(app, state, settings.quake.as_ref()).await?;
+    remote::configure(app, state, settings.remote_attach.as_ref())?;
+    redact::configure(state, settings.redaction.as_ref()).map_err(SteppeError::InvalidConfig)?;
+
+    // before any more shells are started with it
+    let term = settings
+        .terminal_identity
+        .as_ref()
+        .and_then(|identity| identity.term.as_deref());
+    if term == Some(identity::STEPPE_TERM) {
+        if let Err(err) = async_runtime::spawn_blocking(identity::install_terminfo).await? {
+            eprintln!("could not install steppe's terminfo entry: {err}");
+        }
+    }
+
+    let keep_alive = settings.keep_alive_in_tray.unwrap_or(false);
+    state.keep_alive_in_tray.store(keep_alive, Ordering::Relaxed);
+
+    let clipboard_write = settings.allow_clipboard_write.unwrap_or(true);
+    state.clipboard_write.store(clipboard_write, Ordering::Relaxed);
+    let clipboard_read = settings.allow_clipboard_read.unwrap_or(false);
+    state.clipboard_read.store(clipboard_read, Ordering::Relaxed);
+
+    state.command_history.lock().await.enabled = settings.command_history.unwrap_or(true);
+
+    let confirm_close = settings.confirm_close.unwrap_or(true);
+    state.confirm_close.store(confirm_close, Ordering::Relaxed);
+
+    let restore_session = settings.restore_session.unwrap_or(true);
+    state.restore_session.store(restore_session, Ordering::Relaxed);
+
+    let detach_on_close = settings.detach_on_close.unwrap_or(false);
+    state.detach_on_close.store(detach_on_close, Ordering::Relaxed);
+
+    let follow_title = settings.window_title_from_terminal.unwrap_or(true);
+    state.window_title_from_terminal.store(follow_title, Ordering::Relaxed);
+
+    *state.settings.lock().await = settings.clone();
+    state.settings_loaded.send_replace(true);
+    app.emit("settings://changed", settings)?;
+
+    Ok(())
+}
+
+/// Wait for the settings file and config.js to be put in effect the first
+/// time, or for `LOAD_TIMEOUT`, whichever's sooner
+pub async fn wait_until_loaded(state: &AppState) {
+    let mut loaded = state.settings_loaded.subscribe();
+    let _ = tokio::time::timeout(LOAD_TIMEOUT, loaded.wait_for(|loaded| *loaded)).await;
+}
+
+/// The settings in effect, after merging the settings file and config.js
+#[tauri::command]
+pub async fn async_get_effective_config(state: State<'_, AppState>) -> Result<Settings, SteppeError> {
+    Ok(state.settings.lock().await.clone())
+}
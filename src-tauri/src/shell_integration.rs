@@ -0,0 +1,530 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    clipboard, command_history, cwd,
+    error::SteppeError,
+    event_log::{self, SessionEventKind},
+    notifications,
+    scrollback::{self, visible_text},
+    statusline,
+    steppe_api::{self, ScriptEvent},
+    title, AppState, SubTerminal,
+};
+
+/// How many commands each terminal keeps marks for
+const MAX_COMMANDS: usize = 1000;
+
+/// OSC 133 payloads are a few bytes and OSC 7 is a path (and OSC 0 and 2 a
+/// title), so anything longer is some other OSC (like an inline image)
+const MAX_OSC_LEN: usize = 4096;
+
+/// OSC 52 is whatever's being copied, which can be a lot more
+const MAX_CLIPBOARD_OSC_LEN: usize = 1024 * 1024;
+
+/// More than anyone types at a prompt, so a runaway echo doesn't pile up
+const MAX_INPUT_LEN: usize = 16 * 1024;
+
+/// Commands that take at least this long get a `command://finished`
+pub const DEFAULT_LONG_COMMAND_MS: u64 = 10_000;
+
+#[derive(Debug, Default, Clone, Copy)]
+enum ScanState {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    /// An escape inside an OSC, which is the start of ST if `\` comes next
+    OscEscape,
+}
+
+/// Where one command's prompt and output are, as line numbers
+/// counting every line the terminal has ever printed
+#[derive(Debug, Clone)]
+struct CommandMarks {
+    /// OSC 133;A
+    prompt_line: usize,
+    /// OSC 133;C
+    output_line: Option<usize>,
+    /// OSC 133;D
+    end_line: Option<usize>,
+    exit_code: Option<i32>,
+    started_at: Option<Instant>,
+    duration: Option<Duration>,
+    /// What was typed at the prompt, as the shell echoed it
+    command: Option<String>,
+    /// Where it was run, as of the last OSC 7
+    cwd: Option<PathBuf>,
+}
+
+/// Follows the OSC 133 marks shells with semantic prompts (fish, or bash and
+/// zsh with an integration script) print around every prompt and command,
+/// the OSC 7 they print whenever they change directory, the titles programs
+/// set with OSC 0 and 2, the clipboard access they ask for with OSC 52, and
+/// the notifications they raise with OSC 9, OSC 777 or the bell. State is
+/// kept between calls, so a sequence split across two reads is still seen.
+#[derive(Debug, Default)]
+pub struct ShellIntegration {
+    state: ScanState,
+    osc: Vec<u8>,
+    /// Set once the OSC being read is longer than any this cares about
+    osc_too_long: bool,
+    /// How many lines have been printed, which is also the number of the one being printed now
+    line: usize,
+    commands: VecDeque<CommandMarks>,
+    /// The last directory reported over OSC 7
+    cwd: Option<PathBuf>,
+    title: Option<String>,
+    /// The shell's echo of what's being typed, between OSC 133;B and C
+    input: Option<Vec<u8>>,
+}
+
+/// A command that just finished
+#[derive(Debug, Clone)]
+pub struct FinishedCommand {
+    pub command: Option<String>,
+    pub cwd: Option<PathBuf>,
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+}
+
+/// Something the shell said in its output
+#[derive(Debug, Clone)]
+pub enum ShellEvent {
+    CommandFinished(FinishedCommand),
+    CwdChanged(PathBuf),
+    TitleChanged(String),
+    /// Base64 text to copy
+    ClipboardSet(String),
+    /// Which selection to answer with, since a query is answered the way it was asked
+    ClipboardQuery(String),
+    Notification {
+        title: Option<String>,
+        body: String,
+    },
+    Bell,
+}
+
+impl ShellIntegration {
+    /// Returns everything the shell said in `data`.
+    pub fn scan(&mut self, data: &[u8]) -> Vec<ShellEvent> {
+        let mut events = Vec::new();
+
+        for &byte in data {
+            if let Some(input) = &mut self.input {
+                if !matches!(self.state, ScanState::Osc | ScanState::OscEscape) && input.len() < MAX_INPUT_LEN {
+                    input.push(byte);
+                }
+            }
+
+            self.state = match (self.state, byte) {
+                (ScanState::Osc, 0x07) | (ScanState::OscEscape, b'\\') => {
+                    if !self.osc_too_long {
+                        events.extend(self.finish_osc());
+                    }
+                    ScanState::Ground
+                }
+                (ScanState::Osc, 0x1b) => ScanState::OscEscape,
+                (ScanState::Osc, _) => {
+                    let max_len = if self.osc.starts_with(b"52;") {
+                        MAX_CLIPBOARD_OSC_LEN
+                    } else {
+                        MAX_OSC_LEN
+                    };
+
+                    if self.osc.len() < max_len {
+                        self.osc.push(byte);
+                    } else {
+                        self.osc_too_long = true;
+                    }
+                    ScanState::Osc
+                }
+                (ScanState::Ground, 0x07) => {
+                    // a bell that rings a few times in one read is still just the one
+                    if !matches!(events.last(), Some(ShellEvent::Bell)) {
+                        events.push(ShellEvent::Bell);
+                    }
+                    ScanState::Ground
+                }
+                // an escape always starts a new sequence, even in the middle of another one
+                (_, 0x1b) => ScanState::Escape,
+                (ScanState::Escape, b']') => {
+                    self.osc.clear();
+                    self.osc_too_long = false;
+                    ScanState::Osc
+                }
+                (_, b'\n') => {
+                    self.line += 1;
+                    ScanState::Ground
+                }
+                _ => ScanState::Ground,
+            }
+        }
+
+        events
+    }
+
+    fn finish_osc(&mut self) -> Option<ShellEvent> {
+        let payload = std::str::from_utf8(&self.osc).ok()?;
+
+        if let Some(url) = payload.strip_prefix("7;") {
+            let cwd = cwd::parse_osc7(url)?;
+            // shells tend to send it with every prompt, not just when it changes
+            if self.cwd.as_ref() == Some(&cwd) {
+                return None;
+            }
+
+            self.cwd = Some(cwd.clone());
+            return Some(ShellEvent::CwdChanged(cwd));
+        }
+
+        // OSC 1 is the icon name, which nothing shows
+        if let Some(title) = payload.strip_prefix("0;").or_else(|| payload.strip_prefix("2;")) {
+            self.title = Some(title.to_string());
+            return Some(ShellEvent::TitleChanged(title.to_string()));
+        }
+
+        if let Some(clipboard) = payload.strip_prefix("52;") {
+            let (selection, data) = clipboard.split_once(';')?;
+
+            return Some(if data == "?" {
+                ShellEvent::ClipboardQuery(selection.to_string())
+            } else {
+                ShellEvent::ClipboardSet(data.to_string())
+            });
+        }
+
+        if let Some(notification) = payload.strip_prefix("777;notify;") {
+            let (title, body) = notification.split_once(';').unwrap_or(("", notification));
+            return Some(ShellEvent::Notification {
+                title: Some(title.to_string()).filter(|title| !title.is_empty()),
+                body: body.to_string(),
+            });
+        }
+
+        if let Some(body) = payload.strip_prefix("9;") {
+            // ConEmu uses OSC 9 with a number for other things, like `9;4;...` for progress
+            let (first, _) = body.split_once(';').unwrap_or((body, ""));
+            if !first.is_empty() && first.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+
+            return Some(ShellEvent::Notification {
+                title: None,
+                body: body.to_string(),
+            });
+        }
+
+        let mut params = payload.strip_prefix("133;")?.split(';');
+
+        match params.next()? {
+            "A" => {
+                if self.commands.len() == MAX_COMMANDS {
+                    self.commands.pop_front();
+                }
+
+                self.commands.push_back(CommandMarks {
+                    prompt_line: self.line,
+                    output_line: None,
+                    end_line: None,
+                    exit_code: None,
+                    started_at: None,
+                    duration: None,
+                    command: None,
+                    cwd: None,
+                });
+                self.input = None;
+            }
+            "B" => self.input = Some(Vec::new()),
+            "C" => {
+                let input = self.input.take();
+                let command = self.commands.back_mut()?;
+                command.output_line = Some(self.line);
+                command.started_at = Some(Instant::now());
+                command.command = input
+                    .map(|input| typed_command(&String::from_utf8_lossy(&input)))
+                    .filter(|typed| !typed.trim().is_empty());
+                command.cwd = self.cwd.clone();
+            }
+            "D" => {
+                let command = self.commands.back_mut()?;
+                // shells send D before every prompt, including the first one,
+                // and for prompts where nothing was run
+                if command.end_line.is_some() {
+                    return None;
+                }
+
+                let duration = command.started_at?.elapsed();
+                command.end_line = Some(self.line);
+                command.exit_code = params.next().and_then(|code| code.parse().ok());
+                command.duration = Some(duration);
+
+                return Some(ShellEvent::CommandFinished(FinishedCommand {
+                    command: command.command.clone(),
+                    cwd: command.cwd.clone(),
+                    exit_code: command.exit_code,
+                    duration,
+                }));
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// What's been typed at the prompt so far, if the shell's at one
+    pub fn input(&self) -> Option<String> {
+        self.input
+            .as_ref()
+            .map(|input| typed_command(&String::from_utf8_lossy(input)))
+    }
+
+    /// How far up from the line being printed `line` is
+    fn lines_up(&self, line: usize) -> usize {
+        self.line - line
+    }
+
+    /// The closest prompt above the line `lines_up` lines up from the one being
+    /// printed, as lines up from the one being printed
+    fn previous_prompt(&self, lines_up: usize) -> Option<usize> {
+        let from = self.line.saturating_sub(lines_up);
+
+        self.commands
+            .iter()
+            .rev()
+            .find(|command| command.prompt_line < from)
+            .map(|command| self.lines_up(command.prompt_line))
+    }
+
+    /// Same as [`ShellIntegration::previous_prompt`], but looking down
+    fn next_prompt(&self, lines_up: usize) -> Option<usize> {
+        let from = self.line.saturating_sub(lines_up);
+
+        self.commands
+            .iter()
+            .find(|command| command.prompt_line > from)
+            .map(|command| self.lines_up(command.prompt_line))
+    }
+}
+
+/// What was typed, from the shell's echo of it: escape sequences are dropped
+/// and backspaces take back what they erase. Line editors that redraw the
+/// whole line as it's typed can still leave it looking odd.
+fn typed_command(echo: &str) -> String {
+    let mut command = String::new();
+
+    for (i, part) in echo.split('\x08').enumerate() {
+        if i > 0 {
+            command.pop();
+        }
+        command.push_str(&visible_text(part));
+    }
+
+    command
+}
+
+#[derive(Clone, Serialize)]
+struct CommandFinished {
+    terminal_id: u32,
+    exit_code: Option<i32>,
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+pub struct CommandOutput {
+    exit_code: Option<i32>,
+    duration_ms: Option<u64>,
+    /// As it looks on screen, minus anything that's already left the scrollback
+    output: String,
+    /// Where it starts, as lines up from the line being printed
+    start_lines_up: usize,
+    /// Where it ends (not including that line), the same way
+    end_lines_up: usize,
+}
+
+/// Pick the shell's marks out of what terminal `terminal_id` just printed,
+/// sending `command://finished` for anything that took a while,
+/// `terminal://cwd-changed` when it changes directory and
+/// `terminal://title-changed` when it sets its title, and dealing with
+/// OSC 52 clipboard requests and notifications.
+pub async fn scan_output(app: &AppHandle, state: &AppState, terminal_id: u32, terminal: &SubTerminal, data: &str) {
+    let events = terminal.shell_integration.lock().await.scan(data.as_bytes());
+    let long_command = Duration::from_millis(state.long_command_ms.load(Ordering::Relaxed));
+
+    for event in events {
+        match event {
+            ShellEvent::CommandFinished(command) => {
+                if let Err(err) = command_history::record(state, terminal, &command).await {
+                    eprintln!("could not save command history: {err}");
+                }
+                steppe_api::emit_event(
+                    state,
+                    ScriptEvent::CommandFinished {
+                        terminal_id,
+                        command: command.command.clone(),
+                        cwd: command.cwd.clone(),
+                        exit_code: command.exit_code,
+                        duration_ms: command.duration.as_millis() as u64,
+                    },
+                );
+                statusline::command_finished(app, terminal_id, command.exit_code);
+                if let Err(err) = notifications::command_finished(app, state, terminal_id, terminal, &command).await {
+                    eprintln!("could not notify about a finished command: {err}");
+                }
+
+                if command.duration < long_command {
+                    continue;
+                }
+
+                let finished = CommandFinished {
+                    terminal_id,
+                    exit_code: command.exit_code,
+                    duration_ms: command.duration.as_millis() as u64,
+                };
+
+                if let Err(err) = app.emit("command://finished", finished) {
+                    eprintln!("could not send command finished: {err}");
+                }
+            }
+            ShellEvent::CwdChanged(cwd) => {
+                event_log::record(state, terminal_id, SessionEventKind::CwdChanged(cwd.clone()), None).await;
+                if let Err(err) = cwd::emit_changed(app, terminal_id, cwd) {
+                    eprintln!("could not send cwd: {err}");
+                }
+                statusline::spawn_refresh(app, terminal_id);
+            }
+            ShellEvent::TitleChanged(new_title) => {
+                event_log::record(state, terminal_id, SessionEventKind::Titled(new_title.clone()), None).await;
+                if let Err(err) = title::title_changed(app, state, terminal_id, new_title) {
+                    eprintln!("could not update title: {err}");
+                }
+            }
+            ShellEvent::ClipboardSet(data) => {
+                if let Err(err) = clipboard::set(app, state, &data) {
+                    eprintln!("could not copy to the clipboard: {err}");
+                }
+            }
+            ShellEvent::ClipboardQuery(selection) => {
+                if let Err(err) = clipboard::query(app, state, terminal, &selection).await {
+                    eprintln!("could not answer a clipboard query: {err}");
+                }
+            }
+            ShellEvent::Notification { title, body } => {
+                if let Err(err) = notifications::notify(app, terminal, title, &body).await {
+                    eprintln!("could not show notification: {err}");
+                }
+            }
+            ShellEvent::Bell => {
+                if let Err(err) = notifications::bell(app, terminal).await {
+                    eprintln!("could not show notification: {err}");
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptDirection {
+    Previous,
+    Next,
+}
+
+/// Where the prompt before (or after) the line `lines_up` lines up from the
+/// one being printed is, in the same terms, or `None` if there isn't one.
+/// Looks from the line being printed if `lines_up` isn't given.
+#[tauri::command]
+pub async fn async_scroll_to_prompt(
+    terminal_id: u32,
+    direction: PromptDirection,
+    lines_up: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Option<usize>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let shell_integration = terminal.shell_integration.lock().await;
+    let lines_up = lines_up.unwrap_or(0);
+
+    Ok(match direction {
+        PromptDirection::Previous => shell_integration.previous_prompt(lines_up),
+        PromptDirection::Next => shell_integration.next_prompt(lines_up),
+    })
+}
+
+/// What the last command that finished printed, or `None` if the shell hasn't
+/// marked any commands.
+#[tauri::command]
+pub async fn async_get_last_command_output(
+    terminal_id: u32,
+    state: State<'_, AppState>,
+) -> Result<Option<CommandOutput>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let shell_integration = terminal.shell_integration.lock().await;
+
+    let Some(command) = shell_integration
+        .commands
+        .iter()
+        .rev()
+        .find(|command| command.end_line.is_some())
+    else {
+        return Ok(None);
+    };
+
+    let (Some(output_line), Some(end_line)) = (command.output_line, command.end_line) else {
+        return Ok(None);
+    };
+
+    let start_lines_up = shell_integration.lines_up(output_line);
+    let end_lines_up = shell_integration.lines_up(end_line);
+    let range = {
+        let scrollback = terminal.scrollback.lock().await;
+        let current_line = scrollback.current_line();
+
+        scrollback.range(
+            current_line.saturating_sub(start_lines_up),
+            current_line.saturating_sub(end_lines_up),
+        )
+    };
+    let exit_code = command.exit_code;
+    let duration_ms = command.duration.map(|duration| duration.as_millis() as u64);
+    drop(shell_integration);
+
+    // spilled lines are read back from disk, off the runtime
+    let output = scrollback::read(range, |range| {
+        let mut output = Vec::new();
+        range.visit(&mut |_, line| output.push(visible_text(line)));
+
+        output.join("\n")
+    })
+    .await?;
+
+    Ok(Some(CommandOutput {
+        exit_code,
+        duration_ms,
+        output,
+        start_lines_up,
+        end_lines_up,
+    }))
+}
+
+/// How long a command has to run for to get a `command://finished` when it's done.
+#[tauri::command]
+pub async fn async_set_long_command_threshold(ms: u64, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.long_command_ms.store(ms, Ordering::Relaxed);
+
+    Ok(())
+}
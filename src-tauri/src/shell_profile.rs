@@ -0,0 +1,101 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::{error::SteppeError, spawn_in_terminal, tray, AppState, ShellProcess};
+
+/// Something to run instead of the default shell, as defined in config.js,
+/// e.g. `fish`, `nix develop` or a python repl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellProfile {
+    pub name: String,
+    pub command: String,
+    /// Start `command` with `-l`, for shells that should read their login
+    /// files. Does nothing on windows.
+    #[serde(default)]
+    pub login_shell: bool,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub cwd: Option<PathBuf>,
+    /// Typed into the shell once it's started, e.g. `source ~/.work.sh`
+    pub init_command: Option<String>,
+}
+
+impl ShellProfile {
+    fn command(&self) -> CommandBuilder {
+        let mut cmd = CommandBuilder::new(&self.command);
+        #[cfg(not(target_os = "windows"))]
+        if self.login_shell {
+            cmd.arg("-l");
+        }
+        cmd.args(&self.args);
+
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+
+        if let Some(cwd) = &self.cwd {
+            cmd.cwd(cwd);
+        }
+
+        cmd
+    }
+}
+
+#[tauri::command]
+pub async fn async_set_shell_profiles(
+    profiles: Vec<ShellProfile>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if let Some(profile) = profiles.iter().find(|profile| profile.command.is_empty()) {
+        return Err(SteppeError::InvalidConfig(format!("profile {:?} has no command", profile.name)));
+    }
+
+    tray::set_profiles(&app, &profiles)?;
+    *state.shell_profiles.lock().await = profiles;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_list_profiles(state: State<'_, AppState>) -> Result<Vec<ShellProfile>, SteppeError> {
+    Ok(state.shell_profiles.lock().await.clone())
+}
+
+/// Like `async_create_shell`, but runs the profile named `name` instead.
+#[tauri::command]
+pub async fn async_create_shell_with_profile(
+    terminal_id: u32,
+    name: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let profile = state
+        .shell_profiles
+        .lock()
+        .await
+        .iter()
+        .find(|profile| profile.name == name)
+        .cloned()
+        .ok_or_else(|| SteppeError::ProfileNotFound(name.clone()))?;
+
+    let process = ShellProcess {
+        program: profile.command.clone(),
+        shell_profile: Some(name),
+        ssh_profile: None,
+        wsl_distro: None,
+        docker_container: None,
+        k8s_shell: None,
+        pid: None,
+        init_command: profile.init_command.clone(),
+    };
+
+    spawn_in_terminal(terminal_id, profile.command(), process, app, &state)
+        .await
+        .map_err(SteppeError::Pty)
+}
@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::error::SteppeError;
+
+/// A shell that's installed, for config to pick as `defaultShell`
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableShell {
+    /// What it's usually called, like `PowerShell` or `Git Bash`
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// `program` in one of `$PATH`'s directories, if it's in any
+#[cfg(target_os = "windows")]
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// The shells windows (or the user) usually has, in the order they're
+/// preferred as the default
+#[cfg(target_os = "windows")]
+pub fn detect() -> Vec<AvailableShell> {
+    let dir = |var: &str, fallback: &str| std::env::var_os(var).map_or_else(|| PathBuf::from(fallback), PathBuf::from);
+    let program_files = dir("ProgramFiles", r"C:\Program Files");
+    let system_root = dir("SystemRoot", r"C:\Windows");
+
+    let candidates = [
+        (
+            "PowerShell",
+            find_in_path("pwsh.exe").unwrap_or_else(|| program_files.join(r"PowerShell\7\pwsh.exe")),
+        ),
+        (
+            "Windows PowerShell",
+            system_root.join(r"System32\WindowsPowerShell\v1.0\powershell.exe"),
+        ),
+        ("Command Prompt", dir("ComSpec", r"C:\Windows\System32\cmd.exe")),
+        ("Git Bash", program_files.join(r"Git\bin\bash.exe")),
+        ("MSYS2", PathBuf::from(r"C:\msys64\usr\bin\bash.exe")),
+    ];
+
+    candidates
+        .into_iter()
+        .filter(|(_, path)| path.is_file())
+        .map(|(name, path)| AvailableShell {
+            name: name.to_string(),
+            path,
+        })
+        .collect()
+}
+
+/// Whatever's in `/etc/shells` and actually installed
+#[cfg(not(target_os = "windows"))]
+pub fn detect() -> Vec<AvailableShell> {
+    let Ok(shells) = std::fs::read_to_string("/etc/shells") else {
+        return Vec::new();
+    };
+
+    shells
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            Some(AvailableShell { name, path })
+        })
+        .collect()
+}
+
+/// What to start when config doesn't say: powershell 7 if it's installed,
+/// otherwise the windows powershell that always is
+#[cfg(target_os = "windows")]
+pub fn default_shell() -> String {
+    detect()
+        .into_iter()
+        .find(|shell| shell.name.ends_with("PowerShell"))
+        .map_or_else(
+            || "powershell.exe".to_string(),
+            |shell| shell.path.display().to_string(),
+        )
+}
+
+#[tauri::command]
+pub async fn async_list_available_shells() -> Result<Vec<AvailableShell>, SteppeError> {
+    Ok(detect())
+}
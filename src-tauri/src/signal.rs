@@ -0,0 +1,93 @@
+use serde::Deserialize;
+use tauri::State;
+
+use crate::{error::SteppeError, AppState, SubTerminal};
+
+/// What can be sent to whatever's running in a terminal
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Signal {
+    Sigint,
+    Sigterm,
+    Sighup,
+    Sigkill,
+}
+
+/// Send `signal` to the terminal's foreground process group, the same one
+/// ctrl+c would reach, which is the shell's own when nothing else is running.
+#[cfg(unix)]
+pub async fn send(terminal_id: u32, terminal: &SubTerminal, signal: Signal) -> Result<(), SteppeError> {
+    use nix::{
+        sys::signal::{kill, killpg, Signal as UnixSignal},
+        unistd::Pid,
+    };
+
+    let signal = match signal {
+        Signal::Sigint => UnixSignal::SIGINT,
+        Signal::Sigterm => UnixSignal::SIGTERM,
+        Signal::Sighup => UnixSignal::SIGHUP,
+        Signal::Sigkill => UnixSignal::SIGKILL,
+    };
+
+    let group = terminal.pty_pair.lock().await.master.process_group_leader();
+    match group {
+        Some(group) => killpg(Pid::from_raw(group), signal).map_err(std::io::Error::from)?,
+        // no controlling terminal to ask, so it's down to the shell
+        None => {
+            let pid = terminal
+                .shell
+                .get()
+                .and_then(|shell| shell.pid)
+                .ok_or(SteppeError::NoProcess(terminal_id))?;
+            kill(Pid::from_raw(pid as i32), signal).map_err(std::io::Error::from)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// ConPTY has no signals, so SIGINT is a ctrl+c through the pty (which
+/// ConPTY turns into CTRL_C_EVENT) and everything else ends the shell and
+/// everything under it.
+#[cfg(windows)]
+pub async fn send(terminal_id: u32, terminal: &SubTerminal, signal: Signal) -> Result<(), SteppeError> {
+    use std::io::Write;
+
+    if let Signal::Sigint = signal {
+        terminal.writer.lock().await.write_all(b"\x03")?;
+        return Ok(());
+    }
+
+    let pid = terminal
+        .shell
+        .get()
+        .and_then(|shell| shell.pid)
+        .ok_or(SteppeError::NoProcess(terminal_id))?;
+
+    let mut taskkill = tokio::process::Command::new("taskkill");
+    taskkill.args(["/PID", &pid.to_string(), "/T"]);
+    if let Signal::Sigkill = signal {
+        taskkill.arg("/F");
+    }
+
+    let status = taskkill.status().await?;
+    if !status.success() {
+        return Err(SteppeError::Pty(format!("taskkill exited with {status}")));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub async fn send(_terminal_id: u32, _terminal: &SubTerminal, _signal: Signal) -> Result<(), SteppeError> {
+    Err(SteppeError::UnsupportedPlatformFeature("signals"))
+}
+
+/// Send `signal` (`"SIGINT"`, `"SIGTERM"`, `"SIGHUP"` or `"SIGKILL"`) to
+/// what's running in terminal `terminal_id`, e.g. to kill something that's
+/// stopped listening to ctrl+c.
+#[tauri::command]
+pub async fn async_signal_pty(terminal_id: u32, signal: Signal, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    send(terminal_id, &terminal, signal).await
+}
@@ -0,0 +1,100 @@
+use portable_pty::PtySize;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{asciicast, error::SteppeError, telnet, AppState};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FixedSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+/// Limits on how big or small the pty is allowed to get, no matter how the
+/// window is resized.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeConstraints {
+    pub min_rows: u16,
+    pub max_rows: u16,
+    pub min_cols: u16,
+    pub max_cols: u16,
+    /// Ignore resizes entirely, for programs that only look right at one size
+    #[serde(default)]
+    pub fixed_size: Option<FixedSize>,
+}
+
+impl Default for SizeConstraints {
+    fn default() -> Self {
+        Self {
+            min_rows: 1,
+            max_rows: u16::MAX,
+            min_cols: 1,
+            max_cols: u16::MAX,
+            fixed_size: None,
+        }
+    }
+}
+
+impl SizeConstraints {
+    fn validate(&self) -> Result<(), SteppeError> {
+        if self.min_rows == 0 || self.min_cols == 0 {
+            return Err(SteppeError::InvalidConfig("minimum rows and cols must be at least 1".into()));
+        }
+
+        if self.min_rows > self.max_rows || self.min_cols > self.max_cols {
+            return Err(SteppeError::InvalidConfig("minimum size is bigger than the maximum size".into()));
+        }
+
+        if let Some(FixedSize { rows: 0, .. } | FixedSize { cols: 0, .. }) = self.fixed_size {
+            return Err(SteppeError::InvalidConfig("fixed size must be at least 1x1".into()));
+        }
+
+        Ok(())
+    }
+
+    /// The `(rows, cols)` the pty should actually be when the frontend asks for `rows`x`cols`
+    pub fn apply(&self, rows: u16, cols: u16) -> (u16, u16) {
+        match self.fixed_size {
+            Some(FixedSize { rows, cols }) => (rows, cols),
+            None => (
+                rows.clamp(self.min_rows, self.max_rows),
+                cols.clamp(self.min_cols, self.max_cols),
+            ),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn async_set_session_size_constraints(
+    constraints: SizeConstraints,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    constraints.validate()?;
+    *state.size_constraints.lock().await = constraints;
+
+    // bring the current sizes in line with the new constraints right away
+    let metrics = *state.cell_metrics.lock().await;
+    for (&terminal_id, terminal) in state.terminals.lock().await.iter() {
+        let pty_pair = terminal.pty_pair.lock().await;
+        let size = pty_pair.master.get_size().map_err(|err| SteppeError::Pty(err.to_string()))?;
+        let (rows, cols) = constraints.apply(size.rows, size.cols);
+        let (pixel_width, pixel_height) = metrics.pixel_size(rows, cols);
+
+        pty_pair
+            .master
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width,
+                pixel_height,
+            })
+            .map_err(|err| SteppeError::Pty(err.to_string()))?;
+        terminal.screen.lock().await.resize(rows, cols);
+        telnet::resized(terminal, rows, cols);
+        let dimensions = format!("{cols}x{rows}");
+        asciicast::record(&state, terminal_id, terminal, asciicast::Event::Resize, &dimensions).await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use std::{ffi::OsString, path::PathBuf, sync::atomic::Ordering};
+
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::{
+    askpass, connections, error::SteppeError, file_transfer, get_config_dir, port_forward::PortForward,
+    spawn_in_terminal, telnet, AppState, ShellProcess,
+};
+
+/// A host to connect to, as defined in config.js.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshProfile {
+    pub name: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    /// A private key to log in with, whose passphrase (if it has one) is
+    /// asked for in a prompt rather than in the terminal
+    pub key_path: Option<PathBuf>,
+    /// The agent to use in place of `SSH_AUTH_SOCK`'s: a socket, or on
+    /// windows a pipe like Pageant's. `"none"` turns agents off.
+    pub agent: Option<String>,
+    /// Let the host use the agent too, e.g. for git over ssh from there
+    #[serde(default)]
+    pub forward_agent: bool,
+    /// Give the agent `keyPath` once it's unlocked, so its passphrase is only
+    /// asked for the once
+    #[serde(default)]
+    pub add_keys_to_agent: bool,
+    /// Passed to `-J` as is, so `user@host:port` works
+    pub jump_host: Option<String>,
+    /// Opened along with the connection
+    #[serde(default)]
+    pub forwards: Vec<PortForward>,
+    /// Connect with mosh, which survives roaming and flaky connections. ssh
+    /// is still what logs in and starts `mosh-server` on the host.
+    #[serde(default)]
+    pub mosh: bool,
+    /// The UDP ports `mosh-server` can pick from, as `[first, last]`, for
+    /// hosts that only have some of them open
+    pub mosh_ports: Option<(u16, u16)>,
+    /// Connect with telnet instead, for network gear and lab equipment that
+    /// only speaks that. Only `host` and `port` (23 by default) are used,
+    /// logging in is up to the host's own prompt.
+    #[serde(default)]
+    pub telnet: bool,
+}
+
+impl SshProfile {
+    /// Everything that picks out the connection, shared by the interactive
+    /// session and file transfers so they end up on the same one.
+    pub fn connection_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
+
+        // the terminal's connection is the master, so transfers don't need to log in again
+        let mut control_path = OsString::from("ControlPath=");
+        control_path.push(get_config_dir().join("ssh-%C"));
+        args.extend(["-o".into(), control_path]);
+
+        if let Some(port) = self.port {
+            args.extend(["-p".into(), port.to_string().into()]);
+        }
+
+        if let Some(key_path) = &self.key_path {
+            args.extend(["-i".into(), key_path.into()]);
+        }
+
+        if let Some(agent) = &self.agent {
+            args.extend(["-o".into(), format!("IdentityAgent={agent}").into()]);
+        }
+
+        if let Some(jump_host) = &self.jump_host {
+            args.extend(["-J".into(), jump_host.into()]);
+        }
+
+        // whatever's after this is the destination, even if it looks like an option
+        args.push("--".into());
+        args.push(match &self.user {
+            Some(user) => format!("{user}@{}", self.host).into(),
+            None => self.host.clone().into(),
+        });
+
+        args
+    }
+
+    /// Connecting goes through the system's OpenSSH, so known hosts, agents
+    /// and `~/.ssh/config` all work the way they would in any other terminal.
+    fn command(&self) -> CommandBuilder {
+        if self.mosh {
+            return self.mosh_command();
+        }
+
+        let mut cmd = CommandBuilder::new("ssh");
+        // the pty is already there, make sure the remote end gets one too
+        cmd.args(["-t", "-o", "ControlMaster=auto"]);
+
+        if self.forward_agent {
+            cmd.args(["-o", "ForwardAgent=yes"]);
+        }
+        if self.add_keys_to_agent {
+            cmd.args(["-o", "AddKeysToAgent=yes"]);
+        }
+
+        for forward in &self.forwards {
+            cmd.args([forward.flag(), &forward.spec()]);
+        }
+
+        cmd.args(self.connection_args());
+
+        cmd
+    }
+
+    /// The system's mosh, which logs in with the same ssh options as `command`
+    fn mosh_command(&self) -> CommandBuilder {
+        let mut args = self.connection_args();
+        let destination = args.pop().unwrap_or_default();
+        // mosh adds its own after the destination it gives ssh
+        args.pop();
+
+        // mosh splits this up like a shell would
+        let mut ssh = String::from("ssh");
+        for arg in &args {
+            ssh.push(' ');
+            ssh.push_str(&file_transfer::quote(&arg.to_string_lossy()));
+        }
+
+        let mut cmd = CommandBuilder::new("mosh");
+        cmd.arg(format!("--ssh={ssh}"));
+        if let Some((first, last)) = self.mosh_ports {
+            cmd.arg(format!("--port={first}:{last}"));
+        }
+        cmd.arg("--");
+        cmd.arg(destination);
+
+        cmd
+    }
+
+    /// Check for a missing host, anything that would pass for an option, and
+    /// for things mosh and telnet can't do
+    pub fn validate(&self) -> Result<(), SteppeError> {
+        if self.host.is_empty() {
+            return Err(SteppeError::InvalidConfig(format!(
+                "ssh profile {:?} has no host",
+                self.name
+            )));
+        }
+
+        let names = [
+            ("host", Some(&self.host)),
+            ("user", self.user.as_ref()),
+            ("jump host", self.jump_host.as_ref()),
+        ];
+        for (what, value) in names {
+            if value.is_some_and(|value| value.starts_with('-')) {
+                return Err(SteppeError::InvalidConfig(format!(
+                    "ssh profile {:?} has a {what} starting with \"-\", which ssh would take for an option",
+                    self.name
+                )));
+            }
+        }
+
+        if self.telnet {
+            return self.validate_telnet();
+        }
+
+        if !self.mosh {
+            return Ok(());
+        }
+
+        if !self.forwards.is_empty() {
+            return Err(SteppeError::InvalidConfig(format!(
+                "ssh profile {:?} uses mosh, which can't forward ports",
+                self.name
+            )));
+        }
+
+        if self.forward_agent {
+            return Err(SteppeError::InvalidConfig(format!(
+                "ssh profile {:?} uses mosh, which can't forward the agent",
+                self.name
+            )));
+        }
+
+        if let Some((first, last)) = self.mosh_ports.filter(|(first, last)| first > last) {
+            return Err(SteppeError::InvalidConfig(format!(
+                "ssh profile {:?} has mosh ports {first} to {last}, which is backwards",
+                self.name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Everything but the host and port is ssh's, which telnet has no use for
+    fn validate_telnet(&self) -> Result<(), SteppeError> {
+        let unsupported = [
+            (self.user.is_some(), "pick the user, that's up to the host's prompt"),
+            (self.key_path.is_some(), "log in with a key"),
+            (
+                self.agent.is_some() || self.forward_agent || self.add_keys_to_agent,
+                "use an agent",
+            ),
+            (self.jump_host.is_some(), "go through a jump host"),
+            (!self.forwards.is_empty(), "forward ports"),
+            (self.mosh || self.mosh_ports.is_some(), "use mosh"),
+        ];
+
+        match unsupported.into_iter().find(|(set, _)| *set) {
+            Some((_, what)) => Err(SteppeError::InvalidConfig(format!(
+                "ssh profile {:?} uses telnet, which can't {what}",
+                self.name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Run `command` on the host without a pty, for moving files around.
+    pub fn remote_command(&self, command: &str) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new("ssh");
+        // nobody's there to answer a password prompt
+        cmd.args(["-o", "BatchMode=yes"]);
+        cmd.args(self.connection_args());
+        cmd.arg(command);
+
+        cmd
+    }
+}
+
+/// The profile named `name`, from config.js or else the connection book
+pub async fn find_profile(state: &AppState, name: &str) -> Result<SshProfile, SteppeError> {
+    let profile = state
+        .ssh_profiles
+        .lock()
+        .await
+        .iter()
+        .find(|profile| profile.name == name)
+        .cloned();
+
+    match profile {
+        Some(profile) => Ok(profile),
+        None => state
+            .connections
+            .lock()
+            .await
+            .profile(name)
+            .ok_or_else(|| SteppeError::SshProfileNotFound(name.to_string())),
+    }
+}
+
+/// The profile terminal `terminal_id` is connected with.
+pub async fn terminal_profile(state: &AppState, terminal_id: u32) -> Result<SshProfile, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let name = terminal
+        .shell
+        .get()
+        .and_then(|shell| shell.ssh_profile.as_ref())
+        .ok_or(SteppeError::NotSsh(terminal_id))?;
+
+    let profile = find_profile(state, name).await?;
+    // there's no ssh underneath to move files or forward ports over
+    if profile.telnet {
+        return Err(SteppeError::NotSsh(terminal_id));
+    }
+
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn async_set_ssh_profiles(profiles: Vec<SshProfile>, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    for profile in &profiles {
+        profile.validate()?;
+    }
+
+    *state.ssh_profiles.lock().await = profiles;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_list_ssh_profiles(state: State<'_, AppState>) -> Result<Vec<SshProfile>, SteppeError> {
+    Ok(state.ssh_profiles.lock().await.clone())
+}
+
+/// Like `async_create_shell`, but connects to the profile named
+/// `host_profile` instead, from config.js or the connection book.
+#[tauri::command]
+pub async fn async_create_ssh(
+    terminal_id: u32,
+    host_profile: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let profile = find_profile(&state, &host_profile).await?;
+    if profile.telnet {
+        return telnet::connect(&app, &state, terminal_id, &profile).await;
+    }
+
+    let process = ShellProcess {
+        program: if profile.mosh { "mosh" } else { "ssh" }.to_string(),
+        shell_profile: None,
+        ssh_profile: Some(host_profile),
+        wsl_distro: None,
+        docker_container: None,
+        k8s_shell: None,
+        pid: None,
+        init_command: None,
+    };
+
+    let terminal = state.open_terminal(terminal_id).await?;
+    // already connected, leave its forwards alone
+    if terminal.has_terminal.load(Ordering::Acquire) {
+        return Ok(());
+    }
+
+    let mut cmd = profile.command();
+    // passphrases and host keys get asked about in a prompt, falling back on the terminal
+    for (key, value) in askpass::env(&app, Some(terminal_id), "prefer") {
+        cmd.env(key, value);
+    }
+
+    spawn_in_terminal(terminal_id, cmd, process, app, &state)
+        .await
+        .map_err(SteppeError::Pty)?;
+    *terminal.port_forwards.lock().await = profile.forwards;
+    connections::touch(&state, &profile.name).await;
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use std::{path::PathBuf, process::Stdio};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::{askpass, error::SteppeError};
+
+/// A key the agent's holding, as `ssh-add -l` lists it
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentKey {
+    pub bits: u32,
+    pub fingerprint: String,
+    /// Usually the file it came from, or `user@host`
+    pub comment: String,
+    /// Like `ED25519` or `RSA`
+    pub key_type: String,
+}
+
+/// A line of `ssh-add -l`: `256 SHA256:... comment (ED25519)`
+fn parse_key(line: &str) -> Option<AgentKey> {
+    let (bits, rest) = line.split_once(' ')?;
+    let (fingerprint, rest) = rest.split_once(' ')?;
+    let (comment, key_type) = rest.strip_suffix(')')?.rsplit_once(" (")?;
+
+    Some(AgentKey {
+        bits: bits.parse().ok()?,
+        fingerprint: fingerprint.to_string(),
+        comment: comment.to_string(),
+        key_type: key_type.to_string(),
+    })
+}
+
+/// The keys in the agent `SSH_AUTH_SOCK` points to, or on windows the
+/// OpenSSH agent service.
+#[tauri::command]
+pub async fn async_list_ssh_agent_keys() -> Result<Vec<AgentKey>, SteppeError> {
+    let output = tokio::process::Command::new("ssh-add")
+        .arg("-l")
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    // 1 is an agent with no keys, 2 is no agent at all
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(parse_key)
+            .collect()),
+        Some(1) => Ok(Vec::new()),
+        _ => Err(SteppeError::SshAgent(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+    }
+}
+
+/// Give the agent the private key at `path`, with its passphrase asked for
+/// in a prompt if it has one.
+#[tauri::command]
+pub async fn async_add_ssh_key(path: PathBuf, app: AppHandle) -> Result<(), SteppeError> {
+    let mut cmd = tokio::process::Command::new("ssh-add");
+    cmd.arg(&path).stdin(Stdio::null());
+    // there's no terminal for it to ask in
+    for (key, value) in askpass::env(&app, None, "force") {
+        cmd.env(key, value);
+    }
+
+    let output = cmd.output().await?;
+    if !output.status.success() {
+        return Err(SteppeError::SshAgent(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::{
+    io::Write,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use tauri::{async_runtime, State};
+use tokio::{process::Command, time::sleep};
+
+use crate::{error::SteppeError, AppState, SESSION_ID};
+
+/// How long to wait between attempts in [`wait_for_command`].
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Raw bytes to send to a new shell once it's up, like `\x1b[?2004h` for
+/// shells whose profile doesn't turn on bracketed paste themselves
+#[derive(Debug, Clone, Deserialize)]
+pub struct TerminalSequence {
+    /// How long to wait after the previous sequence (or the shell's first output)
+    pub delay_ms: u64,
+    pub data: String,
+}
+
+/// Sleep for the configured startup delay, if any. Called before the first
+/// shell is spawned, so configs can wait for whatever they depend on.
+pub async fn startup_delay(state: &AppState) {
+    let delay = Duration::from_millis(state.startup_delay_ms.load(Ordering::Relaxed));
+
+    if !delay.is_zero() {
+        sleep(delay).await;
+    }
+}
+
+/// Send the startup sequences, the first time the shell prints anything. That's
+/// the earliest point we know it's actually reading its input.
+pub async fn send_startup_sequences(state: &AppState) {
+    if state.startup_sequences_sent.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    let sequences = state.startup_sequences.lock().await.clone();
+    if sequences.is_empty() {
+        return;
+    }
+
+    let Ok(terminal) = state.terminal(SESSION_ID).await else {
+        return;
+    };
+
+    // don't hold up reading while the delays run
+    let writer = terminal.writer.clone();
+    async_runtime::spawn(async move {
+        for sequence in sequences {
+            sleep(Duration::from_millis(sequence.delay_ms)).await;
+
+            if let Err(err) = writer.lock().await.write_all(sequence.data.as_bytes()) {
+                eprintln!("could not send startup sequence: {err}");
+                break;
+            }
+        }
+    });
+}
+
+/// Keep running `program` until it exits successfully, or give up once `timeout` is up.
+/// e.g. `nc -z localhost 5432` to wait for a database to come up.
+pub async fn wait_for_command(program: &str, args: &[String], timeout: Duration) -> Result<(), SteppeError> {
+    let started = Instant::now();
+
+    loop {
+        let status = Command::new(program).args(args).kill_on_drop(true).status().await?;
+        if status.success() {
+            return Ok(());
+        }
+
+        if started.elapsed() + RETRY_INTERVAL > timeout {
+            return Err(SteppeError::Timeout(format!(
+                "`{program}` didn't succeed within {}ms",
+                timeout.as_millis()
+            )));
+        }
+
+        sleep(RETRY_INTERVAL).await;
+    }
+}
+
+#[tauri::command]
+pub async fn async_set_startup_delay(ms: u64, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.startup_delay_ms.store(ms, Ordering::Relaxed);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_startup_sequences(
+    sequences: Vec<TerminalSequence>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    *state.startup_sequences.lock().await = sequences;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_wait_for_command(cmd: String, args: Vec<String>, timeout_ms: u64) -> Result<(), SteppeError> {
+    wait_for_command(&cmd, &args, Duration::from_millis(timeout_ms)).await
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewWindowBuilder};
+
+use crate::{
+    cwd,
+    error::SteppeError,
+    panes::{self, Layout, Pane, SplitDirection},
+    session::{self, SavedSession},
+    start_terminal,
+    window::{self, MAIN_WINDOW},
+    write_input, AppState,
+};
+
+/// One terminal in a startup layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StartupTerminal {
+    /// A shell profile to start instead of the default shell
+    pub profile: Option<String>,
+    /// Where the default shell starts, with `~` for the home directory.
+    /// Profiles have a directory of their own.
+    pub cwd: Option<String>,
+    /// Typed into the shell once it's started, e.g. `htop`
+    pub command: Option<String>,
+}
+
+/// A split in a startup layout, with more on each side. It's generic over
+/// what's on each side so `config_types` can trace it without going into
+/// `StartupPane`, which it can't.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StartupSplit<P = Box<StartupPane>> {
+    pub split: SplitDirection,
+    /// How much of the split `first` gets, half if it's not given
+    pub ratio: Option<f64>,
+    pub first: P,
+    pub second: P,
+}
+
+/// A tab in a startup layout: a terminal, or a split with more on each side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StartupPane {
+    Split(StartupSplit),
+    Terminal(StartupTerminal),
+}
+
+impl StartupPane {
+    /// The terminals in this pane, top left first
+    fn terminals(&self) -> Vec<&StartupTerminal> {
+        match self {
+            Self::Terminal(terminal) => vec![terminal],
+            Self::Split(StartupSplit { first, second, .. }) => {
+                let mut terminals = first.terminals();
+                terminals.extend(second.terminals());
+                terminals
+            }
+        }
+    }
+
+    /// The panes for this, with the terminals started for it in the order
+    /// `terminals` gave them
+    fn pane(&self, ids: &mut impl Iterator<Item = u32>) -> Option<Pane> {
+        match self {
+            Self::Terminal(_) => Some(Pane::Terminal {
+                terminal_id: ids.next()?,
+            }),
+            Self::Split(StartupSplit {
+                split,
+                ratio,
+                first,
+                second,
+            }) => Some(Pane::Split {
+                direction: *split,
+                ratio: ratio.unwrap_or(0.5),
+                first: Box::new(first.pane(ids)?),
+                second: Box::new(second.pane(ids)?),
+            }),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Terminal(_) => Ok(()),
+            Self::Split(StartupSplit { ratio: Some(ratio), .. }) if !(*ratio > 0.0 && *ratio < 1.0) => Err(format!(
+                "a split's ratio in startupLayout has to be between 0 and 1, not {ratio}"
+            )),
+            Self::Split(StartupSplit { first, second, .. }) => {
+                first.validate()?;
+                second.validate()
+            }
+        }
+    }
+}
+
+/// A window in a startup layout, its tabs in order. Generic for the same
+/// reason as `StartupSplit`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StartupWindow<P = StartupPane> {
+    pub tabs: Vec<P>,
+}
+
+/// Check the layout config.js exports, now that serde's checked its types
+pub fn validate(windows: &[StartupWindow]) -> Result<(), String> {
+    if windows.is_empty() {
+        return Err("startupLayout needs a window, for the main one".to_string());
+    }
+
+    for window in windows {
+        if window.tabs.is_empty() {
+            return Err("every window in startupLayout needs a tab".to_string());
+        }
+
+        // a window only has the one set of panes, see `panes::Layout`
+        let splits = window
+            .tabs
+            .iter()
+            .filter(|tab| matches!(tab, StartupPane::Split(_)))
+            .count();
+        if splits > 1 {
+            return Err("only one tab in each window of startupLayout can be split".to_string());
+        }
+
+        for tab in &window.tabs {
+            tab.validate()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Start `terminal` for window `label`, returning its id
+async fn start(app: &AppHandle, state: &AppState, terminal: &StartupTerminal, label: &str) -> Result<u32, SteppeError> {
+    let cwd = terminal.cwd.as_deref().and_then(cwd::expand_home);
+    let terminal_id = start_terminal(app, terminal.profile.clone(), cwd, label).await?;
+
+    if let Some(command) = &terminal.command {
+        // the pty holds onto it until the shell's ready to read
+        write_input(app, state, terminal_id, &format!("{command}\r")).await?;
+    }
+
+    Ok(terminal_id)
+}
+
+/// Start `window`'s terminals for window `label`, splitting its panes the way
+/// it says. Returns the terminals in tab order.
+async fn open_window(
+    app: &AppHandle,
+    state: &AppState,
+    window: &StartupWindow,
+    label: &str,
+) -> Result<Vec<u32>, SteppeError> {
+    let mut terminal_ids = Vec::new();
+
+    for tab in &window.tabs {
+        let mut ids = Vec::new();
+        for terminal in tab.terminals() {
+            ids.push(start(app, state, terminal, label).await?);
+        }
+
+        if let (StartupPane::Split(_), Some(&focused)) = (tab, ids.first()) {
+            if let Some(root) = tab.pane(&mut ids.iter().copied()) {
+                panes::set_layout(state, label, Layout { root, focused }).await;
+            }
+        }
+        terminal_ids.extend(ids);
+    }
+
+    Ok(terminal_ids)
+}
+
+/// Open `windows`, the first in the main window and the rest in new ones.
+/// Returns the main window's part the way a restored session would be.
+async fn open(app: &AppHandle, state: &AppState, windows: &[StartupWindow]) -> Result<SavedSession, SteppeError> {
+    let Some((main, others)) = windows.split_first() else {
+        return Err(SteppeError::InvalidConfig("startupLayout has no windows".to_string()));
+    };
+
+    let mut terminals = Vec::new();
+    for terminal_id in open_window(app, state, main, MAIN_WINDOW).await? {
+        terminals.extend(session::saved_terminal(state, terminal_id).await);
+    }
+
+    // their terminals are running before they're built, so the new pages just show them
+    for window in others {
+        let config = window::new_config(app)?;
+        open_window(app, state, window, &config.label).await?;
+        WebviewWindowBuilder::from_config(app, &config)?.build()?;
+    }
+
+    let size = app
+        .get_webview_window(MAIN_WINDOW)
+        .map(|window| window.inner_size())
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(SavedSession {
+        terminals,
+        window_width: size.width,
+        window_height: size.height,
+        layout: panes::layout(state, MAIN_WINDOW).await,
+    })
+}
+
+/// The main window's part of the `startupLayout` setting, opening the whole
+/// layout the first time it's asked for. `None` if there isn't one.
+pub async fn restore(app: &AppHandle, state: &AppState) -> Result<Option<SavedSession>, SteppeError> {
+    let mut opened = state.startup_session.lock().await;
+
+    // a reload gets the terminals that were opened the first time
+    if opened.is_none() {
+        let Some(windows) = state.settings.lock().await.startup_layout.clone() else {
+            return Ok(None);
+        };
+        *opened = Some(open(app, state, &windows).await?);
+    }
+
+    Ok(opened.clone())
+}
@@ -0,0 +1,176 @@
+use std::{
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use tokio::process::Command;
+
+use crate::{error::SteppeError, AppState, SESSION_ID};
+
+/// OSC 133;A, which shells with prompt integration send right before drawing a prompt
+const PROMPT_START: &[u8] = b"\x1b]133;A";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupNotificationOptions {
+    /// Show a toast once the shell is ready, rather than just emitting `session-ready`
+    pub show: bool,
+    /// `{duration_ms}` gets replaced with how long startup took. Leave it out
+    /// for the default message
+    pub message: Option<String>,
+    /// How long the toast stays up
+    pub duration_ms: u64,
+}
+
+impl Default for StartupNotificationOptions {
+    fn default() -> Self {
+        Self {
+            show: false,
+            message: None,
+            duration_ms: 3000,
+        }
+    }
+}
+
+/// Finds a fixed byte sequence in a stream, even when it's split across reads.
+#[derive(Debug)]
+struct SequenceMatcher {
+    pattern: &'static [u8],
+    matched: usize,
+}
+
+impl SequenceMatcher {
+    fn feed(&mut self, data: &[u8]) -> bool {
+        for &byte in data {
+            if byte == self.pattern[self.matched] {
+                self.matched += 1;
+
+                if self.matched == self.pattern.len() {
+                    self.matched = 0;
+                    return true;
+                }
+            } else {
+                // fine since the pattern only starts with ESC once
+                self.matched = usize::from(byte == self.pattern[0]);
+            }
+        }
+
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct StartupNotification {
+    options: StartupNotificationOptions,
+    /// Set while we're still waiting on the first prompt
+    created_at: Option<Instant>,
+    prompt: SequenceMatcher,
+}
+
+impl Default for StartupNotification {
+    fn default() -> Self {
+        Self {
+            options: StartupNotificationOptions::default(),
+            created_at: None,
+            prompt: SequenceMatcher {
+                pattern: PROMPT_START,
+                matched: 0,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct SessionReady {
+    session_id: u32,
+    duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ShowToast {
+    pub message: String,
+    pub duration_ms: u64,
+}
+
+/// Start timing a new session, up until its first prompt.
+pub async fn session_created(state: &AppState) {
+    let mut notification = state.startup_notification.lock().await;
+    notification.created_at = Some(Instant::now());
+    notification.prompt.matched = 0;
+}
+
+/// Look for the first prompt in pty output
+pub async fn scan_output(app: &AppHandle, state: &AppState, data: &[u8]) -> Result<(), SteppeError> {
+    let mut notification = state.startup_notification.lock().await;
+
+    let Some(created_at) = notification.created_at else {
+        return Ok(());
+    };
+
+    if !notification.prompt.feed(data) {
+        return Ok(());
+    }
+
+    notification.created_at = None;
+    let duration_ms = created_at.elapsed().as_millis() as u64;
+
+    app.emit(
+        "session-ready",
+        SessionReady {
+            session_id: SESSION_ID,
+            duration_ms,
+        },
+    )?;
+
+    let options = &notification.options;
+    if options.show {
+        let message = match &options.message {
+            Some(template) => template.replace("{duration_ms}", &duration_ms.to_string()),
+            None => format!("shell ready in {duration_ms}ms"),
+        };
+
+        app.emit(
+            "show-toast",
+            ShowToast {
+                message,
+                duration_ms: options.duration_ms,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// How long `shell -i -c exit` takes, which is roughly how long the shell's
+/// rc files take to run.
+async fn measure_shell_startup_time(shell: &str) -> Result<Duration, SteppeError> {
+    let started = Instant::now();
+
+    Command::new(shell)
+        .args(["-i", "-c", "exit"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status()
+        .await?;
+
+    Ok(started.elapsed())
+}
+
+#[tauri::command]
+pub async fn async_set_session_startup_notification(
+    opts: StartupNotificationOptions,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    state.startup_notification.lock().await.options = opts;
+    Ok(())
+}
+
+/// In milliseconds
+#[tauri::command]
+pub async fn async_measure_shell_startup_time(shell: String) -> Result<u64, SteppeError> {
+    Ok(measure_shell_startup_time(&shell).await?.as_millis() as u64)
+}
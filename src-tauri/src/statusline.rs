@@ -0,0 +1,205 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+
+use crate::{cwd, error::SteppeError, ssh, AppState};
+
+/// Where a repo's at, as `git status` sees it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatus {
+    /// `None` when no branch is checked out
+    pub branch: Option<String>,
+    /// Whether anything's changed or untracked
+    pub dirty: bool,
+    /// Commits the upstream doesn't have yet, and the other way around
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+/// What a terminal's status bar shows, computed in the backend so the
+/// frontend doesn't have to poll git and friends itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusLine {
+    pub cwd: Option<PathBuf>,
+    /// Only for local shells, since ssh's and the like's cwd is somewhere else
+    pub git: Option<GitStatus>,
+    /// The last command's, going by shell integration
+    pub last_exit_code: Option<i32>,
+    /// `user@host` for ssh connections
+    pub ssh_host: Option<String>,
+    /// What config.js's and plugins' providers say, by their names
+    pub custom: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Serialize)]
+struct StatusLineChanged {
+    terminal_id: u32,
+    status_line: StatusLine,
+}
+
+/// `git status --porcelain=v2 --branch`, boiled down
+fn parse_git_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = (head != "(detached)").then(|| head.to_string());
+        } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+            let mut counts = counts.split(' ');
+            status.ahead = counts
+                .next()
+                .and_then(|ahead| ahead.trim_start_matches('+').parse().ok())
+                .unwrap_or(0);
+            status.behind = counts
+                .next()
+                .and_then(|behind| behind.trim_start_matches('-').parse().ok())
+                .unwrap_or(0);
+        } else if !line.starts_with('#') {
+            status.dirty = true;
+        }
+    }
+
+    status
+}
+
+/// The status of the repo `dir` is in, or `None` if it isn't in one (or
+/// there's no git)
+async fn git_status(dir: &Path) -> Option<GitStatus> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain=v2", "--branch"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| parse_git_status(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Make `change` to terminal `terminal_id`'s status line, sending
+/// `statusline://changed` if that changed anything
+async fn update(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    change: impl FnOnce(&mut StatusLine),
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let mut status_line = terminal.status_line.lock().await;
+
+    let before = status_line.clone();
+    change(&mut status_line);
+    if *status_line != before {
+        let status_line = status_line.clone();
+        app.emit(
+            "statusline://changed",
+            StatusLineChanged {
+                terminal_id,
+                status_line,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Work out the built-in parts of terminal `terminal_id`'s status line again
+pub async fn refresh(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let cwd = cwd::terminal_cwd(&terminal).await;
+    let git = match cwd::inherited_cwd(&terminal).await {
+        Some(dir) => git_status(&dir).await,
+        None => None,
+    };
+
+    let ssh_profile = terminal.shell.get().and_then(|shell| shell.ssh_profile.clone());
+    let ssh_host = match ssh_profile {
+        Some(name) => ssh::find_profile(state, &name)
+            .await
+            .ok()
+            .map(|profile| match profile.user {
+                Some(user) => format!("{user}@{}", profile.host),
+                None => profile.host,
+            }),
+        None => None,
+    };
+
+    update(app, state, terminal_id, |status_line| {
+        status_line.cwd = cwd;
+        status_line.git = git;
+        status_line.ssh_host = ssh_host;
+    })
+    .await
+}
+
+/// `refresh` in the background, for the reader and the like that shouldn't
+/// wait on git
+pub fn spawn_refresh(app: &AppHandle, terminal_id: u32) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = refresh(&app, &app.state::<AppState>(), terminal_id).await {
+            eprintln!("could not update the status line of terminal {terminal_id}: {err}");
+        }
+    });
+}
+
+/// Note the exit code of the command terminal `terminal_id` just finished,
+/// and look at everything else again since the command may have changed it
+pub fn command_finished(app: &AppHandle, terminal_id: u32, exit_code: Option<i32>) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let result = async {
+            update(&app, &state, terminal_id, |status_line| {
+                status_line.last_exit_code = exit_code
+            })
+            .await?;
+            refresh(&app, &state, terminal_id).await
+        };
+        if let Err(err) = result.await {
+            eprintln!("could not update the status line of terminal {terminal_id}: {err}");
+        }
+    });
+}
+
+/// Set (or with `None`, clear) what the provider called `name` shows in
+/// terminal `terminal_id`'s status line
+pub async fn set_custom(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    name: String,
+    text: Option<String>,
+) -> Result<(), SteppeError> {
+    update(app, state, terminal_id, |status_line| match text {
+        Some(text) => {
+            status_line.custom.insert(name, text);
+        }
+        None => {
+            status_line.custom.remove(&name);
+        }
+    })
+    .await
+}
+
+/// What terminal `terminal_id`'s status bar shows right now. It's sent as
+/// `statusline://changed` whenever that changes after.
+#[tauri::command]
+pub async fn async_get_status_line(terminal_id: u32, state: State<'_, AppState>) -> Result<StatusLine, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let status_line = terminal.status_line.lock().await.clone();
+
+    Ok(status_line)
+}
@@ -0,0 +1,685 @@
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+
+use deno_runtime::deno_core::{self, anyhow::anyhow, error::AnyError, op2, ModuleSpecifier, OpState};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime::Mutex as AsyncMutex, AppHandle, Emitter, Manager};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    appearance::{self, FontRenderingOptions},
+    buffers,
+    config_permissions::RunPermission,
+    crash, cursor, diagnostics, gpu, keybindings,
+    mouse::{self, MouseMode},
+    notifications,
+    opacity::{self, Easing},
+    output_batch::{self, BatchModeOptions},
+    output_log, process_icons,
+    renderer::{self, RendererBackend},
+    scrollback, shell_integration,
+    shell_profile::{self, ShellProfile},
+    size::{self, SizeConstraints},
+    spawn_terminal,
+    ssh::{self, SshProfile},
+    startup::{self, TerminalSequence},
+    startup_notification::{self, StartupNotificationOptions},
+    statusline,
+    termios::TermiosSettings,
+    url_history,
+    url_opener::{self, UrlOpenMode},
+    wallpaper,
+    window::MAIN_WINDOW,
+    write_limit::{self, WriteLimitOptions},
+    AppState,
+};
+
+/// How much output `steppe.onOutput` can fall behind before it starts missing some
+pub const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
+
+/// How many events `steppe.on` can fall behind before it starts missing some
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Pty output, as `steppe.onOutput` listeners get it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptOutput {
+    pub terminal_id: u32,
+    pub data: String,
+}
+
+/// Something that happened to a terminal, as `steppe.on` listeners get it,
+/// with `event` being the name they listened for
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum ScriptEvent {
+    #[serde(rename_all = "camelCase")]
+    TerminalOpened {
+        terminal_id: u32,
+        program: String,
+        /// The shell profile it was started from, if any
+        profile: Option<String>,
+        /// The ssh profile it's connected with, if it's an ssh connection
+        ssh_profile: Option<String>,
+        pid: Option<u32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    TerminalClosed { terminal_id: u32, exit_code: u32 },
+    /// Any command the shell integration saw finish, however long it took
+    #[serde(rename_all = "camelCase")]
+    CommandFinished {
+        terminal_id: u32,
+        command: Option<String>,
+        cwd: Option<PathBuf>,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    TitleChanged { terminal_id: u32, title: String },
+}
+
+/// Let config.js's `steppe.on` listeners know about `event`, if there are any
+pub fn emit_event(state: &AppState, event: ScriptEvent) {
+    if state.script_events.receiver_count() > 0 {
+        let _ = state.script_events.send(event);
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpawnOptions {
+    /// A profile from `async_set_shell_profiles`, rather than the default shell
+    profile: Option<String>,
+    /// Where to start the default shell, since profiles have their own
+    cwd: Option<PathBuf>,
+}
+
+/// Lazily subscribed, so output doesn't pile up for a config that never listens
+struct OutputReceiver(Rc<AsyncMutex<broadcast::Receiver<ScriptOutput>>>);
+
+/// Same for events
+struct EventReceiver(Rc<AsyncMutex<broadcast::Receiver<ScriptEvent>>>);
+
+fn app(state: &OpState) -> AppHandle {
+    state.borrow::<AppHandle>().clone()
+}
+
+/// Ops that start programs do it outside deno's own permission checks, so
+/// they're held to permissions.json's `run` here
+fn check_run(state: &OpState, program: &str) -> Result<(), AnyError> {
+    let run = state.borrow::<RunPermission>();
+    run.check(program).map_err(|err| anyhow!(err))
+}
+
+/// Same, for what's as good as running anything
+fn check_run_all(state: &OpState, what: &str) -> Result<(), AnyError> {
+    let run = state.borrow::<RunPermission>();
+    run.check_all(what).map_err(|err| anyhow!(err))
+}
+
+/// `steppe.setTheme({ "--background": "#000", ... })`
+#[op2]
+fn op_steppe_set_theme(state: &mut OpState, #[serde] vars: HashMap<String, String>) -> Result<(), AnyError> {
+    if let Some(name) = vars.keys().find(|name| !name.starts_with("--")) {
+        return Err(anyhow!("{name} isn't a css variable, they start with --"));
+    }
+
+    app(state).emit("css-vars-changed", vars)?;
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+struct PluginError {
+    path: String,
+    message: String,
+}
+
+/// A plugin threw while being turned on or off
+#[op2]
+fn op_steppe_plugin_error(state: &mut OpState, #[string] url: String, #[string] message: String) -> Result<(), AnyError> {
+    let path = ModuleSpecifier::parse(&url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .map_or(url, |path| path.display().to_string());
+
+    app(state).emit("plugin://error", PluginError { path, message })?;
+    Ok(())
+}
+
+/// `steppe.bind("ctrl+shift+t", "new_tab")`. Callbacks are kept on the js
+/// side and bound here as a script action.
+#[op2(async)]
+async fn op_steppe_bind_key(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+    #[string] action: String,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    let state = app.state::<AppState>();
+
+    let mut keybindings = state.keybindings.lock().await;
+    keybindings.bind(&key, action)?;
+    keybindings::emit_changed(&app, &keybindings)?;
+
+    Ok(())
+}
+
+/// `steppe.addTrigger({ pattern: /error:/i, action: "notify" })`, with the
+/// regex taken apart into its source and flags. Callbacks are kept on the js
+/// side, same as keybindings.
+#[op2(async)]
+async fn op_steppe_add_trigger(
+    state: Rc<RefCell<OpState>>,
+    #[string] pattern: String,
+    #[string] flags: String,
+    #[string] action: String,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    app.state::<AppState>().triggers.lock().await.add(&pattern, &flags, action)?;
+
+    Ok(())
+}
+
+/// `steppe.addCommand({ label: "deploy", action: "new_tab" })`. Callbacks are
+/// bound as a script action, same as keybindings.
+#[op2(async)]
+async fn op_steppe_add_command(
+    state: Rc<RefCell<OpState>>,
+    #[string] label: String,
+    #[string] action: String,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    app.state::<AppState>().palette_commands.lock().await.add(label, action);
+
+    Ok(())
+}
+
+/// Wait for the next bit of output from any terminal
+#[op2(async)]
+#[serde]
+async fn op_steppe_next_output(state: Rc<RefCell<OpState>>) -> Result<ScriptOutput, AnyError> {
+    let receiver = {
+        let mut state = state.borrow_mut();
+        if !state.has::<OutputReceiver>() {
+            let receiver = app(&state).state::<AppState>().script_output.subscribe();
+            state.put(OutputReceiver(Rc::new(AsyncMutex::new(receiver))));
+        }
+
+        state.borrow::<OutputReceiver>().0.clone()
+    };
+    let mut receiver = receiver.lock().await;
+
+    loop {
+        match receiver.recv().await {
+            Ok(output) => return Ok(output),
+            // a slow listener just misses what it couldn't keep up with
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Err(anyhow!("steppe is shutting down")),
+        }
+    }
+}
+
+/// Wait for the next `steppe.on` event from any terminal
+#[op2(async)]
+#[serde]
+async fn op_steppe_next_event(state: Rc<RefCell<OpState>>) -> Result<ScriptEvent, AnyError> {
+    let receiver = {
+        let mut state = state.borrow_mut();
+        if !state.has::<EventReceiver>() {
+            let receiver = app(&state).state::<AppState>().script_events.subscribe();
+            state.put(EventReceiver(Rc::new(AsyncMutex::new(receiver))));
+        }
+
+        state.borrow::<EventReceiver>().0.clone()
+    };
+    let mut receiver = receiver.lock().await;
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return Ok(event),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Err(anyhow!("steppe is shutting down")),
+        }
+    }
+}
+
+/// What a `steppe.addStatusProvider` provider says for a terminal, or with
+/// `null`, that it has nothing to say
+#[op2(async)]
+async fn op_steppe_set_status(
+    state: Rc<RefCell<OpState>>,
+    terminal_id: u32,
+    #[string] name: String,
+    #[serde] text: Option<String>,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    statusline::set_custom(&app, &app.state::<AppState>(), terminal_id, name, text).await?;
+
+    Ok(())
+}
+
+/// Start a shell in a new terminal, returning its id
+#[op2(async)]
+async fn op_steppe_spawn_terminal(
+    state: Rc<RefCell<OpState>>,
+    #[serde] options: Option<SpawnOptions>,
+) -> Result<u32, AnyError> {
+    let app = app(&state.borrow());
+    let options = options.unwrap_or_default();
+
+    // the default shell is what any new tab runs, but a profile could be anything
+    if let Some(name) = &options.profile {
+        let profiles = app.state::<AppState>().shell_profiles.lock().await.clone();
+        if let Some(profile) = profiles.iter().find(|profile| &profile.name == name) {
+            check_run(&state.borrow(), &profile.command)?;
+        }
+    }
+
+    Ok(spawn_terminal(&app, options.profile, options.cwd, MAIN_WINDOW).await?)
+}
+
+// The rest just pass through to the tauri commands of the same name, so
+// config.js can set up what the frontend otherwise would
+
+/// `steppe.setPtyLineDisc({ echo, canonicalMode, ... })`
+#[op2(async)]
+async fn op_steppe_set_pty_line_disc(
+    state: Rc<RefCell<OpState>>,
+    #[serde] settings: TermiosSettings,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    crate::async_set_pty_line_disc(settings, app.state()).await?;
+
+    Ok(())
+}
+
+/// `steppe.setUrlOpenMode("browser")`
+#[op2(async)]
+async fn op_steppe_set_url_open_mode(state: Rc<RefCell<OpState>>, #[serde] mode: UrlOpenMode) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    url_opener::async_set_url_open_mode(mode, app.state()).await?;
+
+    Ok(())
+}
+
+/// `steppe.setCustomUrlOpener("firefox", ["--new-tab"])`
+#[op2(async)]
+async fn op_steppe_set_custom_url_opener(
+    state: Rc<RefCell<OpState>>,
+    #[string] command: String,
+    #[serde] args: Vec<String>,
+) -> Result<(), AnyError> {
+    check_run(&state.borrow(), &command)?;
+    let app = app(&state.borrow());
+    url_opener::async_set_custom_url_opener(command, args, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_allow_file_urls(state: Rc<RefCell<OpState>>, allow: bool) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    url_opener::async_set_allow_file_urls(allow, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_url_history_enabled(state: Rc<RefCell<OpState>>, enabled: bool) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    url_history::async_set_url_history_enabled(enabled, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_crash_reporting(enabled: bool, #[serde] endpoint: Option<String>) -> Result<(), AnyError> {
+    crash::async_set_crash_reporting(enabled, endpoint).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_renderer_backend(
+    state: Rc<RefCell<OpState>>,
+    #[serde] backend: RendererBackend,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    renderer::async_set_renderer_backend(backend, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_session_size_constraints(
+    state: Rc<RefCell<OpState>>,
+    #[serde] constraints: SizeConstraints,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    size::async_set_session_size_constraints(constraints, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_expose_secrets_in_diagnostics(
+    state: Rc<RefCell<OpState>>,
+    expose: bool,
+) -> Result<(), AnyError> {
+    // the diagnostics bundle's easy to get hold of, and plugins can call this too
+    if expose {
+        check_run_all(&state.borrow(), "exposing secrets in diagnostics")?;
+    }
+    let app = app(&state.borrow());
+    diagnostics::async_set_expose_secrets_in_diagnostics(expose, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_font_rendering(
+    state: Rc<RefCell<OpState>>,
+    #[serde] opts: FontRenderingOptions,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    appearance::async_set_font_rendering(opts, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_startup_delay(state: Rc<RefCell<OpState>>, #[serde] ms: u64) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    startup::async_set_startup_delay(ms, app.state()).await?;
+
+    Ok(())
+}
+
+/// `steppe.setWriteRateLimit({ maxBytesPerSecond, burstBytes })`, or `null` for no limit
+#[op2(async)]
+async fn op_steppe_set_write_rate_limit(
+    state: Rc<RefCell<OpState>>,
+    #[serde] opts: Option<WriteLimitOptions>,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    write_limit::async_set_write_rate_limit(opts, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_startup_sequences(
+    state: Rc<RefCell<OpState>>,
+    #[serde] sequences: Vec<TerminalSequence>,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    startup::async_set_startup_sequences(sequences, app.state()).await?;
+
+    Ok(())
+}
+
+/// `await steppe.waitForCommand("docker", ["info"], 10000)`, for holding off
+/// on the rest of config.js until something's up
+#[op2(async)]
+async fn op_steppe_wait_for_command(
+    state: Rc<RefCell<OpState>>,
+    #[string] cmd: String,
+    #[serde] args: Vec<String>,
+    #[serde] timeout_ms: u64,
+) -> Result<(), AnyError> {
+    check_run(&state.borrow(), &cmd)?;
+    startup::async_wait_for_command(cmd, args, timeout_ms).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_cursor_colors(
+    state: Rc<RefCell<OpState>>,
+    #[string] fg: String,
+    #[string] bg: String,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    cursor::async_set_cursor_colors(fg, bg, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+/// What a `steppe.registerPasteFromBuffer` keybinding runs
+#[op2(async)]
+async fn op_steppe_paste_from_buffer(state: Rc<RefCell<OpState>>, #[string] name: String) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    buffers::async_paste_from_buffer(name, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_start_gpu_monitoring(
+    state: Rc<RefCell<OpState>>,
+    #[serde] interval_ms: u64,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    gpu::async_start_gpu_monitoring(interval_ms, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_line_height(state: Rc<RefCell<OpState>>, value: f64) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    appearance::async_set_line_height(value, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_cell_spacing(
+    state: Rc<RefCell<OpState>>,
+    horizontal: i32,
+    vertical: i32,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    appearance::async_set_cell_spacing(horizontal, vertical, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_session_startup_notification(
+    state: Rc<RefCell<OpState>>,
+    #[serde] opts: StartupNotificationOptions,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    startup_notification::async_set_session_startup_notification(opts, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_mouse_report_mode(
+    state: Rc<RefCell<OpState>>,
+    terminal_id: u32,
+    #[serde] mode: MouseMode,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    mouse::async_set_mouse_report_mode(terminal_id, mode, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_focus_opacity(
+    state: Rc<RefCell<OpState>>,
+    focused: f64,
+    unfocused: f64,
+    #[serde] animation_duration_ms: u64,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    opacity::async_set_focus_opacity(focused, unfocused, animation_duration_ms, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_focus_opacity_easing(
+    state: Rc<RefCell<OpState>>,
+    #[serde] easing: Easing,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    opacity::async_set_focus_opacity_easing(easing, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_process_icon_map(
+    state: Rc<RefCell<OpState>>,
+    #[serde] map: Vec<(String, String)>,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    process_icons::async_set_process_icon_map(map, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_scrollback_lines(state: Rc<RefCell<OpState>>, #[serde] lines: usize) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    scrollback::async_set_scrollback_lines(lines, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_output_batch_mode(
+    state: Rc<RefCell<OpState>>,
+    #[serde] opts: BatchModeOptions,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    output_batch::async_set_output_batch_mode(opts, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+/// The wallpaper's most common colors, as hex strings
+#[op2(async)]
+#[serde]
+async fn op_steppe_extract_wallpaper_palette(state: Rc<RefCell<OpState>>) -> Result<Vec<String>, AnyError> {
+    let app = app(&state.borrow());
+
+    Ok(wallpaper::async_extract_wallpaper_palette(app.state()).await?)
+}
+
+#[op2(async)]
+async fn op_steppe_set_ssh_profiles(
+    state: Rc<RefCell<OpState>>,
+    #[serde] profiles: Vec<SshProfile>,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    ssh::async_set_ssh_profiles(profiles, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_shell_profiles(
+    state: Rc<RefCell<OpState>>,
+    #[serde] profiles: Vec<ShellProfile>,
+) -> Result<(), AnyError> {
+    for profile in &profiles {
+        check_run(&state.borrow(), &profile.command)?;
+    }
+    let app = app(&state.borrow());
+    shell_profile::async_set_shell_profiles(profiles, app.clone(), app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_long_command_threshold(state: Rc<RefCell<OpState>>, #[serde] ms: u64) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    shell_integration::async_set_long_command_threshold(ms, app.state()).await?;
+
+    Ok(())
+}
+
+#[op2(async)]
+async fn op_steppe_set_terminal_notifications(
+    state: Rc<RefCell<OpState>>,
+    terminal_id: u32,
+    escape_sequences: bool,
+    bell: bool,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    notifications::async_set_terminal_notifications(terminal_id, escape_sequences, bell, app.state()).await?;
+
+    Ok(())
+}
+
+/// `steppe.setOutputLogging("~/logs", { stripAnsi: true })`, or `null` to stop
+#[op2(async)]
+async fn op_steppe_set_output_logging(
+    state: Rc<RefCell<OpState>>,
+    #[serde] dir: Option<PathBuf>,
+    #[serde] strip_ansi: Option<bool>,
+    #[serde] max_bytes: Option<u64>,
+) -> Result<(), AnyError> {
+    let app = app(&state.borrow());
+    output_log::async_set_output_logging(dir, strip_ansi, max_bytes, app.state()).await?;
+
+    Ok(())
+}
+
+deno_core::extension!(
+    steppe,
+    ops = [
+        op_steppe_set_theme,
+        op_steppe_plugin_error,
+        op_steppe_bind_key,
+        op_steppe_add_trigger,
+        op_steppe_add_command,
+        op_steppe_next_output,
+        op_steppe_next_event,
+        op_steppe_set_status,
+        op_steppe_spawn_terminal,
+        op_steppe_set_pty_line_disc,
+        op_steppe_set_url_open_mode,
+        op_steppe_set_custom_url_opener,
+        op_steppe_set_allow_file_urls,
+        op_steppe_set_url_history_enabled,
+        op_steppe_set_crash_reporting,
+        op_steppe_set_renderer_backend,
+        op_steppe_set_session_size_constraints,
+        op_steppe_set_expose_secrets_in_diagnostics,
+        op_steppe_set_font_rendering,
+        op_steppe_set_startup_delay,
+        op_steppe_set_write_rate_limit,
+        op_steppe_set_startup_sequences,
+        op_steppe_wait_for_command,
+        op_steppe_set_cursor_colors,
+        op_steppe_paste_from_buffer,
+        op_steppe_start_gpu_monitoring,
+        op_steppe_set_line_height,
+        op_steppe_set_cell_spacing,
+        op_steppe_set_session_startup_notification,
+        op_steppe_set_mouse_report_mode,
+        op_steppe_set_focus_opacity,
+        op_steppe_set_focus_opacity_easing,
+        op_steppe_set_process_icon_map,
+        op_steppe_set_scrollback_lines,
+        op_steppe_set_output_batch_mode,
+        op_steppe_extract_wallpaper_palette,
+        op_steppe_set_ssh_profiles,
+        op_steppe_set_shell_profiles,
+        op_steppe_set_long_command_threshold,
+        op_steppe_set_terminal_notifications,
+        op_steppe_set_output_logging
+    ],
+    esm_entry_point = "ext:steppe/steppe.js",
+    esm = [dir "src/js", "steppe.js"],
+    options = { app: AppHandle, run: RunPermission },
+    state = |state, options| {
+        state.put(options.app);
+        state.put(options.run);
+    },
+);
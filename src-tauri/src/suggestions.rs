@@ -0,0 +1,166 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::{command_history::HistoryEntry, cwd, error::SteppeError, AppState};
+
+/// How many history entries are looked at before ranking, newest first
+const HISTORY_CANDIDATES: usize = 500;
+
+/// How many suggestions come back when the caller doesn't say
+const DEFAULT_LIMIT: usize = 10;
+
+/// A command run in the directory the shell's in counts for this many runs elsewhere
+const SAME_CWD_WEIGHT: f64 = 4.0;
+
+/// How long it takes for a run to count half as much
+const HALF_LIFE_MS: f64 = 7.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionSource {
+    History,
+    /// A program on `PATH`, for the first word of a command
+    Path,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    /// The whole command, prefix included
+    pub command: String,
+    pub source: SuggestionSource,
+}
+
+/// How much a history entry is worth: every run counts, recent ones more,
+/// and ones in the same directory more still
+fn score(entry: &HistoryEntry, cwd: Option<&Path>, now_ms: u64) -> f64 {
+    let age_ms = now_ms.saturating_sub(entry.timestamp_ms) as f64;
+    let recency = 0.5f64.powf(age_ms / HALF_LIFE_MS);
+    let same_cwd = cwd.is_some() && entry.cwd.as_deref() == cwd;
+
+    entry.runs as f64 * recency * if same_cwd { SAME_CWD_WEIGHT } else { 1.0 }
+}
+
+/// Commands from `entries` that start with `prefix` and have more to them,
+/// best first. A command run in a few directories is scored once, with all
+/// of its runs.
+fn rank_history(entries: &[HistoryEntry], prefix: &str, cwd: Option<&Path>) -> Vec<String> {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.command.len() > prefix.len()) {
+        *scores.entry(&entry.command).or_default() += score(entry, cwd, now_ms);
+    }
+
+    let mut ranked: Vec<(&str, f64)> = scores.into_iter().collect();
+    // shorter commands first when they score the same, since they're less of a guess
+    ranked.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then(a.len().cmp(&b.len())).then(a.cmp(b)));
+
+    ranked.into_iter().map(|(command, _)| command.to_string()).collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| ["exe", "bat", "cmd", "com"].contains(&extension.to_lowercase().as_str()))
+}
+
+/// Programs on steppe's `PATH` whose names start with `prefix`, shortest
+/// first. It's the `PATH` local shells start with, before their rc files
+/// have had a go at it.
+fn path_programs(prefix: &str) -> Vec<String> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+
+    let mut programs = BTreeSet::new();
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.len() > prefix.len() && name.starts_with(prefix) && is_executable(&entry.path()) {
+                programs.insert(name);
+            }
+        }
+    }
+
+    let mut programs: Vec<String> = programs.into_iter().collect();
+    programs.sort_by_key(String::len);
+    programs
+}
+
+/// Ways to finish the command being typed in terminal `terminal_id`, best
+/// first: commands from the history, then (while it's still the first word)
+/// programs on `PATH`. `prefix` is what's typed so far, or what shell
+/// integration saw typed at the prompt if it's not given.
+#[tauri::command]
+pub async fn async_get_suggestions(
+    terminal_id: u32,
+    prefix: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Suggestion>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let prefix = match prefix {
+        Some(prefix) => prefix,
+        None => terminal.shell_integration.lock().await.input().unwrap_or_default(),
+    };
+    // a leading space keeps a command out of the history, so there's nothing to suggest for it
+    if prefix.trim().is_empty() || prefix.starts_with(' ') {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+
+    let cwd = cwd::terminal_cwd(&terminal).await;
+    let database = state.command_history.lock().await.database.clone();
+    let entries = database.search(prefix.clone(), None, HISTORY_CANDIDATES).await?;
+
+    let mut suggestions: Vec<Suggestion> = rank_history(&entries, &prefix, cwd.as_deref())
+        .into_iter()
+        .map(|command| Suggestion {
+            command,
+            source: SuggestionSource::History,
+        })
+        .collect();
+
+    // steppe's PATH only says what's on this machine
+    let local = terminal.shell.get().is_some_and(|shell| {
+        shell.ssh_profile.is_none()
+            && shell.wsl_distro.is_none()
+            && shell.docker_container.is_none()
+            && shell.k8s_shell.is_none()
+    });
+    if suggestions.len() < limit && local && !prefix.contains(char::is_whitespace) {
+        for program in path_programs(&prefix) {
+            if !suggestions.iter().any(|suggestion| suggestion.command == program) {
+                suggestions.push(Suggestion {
+                    command: program,
+                    source: SuggestionSource::Path,
+                });
+            }
+        }
+    }
+
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
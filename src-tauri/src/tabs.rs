@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State, Window};
+
+use crate::{
+    cwd,
+    error::SteppeError,
+    signal::{self, Signal},
+    spawn_terminal, tmux, window, AppState,
+};
+
+/// What the tab bar shows, kept here so it's still right after the page reloads
+#[derive(Debug, Default)]
+pub struct Tabs {
+    /// Terminals in the order their tabs go, across every window
+    order: Vec<u32>,
+    /// Names given with `async_rename_tab`, which win over the terminal's own title
+    names: HashMap<u32, String>,
+    /// Hung up, but the shell might not have exited yet
+    closed: HashSet<u32>,
+}
+
+impl Tabs {
+    /// The open `terminals` in tab order, with any that haven't got a place yet
+    /// (like the ones the frontend opened itself) at the end in the order they opened
+    fn arrange(&mut self, mut terminals: Vec<u32>) -> Vec<u32> {
+        self.closed.retain(|id| terminals.contains(id));
+        terminals.retain(|id| !self.closed.contains(id));
+
+        self.order.retain(|id| terminals.contains(id));
+        terminals.sort_unstable();
+        for id in terminals {
+            if !self.order.contains(&id) {
+                self.order.push(id);
+            }
+        }
+
+        self.order.clone()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tab {
+    pub terminal_id: u32,
+    /// Where it is in its window's tab bar, from 0
+    pub index: usize,
+    /// The name it was given, otherwise whatever the shell set, otherwise the shell
+    pub title: String,
+    pub profile: Option<String>,
+    pub cwd: Option<PathBuf>,
+}
+
+/// The terminals shown in window `label`, in tab order
+pub async fn ordered(state: &AppState, label: &str) -> Vec<u32> {
+    let open: Vec<u32> = state.terminals.lock().await.keys().copied().collect();
+    let order = state.tabs.lock().await.arrange(open);
+    let in_window = window::terminals_in(state, label).await;
+
+    order.into_iter().filter(|id| in_window.contains(id)).collect()
+}
+
+async fn list(state: &AppState, label: &str) -> Vec<Tab> {
+    let names = state.tabs.lock().await.names.clone();
+    let foreground = state.foreground_process.lock().await.clone();
+
+    let mut tabs = Vec::new();
+    for terminal_id in ordered(state, label).await {
+        let Ok(terminal) = state.terminal(terminal_id).await else {
+            continue;
+        };
+        let shell = terminal.shell.get();
+
+        let title = match names.get(&terminal_id) {
+            Some(name) => name.clone(),
+            None => match terminal.shell_integration.lock().await.title() {
+                Some(title) => title.to_string(),
+                // without a title from the shell, whatever it's running says more than the shell does
+                None => match (shell, foreground.get(&terminal_id)) {
+                    (Some(shell), Some(process)) if shell.pid != Some(process.pid) => process.name.clone(),
+                    (Some(shell), _) => shell.program.clone(),
+                    (None, _) => format!("terminal {terminal_id}"),
+                },
+            },
+        };
+
+        tabs.push(Tab {
+            terminal_id,
+            index: tabs.len(),
+            title,
+            profile: shell.and_then(|shell| shell.shell_profile.clone()),
+            cwd: cwd::terminal_cwd(&terminal).await,
+        });
+    }
+
+    tabs
+}
+
+/// Send window `label` its tabs, for after they've been added to, moved around or renamed
+pub async fn emit_changed(app: &AppHandle, state: &AppState, label: &str) -> Result<(), SteppeError> {
+    app.emit_to(label, "tabs://changed", list(state, label).await)?;
+    Ok(())
+}
+
+/// Open a tab at the end of the calling window's tab bar, running `profile`
+/// or the default shell in `cwd`
+#[tauri::command]
+pub async fn async_create_tab(
+    profile: Option<String>,
+    cwd: Option<PathBuf>,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Tab, SteppeError> {
+    let terminal_id = spawn_terminal(&app, profile, cwd, window.label()).await?;
+    emit_changed(&app, &state, window.label()).await?;
+
+    list(&state, window.label())
+        .await
+        .into_iter()
+        .find(|tab| tab.terminal_id == terminal_id)
+        .ok_or(SteppeError::SessionNotFound(terminal_id))
+}
+
+/// Hang up on tab `terminal_id`'s shell and take the tab away, sending its
+/// window `tab://closed` so the terminal goes too
+pub async fn close(app: &AppHandle, state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let label = window::window_of(state, terminal_id).await;
+
+    {
+        let mut tabs = state.tabs.lock().await;
+        tabs.order.retain(|id| *id != terminal_id);
+        tabs.names.remove(&terminal_id);
+        tabs.closed.insert(terminal_id);
+    }
+
+    // tmux's panes have nothing here to hang up on, tmux closes them instead
+    if !tmux::kill_pane(state, terminal_id).await? {
+        signal::send(terminal_id, &terminal, Signal::Sighup).await?;
+    }
+
+    app.emit_to(&label, "tab://closed", terminal_id)?;
+    emit_changed(app, state, &label).await
+}
+
+#[tauri::command]
+pub async fn async_close_tab(terminal_id: u32, app: AppHandle, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    close(&app, &state, terminal_id).await
+}
+
+/// The calling window's tabs, in order
+#[tauri::command]
+pub async fn async_list_tabs(window: Window, state: State<'_, AppState>) -> Result<Vec<Tab>, SteppeError> {
+    Ok(list(&state, window.label()).await)
+}
+
+/// Name tab `terminal_id`, or go back to the terminal's own title for `None`
+/// (or nothing but whitespace)
+pub async fn set_name(state: &AppState, terminal_id: u32, title: Option<String>) {
+    let mut tabs = state.tabs.lock().await;
+    match title.filter(|title| !title.trim().is_empty()) {
+        Some(title) => tabs.names.insert(terminal_id, title),
+        None => tabs.names.remove(&terminal_id),
+    };
+}
+
+#[tauri::command]
+pub async fn async_rename_tab(
+    terminal_id: u32,
+    title: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    state.terminal(terminal_id).await?;
+    set_name(&state, terminal_id, title).await;
+
+    emit_changed(&app, &state, &window::window_of(&state, terminal_id).await).await
+}
+
+/// Move tab `terminal_id` to `index` in its window's tab bar, or to the end
+/// if that's past it
+#[tauri::command]
+pub async fn async_move_tab(
+    terminal_id: u32,
+    index: usize,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let label = window::window_of(&state, terminal_id).await;
+    let mut siblings = ordered(&state, &label).await;
+
+    let Some(from) = siblings.iter().position(|id| *id == terminal_id) else {
+        return Err(SteppeError::SessionNotFound(terminal_id));
+    };
+    siblings.remove(from);
+    siblings.insert(index.min(siblings.len()), terminal_id);
+
+    {
+        // the other windows' tabs keep their places, this window's take the
+        // spots its tabs had between them
+        let in_window: HashSet<u32> = siblings.iter().copied().collect();
+        let mut siblings = siblings.into_iter();
+        let mut tabs = state.tabs.lock().await;
+        for id in tabs.order.iter_mut().filter(|id| in_window.contains(id)) {
+            if let Some(sibling) = siblings.next() {
+                *id = sibling;
+            }
+        }
+    }
+
+    emit_changed(&app, &state, &label).await
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State, Window};
+
+use crate::{cwd, error::SteppeError, spawn_terminal, tabs, write_input, AppState, SubTerminal};
+
+/// A command config.js can run in a terminal of its own by name, like
+/// `{ name: "dev", command: "npm run dev", cwd: "~/proj" }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    /// What the tab's called, and what `async_run_task` takes
+    pub name: String,
+    /// Typed into the default shell, so it's run the way it would be by hand
+    pub command: String,
+    /// Where the shell starts, with `~` for the home directory
+    pub cwd: Option<String>,
+    /// Open a new terminal every time, rather than running it again in the
+    /// one it last ran in while that's still open
+    #[serde(default)]
+    pub new_terminal: bool,
+}
+
+/// The terminal each task last ran in, by task name
+#[derive(Debug, Default)]
+pub struct TaskTerminals {
+    terminals: HashMap<String, (u32, Weak<SubTerminal>)>,
+}
+
+/// Check the tasks config.js exports, now that serde's checked their types
+pub fn validate(tasks: &[Task]) -> Result<(), String> {
+    for (index, task) in tasks.iter().enumerate() {
+        if task.name.trim().is_empty() {
+            return Err("every task needs a name".to_string());
+        }
+        if task.command.trim().is_empty() {
+            return Err(format!("task {:?} has no command", task.name));
+        }
+        if tasks[..index].iter().any(|other| other.name == task.name) {
+            return Err(format!("there's more than one task named {:?}", task.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// The terminal `name` last ran in, if it's still open. Its id alone could
+/// belong to a newer terminal by now.
+async fn reusable(state: &AppState, name: &str) -> Option<u32> {
+    let (terminal_id, last) = state.task_terminals.lock().await.terminals.get(name).cloned()?;
+    let terminal = state.terminal(terminal_id).await.ok()?;
+
+    Weak::ptr_eq(&last, &Arc::downgrade(&terminal)).then_some(terminal_id)
+}
+
+/// Run task `name`, in window `label` if it needs a new terminal. Returns
+/// the terminal it's running in.
+pub async fn run(app: &AppHandle, state: &AppState, name: &str, label: &str) -> Result<u32, SteppeError> {
+    let task = state
+        .settings
+        .lock()
+        .await
+        .tasks
+        .iter()
+        .flatten()
+        .find(|task| task.name == name)
+        .cloned()
+        .ok_or_else(|| SteppeError::TaskNotFound(name.to_string()))?;
+
+    let reused = match task.new_terminal {
+        true => None,
+        false => reusable(state, name).await,
+    };
+    let terminal_id = match reused {
+        Some(terminal_id) => terminal_id,
+        None => {
+            let terminal_id = spawn_terminal(app, None, task.cwd.as_deref().and_then(cwd::expand_home), label).await?;
+            tabs::set_name(state, terminal_id, Some(task.name.clone())).await;
+            tabs::emit_changed(app, state, label).await?;
+
+            let terminal = state.terminal(terminal_id).await?;
+            state
+                .task_terminals
+                .lock()
+                .await
+                .terminals
+                .insert(task.name.clone(), (terminal_id, Arc::downgrade(&terminal)));
+            terminal_id
+        }
+    };
+
+    // the pty holds onto it until the shell's ready to read
+    write_input(app, state, terminal_id, &format!("{}\r", task.command)).await?;
+
+    Ok(terminal_id)
+}
+
+/// The tasks config.js exports
+#[tauri::command]
+pub async fn async_list_tasks(state: State<'_, AppState>) -> Result<Vec<Task>, SteppeError> {
+    Ok(state.settings.lock().await.tasks.clone().unwrap_or_default())
+}
+
+/// Run task `name` again in the terminal it last ran in, or in a new one in
+/// the calling window. Returns the terminal it's running in.
+#[tauri::command]
+pub async fn async_run_task(
+    name: String,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<u32, SteppeError> {
+    run(&app, &state, &name, window.label()).await
+}
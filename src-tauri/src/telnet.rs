@@ -0,0 +1,512 @@
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use tauri::{async_runtime, AppHandle};
+
+use crate::{
+    connections, crash,
+    error::SteppeError,
+    event_log::{self, SessionEventKind},
+    identity, reader,
+    ssh::SshProfile,
+    terminal_exited, AppState, ShellProcess, SubTerminal,
+};
+
+/// Where hosts listen for telnet unless the profile says otherwise
+const DEFAULT_PORT: u16 = 23;
+
+// RFC 854's commands
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+// the options steppe goes along with, anything else is refused
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const TERMINAL_TYPE: u8 = 24;
+const NAWS: u8 = 31;
+
+/// TERMINAL-TYPE's `IS` and `SEND` (RFC 1091)
+const TTYPE_IS: u8 = 0;
+const TTYPE_SEND: u8 = 1;
+
+/// Longer than any subnegotiation steppe answers, so a host can't make it
+/// hold on to an endless one
+const MAX_SUBNEGOTIATION_LEN: usize = 256;
+
+/// A terminal's telnet connection, for what goes to the host besides typing:
+/// answers to its option negotiation, and the window size
+pub struct Telnet {
+    stream: Mutex<TcpStream>,
+    /// What the host's told for TERMINAL-TYPE
+    term: String,
+    /// Whether the host asked to be told the window size (NAWS, RFC 1073)
+    naws: AtomicBool,
+    /// The terminal's rows and columns, for whenever NAWS is turned on
+    size: Mutex<(u16, u16)>,
+}
+
+impl Telnet {
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        self.stream
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .write_all(bytes)
+    }
+
+    fn send_size(&self) -> io::Result<()> {
+        let (rows, cols) = *self.size.lock().unwrap_or_else(|err| err.into_inner());
+
+        let mut bytes = vec![IAC, SB, NAWS];
+        for byte in cols.to_be_bytes().into_iter().chain(rows.to_be_bytes()) {
+            bytes.push(byte);
+            // a 255 in the size is data, which gets doubled like anywhere else
+            if byte == IAC {
+                bytes.push(IAC);
+            }
+        }
+        bytes.extend([IAC, SE]);
+
+        self.send(&bytes)
+    }
+}
+
+/// Typing, with IAC doubled so the host doesn't take it for a command, and
+/// a bare return followed by NUL, the way telnet wants it
+struct TelnetWriter(Arc<Telnet>);
+
+impl Write for TelnetWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut bytes = Vec::with_capacity(buf.len());
+        for (index, &byte) in buf.iter().enumerate() {
+            bytes.push(byte);
+            match byte {
+                IAC => bytes.push(IAC),
+                b'\r' if buf.get(index + 1) != Some(&b'\n') => bytes.push(0),
+                _ => {}
+            }
+        }
+
+        self.0.send(&bytes)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum Scan {
+    #[default]
+    Data,
+    /// Just after an IAC
+    Command,
+    /// After IAC and WILL, WONT, DO or DONT, waiting on which option
+    Option(u8),
+    Subnegotiation,
+    /// An IAC in a subnegotiation, which either ends it or is a doubled one
+    SubnegotiationCommand,
+}
+
+/// The host's output, with its option negotiation taken out and answered
+struct TelnetReader {
+    telnet: Arc<Telnet>,
+    /// Read from without the lock writes go through
+    stream: TcpStream,
+    scan: Scan,
+    subnegotiation: Vec<u8>,
+    /// Options on at this end
+    ours: HashSet<u8>,
+    /// Options on at the host's end
+    theirs: HashSet<u8>,
+}
+
+impl TelnetReader {
+    fn new(telnet: Arc<Telnet>, stream: TcpStream) -> Self {
+        Self {
+            telnet,
+            stream,
+            scan: Scan::default(),
+            subnegotiation: Vec::new(),
+            ours: HashSet::new(),
+            theirs: HashSet::new(),
+        }
+    }
+
+    /// Take in `byte` from the host, returning it if it's output
+    fn scan(&mut self, byte: u8) -> io::Result<Option<u8>> {
+        match self.scan {
+            Scan::Data if byte == IAC => self.scan = Scan::Command,
+            Scan::Data => return Ok(Some(byte)),
+            Scan::Command => {
+                self.scan = match byte {
+                    WILL | WONT | DO | DONT => Scan::Option(byte),
+                    SB => {
+                        self.subnegotiation.clear();
+                        Scan::Subnegotiation
+                    }
+                    IAC => {
+                        self.scan = Scan::Data;
+                        return Ok(Some(IAC));
+                    }
+                    // NOP, go ahead and the rest mean nothing to a terminal
+                    _ => Scan::Data,
+                }
+            }
+            Scan::Option(command) => {
+                self.scan = Scan::Data;
+                self.negotiate(command, byte)?;
+            }
+            Scan::Subnegotiation if byte == IAC => self.scan = Scan::SubnegotiationCommand,
+            Scan::Subnegotiation => self.push_subnegotiation(byte),
+            Scan::SubnegotiationCommand if byte == SE => {
+                self.scan = Scan::Data;
+                self.subnegotiate()?;
+            }
+            Scan::SubnegotiationCommand => {
+                self.scan = Scan::Subnegotiation;
+                self.push_subnegotiation(byte);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn push_subnegotiation(&mut self, byte: u8) {
+        if self.subnegotiation.len() < MAX_SUBNEGOTIATION_LEN {
+            self.subnegotiation.push(byte);
+        }
+    }
+
+    /// Answer the host's WILL, WONT, DO or DONT `option`. Ones that don't
+    /// change anything go unanswered, so the two ends don't go back and forth
+    /// forever.
+    fn negotiate(&mut self, command: u8, option: u8) -> io::Result<()> {
+        let reply = match command {
+            DO if [SUPPRESS_GO_AHEAD, TERMINAL_TYPE, NAWS].contains(&option) => {
+                if !self.ours.insert(option) {
+                    return Ok(());
+                }
+                WILL
+            }
+            DO => WONT,
+            DONT if self.ours.remove(&option) => WONT,
+            WILL if [ECHO, SUPPRESS_GO_AHEAD].contains(&option) => {
+                if !self.theirs.insert(option) {
+                    return Ok(());
+                }
+                DO
+            }
+            WILL => DONT,
+            WONT if self.theirs.remove(&option) => DONT,
+            _ => return Ok(()),
+        };
+        self.telnet.send(&[IAC, reply, option])?;
+
+        if option == NAWS {
+            let on = reply == WILL;
+            self.telnet.naws.store(on, Ordering::Release);
+            if on {
+                self.telnet.send_size()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Answer the host asking for the terminal type, the only subnegotiation
+    /// that needs answering
+    fn subnegotiate(&mut self) -> io::Result<()> {
+        if self.subnegotiation != [TERMINAL_TYPE, TTYPE_SEND] || !self.ours.contains(&TERMINAL_TYPE) {
+            return Ok(());
+        }
+
+        let mut bytes = vec![IAC, SB, TERMINAL_TYPE, TTYPE_IS];
+        bytes.extend(self.telnet.term.bytes());
+        bytes.extend([IAC, SE]);
+
+        self.telnet.send(&bytes)
+    }
+}
+
+impl Read for TelnetReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.stream.read(buf)?;
+            if read == 0 {
+                return Ok(0);
+            }
+
+            let mut output = 0;
+            for index in 0..read {
+                if let Some(byte) = self.scan(buf[index])? {
+                    buf[output] = byte;
+                    output += 1;
+                }
+            }
+
+            // nothing but negotiation, which the reader would take as the host hanging up
+            if output > 0 {
+                return Ok(output);
+            }
+        }
+    }
+}
+
+/// Let terminal `terminal`'s host know it's now `rows` by `cols`, if it's a
+/// telnet connection whose host asked to be told
+pub fn resized(terminal: &SubTerminal, rows: u16, cols: u16) {
+    let Some(telnet) = terminal.telnet.get() else {
+        return;
+    };
+
+    *telnet.size.lock().unwrap_or_else(|err| err.into_inner()) = (rows, cols);
+    if telnet.naws.load(Ordering::Acquire) {
+        if let Err(err) = telnet.send_size() {
+            eprintln!("could not send the window size over telnet: {err}");
+        }
+    }
+}
+
+/// Connect terminal `terminal_id` to `profile`'s host over telnet. Like a
+/// serial port, the terminal keeps its pty but never gets a shell.
+pub async fn connect(
+    app: &AppHandle,
+    state: &AppState,
+    terminal_id: u32,
+    profile: &SshProfile,
+) -> Result<(), SteppeError> {
+    let terminal = state.open_terminal(terminal_id).await?;
+    if terminal.has_terminal.load(Ordering::Acquire) {
+        return Ok(());
+    }
+
+    let address = (profile.host.clone(), profile.port.unwrap_or(DEFAULT_PORT));
+    event_log::record(
+        state,
+        terminal_id,
+        SessionEventKind::Created,
+        Some(format!("telnet {}:{}", address.0, address.1)),
+    )
+    .await;
+
+    let stream = async_runtime::spawn_blocking(move || TcpStream::connect(address)).await??;
+    // typing goes out a key at a time
+    stream.set_nodelay(true)?;
+
+    let size = terminal
+        .pty_pair
+        .lock()
+        .await
+        .master
+        .get_size()
+        .map_err(|err| SteppeError::Pty(err.to_string()))?;
+    let telnet = Arc::new(Telnet {
+        stream: Mutex::new(stream.try_clone()?),
+        // the host is as unlikely to have steppe's terminfo entry as one over ssh
+        term: identity::term(state, true).await,
+        naws: AtomicBool::new(false),
+        size: Mutex::new((size.rows, size.cols)),
+    });
+    let _ = terminal.telnet.set(telnet.clone());
+    // restored sessions connect again with the profile, like they would over ssh
+    let _ = terminal.shell.set(ShellProcess {
+        program: "telnet".to_string(),
+        shell_profile: None,
+        ssh_profile: Some(profile.name.clone()),
+        wsl_distro: None,
+        docker_container: None,
+        k8s_shell: None,
+        pid: None,
+        init_command: None,
+    });
+
+    *terminal.writer.lock().await = Box::new(TelnetWriter(telnet.clone()));
+    let reader = reader::spawn_reader(
+        app.clone(),
+        terminal_id,
+        terminal.clone(),
+        Box::new(TelnetReader::new(telnet, stream)),
+    )?;
+
+    terminal.has_terminal.store(true, Ordering::Release);
+    crash::ACTIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+    connections::touch(state, &profile.name).await;
+
+    // the reader stops once the host hangs up
+    let app = app.clone();
+    thread::spawn(move || {
+        let _ = reader.join();
+        async_runtime::block_on(terminal_exited(&app, terminal_id, 0, None));
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, time::Duration};
+
+    use super::*;
+
+    /// A reader for a connection to a made-up host, and the host's end of it
+    fn connect() -> (TelnetReader, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (host, _) = listener.accept().unwrap();
+        host.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+
+        let telnet = Arc::new(Telnet {
+            stream: Mutex::new(stream.try_clone().unwrap()),
+            term: "xterm-256color".to_string(),
+            naws: AtomicBool::new(false),
+            size: Mutex::new((24, 80)),
+        });
+        (TelnetReader::new(telnet, stream), host)
+    }
+
+    /// What's left of `bytes` from the host once the negotiation's taken out
+    fn feed(reader: &mut TelnetReader, bytes: &[u8]) -> Vec<u8> {
+        bytes.iter().filter_map(|byte| reader.scan(*byte).unwrap()).collect()
+    }
+
+    /// Everything the host's been sent so far
+    fn sent(host: &mut TcpStream) -> Vec<u8> {
+        let mut sent = Vec::new();
+        let mut buf = [0; 1024];
+        while let Ok(len @ 1..) = host.read(&mut buf) {
+            sent.extend_from_slice(&buf[..len]);
+        }
+        sent
+    }
+
+    #[test]
+    fn takes_out_negotiation_split_across_reads() {
+        let (mut reader, mut host) = connect();
+
+        // an IAC at the end of a read is a command still coming
+        assert_eq!(feed(&mut reader, &[b'a', b'b', IAC]), b"ab");
+        assert_eq!(feed(&mut reader, &[DO]), b"");
+        assert_eq!(feed(&mut reader, &[NAWS, b'c']), b"c");
+        assert_eq!(sent(&mut host), [IAC, WILL, NAWS, IAC, SB, NAWS, 0, 80, 0, 24, IAC, SE]);
+
+        // or a doubled one, which is data
+        assert_eq!(feed(&mut reader, &[IAC]), b"");
+        assert_eq!(feed(&mut reader, &[IAC, b'd']), [IAC, b'd']);
+        // commands that mean nothing to a terminal
+        assert_eq!(feed(&mut reader, &[IAC, 241, b'e', IAC, 249]), b"e");
+        assert_eq!(sent(&mut host), b"");
+    }
+
+    #[test]
+    fn answers_each_option_once() {
+        let (mut reader, mut host) = connect();
+        let sga = SUPPRESS_GO_AHEAD;
+
+        feed(
+            &mut reader,
+            &[IAC, DO, sga, IAC, WILL, ECHO, IAC, DO, 99, IAC, WILL, 99],
+        );
+        assert_eq!(
+            sent(&mut host),
+            [IAC, WILL, sga, IAC, DO, ECHO, IAC, WONT, 99, IAC, DONT, 99]
+        );
+
+        // asking again doesn't start it all over
+        feed(&mut reader, &[IAC, DO, sga, IAC, WILL, ECHO, IAC, DONT, 99]);
+        assert_eq!(sent(&mut host), b"");
+
+        feed(&mut reader, &[IAC, DONT, sga, IAC, WONT, ECHO]);
+        assert_eq!(sent(&mut host), [IAC, WONT, sga, IAC, DONT, ECHO]);
+    }
+
+    #[test]
+    fn answers_terminal_type_split_across_reads() {
+        let (mut reader, mut host) = connect();
+
+        feed(&mut reader, &[IAC, DO, TERMINAL_TYPE]);
+        assert_eq!(sent(&mut host), [IAC, WILL, TERMINAL_TYPE]);
+
+        assert_eq!(feed(&mut reader, &[IAC, SB]), b"");
+        assert_eq!(feed(&mut reader, &[TERMINAL_TYPE, TTYPE_SEND, IAC]), b"");
+        assert_eq!(feed(&mut reader, &[SE, b'$']), b"$");
+
+        let mut answer = vec![IAC, SB, TERMINAL_TYPE, TTYPE_IS];
+        answer.extend(b"xterm-256color");
+        answer.extend([IAC, SE]);
+        assert_eq!(sent(&mut host), answer);
+    }
+
+    #[test]
+    fn holds_on_to_only_so_much_of_an_unterminated_subnegotiation() {
+        let (mut reader, mut host) = connect();
+        feed(&mut reader, &[IAC, DO, TERMINAL_TYPE]);
+        sent(&mut host);
+
+        // none of it's output, however long it goes on
+        let mut endless = vec![IAC, SB, TERMINAL_TYPE, TTYPE_SEND];
+        endless.resize(64 * 1024, b'x');
+        assert_eq!(feed(&mut reader, &endless), b"");
+        assert_eq!(reader.subnegotiation.len(), MAX_SUBNEGOTIATION_LEN);
+
+        // and once it ends, it isn't taken for the request it started as
+        assert_eq!(feed(&mut reader, &[IAC, SE, b'o', b'k']), b"ok");
+        assert_eq!(sent(&mut host), b"");
+    }
+
+    #[test]
+    fn keeps_doubled_iacs_in_subnegotiations() {
+        let (mut reader, _host) = connect();
+
+        assert_eq!(feed(&mut reader, &[IAC, SB, NAWS, IAC, IAC, 1, IAC, SE]), b"");
+        assert_eq!(reader.subnegotiation, [NAWS, IAC, 1]);
+    }
+
+    #[test]
+    fn doubles_iac_in_the_window_size() {
+        let (reader, mut host) = connect();
+
+        *reader.telnet.size.lock().unwrap() = (255, 511);
+        reader.telnet.send_size().unwrap();
+        assert_eq!(sent(&mut host), [IAC, SB, NAWS, 1, IAC, IAC, 0, IAC, IAC, IAC, SE]);
+    }
+
+    #[test]
+    fn escapes_typing() {
+        let (reader, mut host) = connect();
+
+        let mut writer = TelnetWriter(reader.telnet.clone());
+        writer.write_all(&[b'a', IAC, b'\r', b'\r', b'\n']).unwrap();
+        assert_eq!(sent(&mut host), [b'a', IAC, IAC, b'\r', 0, b'\r', b'\n']);
+    }
+
+    #[test]
+    fn skips_reads_with_nothing_but_negotiation() {
+        let (mut reader, mut host) = connect();
+
+        host.write_all(&[IAC, DO, 99]).unwrap();
+        host.write_all(b"login: ").unwrap();
+
+        let mut buf = [0; 64];
+        let mut output = Vec::new();
+        while output.len() < 7 {
+            let len = reader.read(&mut buf).unwrap();
+            assert!(len > 0);
+            output.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(output, b"login: ");
+    }
+}
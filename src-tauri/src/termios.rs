@@ -0,0 +1,81 @@
+use portable_pty::MasterPty;
+use serde::{Deserialize, Serialize};
+
+use crate::error::SteppeError;
+
+/// A subset of the POSIX termios line discipline, as set by `stty`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermiosSettings {
+    pub echo: bool,
+    pub canonical_mode: bool,
+    pub signal_chars: bool,
+    /// `VMIN`: minimum number of bytes for a non-canonical read
+    pub min_read: u8,
+    /// `VTIME`: non-canonical read timeout, in tenths of a second
+    pub timeout: u8,
+}
+
+/// Apply the settings to the pty. The master and slave share the same
+/// terminal device, so setting attributes through the master fd is
+/// equivalent to doing it on the slave.
+#[cfg(unix)]
+pub fn apply(master: &dyn MasterPty, settings: &TermiosSettings) -> Result<(), SteppeError> {
+    use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg, SpecialCharacterIndices};
+    use std::os::fd::BorrowedFd;
+
+    let fd = master
+        .as_raw_fd()
+        .ok_or_else(|| SteppeError::Pty("pty has no file descriptor".to_string()))?;
+    // the fd is owned by the master, which outlives this call
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+
+    let mut attrs = tcgetattr(fd).map_err(|err| SteppeError::Pty(err.to_string()))?;
+
+    attrs.local_flags.set(LocalFlags::ECHO, settings.echo);
+    attrs.local_flags.set(LocalFlags::ICANON, settings.canonical_mode);
+    attrs.local_flags.set(LocalFlags::ISIG, settings.signal_chars);
+    attrs.control_chars[SpecialCharacterIndices::VMIN as usize] = settings.min_read;
+    attrs.control_chars[SpecialCharacterIndices::VTIME as usize] = settings.timeout;
+
+    tcsetattr(fd, SetArg::TCSANOW, &attrs).map_err(|err| SteppeError::Pty(err.to_string()))
+}
+
+/// Whether the pty echoes what's typed, and whether it's in canonical
+/// (line at a time) mode, or `None` if that can't be told
+#[cfg(unix)]
+pub fn echo_modes(master: &dyn MasterPty) -> Option<(bool, bool)> {
+    use nix::sys::termios::{tcgetattr, LocalFlags};
+    use std::os::fd::BorrowedFd;
+
+    let fd = master.as_raw_fd()?;
+    // the fd is owned by the master, which outlives this call
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    let attrs = tcgetattr(fd).ok()?;
+
+    Some((
+        attrs.local_flags.contains(LocalFlags::ECHO),
+        attrs.local_flags.contains(LocalFlags::ICANON),
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn echo_modes(_master: &dyn MasterPty) -> Option<(bool, bool)> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn apply(_master: &dyn MasterPty, _settings: &TermiosSettings) -> Result<(), SteppeError> {
+    check_supported()
+}
+
+/// Whether there's a line discipline to set here at all, which ConPTY doesn't have
+#[cfg(unix)]
+pub fn check_supported() -> Result<(), SteppeError> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn check_supported() -> Result<(), SteppeError> {
+    Err(SteppeError::UnsupportedPlatformFeature("pty line discipline"))
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{cursor::validate_hex, error::SteppeError, AppState};
+
+/// How many colors a theme gives the terminal: the 8 normal ones, then the 8 bright ones
+const ANSI_COLORS: usize = 16;
+
+/// Terminal colors, as hex strings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub foreground: String,
+    pub background: String,
+    pub cursor: String,
+    /// black, red, green, yellow, blue, magenta, cyan, white, then their bright versions
+    pub ansi: Vec<String>,
+}
+
+/// What config.js exports as `theme`: a whole theme, or the name of a built-in one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ThemeSource {
+    Builtin(String),
+    Custom(Theme),
+}
+
+struct BuiltinTheme {
+    name: &'static str,
+    foreground: &'static str,
+    background: &'static str,
+    cursor: &'static str,
+    ansi: [&'static str; ANSI_COLORS],
+}
+
+const BUILTIN_THEMES: [BuiltinTheme; 3] = [
+    BuiltinTheme {
+        name: "gruvbox",
+        foreground: "#ebdbb2",
+        background: "#282828",
+        cursor: "#ebdbb2",
+        ansi: [
+            "#282828", "#cc241d", "#98971a", "#d79921", "#458588", "#b16286", "#689d6a", "#a89984", "#928374",
+            "#fb4934", "#b8bb26", "#fabd2f", "#83a598", "#d3869b", "#8ec07c", "#ebdbb2",
+        ],
+    },
+    BuiltinTheme {
+        name: "solarized",
+        foreground: "#839496",
+        background: "#002b36",
+        cursor: "#93a1a1",
+        ansi: [
+            "#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682", "#2aa198", "#eee8d5", "#002b36",
+            "#cb4b16", "#586e75", "#657b83", "#839496", "#6c71c4", "#93a1a1", "#fdf6e3",
+        ],
+    },
+    BuiltinTheme {
+        name: "dracula",
+        foreground: "#f8f8f2",
+        background: "#282a36",
+        cursor: "#f8f8f2",
+        ansi: [
+            "#21222c", "#ff5555", "#50fa7b", "#f1fa8c", "#bd93f9", "#ff79c6", "#8be9fd", "#f8f8f2", "#6272a4",
+            "#ff6e6e", "#69ff94", "#ffffa5", "#d6acff", "#ff92df", "#a4ffff", "#ffffff",
+        ],
+    },
+];
+
+impl From<&BuiltinTheme> for Theme {
+    fn from(theme: &BuiltinTheme) -> Self {
+        Self {
+            foreground: theme.foreground.to_string(),
+            background: theme.background.to_string(),
+            cursor: theme.cursor.to_string(),
+            ansi: theme.ansi.iter().map(|color| color.to_string()).collect(),
+        }
+    }
+}
+
+impl ThemeSource {
+    /// The theme to draw with, once it's been checked over
+    pub fn resolve(self) -> Result<Theme, SteppeError> {
+        match self {
+            Self::Builtin(name) => BUILTIN_THEMES
+                .iter()
+                .find(|theme| theme.name == name)
+                .map(Theme::from)
+                .ok_or_else(|| SteppeError::InvalidConfig(format!("there's no built-in theme named {name:?}"))),
+            Self::Custom(theme) => {
+                if theme.ansi.len() != ANSI_COLORS {
+                    return Err(SteppeError::InvalidConfig(format!(
+                        "a theme needs {ANSI_COLORS} ansi colors, not {}",
+                        theme.ansi.len()
+                    )));
+                }
+
+                for color in [&theme.foreground, &theme.background, &theme.cursor]
+                    .into_iter()
+                    .chain(&theme.ansi)
+                {
+                    validate_hex(color)?;
+                }
+
+                Ok(theme)
+            }
+        }
+    }
+}
+
+/// Switch to `theme`, or back to the frontend's own colors for `None`
+pub async fn set(app: &AppHandle, state: &AppState, theme: Option<Theme>) -> Result<(), SteppeError> {
+    *state.theme.lock().await = theme.clone();
+    app.emit("theme://changed", theme)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_get_theme(state: State<'_, AppState>) -> Result<Option<Theme>, SteppeError> {
+    Ok(state.theme.lock().await.clone())
+}
+
+#[tauri::command]
+pub async fn async_list_builtin_themes() -> Result<Vec<&'static str>, SteppeError> {
+    Ok(BUILTIN_THEMES.iter().map(|theme| theme.name).collect())
+}
@@ -0,0 +1,56 @@
+use std::sync::atomic::Ordering;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{
+    error::SteppeError,
+    steppe_api::{self, ScriptEvent},
+    AppState, SESSION_ID,
+};
+
+#[derive(Clone, Serialize)]
+struct TitleChanged {
+    terminal_id: u32,
+    title: String,
+}
+
+/// Let the frontend know terminal `terminal_id` set its title (OSC 0 or 2),
+/// and put it on the window too if that's the main terminal.
+pub fn title_changed(app: &AppHandle, state: &AppState, terminal_id: u32, title: String) -> Result<(), SteppeError> {
+    if terminal_id == SESSION_ID && state.window_title_from_terminal.load(Ordering::Relaxed) {
+        if let Some(window) = app.get_webview_window("main") {
+            window.set_title(&title)?;
+        }
+    }
+
+    steppe_api::emit_event(
+        state,
+        ScriptEvent::TitleChanged {
+            terminal_id,
+            title: title.clone(),
+        },
+    );
+    app.emit("terminal://title-changed", TitleChanged { terminal_id, title })?;
+
+    Ok(())
+}
+
+/// The title terminal `terminal_id` last set, if it's set one.
+#[tauri::command]
+pub async fn async_get_title(terminal_id: u32, state: State<'_, AppState>) -> Result<Option<String>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let title = terminal.shell_integration.lock().await.title().map(str::to_string);
+
+    Ok(title)
+}
+
+/// Whether the window title follows whatever the main terminal sets its title to.
+#[tauri::command]
+pub async fn async_set_window_title_from_terminal(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    state.window_title_from_terminal.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
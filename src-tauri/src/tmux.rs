@@ -0,0 +1,903 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+    sync::{atomic::Ordering, Arc, Mutex},
+};
+
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::{
+    crash,
+    error::SteppeError,
+    event_log::{self, SessionEventKind},
+    panes::{self, Layout, Pane, SplitDirection},
+    reader::{self, Utf8Decoder},
+    tabs, terminal_exited, window, AppState,
+};
+
+/// What `tmux -CC` starts with, a DCS that lasts until it exits
+const CONTROL_START: &[u8] = b"\x1bP1000p";
+
+/// ST, which ends it
+const CONTROL_END: &[u8] = b"\x1b\\";
+
+/// What `list-windows` prints for each window, with the name last since it can have spaces in it
+const WINDOW_FORMAT: &str = "#{window_id} #{window_layout} #{window_name}";
+
+/// A terminal's output, split up around tmux control mode
+#[derive(Debug)]
+pub enum Part {
+    /// Everything outside control mode, which is the terminal's own
+    Output(Vec<u8>),
+    Started,
+    /// A line of control mode, without its line ending
+    Line(String),
+    Exited,
+}
+
+/// Picks control mode out of a terminal's output, for when something in it
+/// runs `tmux -CC`
+#[derive(Debug, Default)]
+pub struct ControlScanner {
+    active: bool,
+    /// The start of `CONTROL_START`, or of a line in control mode, still waiting on the rest
+    pending: Vec<u8>,
+}
+
+impl ControlScanner {
+    pub fn split(&mut self, bytes: &[u8]) -> Vec<Part> {
+        let mut parts = Vec::new();
+        let mut output = Vec::new();
+
+        for &byte in bytes {
+            if !self.active {
+                if byte == CONTROL_START[self.pending.len()] {
+                    self.pending.push(byte);
+                } else {
+                    output.append(&mut self.pending);
+                    if byte == CONTROL_START[0] {
+                        self.pending.push(byte);
+                    } else {
+                        output.push(byte);
+                    }
+                }
+
+                if self.pending == CONTROL_START {
+                    self.pending.clear();
+                    self.active = true;
+                    if !output.is_empty() {
+                        parts.push(Part::Output(std::mem::take(&mut output)));
+                    }
+                    parts.push(Part::Started);
+                }
+                continue;
+            }
+
+            self.pending.push(byte);
+            if self.pending == CONTROL_END {
+                self.pending.clear();
+                self.active = false;
+                parts.push(Part::Exited);
+            } else if byte == b'\n' {
+                let line = String::from_utf8_lossy(&self.pending);
+                parts.push(Part::Line(line.trim_end_matches(['\r', '\n']).to_string()));
+                self.pending.clear();
+            }
+        }
+
+        if !output.is_empty() {
+            parts.push(Part::Output(output));
+        }
+
+        parts
+    }
+}
+
+/// What a command's response is for, in the order the commands were sent
+#[derive(Debug)]
+enum Pending {
+    Ignore,
+    /// `list-windows` with `WINDOW_FORMAT`
+    ListWindows,
+    /// `capture-pane` for tmux pane `.0`, to fill in what was there before attaching
+    Capture(u32),
+}
+
+struct Command {
+    line: String,
+    pending: Pending,
+}
+
+/// Typing into a tmux pane's terminal, which becomes `send-keys` for tmux
+struct PaneWriter {
+    pane: u32,
+    commands: UnboundedSender<Command>,
+}
+
+impl Write for PaneWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let keys: Vec<String> = buf.iter().map(|byte| format!("{byte:02x}")).collect();
+        let command = Command {
+            line: format!("send-keys -t %{} -H {}", self.pane, keys.join(" ")),
+            pending: Pending::Ignore,
+        };
+
+        self.commands
+            .send(command)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A tmux pane, and the terminal showing it
+struct PaneTerminal {
+    terminal_id: u32,
+    window: u32,
+    /// `%output` can end partway through a character
+    decoder: Utf8Decoder,
+}
+
+/// A `tmux -CC` running in a terminal (the gateway), with each of its panes
+/// in a terminal of their own
+pub struct TmuxSession {
+    /// The window the gateway's in, where the panes go too
+    label: String,
+    commands: UnboundedSender<Command>,
+    /// Shared with the task writing `commands` to tmux, which adds to it as they go out
+    pending: Arc<Mutex<VecDeque<Pending>>>,
+    /// Lines of the response being read, between `%begin` and `%end`
+    response: Option<Vec<String>>,
+    /// By tmux pane id
+    panes: HashMap<u32, PaneTerminal>,
+    /// tmux window id -> its panes
+    windows: HashMap<u32, Vec<u32>>,
+    /// The tmux window whose splits are the steppe window's panes, if any are split
+    split_window: Option<u32>,
+}
+
+impl TmuxSession {
+    fn send(&self, line: String, pending: Pending) {
+        // the writing task only goes away with the session
+        let _ = self.commands.send(Command { line, pending });
+    }
+
+    fn terminal_of(&self, pane: u32) -> Option<u32> {
+        self.panes.get(&pane).map(|pane| pane.terminal_id)
+    }
+}
+
+/// `%12` or `@3` to 12 or 3
+fn parse_id(id: &str, sigil: char) -> Option<u32> {
+    id.strip_prefix(sigil)?.parse().ok()
+}
+
+/// `%output`'s data, where anything below a space and backslashes are `\ooo`
+fn unescape(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let octal = bytes
+            .get(index + 1..index + 4)
+            .filter(|digits| bytes[index] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit)));
+
+        match octal {
+            Some(digits) => {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |value, digit| value * 8 + u32::from(digit - b'0'));
+                unescaped.push(value as u8);
+                index += 4;
+            }
+            None => {
+                unescaped.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    unescaped
+}
+
+/// A tmux layout, like `b25d,80x24,0,0{40x24,0,0,1,39x24,41,0,2}`
+#[derive(Debug)]
+enum LayoutNode {
+    Pane(u32),
+    /// Each child with how much of the split it gets, in cells
+    Split(SplitDirection, Vec<(u32, LayoutNode)>),
+}
+
+struct LayoutParser<'a> {
+    layout: &'a [u8],
+    index: usize,
+}
+
+impl LayoutParser<'_> {
+    fn number(&mut self) -> Option<u32> {
+        let start = self.index;
+        while self.layout.get(self.index).is_some_and(u8::is_ascii_digit) {
+            self.index += 1;
+        }
+
+        std::str::from_utf8(&self.layout[start..self.index]).ok()?.parse().ok()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        if self.layout.get(self.index) != Some(&byte) {
+            return None;
+        }
+
+        self.index += 1;
+        Some(())
+    }
+
+    /// `WxH,X,Y` and then a pane id or the cells it's split into, as `(W, H, node)`
+    fn cell(&mut self) -> Option<(u32, u32, LayoutNode)> {
+        let width = self.number()?;
+        self.expect(b'x')?;
+        let height = self.number()?;
+        self.expect(b',')?;
+        self.number()?;
+        self.expect(b',')?;
+        self.number()?;
+
+        let (direction, close) = match self.layout.get(self.index) {
+            Some(b',') => {
+                self.index += 1;
+                return Some((width, height, LayoutNode::Pane(self.number()?)));
+            }
+            // left to right, with a vertical line between them
+            Some(b'{') => (SplitDirection::Vertical, b'}'),
+            Some(b'[') => (SplitDirection::Horizontal, b']'),
+            _ => return None,
+        };
+        self.index += 1;
+
+        let mut children = Vec::new();
+        loop {
+            let (child_width, child_height, child) = self.cell()?;
+            let size = match direction {
+                SplitDirection::Vertical => child_width,
+                SplitDirection::Horizontal => child_height,
+            };
+            children.push((size, child));
+
+            if self.expect(close).is_some() {
+                return Some((width, height, LayoutNode::Split(direction, children)));
+            }
+            self.expect(b',')?;
+        }
+    }
+}
+
+impl LayoutNode {
+    fn parse(layout: &str) -> Option<Self> {
+        // past the checksum
+        let (_, cells) = layout.split_once(',')?;
+        let mut parser = LayoutParser {
+            layout: cells.as_bytes(),
+            index: 0,
+        };
+
+        parser.cell().map(|(_, _, node)| node)
+    }
+
+    fn panes(&self, into: &mut Vec<u32>) {
+        match self {
+            Self::Pane(pane) => into.push(*pane),
+            Self::Split(_, children) => children.iter().for_each(|(_, child)| child.panes(into)),
+        }
+    }
+
+    /// The same splits as steppe's panes, with each tmux pane's terminal at the leaves
+    fn to_pane(&self, session: &TmuxSession) -> Option<Pane> {
+        match self {
+            Self::Pane(pane) => Some(Pane::Terminal {
+                terminal_id: session.terminal_of(*pane)?,
+            }),
+            Self::Split(direction, children) => split_children(*direction, children, session),
+        }
+    }
+}
+
+/// Steppe's splits only have two sides, so the first child gets its share and
+/// the rest split what's left between them
+fn split_children(direction: SplitDirection, children: &[(u32, LayoutNode)], session: &TmuxSession) -> Option<Pane> {
+    let ((size, first), rest) = children.split_first()?;
+    if rest.is_empty() {
+        return first.to_pane(session);
+    }
+
+    let total: u32 = children.iter().map(|(size, _)| size).sum();
+    Some(Pane::Split {
+        direction,
+        ratio: f64::from(*size) / f64::from(total.max(1)),
+        first: Box::new(first.to_pane(session)?),
+        second: Box::new(split_children(direction, rest, session)?),
+    })
+}
+
+/// Give tmux pane `pane` a terminal of its own in the gateway's window
+async fn open_pane(app: &AppHandle, state: &AppState, session: &mut TmuxSession, pane: u32, window: u32) {
+    let (terminal_id, terminal) = match state.open_new_terminal().await {
+        Ok(opened) => opened,
+        Err(err) => {
+            eprintln!("could not open a terminal for tmux pane %{pane}: {err}");
+            return;
+        }
+    };
+    window::assign(state, terminal_id, &session.label).await;
+
+    *terminal.writer.lock().await = Box::new(PaneWriter {
+        pane,
+        commands: session.commands.clone(),
+    });
+    terminal.has_terminal.store(true, Ordering::Release);
+    crash::ACTIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+    event_log::record(
+        state,
+        terminal_id,
+        SessionEventKind::Created,
+        Some(format!("tmux pane %{pane}")),
+    )
+    .await;
+
+    session.panes.insert(
+        pane,
+        PaneTerminal {
+            terminal_id,
+            window,
+            decoder: Utf8Decoder::default(),
+        },
+    );
+    session.send(format!("capture-pane -p -e -t %{pane}"), Pending::Capture(pane));
+
+    if let Err(err) = app.emit_to(&session.label, "terminal://spawned", terminal_id) {
+        eprintln!("could not send new tmux pane: {err}");
+    }
+}
+
+/// Take away tmux pane `pane`'s terminal, once tmux has closed it
+async fn close_pane(app: &AppHandle, session: &mut TmuxSession, pane: u32) {
+    let Some(closed) = session.panes.remove(&pane) else {
+        return;
+    };
+
+    terminal_exited(app, closed.terminal_id, 0, None).await;
+    if let Err(err) = app.emit_to(&session.label, "tab://closed", closed.terminal_id) {
+        eprintln!("could not close tmux pane: {err}");
+    }
+}
+
+async fn close_window(app: &AppHandle, state: &AppState, session: &mut TmuxSession, window: u32) {
+    for pane in session.windows.remove(&window).unwrap_or_default() {
+        close_pane(app, session, pane).await;
+    }
+
+    if session.split_window == Some(window) {
+        session.split_window = None;
+        state.layouts.lock().await.remove(&session.label);
+        if let Err(err) = panes::emit_changed(app, state, &session.label).await {
+            eprintln!("could not send tmux layout: {err}");
+        }
+    }
+}
+
+/// Bring window `window`'s terminals in line with its `layout`, and its tabs
+/// with its `name` if that's known
+async fn apply_layout(
+    app: &AppHandle,
+    state: &AppState,
+    session: &mut TmuxSession,
+    window: u32,
+    layout: &str,
+    name: Option<&str>,
+) {
+    let Some(layout) = LayoutNode::parse(layout) else {
+        eprintln!("could not parse tmux layout {layout:?}");
+        return;
+    };
+
+    let mut current = Vec::new();
+    layout.panes(&mut current);
+
+    for pane in session.windows.get(&window).cloned().unwrap_or_default() {
+        if !current.contains(&pane) {
+            close_pane(app, session, pane).await;
+        }
+    }
+    for &pane in &current {
+        if !session.panes.contains_key(&pane) {
+            open_pane(app, state, session, pane, window).await;
+        }
+    }
+    session.windows.insert(window, current.clone());
+
+    if let Some(name) = name {
+        rename_window(app, state, session, window, name).await;
+    }
+
+    // the steppe window only has the one set of panes, so it's whichever tmux window was split last
+    let root = layout.to_pane(session);
+    match root {
+        Some(root @ Pane::Split { .. }) => {
+            let focused = current
+                .first()
+                .and_then(|pane| session.terminal_of(*pane))
+                .unwrap_or_default();
+            panes::set_layout(state, &session.label, Layout { root, focused }).await;
+            session.split_window = Some(window);
+        }
+        _ if session.split_window == Some(window) => {
+            state.layouts.lock().await.remove(&session.label);
+            session.split_window = None;
+        }
+        _ => return,
+    }
+
+    if let Err(err) = panes::emit_changed(app, state, &session.label).await {
+        eprintln!("could not send tmux layout: {err}");
+    }
+}
+
+/// Every tab in tmux window `window` takes its name
+async fn rename_window(app: &AppHandle, state: &AppState, session: &TmuxSession, window: u32, name: &str) {
+    for pane in session.windows.get(&window).into_iter().flatten() {
+        if let Some(terminal_id) = session.terminal_of(*pane) {
+            tabs::set_name(state, terminal_id, Some(name.to_string())).await;
+        }
+    }
+
+    if let Err(err) = tabs::emit_changed(app, state, &session.label).await {
+        eprintln!("could not send tmux window name: {err}");
+    }
+}
+
+async fn handle_response(app: &AppHandle, state: &AppState, session: &mut TmuxSession, lines: Vec<String>) {
+    let pending = session
+        .pending
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .pop_front();
+
+    match pending {
+        Some(Pending::ListWindows) => {
+            for line in lines {
+                let mut fields = line.splitn(3, ' ');
+                let (Some(window), Some(layout)) = (fields.next().and_then(|id| parse_id(id, '@')), fields.next())
+                else {
+                    continue;
+                };
+                apply_layout(app, state, session, window, layout, fields.next()).await;
+            }
+        }
+        Some(Pending::Capture(pane)) => {
+            let Some(terminal_id) = session.terminal_of(pane) else {
+                return;
+            };
+            if let Ok(terminal) = state.terminal(terminal_id).await {
+                reader::handle_output(app, terminal_id, &terminal, lines.join("\r\n")).await;
+            }
+        }
+        Some(Pending::Ignore) | None => {}
+    }
+}
+
+/// Where a line of control mode falls with respect to the commands' responses
+#[derive(Debug, PartialEq)]
+enum Response {
+    /// Not in one, so it's a notification
+    Outside,
+    /// `%begin`, or a line of the response that started with it
+    Continued,
+    /// `%end` or `%error`, with the response's lines and whether it was `%error`
+    Ended(Vec<String>, bool),
+}
+
+/// Keep track of the response being read in `response`, between `%begin` and `%end`
+fn read_response(response: &mut Option<Vec<String>>, line: &str) -> Response {
+    let Some(lines) = response else {
+        if line == "%begin" || line.starts_with("%begin ") {
+            *response = Some(Vec::new());
+            return Response::Continued;
+        }
+        return Response::Outside;
+    };
+
+    let failed = line.starts_with("%error ");
+    if failed || line.starts_with("%end ") {
+        return Response::Ended(response.take().unwrap_or_default(), failed);
+    }
+
+    lines.push(line.to_string());
+    Response::Continued
+}
+
+/// Handle one line of control mode: a notification, or part of a response
+async fn handle_line(app: &AppHandle, state: &AppState, session: &mut TmuxSession, line: &str) {
+    match read_response(&mut session.response, line) {
+        Response::Outside => {}
+        Response::Continued => return,
+        Response::Ended(lines, failed) => {
+            if failed {
+                eprintln!("tmux command failed: {}", lines.join("\n"));
+            }
+            handle_response(app, state, session, lines).await;
+            return;
+        }
+    }
+
+    let (notification, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match notification {
+        "%output" => {
+            let Some((pane, data)) = rest.split_once(' ') else {
+                return;
+            };
+            let Some(pane) = parse_id(pane, '%').and_then(|pane| session.panes.get_mut(&pane)) else {
+                return;
+            };
+
+            let data = pane.decoder.decode(unescape(data));
+            let terminal_id = pane.terminal_id;
+            if let Ok(terminal) = state.terminal(terminal_id).await {
+                reader::handle_output(app, terminal_id, &terminal, data).await;
+            }
+        }
+        "%layout-change" => {
+            let mut fields = rest.split(' ');
+            if let (Some(window), Some(layout)) = (fields.next().and_then(|id| parse_id(id, '@')), fields.next()) {
+                apply_layout(app, state, session, window, layout, None).await;
+            }
+        }
+        // new windows don't always get a %layout-change, so ask about all of them again
+        "%window-add" => {
+            session.send(format!("list-windows -F '{WINDOW_FORMAT}'"), Pending::ListWindows);
+        }
+        // a whole other set of windows
+        "%session-changed" => {
+            let windows: Vec<u32> = session.windows.keys().copied().collect();
+            for window in windows {
+                close_window(app, state, session, window).await;
+            }
+            session.send(format!("list-windows -F '{WINDOW_FORMAT}'"), Pending::ListWindows);
+        }
+        "%window-close" | "%unlinked-window-close" => {
+            if let Some(window) = parse_id(rest, '@') {
+                close_window(app, state, session, window).await;
+            }
+        }
+        "%window-renamed" => {
+            if let Some((window, name)) = rest.split_once(' ') {
+                if let Some(window) = parse_id(window, '@') {
+                    rename_window(app, state, session, window, name).await;
+                }
+            }
+        }
+        "%window-pane-changed" => {
+            let Some((window, pane)) = rest.split_once(' ') else {
+                return;
+            };
+            if parse_id(window, '@') != session.split_window {
+                return;
+            }
+            let Some(terminal_id) = parse_id(pane, '%').and_then(|pane| session.terminal_of(pane)) else {
+                return;
+            };
+
+            if let Some(layout) = state.layouts.lock().await.get_mut(&session.label) {
+                layout.focused = terminal_id;
+            }
+            if let Err(err) = panes::emit_changed(app, state, &session.label).await {
+                eprintln!("could not send tmux layout: {err}");
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Start treating terminal `gateway` as a tmux client
+async fn start(app: &AppHandle, state: &AppState, gateway: u32) -> Result<(), SteppeError> {
+    let terminal = state.terminal(gateway).await?;
+    let (commands, mut outgoing) = mpsc::unbounded_channel::<Command>();
+    // whatever started control mode gets a response too
+    let pending = Arc::new(Mutex::new(VecDeque::from([Pending::Ignore])));
+
+    let writer = terminal.writer.clone();
+    let sent = pending.clone();
+    async_runtime::spawn(async move {
+        while let Some(command) = outgoing.recv().await {
+            sent.lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .push_back(command.pending);
+            if let Err(err) = writeln!(writer.lock().await, "{}", command.line) {
+                eprintln!("could not send tmux a command: {err}");
+                break;
+            }
+        }
+    });
+
+    let session = TmuxSession {
+        label: window::window_of(state, gateway).await,
+        commands,
+        pending,
+        response: None,
+        panes: HashMap::new(),
+        windows: HashMap::new(),
+        split_window: None,
+    };
+    session.send(format!("list-windows -F '{WINDOW_FORMAT}'"), Pending::ListWindows);
+    state.tmux.lock().await.insert(gateway, session);
+
+    let note = "\r\n[tmux control mode: its windows are in tabs of their own]\r\n".to_string();
+    reader::handle_output(app, gateway, &terminal, note).await;
+
+    Ok(())
+}
+
+/// Close every pane of the session in terminal `gateway`, now tmux has gone
+async fn exit(app: &AppHandle, state: &AppState, gateway: u32) {
+    let Some(mut session) = state.tmux.lock().await.remove(&gateway) else {
+        return;
+    };
+
+    let windows: Vec<u32> = session.windows.keys().copied().collect();
+    for window in windows {
+        close_window(app, state, &mut session, window).await;
+    }
+}
+
+/// Handle a part of terminal `gateway`'s output that's to do with control mode
+pub async fn handle(app: &AppHandle, gateway: u32, part: Part) -> Result<(), SteppeError> {
+    let state = app.state::<AppState>();
+
+    match part {
+        Part::Output(_) => {}
+        Part::Started => start(app, &state, gateway).await?,
+        Part::Line(line) => {
+            if let Some(session) = state.tmux.lock().await.get_mut(&gateway) {
+                handle_line(app, &state, session, &line).await;
+            }
+        }
+        Part::Exited => exit(app, &state, gateway).await,
+    }
+
+    Ok(())
+}
+
+/// The gateway of the tmux session terminal `terminal_id` is in (or is the
+/// gateway of), and the tmux pane it's showing
+async fn find(state: &AppState, terminal_id: u32) -> Result<(u32, Option<u32>), SteppeError> {
+    let sessions = state.tmux.lock().await;
+    if sessions.contains_key(&terminal_id) {
+        return Ok((terminal_id, None));
+    }
+
+    sessions
+        .iter()
+        .find_map(|(gateway, session)| {
+            let pane = session.panes.iter().find(|(_, pane)| pane.terminal_id == terminal_id)?;
+            Some((*gateway, Some(*pane.0)))
+        })
+        .ok_or(SteppeError::NotTmux(terminal_id))
+}
+
+/// Send tmux `command` for the session terminal `terminal_id` is in
+async fn send(state: &AppState, terminal_id: u32, command: String) -> Result<(), SteppeError> {
+    let (gateway, _) = find(state, terminal_id).await?;
+    if let Some(session) = state.tmux.lock().await.get(&gateway) {
+        session.send(command, Pending::Ignore);
+    }
+
+    Ok(())
+}
+
+/// The tmux pane terminal `terminal_id` is showing
+async fn pane_of(state: &AppState, terminal_id: u32) -> Result<u32, SteppeError> {
+    find(state, terminal_id)
+        .await?
+        .1
+        .ok_or(SteppeError::NotTmux(terminal_id))
+}
+
+/// Ask tmux to close terminal `terminal_id`'s pane, if it's showing one.
+/// Whether it was is returned, since other terminals have a process to hang up on instead.
+pub async fn kill_pane(state: &AppState, terminal_id: u32) -> Result<bool, SteppeError> {
+    let Ok(pane) = pane_of(state, terminal_id).await else {
+        return Ok(false);
+    };
+
+    send(state, terminal_id, format!("kill-pane -t %{pane}")).await?;
+    Ok(true)
+}
+
+/// Let tmux know how big terminal `terminal_id` is, if it's the only pane in
+/// its tmux window. Split windows are sized by whoever's showing them.
+pub async fn resized(state: &AppState, terminal_id: u32, rows: u16, cols: u16) {
+    let Ok((gateway, Some(pane))) = find(state, terminal_id).await else {
+        return;
+    };
+
+    if let Some(session) = state.tmux.lock().await.get(&gateway) {
+        let alone = session
+            .panes
+            .get(&pane)
+            .and_then(|pane| session.windows.get(&pane.window))
+            .is_some_and(|panes| panes.len() == 1);
+
+        if alone {
+            session.send(format!("refresh-client -C {cols}x{rows}"), Pending::Ignore);
+        }
+    }
+}
+
+pub async fn new_window(state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    send(state, terminal_id, "new-window".to_string()).await
+}
+
+pub async fn kill_window(state: &AppState, terminal_id: u32) -> Result<(), SteppeError> {
+    let pane = pane_of(state, terminal_id).await?;
+    send(state, terminal_id, format!("kill-window -t %{pane}")).await
+}
+
+/// Open a tmux window in the session terminal `terminal_id` is in, which
+/// shows up as a tab once tmux says it's there
+#[tauri::command]
+pub async fn async_tmux_new_window(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    new_window(&state, terminal_id).await
+}
+
+/// Close the tmux window terminal `terminal_id`'s pane is in
+#[tauri::command]
+pub async fn async_tmux_kill_window(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    kill_window(&state, terminal_id).await
+}
+
+/// Split terminal `terminal_id`'s tmux pane, the way `async_split_pane` does local ones
+#[tauri::command]
+pub async fn async_tmux_split_pane(
+    terminal_id: u32,
+    direction: SplitDirection,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let pane = pane_of(&state, terminal_id).await?;
+    let flag = match direction {
+        SplitDirection::Horizontal => "-v",
+        SplitDirection::Vertical => "-h",
+    };
+
+    send(&state, terminal_id, format!("split-window {flag} -t %{pane}")).await
+}
+
+/// Leave tmux running, but stop showing it here
+#[tauri::command]
+pub async fn async_tmux_detach(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    send(&state, terminal_id, "detach-client".to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `node` written out shorter, as `{size:child,...}` for left to right and `[...]` for top to bottom
+    fn describe(node: &LayoutNode) -> String {
+        let (direction, children) = match node {
+            LayoutNode::Pane(pane) => return pane.to_string(),
+            LayoutNode::Split(direction, children) => (direction, children),
+        };
+
+        let children: Vec<String> = children
+            .iter()
+            .map(|(size, child)| format!("{size}:{}", describe(child)))
+            .collect();
+        match direction {
+            SplitDirection::Vertical => format!("{{{}}}", children.join(",")),
+            SplitDirection::Horizontal => format!("[{}]", children.join(",")),
+        }
+    }
+
+    fn parse(layout: &str) -> Option<String> {
+        LayoutNode::parse(layout).as_ref().map(describe)
+    }
+
+    #[test]
+    fn unescapes_output() {
+        assert_eq!(unescape("plain text"), b"plain text");
+        assert_eq!(unescape(r"\033[1mbold\015\012"), b"\x1b[1mbold\r\n");
+        assert_eq!(unescape(r"back\134slash"), b"back\\slash");
+        // utf-8 comes through as it is, and tmux escapes bytes past 0x7f too sometimes
+        assert_eq!(unescape("é"), "é".as_bytes());
+        assert_eq!(unescape(r"\303\251"), "é".as_bytes());
+        // not escapes after all
+        assert_eq!(unescape(r"\9"), br"\9");
+        assert_eq!(unescape(r"\01"), br"\01");
+        assert_eq!(unescape(r"ends in \"), br"ends in \");
+    }
+
+    #[test]
+    fn parses_layouts() {
+        assert_eq!(parse("b25d,80x24,0,0,5").as_deref(), Some("5"));
+        assert_eq!(
+            parse("b25d,80x24,0,0{40x24,0,0,1,39x24,41,0,2}").as_deref(),
+            Some("{40:1,39:2}")
+        );
+        assert_eq!(
+            parse("c3a1,80x24,0,0[80x12,0,0,1,80x11,0,13{40x11,0,13,2,39x11,41,13,3}]").as_deref(),
+            Some("[12:1,11:{40:2,39:3}]")
+        );
+
+        let mut panes = Vec::new();
+        LayoutNode::parse("c3a1,80x24,0,0[80x12,0,0,1,80x11,0,13{40x11,0,13,2,39x11,41,13,3}]")
+            .unwrap()
+            .panes(&mut panes);
+        assert_eq!(panes, [1, 2, 3]);
+    }
+
+    #[test]
+    fn refuses_broken_layouts() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("b25d"), None);
+        assert_eq!(parse("b25d,80x24,0,0"), None);
+        assert_eq!(parse("b25d,80x24,0,0{40x24,0,0,1"), None);
+        assert_eq!(parse("b25d,80x24,0,0{40x24,0,0,1,39x24,41,0,2]"), None);
+        assert_eq!(parse("b25d,80by24,0,0,1"), None);
+    }
+
+    #[test]
+    fn reads_responses_between_begin_and_end() {
+        let mut response = None;
+
+        assert_eq!(read_response(&mut response, "%window-add @1"), Response::Outside);
+        assert_eq!(read_response(&mut response, "%begin 1 12 1"), Response::Continued);
+        // notifications don't come in the middle of a response, so this is part of it
+        assert_eq!(read_response(&mut response, "%output %1 hi"), Response::Continued);
+        assert_eq!(read_response(&mut response, "@1 80x24,0,0,1"), Response::Continued);
+        assert_eq!(
+            read_response(&mut response, "%end 1 12 1"),
+            Response::Ended(vec!["%output %1 hi".to_string(), "@1 80x24,0,0,1".to_string()], false)
+        );
+        assert_eq!(response, None);
+
+        assert_eq!(read_response(&mut response, "%begin 1 13 1"), Response::Continued);
+        assert_eq!(read_response(&mut response, "no such window"), Response::Continued);
+        assert_eq!(
+            read_response(&mut response, "%error 1 13 1"),
+            Response::Ended(vec!["no such window".to_string()], true)
+        );
+
+        assert_eq!(read_response(&mut response, "%begin 1 14 1"), Response::Continued);
+        assert_eq!(
+            read_response(&mut response, "%end 1 14 1"),
+            Response::Ended(Vec::new(), false)
+        );
+        assert_eq!(read_response(&mut response, "%end 1 14 1"), Response::Outside);
+    }
+
+    #[test]
+    fn scans_for_control_mode() {
+        let mut scanner = ControlScanner::default();
+
+        // the start of it split across reads
+        let parts = scanner.split(b"$ tmux -CC\r\n\x1bP10");
+        assert!(matches!(&parts[..], [Part::Output(output)] if output == b"$ tmux -CC\r\n"));
+        let parts = scanner.split(b"00p%begin 1 2 1\r\n%end 1 2 1\n%outp");
+        assert!(matches!(
+            &parts[..],
+            [Part::Started, Part::Line(begin), Part::Line(end)] if begin == "%begin 1 2 1" && end == "%end 1 2 1"
+        ));
+        let parts = scanner.split(b"ut %1 x\n\x1b\\$ ");
+        assert!(matches!(
+            &parts[..],
+            [Part::Line(line), Part::Exited, Part::Output(output)] if line == "%output %1 x" && output == b"$ "
+        ));
+
+        // an escape that isn't control mode is left alone
+        let parts = scanner.split(b"\x1bP1\x1b[0m");
+        assert!(matches!(&parts[..], [Part::Output(output)] if output == b"\x1bP1\x1b[0m"));
+    }
+}
@@ -0,0 +1,114 @@
+use tauri::{
+    async_runtime,
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu},
+    tray::TrayIconBuilder,
+    AppHandle, Manager, Wry,
+};
+
+use crate::{
+    detach,
+    error::SteppeError,
+    shell_profile::ShellProfile,
+    spawn_terminal,
+    window::{self, MAIN_WINDOW},
+    AppState,
+};
+
+const TRAY_ID: &str = "steppe";
+
+const NEW_WINDOW: &str = "tray:new-window";
+const SHOW_HIDE: &str = "tray:show-hide";
+const QUIT: &str = "tray:quit";
+/// Followed by the profile's name
+const NEW_TAB_PREFIX: &str = "tray:new-tab:";
+
+/// The tray's menu, with a "New tab with" entry for each of `profiles`
+fn menu(app: &AppHandle, profiles: &[ShellProfile]) -> Result<Menu<Wry>, SteppeError> {
+    let new_tab = Submenu::new(app, "New tab with", !profiles.is_empty())?;
+    for profile in profiles {
+        let id = format!("{NEW_TAB_PREFIX}{}", profile.name);
+        new_tab.append(&MenuItem::with_id(app, id, &profile.name, true, None::<&str>)?)?;
+    }
+
+    let menu = Menu::new(app)?;
+    menu.append(&MenuItem::with_id(app, NEW_WINDOW, "New window", true, None::<&str>)?)?;
+    menu.append(&new_tab)?;
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(app, SHOW_HIDE, "Show/Hide", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?)?;
+
+    Ok(menu)
+}
+
+/// Put steppe's icon in the tray, before config's had a chance to set up any profiles
+pub fn create(app: &AppHandle) -> Result<(), SteppeError> {
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("steppe")
+        .menu(&menu(app, &[])?)
+        .on_menu_event(handle_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    Ok(())
+}
+
+/// Rebuild the tray's menu for `profiles`, since menus can't be edited in place everywhere
+pub fn set_profiles(app: &AppHandle, profiles: &[ShellProfile]) -> Result<(), SteppeError> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(menu(app, profiles)?))?;
+    }
+
+    Ok(())
+}
+
+/// Bring the main window up, reopening it if it was closed while steppe kept running
+fn show_main(app: &AppHandle) -> Result<(), SteppeError> {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        window.show()?;
+    }
+
+    detach::attach(app)
+}
+
+fn show_hide(app: &AppHandle) -> Result<(), SteppeError> {
+    match app.get_webview_window(MAIN_WINDOW) {
+        Some(window) if window.is_visible()? => Ok(window.hide()?),
+        _ => show_main(app),
+    }
+}
+
+async fn execute_tray_action(app: &AppHandle, id: &str) -> Result<(), SteppeError> {
+    let state = app.state::<AppState>();
+
+    match id {
+        NEW_WINDOW => {
+            window::open(app, &state).await?;
+        }
+        SHOW_HIDE => show_hide(app)?,
+        QUIT => app.exit(0),
+        _ => {
+            if let Some(profile) = id.strip_prefix(NEW_TAB_PREFIX) {
+                show_main(app)?;
+                spawn_terminal(app, Some(profile.to_string()), None, MAIN_WINDOW).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Called for every menu event, not just the tray's, so anything else is ignored
+pub fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    if !event.id().0.starts_with("tray:") {
+        return;
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = execute_tray_action(&app, &event.id().0).await {
+            eprintln!("tray action failed: {err}");
+        }
+    });
+}
@@ -0,0 +1,158 @@
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::{
+    config::WorkerCommand, error::SteppeError, keybindings, scrollback::visible_text, startup_notification::ShowToast,
+    AppState,
+};
+
+/// A line that long with no newline in sight is probably a progress bar or
+/// binary junk, so it's checked as it is rather than buffered forever
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// How long the toast from a `notify` trigger stays up
+const NOTIFY_DURATION_MS: u64 = 5000;
+
+/// A pattern config.js wants to hear about whenever a terminal prints a line matching it
+#[derive(Debug)]
+struct Trigger {
+    pattern: Regex,
+    /// Same as a keybinding's action, plus `notify` for a toast
+    action: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Triggers {
+    triggers: Vec<Trigger>,
+}
+
+/// The end of a terminal's output that isn't a whole line yet, so matches
+/// aren't missed when a line comes in over a few reads
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    partial: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TriggerFired {
+    action: String,
+    terminal_id: u32,
+    line: String,
+}
+
+impl LineBuffer {
+    /// Take in more output, returning the lines it finished
+    fn push(&mut self, data: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut rest = data;
+
+        while let Some(end) = rest.find('\n') {
+            self.partial.push_str(&rest[..end]);
+            lines.push(std::mem::take(&mut self.partial));
+            rest = &rest[end + 1..];
+        }
+
+        self.partial.push_str(rest);
+        if self.partial.len() > MAX_LINE_BYTES {
+            lines.push(std::mem::take(&mut self.partial));
+        }
+
+        lines
+    }
+}
+
+impl Triggers {
+    /// Watch for `pattern`, a js regex's source with its `flags`.
+    pub fn add(&mut self, pattern: &str, flags: &str, action: String) -> Result<(), SteppeError> {
+        let pattern = RegexBuilder::new(pattern)
+            .case_insensitive(flags.contains('i'))
+            .multi_line(flags.contains('m'))
+            .dot_matches_new_line(flags.contains('s'))
+            .build()
+            .map_err(|err| SteppeError::InvalidConfig(err.to_string()))?;
+
+        self.triggers.push(Trigger { pattern, action });
+
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.triggers.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// Every trigger the finished lines in `data` set off, in order
+    fn scan(&self, lines: &mut LineBuffer, terminal_id: u32, data: &str) -> Vec<TriggerFired> {
+        let mut fired = Vec::new();
+
+        for line in lines.push(data) {
+            let line = visible_text(&line);
+
+            for trigger in self.triggers.iter().filter(|trigger| trigger.pattern.is_match(&line)) {
+                fired.push(TriggerFired {
+                    action: trigger.action.clone(),
+                    terminal_id,
+                    line: line.clone(),
+                });
+            }
+        }
+
+        fired
+    }
+}
+
+async fn fire(app: &AppHandle, state: &AppState, fired: TriggerFired) -> Result<(), SteppeError> {
+    app.emit("trigger://fired", fired.clone())?;
+
+    let TriggerFired {
+        action,
+        terminal_id,
+        line,
+    } = fired;
+
+    if let Some(id) = action.strip_prefix(keybindings::SCRIPT_ACTION_PREFIX) {
+        let id = id
+            .parse()
+            .map_err(|_| SteppeError::InvalidConfig(format!("{action} isn't a script action")))?;
+
+        // the worker's only gone if steppe is shutting down
+        let _ = state.worker_commands.send(WorkerCommand::RunTrigger { id, terminal_id, line });
+        return Ok(());
+    }
+
+    if action == "notify" {
+        app.emit(
+            "show-toast",
+            ShowToast {
+                message: line,
+                duration_ms: NOTIFY_DURATION_MS,
+            },
+        )?;
+        return Ok(());
+    }
+
+    keybindings::invoke(action, terminal_id, app, state).await
+}
+
+/// Check what terminal `terminal_id` just printed against config.js's triggers.
+pub async fn scan_output(app: &AppHandle, state: &AppState, terminal_id: u32, lines: &mut LineBuffer, data: &str) {
+    let fired = {
+        let triggers = state.triggers.lock().await;
+        // nothing to match against, so there's no point keeping lines around either
+        if triggers.is_empty() {
+            return;
+        }
+
+        triggers.scan(lines, terminal_id, data)
+    };
+
+    for fired in fired {
+        if let Err(err) = fire(app, state, fired).await {
+            eprintln!("could not run trigger: {err}");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, State};
+
+use crate::{error::SteppeError, get_config_dir, AppState};
+
+/// Older urls get dropped past this many
+const MAX_ENTRIES: usize = 500;
+
+/// How many changes the history had when it was last written, so a write
+/// that finishes late doesn't put back what a newer one replaced
+static WRITTEN: Mutex<u64> = Mutex::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedUrl {
+    pub url: String,
+    pub timestamp_ms: u64,
+    pub session_id: u32,
+}
+
+/// Every url opened from the terminal, kept in `<config dir>/url_history.json`.
+#[derive(Debug)]
+pub struct UrlHistory {
+    pub enabled: bool,
+    entries: VecDeque<OpenedUrl>,
+    changes: u64,
+}
+
+impl Default for UrlHistory {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entries: VecDeque::new(),
+            changes: 0,
+        }
+    }
+}
+
+fn history_path() -> PathBuf {
+    get_config_dir().join("url_history.json")
+}
+
+impl UrlHistory {
+    /// Pick up the history from last time, starting fresh if there isn't one
+    /// or it can't be read.
+    pub fn load() -> Self {
+        let entries = fs::read(history_path())
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+
+        Self { entries, ..Self::default() }
+    }
+
+    /// The history as it's written to disk, to [`save`] once the lock on it
+    /// is released
+    fn snapshot(&mut self) -> Result<(u64, Vec<u8>), SteppeError> {
+        self.changes += 1;
+        let contents = serde_json::to_vec(&self.entries).map_err(std::io::Error::from)?;
+
+        Ok((self.changes, contents))
+    }
+}
+
+/// Write a [`UrlHistory::snapshot`] out, off the runtime, unless a newer one
+/// got there first
+async fn save((changes, contents): (u64, Vec<u8>)) -> Result<(), SteppeError> {
+    async_runtime::spawn_blocking(move || -> Result<(), SteppeError> {
+        let mut written = WRITTEN.lock().unwrap_or_else(|err| err.into_inner());
+        if *written > changes {
+            return Ok(());
+        }
+
+        fs::write(history_path(), contents)?;
+        *written = changes;
+
+        Ok(())
+    })
+    .await?
+}
+
+/// Add `url` to the history, unless the user turned it off.
+pub async fn record(state: &AppState, session_id: u32, url: &str) -> Result<(), SteppeError> {
+    let mut history = state.url_history.lock().await;
+    if !history.enabled {
+        return Ok(());
+    }
+
+    if history.entries.len() == MAX_ENTRIES {
+        history.entries.pop_front();
+    }
+
+    history.entries.push_back(OpenedUrl {
+        url: url.to_string(),
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        session_id,
+    });
+
+    let snapshot = history.snapshot()?;
+    drop(history);
+
+    save(snapshot).await
+}
+
+#[tauri::command]
+pub async fn async_get_url_history(state: State<'_, AppState>) -> Result<Vec<OpenedUrl>, SteppeError> {
+    Ok(state.url_history.lock().await.entries.iter().cloned().collect())
+}
+
+#[tauri::command]
+pub async fn async_clear_url_history(state: State<'_, AppState>) -> Result<(), SteppeError> {
+    let snapshot = {
+        let mut history = state.url_history.lock().await;
+        history.entries.clear();
+        history.snapshot()?
+    };
+
+    save(snapshot).await
+}
+
+/// Urls containing `query`, ignoring case.
+#[tauri::command]
+pub async fn async_search_url_history(
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<OpenedUrl>, SteppeError> {
+    let query = query.to_lowercase();
+
+    Ok(state
+        .url_history
+        .lock()
+        .await
+        .entries
+        .iter()
+        .filter(|entry| entry.url.to_lowercase().contains(&query))
+        .cloned()
+        .collect())
+}
+
+/// Stop (or start) keeping track of opened urls. Turning it off doesn't
+/// delete what's already there, that's what clearing is for.
+#[tauri::command]
+pub async fn async_set_url_history_enabled(enabled: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.url_history.lock().await.enabled = enabled;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, State};
+use tokio::process::Command;
+use url::Url;
+
+use crate::{error::SteppeError, url_history, AppState};
+
+/// What the OS gets to open in `System` mode, since whatever it has
+/// registered for other schemes could be anything. `file://` is too, when
+/// it's allowed.
+const SYSTEM_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlOpenMode {
+    /// Whatever the OS has registered for the url's scheme
+    #[default]
+    System,
+    /// Always hand the url to a web browser, respecting `$BROWSER`
+    Browser,
+    /// Run the command set with `async_set_custom_url_opener`
+    Custom,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UrlOpener {
+    pub mode: UrlOpenMode,
+    /// Program and its arguments; the url is passed as the last argument
+    pub custom_command: Option<(String, Vec<String>)>,
+    /// `file://` urls can point at anything on disk, so they're opt-in
+    pub allow_file_urls: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct UrlOpened {
+    url: String,
+}
+
+/// Open `url`, from terminal `terminal_id`, according to the user's
+/// [`UrlOpener`] settings.
+pub async fn open_url(app: &AppHandle, state: &AppState, terminal_id: u32, url: &str) -> Result<(), SteppeError> {
+    let url = Url::parse(url).map_err(|err| SteppeError::InvalidUrl(err.to_string()))?;
+    let opener = state.url_opener.lock().await.clone();
+
+    if url.scheme() == "file" && !opener.allow_file_urls {
+        return Err(SteppeError::BlockedUrl(url.to_string()));
+    }
+
+    match opener.mode {
+        UrlOpenMode::System => {
+            if url.scheme() != "file" && !SYSTEM_SCHEMES.contains(&url.scheme()) {
+                return Err(SteppeError::BlockedUrl(url.to_string()));
+            }
+
+            open::that_detached(url.as_str())?
+        }
+        UrlOpenMode::Browser => webbrowser::open(url.as_str())?,
+        UrlOpenMode::Custom => {
+            let (program, args) = opener.custom_command.ok_or_else(|| {
+                SteppeError::InvalidConfig("url open mode is custom, but no opener is set".into())
+            })?;
+
+            let mut child = Command::new(program).args(args).arg(url.as_str()).spawn()?;
+            // waited on so it doesn't linger as a zombie once it's done
+            async_runtime::spawn(async move {
+                if let Err(err) = child.wait().await {
+                    eprintln!("could not wait on the url opener: {err}");
+                }
+            });
+        }
+    }
+
+    app.emit("url-opened", UrlOpened { url: url.to_string() })?;
+
+    // the url already opened, so a history that can't be saved isn't worth failing over
+    if let Err(err) = url_history::record(state, terminal_id, url.as_str()).await {
+        eprintln!("could not save url history: {err}");
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_open_url(
+    terminal_id: u32,
+    url: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    open_url(&app, &state, terminal_id, &url).await
+}
+
+#[tauri::command]
+pub async fn async_set_url_open_mode(mode: UrlOpenMode, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.url_opener.lock().await.mode = mode;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_custom_url_opener(
+    command: String,
+    args: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    state.url_opener.lock().await.custom_command = Some((command, args));
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn async_set_allow_file_urls(allow: bool, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.url_opener.lock().await.allow_file_urls = allow;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use kmeans_colors::{get_kmeans_hamerly, Sort};
+use palette::{FromColor, IntoColor, Lab, Srgb};
+use tauri::{async_runtime, State};
+use tokio::process::Command;
+
+use crate::{error::SteppeError, AppState};
+
+/// How many colors [`extract_palette`] pulls out of the wallpaper
+const PALETTE_SIZE: usize = 8;
+
+/// Extracting is slow, and wallpapers don't change that often
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// A palette and when it was extracted
+pub type CachedPalette = Option<(Instant, Vec<String>)>;
+
+/// Ask the desktop which image it's showing, if we know how to.
+#[cfg(target_os = "macos")]
+async fn wallpaper_path() -> Result<Option<PathBuf>, SteppeError> {
+    let output = Command::new("defaults")
+        .args(["read", "com.apple.desktop", "Background"])
+        .output()
+        .await?;
+
+    // a plist dict, with a line like `ImageFilePath = "/path/to/image.jpg";`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let path = stdout.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("ImageFilePath = ")?;
+        Some(PathBuf::from(value.trim_end_matches(';').trim_matches('"')))
+    });
+
+    Ok(path)
+}
+
+#[cfg(target_os = "linux")]
+async fn wallpaper_path() -> Result<Option<PathBuf>, SteppeError> {
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.background", "picture-uri"])
+        .output()
+        .await?;
+
+    // a quoted uri, like `'file:///path/to/image.jpg'`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let uri = stdout.trim().trim_matches('\'');
+
+    Ok(url::Url::parse(uri).ok().and_then(|url| url.to_file_path().ok()))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+async fn wallpaper_path() -> Result<Option<PathBuf>, SteppeError> {
+    Err(SteppeError::UnsupportedPlatformFeature("wallpaper detection"))
+}
+
+fn to_hex(color: Srgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+}
+
+/// The dominant colors of the image at `path` as hex strings, most common first.
+pub fn extract_palette(path: &Path) -> Result<Vec<String>, SteppeError> {
+    // a thumbnail has the same dominant colors, and is a lot quicker to cluster
+    let image = image::open(path)?.thumbnail(256, 256).to_rgb8();
+
+    // cluster in lab so the colors are grouped the way they look, not the way they're stored
+    let pixels: Vec<Lab> = image
+        .pixels()
+        .map(|pixel| Srgb::new(pixel[0], pixel[1], pixel[2]).into_format::<f32>().into_color())
+        .collect();
+
+    let result = get_kmeans_hamerly(PALETTE_SIZE, 20, 5.0, false, &pixels, 0);
+
+    let mut centroids = Lab::sort_indexed_colors(&result.centroids, &result.indices);
+    centroids.sort_unstable_by(|a, b| b.percentage.total_cmp(&a.percentage));
+
+    Ok(centroids
+        .into_iter()
+        .map(|data| to_hex(Srgb::from_color(data.centroid).into_format()))
+        .collect())
+}
+
+/// Extract a palette from the desktop wallpaper, for configs to build color schemes out of.
+#[tauri::command]
+pub async fn async_extract_wallpaper_palette(state: State<'_, AppState>) -> Result<Vec<String>, SteppeError> {
+    if let Some((extracted, palette)) = &*state.wallpaper_palette.lock().await {
+        if extracted.elapsed() < CACHE_TTL {
+            return Ok(palette.clone());
+        }
+    }
+
+    let path = wallpaper_path().await?.ok_or(SteppeError::WallpaperNotFound)?;
+
+    let palette = async_runtime::spawn_blocking(move || extract_palette(&path)).await??;
+    *state.wallpaper_palette.lock().await = Some((Instant::now(), palette.clone()));
+
+    Ok(palette)
+}
@@ -0,0 +1,150 @@
+use std::{
+    ffi::OsStr,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Manager, State};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    time::{sleep, timeout},
+};
+
+use crate::{cwd, error::SteppeError, write_input, AppState};
+
+/// How long files have to stop changing before the command runs again, so a
+/// save that touches a few of them only runs it once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How long the last run gets to die of its ctrl+c before the command's
+/// typed, so the shell isn't still throwing away its line when it comes
+const INTERRUPT_GRACE: Duration = Duration::from_millis(50);
+
+/// Directories whose changes never count, since they're version control's or
+/// where builds write to, and a build writing there would only start itself again
+const IGNORED_DIRS: [&str; 3] = [".git", "node_modules", "target"];
+
+/// What a terminal's watching, and what it runs when that changes
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchTarget {
+    pub paths: Vec<PathBuf>,
+    pub command: String,
+}
+
+/// A terminal's watch mode, which lasts as long as this does
+pub struct Watch {
+    /// Dropping it hangs up on `rerun_on_change` too
+    _watcher: RecommendedWatcher,
+    target: WatchTarget,
+}
+
+/// Whether `path` is in one of `IGNORED_DIRS`
+fn ignored(path: &Path) -> bool {
+    path.components().any(|component| match component {
+        Component::Normal(name) => IGNORED_DIRS.iter().any(|dir| name == OsStr::new(dir)),
+        _ => false,
+    })
+}
+
+/// Whether `event` is something changing, other than in `IGNORED_DIRS`
+fn counts(event: &notify::Event) -> bool {
+    !event.kind.is_access() && !event.paths.iter().all(|path| ignored(path))
+}
+
+/// Interrupt whatever's running in terminal `terminal_id` and type `command`
+/// every time `changes` settles down, until the watcher's dropped
+async fn rerun_on_change(
+    app: AppHandle,
+    terminal_id: u32,
+    command: String,
+    mut changes: UnboundedReceiver<notify::Result<notify::Event>>,
+) {
+    while let Some(event) = changes.recv().await {
+        if !event.is_ok_and(|event| counts(&event)) {
+            continue;
+        }
+
+        // drain the rest of this save
+        while let Ok(Some(_)) = timeout(DEBOUNCE, changes.recv()).await {}
+
+        let state = app.state::<AppState>();
+        let rerun = async {
+            write_input(&app, &state, terminal_id, "\x03").await?;
+            sleep(INTERRUPT_GRACE).await;
+            write_input(&app, &state, terminal_id, &format!("{command}\r")).await
+        };
+        if let Err(err) = rerun.await {
+            eprintln!("could not rerun watch command in terminal {terminal_id}: {err}");
+        }
+    }
+}
+
+/// Run `command` in terminal `terminal_id` whenever something under `paths`
+/// changes, interrupting the last run first. Relative paths are from where
+/// the shell is, and no paths at all watches the whole of it. Replaces the
+/// terminal's last watch, if it had one.
+#[tauri::command]
+pub async fn async_set_watch(
+    terminal_id: u32,
+    paths: Vec<PathBuf>,
+    command: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let cwd = cwd::terminal_cwd(&terminal).await;
+
+    let paths: Vec<PathBuf> = match (paths.is_empty(), &cwd) {
+        (true, Some(cwd)) => vec![cwd.clone()],
+        _ => paths
+            .into_iter()
+            .map(|path| match &cwd {
+                Some(cwd) if path.is_relative() => cwd.join(path),
+                _ => path,
+            })
+            .collect(),
+    };
+    if paths.is_empty() {
+        return Err(SteppeError::Watch(format!(
+            "nothing to watch, since where terminal {terminal_id}'s shell is isn't known"
+        )));
+    }
+
+    let (events, changes) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = events.send(event);
+    })
+    .map_err(|err| SteppeError::Watch(err.to_string()))?;
+    for path in &paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|err| SteppeError::Watch(format!("{}: {err}", path.display())))?;
+    }
+
+    async_runtime::spawn(rerun_on_change(app, terminal_id, command.clone(), changes));
+
+    *terminal.watch.lock().await = Some(Watch {
+        _watcher: watcher,
+        target: WatchTarget { paths, command },
+    });
+
+    Ok(())
+}
+
+/// Stop terminal `terminal_id`'s watch mode
+#[tauri::command]
+pub async fn async_clear_watch(terminal_id: u32, state: State<'_, AppState>) -> Result<(), SteppeError> {
+    state.terminal(terminal_id).await?.watch.lock().await.take();
+    Ok(())
+}
+
+/// What terminal `terminal_id` is watching, if anything
+#[tauri::command]
+pub async fn async_get_watch(terminal_id: u32, state: State<'_, AppState>) -> Result<Option<WatchTarget>, SteppeError> {
+    let terminal = state.terminal(terminal_id).await?;
+    let watch = terminal.watch.lock().await;
+
+    Ok(watch.as_ref().map(|watch| watch.target.clone()))
+}
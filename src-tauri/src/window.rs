@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use tauri::{
+    async_runtime, utils::config::WindowConfig, AppHandle, Manager, State, WebviewWindow, WebviewWindowBuilder, Window,
+    WindowEvent,
+};
+
+use crate::{
+    error::SteppeError,
+    next_terminal_id,
+    signal::{self, Signal},
+    AppState,
+};
+
+/// The window steppe starts with, which terminals go in unless they were opened from another one
+pub const MAIN_WINDOW: &str = "main";
+
+/// Windows need labels no other window's had, even a closed one
+static NEXT_WINDOW: AtomicU32 = AtomicU32::new(1);
+
+/// Which window terminal `terminal_id` is shown in
+pub async fn window_of(state: &AppState, terminal_id: u32) -> String {
+    state
+        .terminal_windows
+        .lock()
+        .await
+        .get(&terminal_id)
+        .cloned()
+        .unwrap_or_else(|| MAIN_WINDOW.to_string())
+}
+
+/// Show terminal `terminal_id` in window `label` from now on
+pub async fn assign(state: &AppState, terminal_id: u32, label: &str) {
+    state
+        .terminal_windows
+        .lock()
+        .await
+        .insert(terminal_id, label.to_string());
+}
+
+/// The open terminals shown in window `label`
+pub async fn terminals_in(state: &AppState, label: &str) -> Vec<u32> {
+    let ids: Vec<u32> = state.terminals.lock().await.keys().copied().collect();
+    let windows = state.terminal_windows.lock().await;
+
+    let mut terminals: Vec<u32> = ids
+        .into_iter()
+        .filter(|id| windows.get(id).map_or(MAIN_WINDOW, String::as_str) == label)
+        .collect();
+    terminals.sort_unstable();
+
+    terminals
+}
+
+/// The main window's config, for other windows to start from
+pub fn main_config(app: &AppHandle) -> Result<WindowConfig, SteppeError> {
+    app.config()
+        .app
+        .windows
+        .first()
+        .cloned()
+        .ok_or_else(|| SteppeError::InvalidConfig("there's no window config to copy".to_string()))
+}
+
+/// Build a window from `config` with a terminal of its own.
+pub async fn build(app: &AppHandle, state: &AppState, config: &WindowConfig) -> Result<WebviewWindow, SteppeError> {
+    // the pty's opened now so the id's taken, the new page starts the shell in it
+    let terminal_id = next_terminal_id(state).await?;
+    assign(state, terminal_id, &config.label).await;
+
+    Ok(WebviewWindowBuilder::from_config(app, config)?.build()?)
+}
+
+/// The main window's config under a label of its own, for another window
+pub fn new_config(app: &AppHandle) -> Result<WindowConfig, SteppeError> {
+    let mut config = main_config(app)?;
+    config.label = format!("window-{}", NEXT_WINDOW.fetch_add(1, Ordering::Relaxed));
+
+    Ok(config)
+}
+
+/// Open another window with a terminal of its own, returning the window's label.
+pub async fn open(app: &AppHandle, state: &AppState) -> Result<String, SteppeError> {
+    let config = new_config(app)?;
+    build(app, state, &config).await?;
+
+    Ok(config.label)
+}
+
+/// Hang up on the terminals of a window that's gone, other than the main one
+/// (whose terminals stay around for detaching). Terminals keep their window
+/// until then, even through exiting, since the frontend starts a new shell
+/// under the same id.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if !matches!(event, WindowEvent::Destroyed) || window.label() == MAIN_WINDOW {
+        return;
+    }
+
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+
+        for terminal_id in terminals_in(&state, &label).await {
+            let Ok(terminal) = state.terminal(terminal_id).await else {
+                continue;
+            };
+
+            if let Err(err) = signal::send(terminal_id, &terminal, Signal::Sighup).await {
+                eprintln!("could not hang up terminal {terminal_id}: {err}");
+            }
+        }
+
+        state.terminal_windows.lock().await.retain(|_, window| *window != label);
+        state.layouts.lock().await.remove(&label);
+        state.focused_terminals.lock().await.remove(&label);
+        state.pending_focus.lock().await.remove(&label);
+    });
+}
+
+#[tauri::command]
+pub async fn async_new_window(app: AppHandle, state: State<'_, AppState>) -> Result<String, SteppeError> {
+    open(&app, &state).await
+}
+
+/// The terminals the window calling this should show
+#[tauri::command]
+pub async fn async_list_window_terminals(window: Window, state: State<'_, AppState>) -> Result<Vec<u32>, SteppeError> {
+    Ok(terminals_in(&state, window.label()).await)
+}
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tauri::State;
+
+use crate::{error::SteppeError, AppState};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteLimitOptions {
+    pub max_bytes_per_second: f64,
+    /// How much can be written at once before the limit kicks in
+    pub burst_bytes: f64,
+}
+
+/// A token bucket over bytes written to the pty, so a runaway paste or
+/// script can't flood the shell faster than it can keep up.
+#[derive(Debug)]
+pub struct WriteLimiter {
+    tokens: f64,
+    last_refill: Instant,
+    max_bytes_per_sec: f64,
+    burst: f64,
+}
+
+impl WriteLimiter {
+    fn new(opts: WriteLimitOptions) -> Self {
+        Self {
+            tokens: opts.burst_bytes,
+            last_refill: Instant::now(),
+            max_bytes_per_sec: opts.max_bytes_per_second,
+            burst: opts.burst_bytes,
+        }
+    }
+
+    /// Take `bytes` worth of tokens, returning how long to wait before they've
+    /// been earned. Writes bigger than the burst go into debt rather than
+    /// never getting through.
+    fn take(&mut self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        let earned = now.duration_since(self.last_refill).as_secs_f64() * self.max_bytes_per_sec;
+        self.tokens = (self.tokens + earned).min(self.burst);
+        self.last_refill = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.max_bytes_per_sec)
+        }
+    }
+}
+
+/// Wait until `bytes` can be written under the rate limit, if there is one.
+/// Writes that have to go through right away (mouse reports, startup
+/// sequences) skip this.
+pub async fn throttle(state: &AppState, bytes: usize) {
+    // held through the sleep, so writes go out in the order they came in
+    let mut limiter = state.write_limiter.lock().await;
+
+    if let Some(limiter) = &mut *limiter {
+        let wait = limiter.take(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Limit writes to the pty, or take the limit off with `None`.
+#[tauri::command]
+pub async fn async_set_write_rate_limit(
+    opts: Option<WriteLimitOptions>,
+    state: State<'_, AppState>,
+) -> Result<(), SteppeError> {
+    if let Some(opts) = opts {
+        if !(opts.max_bytes_per_second > 0.0 && opts.burst_bytes > 0.0) {
+            return Err(SteppeError::InvalidConfig(
+                "write rate limit needs a rate and burst size above 0".into(),
+            ));
+        }
+    }
+
+    *state.write_limiter.lock().await = opts.map(WriteLimiter::new);
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use portable_pty::CommandBuilder;
+use serde::Serialize;
+
+use crate::error::SteppeError;
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub struct WslDistro {
+    pub name: String,
+    /// `Running`, `Stopped`, `Installing`, ...
+    pub state: String,
+    /// 1 or 2
+    pub version: u8,
+    /// Whether it's the one plain `wsl.exe` starts
+    pub default: bool,
+}
+
+/// `wsl.exe` writes utf-16, even into a pipe
+#[cfg(target_os = "windows")]
+fn decode_output(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Parse `wsl.exe -l -v`, which looks like
+///
+/// ```text
+///   NAME      STATE           VERSION
+/// * Ubuntu    Running         2
+///   Debian    Stopped         1
+/// ```
+#[cfg(target_os = "windows")]
+fn parse_distros(output: &str) -> Vec<WslDistro> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let line = line.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            let (default, line) = match line.strip_prefix('*') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            // names can't have spaces in them, so splitting on whitespace is fine
+            let mut columns = line.split_whitespace();
+            Some(WslDistro {
+                name: columns.next()?.to_string(),
+                state: columns.next()?.to_string(),
+                version: columns.next()?.parse().ok()?,
+                default,
+            })
+        })
+        .collect()
+}
+
+/// A shell in `distro`, starting in `cwd`, or the distro user's home directory.
+#[cfg(target_os = "windows")]
+pub fn command(distro: &str, cwd: Option<&Path>) -> Result<CommandBuilder, SteppeError> {
+    let mut cmd = CommandBuilder::new("wsl.exe");
+    cmd.args(["--distribution", distro, "--cd"]);
+
+    // without --cd it starts wherever steppe was started, as a /mnt/c path
+    match cwd {
+        Some(cwd) => cmd.arg(cwd),
+        None => cmd.arg("~"),
+    }
+
+    Ok(cmd)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn command(_distro: &str, _cwd: Option<&Path>) -> Result<CommandBuilder, SteppeError> {
+    Err(SteppeError::UnsupportedPlatformFeature("wsl"))
+}
+
+/// `path` the way `distro` sees it, like `/mnt/c/Users` for `C:\Users`
+#[cfg(target_os = "windows")]
+pub async fn linux_path(distro: &str, path: &Path) -> Result<String, SteppeError> {
+    let output = tokio::process::Command::new("wsl.exe")
+        .args(["--distribution", distro, "--exec", "wslpath", "-u"])
+        .arg(path)
+        .output()
+        .await?;
+
+    // wsl.exe's own complaints (like there being no such distro) are in
+    // utf-16, but what wslpath writes is utf-8
+    if !output.status.success() {
+        let message = match std::str::from_utf8(&output.stderr) {
+            Ok(message) if !message.contains('\0') => message.to_string(),
+            _ => decode_output(&output.stderr),
+        };
+        return Err(SteppeError::Wsl(message.trim().to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\r', '\n'])
+        .to_string())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub async fn linux_path(_distro: &str, _path: &Path) -> Result<String, SteppeError> {
+    Err(SteppeError::UnsupportedPlatformFeature("wsl"))
+}
+
+#[cfg(target_os = "windows")]
+#[tauri::command]
+pub async fn async_list_wsl_distros() -> Result<Vec<WslDistro>, SteppeError> {
+    let output = tokio::process::Command::new("wsl.exe").args(["-l", "-v"]).output().await?;
+
+    // no distros installed (or wsl at all) exits with an error, but that's
+    // just an empty list
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    Ok(parse_distros(&decode_output(&output.stdout)))
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tauri::command]
+pub async fn async_list_wsl_distros() -> Result<Vec<WslDistro>, SteppeError> {
+    Ok(Vec::new())
+}
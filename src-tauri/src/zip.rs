@@ -0,0 +1,196 @@
+use std::io::{self, Write};
+
+use flate2::{write::DeflateEncoder, Compression, Crc};
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// 2.0, the first version with deflate
+const VERSION: u16 = 20;
+/// Names are utf-8
+const FLAGS: u16 = 1 << 11;
+const DEFLATE: u16 = 8;
+/// 1980-01-01 00:00, zip's epoch. When the files were written doesn't
+/// matter, the bundle's name says when it was made.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed_len: u32,
+    len: u32,
+    offset: u32,
+}
+
+/// Just enough of a zip writer for diagnostic bundles: deflated files, all
+/// in memory, none of them anywhere near 4GiB
+#[derive(Default)]
+pub struct ZipWriter {
+    out: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "too large for a zip without zip64")
+}
+
+fn len_u32(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| too_large())
+}
+
+impl ZipWriter {
+    fn put_u16(&mut self, value: u16) {
+        self.out.extend(value.to_le_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.out.extend(value.to_le_bytes());
+    }
+
+    /// The part of the local and central headers they have in common, from
+    /// the version needed on
+    fn put_common(&mut self, entry_index: usize) {
+        let (crc, compressed_len, len, name_len) = {
+            let entry = &self.entries[entry_index];
+            (entry.crc, entry.compressed_len, entry.len, entry.name.len() as u16)
+        };
+
+        self.put_u16(VERSION);
+        self.put_u16(FLAGS);
+        self.put_u16(DEFLATE);
+        self.put_u16(DOS_TIME);
+        self.put_u16(DOS_DATE);
+        self.put_u32(crc);
+        self.put_u32(compressed_len);
+        self.put_u32(len);
+        self.put_u16(name_len);
+        // no extra field
+        self.put_u16(0);
+    }
+
+    /// Add a file named `name` (with `/` between directories) holding `contents`
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+
+        let mut crc = Crc::new();
+        crc.update(contents);
+
+        if name.len() > u16::MAX as usize {
+            return Err(too_large());
+        }
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc: crc.sum(),
+            compressed_len: len_u32(compressed.len())?,
+            len: len_u32(contents.len())?,
+            offset: len_u32(self.out.len())?,
+        });
+
+        self.put_u32(LOCAL_HEADER);
+        self.put_common(self.entries.len() - 1);
+        self.out.extend(name.as_bytes());
+        self.out.extend(compressed);
+
+        Ok(())
+    }
+
+    /// The whole zip, with its central directory
+    pub fn finish(mut self) -> io::Result<Vec<u8>> {
+        let directory_offset = len_u32(self.out.len())?;
+        let count = u16::try_from(self.entries.len()).map_err(|_| too_large())?;
+
+        for index in 0..self.entries.len() {
+            self.put_u32(CENTRAL_HEADER);
+            // made by the same version it needs
+            self.put_u16(VERSION);
+            self.put_common(index);
+            // no comment, on the first (and only) disk, with no attributes
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u32(0);
+
+            let offset = self.entries[index].offset;
+            self.put_u32(offset);
+            let name = std::mem::take(&mut self.entries[index].name);
+            self.out.extend(name.as_bytes());
+        }
+
+        let directory_len = len_u32(self.out.len())? - directory_offset;
+        self.put_u32(END_OF_CENTRAL_DIRECTORY);
+        // this disk, and the one the directory starts on
+        self.put_u16(0);
+        self.put_u16(0);
+        self.put_u16(count);
+        self.put_u16(count);
+        self.put_u32(directory_len);
+        self.put_u32(directory_offset);
+        // no comment
+        self.put_u16(0);
+
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+
+    use super::*;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn writes_entries_and_directory() {
+        let first = b"steppe diagnostics\n".repeat(20);
+        let second = b"{\"terminals\":[]}".to_vec();
+
+        let mut zip = ZipWriter::default();
+        zip.add("logs/steppe.log", &first).unwrap();
+        zip.add("state.json", &second).unwrap();
+        let out = zip.finish().unwrap();
+
+        let mut at = 0;
+        for (name, contents) in [("logs/steppe.log", &first), ("state.json", &second)] {
+            assert_eq!(u32_at(&out, at), LOCAL_HEADER);
+            assert_eq!(u16_at(&out, at + 8), DEFLATE);
+
+            let mut crc = Crc::new();
+            crc.update(contents);
+            assert_eq!(u32_at(&out, at + 14), crc.sum());
+            assert_eq!(u32_at(&out, at + 22) as usize, contents.len());
+
+            let compressed_len = u32_at(&out, at + 18) as usize;
+            let name_len = u16_at(&out, at + 26) as usize;
+            let data = at + 30 + name_len;
+            assert_eq!(&out[at + 30..data], name.as_bytes());
+
+            let mut inflated = Vec::new();
+            DeflateDecoder::new(&out[data..data + compressed_len])
+                .read_to_end(&mut inflated)
+                .unwrap();
+            assert_eq!(&inflated, contents);
+
+            at = data + compressed_len;
+        }
+
+        // the central directory starts right after the last file
+        let end = out.len() - 22;
+        assert_eq!(u32_at(&out, end), END_OF_CENTRAL_DIRECTORY);
+        assert_eq!(u16_at(&out, end + 10), 2);
+        assert_eq!(u32_at(&out, end + 16) as usize, at);
+        assert_eq!(u32_at(&out, at), CENTRAL_HEADER);
+    }
+}
@@ -0,0 +1,199 @@
+// generated from the rust config types by src-tauri/src/config_types.rs, don't edit by hand
+
+export type Antialiasing = "none" | "grayscale" | "subpixel";
+
+export interface BatchModeOptions {
+    enabled: boolean;
+    maxLineBufferSize: number;
+    flushIntervalMs: number;
+}
+
+export interface CommandNotificationOptions {
+    minDurationMs?: number | null;
+    onlyFailures?: boolean;
+    exclude?: string[];
+}
+
+export type DropSeparator = "space" | "newline";
+
+export type Easing = "linear" | "ease-in" | "ease-out" | "ease-in-out";
+
+export interface EnvironmentOptions {
+    enabled?: boolean | null;
+    exclude?: string[];
+}
+
+export interface FileDropOptions {
+    separator?: DropSeparator;
+}
+
+export interface FixedSize {
+    rows: number;
+    cols: number;
+}
+
+export interface FontRenderingOptions {
+    antialiasing: Antialiasing;
+    hinting: Hinting;
+    subpixelOrder: SubpixelOrder;
+}
+
+export type Hinting = "none" | "slight" | "medium" | "full";
+
+export interface IdentityOptions {
+    term?: string | null;
+    answerback?: string | null;
+    deviceAttributes?: string | null;
+    secondaryDeviceAttributes?: string | null;
+    version?: string | null;
+}
+
+export interface LinkPatterns {
+    url?: string | null;
+    path?: string | null;
+    rules?: LinkRule[];
+}
+
+export interface LinkRule {
+    name?: string;
+    pattern?: string;
+    url?: string | null;
+    path?: string | null;
+    line?: string | null;
+    column?: string | null;
+}
+
+export type MouseMode = "x10" | "normal" | "buttonEvent" | "anyEvent" | "sgr" | "urxvt" | "pixel";
+
+export type OnExit = "close" | "restart" | "hold";
+
+export interface QuakeOptions {
+    hotkey?: string;
+    height?: number;
+    monitor?: number | null;
+}
+
+export interface RedactionOptions {
+    builtin?: boolean | null;
+    patterns?: string[];
+}
+
+export interface RemoteAttachOptions {
+    listen: string;
+    token: string;
+}
+
+export type RendererBackend = "webgl2" | "canvas2d" | "dom";
+
+export interface SizeConstraints {
+    minRows: number;
+    maxRows: number;
+    minCols: number;
+    maxCols: number;
+    fixedSize?: FixedSize | null;
+}
+
+export interface SpillOptions {
+    maxLines?: number | null;
+}
+
+export type SplitDirection = "horizontal" | "vertical";
+
+export interface StartupNotificationOptions {
+    show: boolean;
+    message?: string | null;
+    durationMs: number;
+}
+
+export type StartupPane = StartupSplit | StartupTerminal;
+
+export interface StartupSplit {
+    split: SplitDirection;
+    ratio?: number | null;
+    first: StartupPane;
+    second: StartupPane;
+}
+
+export interface StartupTerminal {
+    profile?: string | null;
+    cwd?: string | null;
+    command?: string | null;
+}
+
+export interface StartupWindow {
+    tabs: StartupPane[];
+}
+
+export type SubpixelOrder = "RGB" | "BGR" | "VRGB" | "VBGR";
+
+export interface Task {
+    name: string;
+    command: string;
+    cwd?: string | null;
+    newTerminal?: boolean;
+}
+
+export interface TerminalSequence {
+    delay_ms: number;
+    data: string;
+}
+
+export interface TermiosSettings {
+    echo: boolean;
+    canonicalMode: boolean;
+    signalChars: boolean;
+    minRead: number;
+    timeout: number;
+}
+
+export interface Theme {
+    foreground: string;
+    background: string;
+    cursor: string;
+    ansi: string[];
+}
+
+export type ThemeSource = string | Theme;
+
+export type UrlOpenMode = "system" | "browser" | "custom";
+
+export interface WriteLimitOptions {
+    maxBytesPerSecond: number;
+    burstBytes: number;
+}
+
+/** What config.js can export, and settings.toml or settings.json can set */
+export interface Settings {
+    fontSize?: number | null;
+    scrollbackLines?: number | null;
+    outputCoalesceBytes?: number | null;
+    outputCoalesceMs?: number | null;
+    defaultShell?: string | null;
+    loginShell?: boolean | null;
+    shellArgs?: string[] | null;
+    shellIntegration?: boolean | null;
+    shellEnv?: Record<string, string> | null;
+    initCommand?: string | null;
+    theme?: ThemeSource | null;
+    quake?: QuakeOptions | null;
+    keepAliveInTray?: boolean | null;
+    maxImageBytes?: number | null;
+    linkPatterns?: LinkPatterns | null;
+    onExit?: OnExit | null;
+    tasks?: Task[] | null;
+    startupLayout?: StartupWindow[] | null;
+    remoteAttach?: RemoteAttachOptions | null;
+    redaction?: RedactionOptions | null;
+    fileDrop?: FileDropOptions | null;
+    scrollbackSpill?: SpillOptions | null;
+    environmentSnapshot?: EnvironmentOptions | null;
+    commandNotifications?: CommandNotificationOptions | null;
+    terminalIdentity?: IdentityOptions | null;
+    allowClipboardWrite?: boolean | null;
+    allowClipboardRead?: boolean | null;
+    commandHistory?: boolean | null;
+    confirmClose?: boolean | null;
+    restoreSession?: boolean | null;
+    detachOnClose?: boolean | null;
+    windowTitleFromTerminal?: boolean | null;
+}
@@ -0,0 +1,417 @@
+// types for the `steppe` global config.ts and plugins get, see src-tauri/src/js/steppe.js.
+// steppe keeps a copy of this next to your config, so `/// <reference path="./steppe.d.ts" />` picks it up
+
+declare namespace steppe {
+    /** Set css variables on the page, e.g. `{ "--background": "#000" }` */
+    function setTheme(vars: Record<`--${string}`, string>): void;
+
+    /** Call `listener` with everything any terminal prints, until the returned function is called */
+    function onOutput(listener: (terminalId: number, data: string) => void): () => void;
+
+    /** What `on` listeners get for each event, always with the terminal's id */
+    interface Events {
+        terminalOpened: {
+            terminalId: number;
+            program: string;
+            /** The shell profile it was started from */
+            profile: string | null;
+            /** The ssh profile, when it's an ssh connection */
+            sshProfile: string | null;
+            pid: number | null;
+        };
+        terminalClosed: { terminalId: number; exitCode: number };
+        /** Any command shell integration saw finish, however long it took */
+        commandFinished: {
+            terminalId: number;
+            command: string | null;
+            cwd: string | null;
+            exitCode: number | null;
+            durationMs: number;
+        };
+        titleChanged: { terminalId: number; title: string };
+    }
+
+    /** Call `listener` every time `event` happens to any terminal, until the returned function is called */
+    function on<E extends keyof Events>(
+        event: E,
+        listener: (event: Events[E] & { event: E }) => void | Promise<void>,
+    ): () => void;
+
+    /** Bind `key` (like `"ctrl+shift+t"`) to a named action, or a function to call */
+    function bind(key: string, action: string | (() => void | Promise<void>)): Promise<void>;
+
+    /**
+     * Run `action` for every line of output matching `pattern`: a named action
+     * (like `"notify"`, for a toast with the line), or a function to call
+     */
+    function addTrigger(trigger: {
+        pattern: RegExp | string;
+        action: string | ((line: string, terminalId: number) => void | Promise<void>);
+    }): Promise<void>;
+
+    /** Add `label` to the command palette, running a named action or a function */
+    function addCommand(command: { label: string; action: string | (() => void | Promise<void>) }): Promise<void>;
+
+    /**
+     * Show what `provider` returns in each terminal's status bar as `name`,
+     * or nothing for null. It's asked again whenever a terminal opens,
+     * finishes a command or changes its title, until the returned function is called.
+     */
+    function addStatusProvider(
+        name: string,
+        provider: (terminalId: number) => string | null | Promise<string | null>,
+    ): () => void;
+
+    /**
+     * Start a shell (or a profile from config) in a new terminal, resolving to its id.
+     * A profile's command has to be allowed by `run` in permissions.json.
+     */
+    function spawnTerminal(options?: { profile?: string; cwd?: string }): Promise<number>;
+
+    // the rest set up what the frontend otherwise would. Their option types are in
+    // steppe-config.d.ts, generated from steppe's own
+
+    /** Set the pty's line discipline, the way `stty` would, for every terminal */
+    function setPtyLineDisc(settings: import("./steppe-config").TermiosSettings): Promise<void>;
+
+    /** Open links with the system's handler, a browser, or `setCustomUrlOpener`'s command */
+    function setUrlOpenMode(mode: import("./steppe-config").UrlOpenMode): Promise<void>;
+
+    /** Open links by running `command` with `args` and the url, if `run` in permissions.json allows it */
+    function setCustomUrlOpener(command: string, args?: string[]): Promise<void>;
+
+    function setAllowFileUrls(allow: boolean): Promise<void>;
+
+    function setUrlHistoryEnabled(enabled: boolean): Promise<void>;
+
+    /** Upload crash reports to `endpoint`, or stop with `false` */
+    function setCrashReporting(enabled: boolean, endpoint?: string): Promise<void>;
+
+    function setRendererBackend(backend: import("./steppe-config").RendererBackend): Promise<void>;
+
+    function setSessionSizeConstraints(constraints: import("./steppe-config").SizeConstraints): Promise<void>;
+
+    /** Only with `"run": true` in permissions.json, since it can give away anything */
+    function setExposeSecretsInDiagnostics(expose: boolean): Promise<void>;
+
+    function setFontRendering(opts: import("./steppe-config").FontRenderingOptions): Promise<void>;
+
+    function setStartupDelay(ms: number): Promise<void>;
+
+    /** Throttle writes to terminals, or stop with `null` */
+    function setWriteRateLimit(opts: import("./steppe-config").WriteLimitOptions | null): Promise<void>;
+
+    function setStartupSequences(sequences: import("./steppe-config").TerminalSequence[]): Promise<void>;
+
+    /**
+     * Resolve once `cmd` with `args` succeeds, trying again until `timeoutMs` is up.
+     * `cmd` has to be allowed by `run` in permissions.json.
+     */
+    function waitForCommand(cmd: string, args: string[], timeoutMs: number): Promise<void>;
+
+    function setCursorColors(fg: string, bg: string): Promise<void>;
+
+    /** Bind `key` to pasting the buffer named `bufferName` (`"+"` for the clipboard) */
+    function registerPasteFromBuffer(key: string, bufferName: string): Promise<void>;
+
+    function startGpuMonitoring(intervalMs: number): Promise<void>;
+
+    function setLineHeight(value: number): Promise<void>;
+
+    function setCellSpacing(horizontal: number, vertical: number): Promise<void>;
+
+    function setSessionStartupNotification(
+        opts: import("./steppe-config").StartupNotificationOptions,
+    ): Promise<void>;
+
+    function setMouseReportMode(terminalId: number, mode: import("./steppe-config").MouseMode): Promise<void>;
+
+    function setFocusOpacity(focused: number, unfocused: number, animationDurationMs?: number): Promise<void>;
+
+    function setFocusOpacityEasing(easing: import("./steppe-config").Easing): Promise<void>;
+
+    /**
+     * Which icon a tab shows for the program running in it, e.g. `{ "n*vim": "editor", "git": "git" }`.
+     * `*` in a name matches anything, and the first name that matches wins. The icons are
+     * `docker`, `editor`, `git`, `monitor`, `node`, `python`, `rust` and `ssh`
+     */
+    function setProcessIconMap(map: Record<string, string>): Promise<void>;
+
+    function setScrollbackLines(lines: number): Promise<void>;
+
+    function setOutputBatchMode(opts: import("./steppe-config").BatchModeOptions): Promise<void>;
+
+    /** The wallpaper's most common colors, as hex strings */
+    function extractWallpaperPalette(): Promise<string[]>;
+
+    function setSshProfiles(profiles: SteppeSshProfile[]): Promise<void>;
+
+    /** Every profile's command has to be allowed by `run` in permissions.json */
+    function setShellProfiles(profiles: SteppeShellProfile[]): Promise<void>;
+
+    /** How long a command has to run for, in milliseconds, to notify when it's done */
+    function setLongCommandThreshold(ms: number): Promise<void>;
+
+    function setTerminalNotifications(terminalId: number, escapeSequences: boolean, bell: boolean): Promise<void>;
+
+    /** Log every terminal opened from now on into its own file in `dir`, or stop for `null` */
+    function setOutputLogging(dir: string | null, opts?: { stripAnsi?: boolean; maxBytes?: number }): Promise<void>;
+}
+
+/** What a plugin in `plugins/` can export */
+interface SteppePlugin {
+    activate?(api: typeof steppe): void | Promise<void>;
+    deactivate?(): void | Promise<void>;
+}
+
+/** A host for `setSshProfiles`, e.g. `{ name: "prod", host: "prod.example.com", user: "deploy" }` */
+interface SteppeSshProfile {
+    name: string;
+    host: string;
+    port?: number;
+    user?: string;
+    /** A private key to log in with, whose passphrase is asked for in a prompt */
+    keyPath?: string;
+    /** A socket (or a pipe on windows) to use in place of `SSH_AUTH_SOCK`'s agent, or `"none"` */
+    agent?: string;
+    forwardAgent?: boolean;
+    addKeysToAgent?: boolean;
+    /** Passed to `-J` as is, so `user@host:port` works */
+    jumpHost?: string;
+    forwards?: {
+        kind: "local" | "remote";
+        bindAddress?: string;
+        bindPort: number;
+        host: string;
+        port: number;
+    }[];
+    mosh?: boolean;
+    /** The UDP ports `mosh-server` can pick from, as `[first, last]` */
+    moshPorts?: [number, number];
+    /** Connect with telnet instead, with only `host` and `port` */
+    telnet?: boolean;
+}
+
+/** Something to run instead of the default shell, for `setShellProfiles` */
+interface SteppeShellProfile {
+    name: string;
+    command: string;
+    /** Start `command` with `-l` */
+    loginShell?: boolean;
+    args?: string[];
+    env?: Record<string, string>;
+    cwd?: string;
+    /** Typed into the shell once it's started, e.g. `"source ~/.work.sh"` */
+    initCommand?: string;
+}
+
+/** What config.ts can export in `tasks`, e.g. `{ name: "dev", command: "npm run dev", cwd: "~/proj" }` */
+interface SteppeTask {
+    /** Also what its tab's called */
+    name: string;
+    /** Typed into the default shell */
+    command: string;
+    cwd?: string;
+    /** Open a new terminal every time, instead of running it again in the last one while that's still open */
+    newTerminal?: boolean;
+}
+
+/**
+ * What config.ts can export alongside `theme`. The same names go in
+ * `settings.toml` or `settings.json` in the config directory, and an export
+ * wins over the file.
+ */
+interface SteppeSettings {
+    fontSize?: number;
+    /** For terminals opened from now on */
+    scrollbackLines?: number;
+    /** The most output sent to the screen at once, in bytes (16 KiB by default) */
+    outputCoalesceBytes?: number;
+    /** How often a terminal that keeps printing updates the screen, in milliseconds (8 by default) */
+    outputCoalesceMs?: number;
+    /** Started in place of `$SHELL`, or powershell on windows */
+    defaultShell?: string;
+    /** Start the default shell with `-l` so it reads `.zprofile` and the like, on by default on macos */
+    loginShell?: boolean;
+    /** Passed to the default shell, e.g. `["--norc"]` */
+    shellArgs?: string[];
+    /**
+     * Load steppe's shell integration into the default shell (bash, zsh, fish
+     * or powershell) as it starts, so prompt marks and the working directory
+     * work without editing dotfiles. On unless this is false or `shellArgs` is set
+     */
+    shellIntegration?: boolean;
+    /** Set for the default shell, e.g. `{ EDITOR: "nvim" }` */
+    shellEnv?: Record<string, string>;
+    /** Typed into the default shell once it's started, e.g. `"source ~/.work.sh"` */
+    initCommand?: string;
+    /**
+     * A built-in theme's name (`"gruvbox"`, `"solarized"` or `"dracula"`), or its
+     * own colors as hex strings, with 16 `ansi` ones: black, red, green, yellow,
+     * blue, magenta, cyan, white, then their bright versions
+     */
+    theme?: import("./steppe-config").ThemeSource;
+    /** A terminal window that drops down from the top of the screen on a global hotkey */
+    quake?: {
+        /** Like `"ctrl+backquote"`, `"F12"` by default */
+        hotkey?: string;
+        /** How much of the monitor's height it takes up, 0.4 by default */
+        height?: number;
+        /** Which monitor it drops down on, counting from 0, rather than the primary one */
+        monitor?: number;
+    };
+    /** Keep steppe running in the tray once the last window's closed */
+    keepAliveInTray?: boolean;
+    /** The biggest image (sixel, kitty or iterm) a program can show, in bytes (16 MiB by default) */
+    maxImageBytes?: number;
+    /** What ctrl+click and hint mode pick out of output as links, in place of the built-in regexes */
+    linkPatterns?: {
+        url?: string;
+        /** Named groups `path`, `line` and `column` make `src/main.rs:12:4` open at that line */
+        path?: string;
+        /**
+         * Links of kinds of their own, which win over urls and paths. `url`,
+         * `path`, `line` and `column` get `$1`, `${name}` and the like filled
+         * in from the match, as in `{ name: "jira", pattern: "JIRA-\\d+", url: "https://jira.example.com/browse/$0" }`
+         */
+        rules?: {
+            /** Sent along with the links it finds, for styling them */
+            name: string;
+            pattern: string;
+            /** The url its links open */
+            url?: string;
+            /** Or the file they open in the editor, at `line` */
+            path?: string;
+            line?: string;
+            column?: string;
+        }[];
+    };
+    /**
+     * What happens once a terminal's shell exits: `"close"` its tab, `"restart"`
+     * it in the same directory if it died (a plain `exit` still closes), or
+     * `"hold"` it with the exit code shown, which is the default
+     */
+    onExit?: "close" | "restart" | "hold";
+    /**
+     * Commands to run by name, from the command palette or `async_run_task`. Exporting
+     * them from config.js needs `"run": true` in permissions.json.
+     */
+    tasks?: SteppeTask[];
+    /**
+     * The windows steppe opens at launch, in place of the last session's,
+     * with the main one first. Each tab's a terminal, or a split with more on
+     * each side, whose `ratio` (0.5 by default) is how much `first` gets. Only
+     * one tab in each window can be split.
+     */
+    startupLayout?: import("./steppe-config").StartupWindow[];
+    /**
+     * Let `steppe attach <id> --remote <host:port> --token <token>` attach from
+     * elsewhere on the network. Nothing's encrypted, so only use it on networks
+     * you trust, or over an ssh tunnel.
+     */
+    remoteAttach?: {
+        /** Like `"0.0.0.0:7681"` */
+        listen: string;
+        /** At least 16 characters, which clients have to give */
+        token: string;
+    };
+    /**
+     * Secrets to take out of output logs, recordings, the command history and
+     * exports. Tokens, keys and passwords that look like the usual kinds are
+     * taken out unless `builtin` is false.
+     */
+    redaction?: {
+        builtin?: boolean;
+        /** More regexes, with a `secret` group to only take out part of the match */
+        patterns?: string[];
+    };
+    /**
+     * How the paths of files dropped on a terminal go in, quoted for its shell:
+     * on one line with a `"space"` between them (the default), or a line each
+     * with `"newline"`
+     */
+    fileDrop?: {
+        separator?: "space" | "newline";
+    };
+    /**
+     * Keep scrollback past `scrollbackLines` in a compressed temp file rather
+     * than letting it go, up to `maxLines` more lines (no limit by default).
+     * Search and exports read it back from there.
+     */
+    scrollbackSpill?: {
+        maxLines?: number;
+    };
+    /**
+     * Remember what's changed in a shell's environment since it started, so
+     * restarting it (`onExit: "restart"`) and restoring the session bring
+     * back an activated `nix develop` or `poetry shell` along with the
+     * directory. It's read from whatever's in the foreground, as it was
+     * started, so an `export` (or `source .venv/bin/activate`) in the shell
+     * itself isn't seen until something's started from it. Linux only, and
+     * local shells only. On by default.
+     *
+     * Variables that look like they hold a secret by name or by value are
+     * never kept, and nor is anything matching a regex in `exclude`.
+     */
+    environmentSnapshot?: {
+        enabled?: boolean;
+        exclude?: string[];
+    };
+    /**
+     * A desktop notification when a command runs for more than
+     * `minDurationMs` (10 seconds by default) and finishes in a terminal
+     * that isn't focused, saying what it was, how long it took and how it
+     * exited. Clicking it focuses that terminal. Needs shell integration
+     * (OSC 133) to know where commands start and end.
+     *
+     * `onlyFailures` leaves out commands that exited with 0, and commands
+     * matching a regex in `exclude` (say `"^(vim|less|man)\\b"`) are left
+     * out too.
+     */
+    commandNotifications?: {
+        minDurationMs?: number;
+        onlyFailures?: boolean;
+        exclude?: string[];
+    };
+    /**
+     * What steppe says it is. `term` is what `TERM` is set to,
+     * `"xterm-256color"` by default; `"steppe"` installs steppe's own
+     * terminfo entry into ~/.terminfo (with `tic`, which has to be
+     * installed) and uses that, except for ssh connections, where the other
+     * end wouldn't have it.
+     *
+     * The rest are the answers to programs asking: `answerback` for ENQ
+     * (nothing by default), `deviceAttributes` and
+     * `secondaryDeviceAttributes` for DA1 and DA2 as their parameters (like
+     * `"62;4;22"`), and `version` for XTVERSION (`"steppe(<version>)"` by
+     * default).
+     */
+    terminalIdentity?: {
+        term?: string;
+        answerback?: string;
+        deviceAttributes?: string;
+        secondaryDeviceAttributes?: string;
+        version?: string;
+    };
+    /** Let programs put text on the clipboard with OSC 52 (on by default) */
+    allowClipboardWrite?: boolean;
+    /**
+     * Let programs read the clipboard with OSC 52. Off by default, since
+     * anything that can print to a terminal could ask, ssh'd-into hosts
+     * included.
+     */
+    allowClipboardRead?: boolean;
+    /**
+     * Save finished commands to the searchable history (on by default).
+     * Turning it off keeps what's already there, clear it for that.
+     */
+    commandHistory?: boolean;
+    /** Ask before closing a window with something other than a shell still running in it (on by default) */
+    confirmClose?: boolean;
+    /** Save the main window's terminals on exit and open them again next launch (on by default) */
+    restoreSession?: boolean;
+    /** Keep the shells running with no window once the last one's closed, for `steppe attach` to bring back */
+    detachOnClose?: boolean;
+    /** Follow the main terminal's title (OSC 0 and 2) in the window's (on by default) */
+    windowTitleFromTerminal?: boolean;
+}